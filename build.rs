@@ -32,15 +32,26 @@ fn main() -> Result<()> {
 
     let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
 
+    println!("cargo:rerun-if-env-changed=COMPONENTIZE_PY_EXTERNAL_ARTIFACTS");
+
     if matches!(env::var("CARGO_CFG_FEATURE").as_deref(), Ok("cargo-clippy"))
         || env::var("CLIPPY_ARGS").is_ok()
         || env::var("CARGO_EXPAND_NO_RUN_NIGHTLY").is_ok()
     {
         stubs_for_clippy(&out_dir)
+    } else if env::var_os("COMPONENTIZE_PY_EXTERNAL_ARTIFACTS").is_some()
+        || env::var_os("CARGO_FEATURE_SLIM").is_some()
+    {
+        // The artifacts will be read at run time from a directory specified by the user (or the artifact cache),
+        // so there's nothing to build (and no need for the WASI SDK or a nightly toolchain); just embed empty
+        // placeholders.
+        stubs(&out_dir)
     } else {
         package_all_the_things(&out_dir)
     }?;
 
+    pin_artifact_checksums(&out_dir)?;
+
     // TODO: how can we detect `cargo test` and only run this in that case (or more specifically, run it so it
     // generates an empty file)?
     test_generator::generate()
@@ -51,6 +62,10 @@ fn stubs_for_clippy(out_dir: &Path) -> Result<()> {
         "cargo:warning=using stubbed runtime, core library, and adapter for static analysis purposes..."
     );
 
+    stubs(out_dir)
+}
+
+fn stubs(out_dir: &Path) -> Result<()> {
     let files = [
         "libcomponentize_py_runtime.so.zst",
         "libpython3.12.so.zst",
@@ -91,6 +106,24 @@ fn stubs_for_clippy(out_dir: &Path) -> Result<()> {
     Ok(())
 }
 
+/// Copy the checksums file named by `COMPONENTIZE_PY_ARTIFACT_CHECKSUMS` (if any) into `out_dir`, or else write an
+/// empty one, meaning artifacts read at run time are checked against their own directory's `SHA256SUMS` file.
+fn pin_artifact_checksums(out_dir: &Path) -> Result<()> {
+    println!("cargo:rerun-if-env-changed=COMPONENTIZE_PY_ARTIFACT_CHECKSUMS");
+
+    let checksums = if let Some(path) = env::var_os("COMPONENTIZE_PY_ARTIFACT_CHECKSUMS") {
+        println!("cargo:rerun-if-changed={}", Path::new(&path).display());
+        fs::read_to_string(&path)
+            .with_context(|| format!("unable to read {}", Path::new(&path).display()))?
+    } else {
+        String::new()
+    };
+
+    fs::write(out_dir.join("artifact-checksums.txt"), checksums)?;
+
+    Ok(())
+}
+
 fn package_all_the_things(out_dir: &Path) -> Result<()> {
     let repo_dir = PathBuf::from(env::var_os("CARGO_MANIFEST_DIR").unwrap());
 
@@ -114,6 +147,10 @@ fn package_all_the_things(out_dir: &Path) -> Result<()> {
         .arg("--release")
         .arg("--target=wasm32-wasip1");
 
+    if env::var_os("CARGO_FEATURE_JSON").is_some() {
+        cmd.arg("--features=json");
+    }
+
     for (key, _) in env::vars_os() {
         if key
             .to_str()
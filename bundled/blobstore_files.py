@@ -0,0 +1,262 @@
+"""File-like access to `wasi:blobstore` containers.
+
+This is available to any app targeting a world which imports
+`wasi:blobstore/blobstore`, and wraps the low-level container, value, and
+stream resources in familiar Python idioms:
+
+    import blobstore_files
+
+    with blobstore_files.container("notes", create=True) as notes:
+        with notes.open("todo.txt", "w") as f:
+            f.write("buy milk\\n")
+
+        with notes.open("todo.txt") as f:
+            for line in f:
+                print(line, end="")
+
+        for name in notes:
+            print(name, notes.size(name))
+
+Objects are streamed to and from the host in chunks, so neither reading nor
+writing requires an entire object to be buffered in memory at once.
+
+All calls block the calling task until they complete.
+"""
+
+import io
+
+from blobstore_bindings import Err, blobstore, container as _container, types, streams
+from typing import IO, Any, Iterator, Optional
+
+# Maximum number of bytes to read at a time
+READ_SIZE: int = 16 * 1024
+
+# Maximum number of bytes `output-stream.blocking-write-and-flush` accepts at a
+# time
+WRITE_SIZE: int = 4096
+
+# Maximum number of object names to request from the host at a time
+LIST_SIZE: int = 100
+
+
+class BlobstoreError(OSError):
+    """Raised when the host reports an error for a blobstore operation."""
+
+
+def _call(description: str, function: Any, *args: Any) -> Any:
+    try:
+        return function(*args)
+    except Err as e:
+        raise BlobstoreError(f"{description}: {e.value}")
+
+
+class _Reader(io.RawIOBase):
+    def __init__(self, container: _container.Container, name: str):
+        super().__init__()
+        self.name = name
+        self._stream: Optional[streams.InputStream] = None
+        size = _call(f"error opening {name}", container.object_info, name).size
+        # `get-data` takes an inclusive range, so an empty object can't be
+        # requested at all.
+        if size > 0:
+            value = _call(f"error opening {name}", container.get_data, name, 0, size - 1)
+            self._stream = _call(
+                f"error opening {name}",
+                types.IncomingValue.incoming_value_consume_async,
+                value,
+            )
+
+    def readable(self) -> bool:
+        return True
+
+    def readinto(self, buffer: Any) -> int:
+        while self._stream is not None:
+            try:
+                chunk = self._stream.blocking_read(len(buffer))
+            except Err as e:
+                self._release()
+                if isinstance(e.value, streams.StreamError_Closed):
+                    return 0
+                else:
+                    raise BlobstoreError(f"error reading {self.name}: {e.value}")
+            if chunk or not len(buffer):
+                buffer[: len(chunk)] = chunk
+                return len(chunk)
+        return 0
+
+    def _release(self) -> None:
+        if self._stream is not None:
+            self._stream.__exit__(None, None, None)
+            self._stream = None
+
+    def close(self) -> None:
+        self._release()
+        super().close()
+
+
+class _Writer(io.RawIOBase):
+    def __init__(self, container: _container.Container, name: str):
+        super().__init__()
+        self.name = name
+        self._value: Optional[types.OutgoingValue] = (
+            types.OutgoingValue.new_outgoing_value()
+        )
+        self._stream: Optional[streams.OutputStream] = _call(
+            f"error opening {name}", self._value.outgoing_value_write_body
+        )
+        # Hand the value to the host before writing anything so that it can
+        # consume the body as we produce it rather than us having to buffer it.
+        _call(f"error opening {name}", container.write_data, name, self._value)
+
+    def writable(self) -> bool:
+        return True
+
+    def write(self, data: Any) -> int:
+        if self._stream is None:
+            raise ValueError("write to closed file")
+        data = memoryview(data).cast("B")
+        for offset in range(0, len(data), WRITE_SIZE):
+            try:
+                self._stream.blocking_write_and_flush(
+                    bytes(data[offset : offset + WRITE_SIZE])
+                )
+            except Err as e:
+                raise BlobstoreError(f"error writing {self.name}: {e.value}")
+        return len(data)
+
+    def close(self) -> None:
+        if self._stream is not None:
+            self._stream.__exit__(None, None, None)
+            self._stream = None
+        if self._value is not None:
+            value = self._value
+            self._value = None
+            _call(f"error writing {self.name}", types.OutgoingValue.finish, value)
+        super().close()
+
+
+class Container:
+    """A named collection of objects in a blobstore."""
+
+    def __init__(self, container: _container.Container):
+        self._container: Optional[_container.Container] = container
+
+    @property
+    def name(self) -> str:
+        """The name of the container."""
+        return _call("error reading container name", self._handle().name)
+
+    def open(
+        self,
+        name: str,
+        mode: str = "r",
+        encoding: Optional[str] = None,
+        errors: Optional[str] = None,
+        newline: Optional[str] = None,
+    ) -> IO[Any]:
+        """Open the named object for reading or writing, like the built-in
+        `open`.
+
+        `mode` must be one of "r", "rb", "w", or "wb".  Opening an object for
+        writing replaces any existing object of the same name once the file is
+        closed.  Text modes default to UTF-8.
+        """
+        if mode not in ("r", "rb", "w", "wb"):
+            raise ValueError(f"invalid mode: {mode!r}")
+
+        buffered: Any
+        if mode.startswith("r"):
+            buffered = io.BufferedReader(
+                _Reader(self._handle(), name), buffer_size=READ_SIZE
+            )
+        else:
+            buffered = io.BufferedWriter(
+                _Writer(self._handle(), name), buffer_size=WRITE_SIZE
+            )
+
+        if mode.endswith("b"):
+            return buffered
+        else:
+            return io.TextIOWrapper(
+                buffered, encoding=encoding or "utf-8", errors=errors, newline=newline
+            )
+
+    def read_bytes(self, name: str) -> bytes:
+        """Return the entire contents of the named object."""
+        with self.open(name, "rb") as f:
+            return f.read()
+
+    def write_bytes(self, name: str, data: bytes) -> None:
+        """Replace the contents of the named object with `data`."""
+        with self.open(name, "wb") as f:
+            f.write(data)
+
+    def size(self, name: str) -> int:
+        """Return the size of the named object in bytes."""
+        return _call(f"error reading {name}", self._handle().object_info, name).size
+
+    def delete(self, name: str) -> None:
+        """Delete the named object."""
+        _call(f"error deleting {name}", self._handle().delete_object, name)
+
+    def clear(self) -> None:
+        """Delete all objects in the container."""
+        _call("error clearing container", self._handle().clear)
+
+    def __contains__(self, name: object) -> bool:
+        if not isinstance(name, str):
+            return False
+        return _call(f"error reading {name}", self._handle().has_object, name)
+
+    def __iter__(self) -> Iterator[str]:
+        """Yield the name of each object in the container."""
+        with _call("error listing objects", self._handle().list_objects) as names:
+            while True:
+                batch, done = _call(
+                    "error listing objects", names.read_stream_object_names, LIST_SIZE
+                )
+                yield from batch
+                if done:
+                    return
+
+    def close(self) -> None:
+        """Release the underlying container resource."""
+        if self._container is not None:
+            self._container.__exit__(None, None, None)
+            self._container = None
+
+    def _handle(self) -> _container.Container:
+        if self._container is None:
+            raise ValueError("operation on closed container")
+        return self._container
+
+    def __enter__(self) -> "Container":
+        return self
+
+    def __exit__(self, *args) -> None:
+        self.close()
+
+    def __repr__(self) -> str:
+        return f"<Container {self.name!r}>"
+
+
+def container(name: str, create: bool = False) -> Container:
+    """Open the named container.
+
+    If `create` is true, the container is created first if it doesn't already
+    exist.
+    """
+    if create and not _call(
+        f"error opening container {name}", blobstore.container_exists, name
+    ):
+        handle = _call(
+            f"error creating container {name}", blobstore.create_container, name
+        )
+    else:
+        handle = _call(f"error opening container {name}", blobstore.get_container, name)
+    return Container(handle)
+
+
+def delete_container(name: str) -> None:
+    """Delete the named container and all the objects in it."""
+    _call(f"error deleting container {name}", blobstore.delete_container, name)
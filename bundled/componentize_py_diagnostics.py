@@ -0,0 +1,105 @@
+"""Implements the `componentize-py:diagnostics/counters` and
+`componentize-py:diagnostics/memory` interfaces.
+
+When a component is built with `--diagnostics`, `componentize-py` adds exports
+of those interfaces to the world and routes calls to them here rather than to
+the app.  The names of the counted functions and types are provided at build
+time via the `COMPONENTIZE_PY_DIAGNOSTICS` environment variable and captured
+when this module is imported during pre-initialization.
+"""
+
+import gc
+import os
+import sys
+
+import componentize_py_runtime
+from dataclasses import dataclass
+from typing import Dict, List, Tuple
+
+
+@dataclass
+class FunctionCounters:
+    name: str
+    calls: int
+    errors: int
+
+
+@dataclass
+class Snapshot:
+    exports: List[FunctionCounters]
+    imports: List[FunctionCounters]
+
+
+@dataclass
+class Generation:
+    collections: int
+    collected: int
+    uncollectable: int
+    pending: int
+
+
+@dataclass
+class TypeCount:
+    name: str
+    count: int
+
+
+@dataclass
+class Statistics:
+    linear_memory_bytes: int
+    allocated_blocks: int
+    generations: List[Generation]
+    objects: List[TypeCount]
+
+
+def _parse_manifest(manifest: str) -> Dict[str, List[Tuple[int, str]]]:
+    functions: Dict[str, List[Tuple[int, str]]] = {
+        "export": [],
+        "import": [],
+        "type": [],
+    }
+    for line in manifest.splitlines():
+        direction, index, name = line.split("\t", 2)
+        functions[direction].append((int(index), name))
+    return functions
+
+
+_FUNCTIONS = _parse_manifest(os.environ.get("COMPONENTIZE_PY_DIAGNOSTICS", ""))
+
+
+def _snapshot(
+    functions: List[Tuple[int, str]], counters: List[Tuple[int, int]]
+) -> List[FunctionCounters]:
+    result = []
+    for index, name in functions:
+        calls, errors = counters[index] if index < len(counters) else (0, 0)
+        result.append(FunctionCounters(name, calls, errors))
+    return result
+
+
+class Counters:
+    def get(self) -> Snapshot:
+        exports, imports = componentize_py_runtime.counters()
+        return Snapshot(
+            _snapshot(_FUNCTIONS["export"], exports),
+            _snapshot(_FUNCTIONS["import"], imports),
+        )
+
+
+class Memory:
+    def get(self) -> Statistics:
+        linear_memory_bytes, counts = componentize_py_runtime.memory_statistics()
+        generations = [
+            Generation(
+                stats["collections"], stats["collected"], stats["uncollectable"], pending
+            )
+            for stats, pending in zip(gc.get_stats(), gc.get_count())
+        ]
+        objects = [
+            TypeCount(name, counts[index])
+            for index, name in _FUNCTIONS["type"]
+            if index < len(counts) and counts[index] > 0
+        ]
+        return Statistics(
+            linear_memory_bytes, sys.getallocatedblocks(), generations, objects
+        )
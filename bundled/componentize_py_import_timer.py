@@ -0,0 +1,108 @@
+"""Measure how long each module takes to import during pre-initialization
+
+When an app is built with `--import-time-budget`, the runtime installs this
+hook before importing the app.  Each module's "self" time (i.e. excluding any
+modules it imports in turn) is recorded, and the build fails as soon as one
+exceeds the budget, reporting a table of the slowest imports similar to that
+printed by `python -X importtime`.
+"""
+
+import sys
+import time
+from importlib.abc import MetaPathFinder
+from typing import Any, Dict, List, Optional, Tuple
+
+# Maximum number of modules to list when reporting a budget overrun
+TABLE_SIZE: int = 20
+
+
+class ImportBudgetExceeded(Exception):
+    """Raised when a module takes longer to import than the budget allows.
+
+    This deliberately does not derive from `ImportError` so that apps which
+    import optional dependencies inside `try`/`except ImportError` blocks
+    can't swallow it.
+    """
+
+
+class _Timer(MetaPathFinder):
+    def __init__(self, budget: float):
+        self.budget = budget
+        # Self and cumulative time, in seconds, for each module imported so far
+        self.times: Dict[str, Tuple[float, float]] = {}
+        # `[name, start, time spent in nested imports]` for each import in
+        # progress, innermost last
+        self.stack: List[List[Any]] = []
+
+    def find_spec(self, name: str, path: Any, target: Any = None) -> Any:
+        for finder in sys.meta_path:
+            if finder is self or not hasattr(finder, "find_spec"):
+                continue
+            spec = finder.find_spec(name, path, target)
+            if spec is not None:
+                # Built-in and frozen modules use their (shared) loader class
+                # directly, and are fast enough not to be worth timing anyway.
+                if spec.loader is not None and not isinstance(spec.loader, type):
+                    self._wrap(spec.loader)
+                return spec
+        return None
+
+    def _wrap(self, loader: Any) -> None:
+        # Some loaders (e.g. `zipimporter`) are shared by many modules, so only
+        # wrap each once.
+        exec_module = loader.exec_module
+        if getattr(exec_module, "_componentize_py_timed", False):
+            return
+
+        def timed_exec_module(module: Any) -> None:
+            name = module.__name__
+            self.stack.append([name, time.perf_counter(), 0.0])
+            try:
+                exec_module(module)
+            finally:
+                _, start, nested = self.stack.pop()
+                elapsed = time.perf_counter() - start
+                if self.stack:
+                    self.stack[-1][2] += elapsed
+                self.times[name] = (elapsed - nested, elapsed)
+
+            if elapsed - nested > self.budget:
+                raise ImportBudgetExceeded(self._report(name))
+
+        timed_exec_module._componentize_py_timed = True  # type: ignore
+        try:
+            loader.exec_module = timed_exec_module
+        except AttributeError:
+            pass
+
+    def _report(self, name: str) -> str:
+        self_time, _ = self.times[name]
+        lines = [
+            f"importing `{name}` took {self_time:.3f}s, exceeding the import time"
+            f" budget of {self.budget:.3f}s",
+            "",
+            "    self [s] | cumulative [s] | module",
+        ]
+        slowest = sorted(self.times.items(), key=lambda item: item[1][1], reverse=True)
+        for module, (self_time, cumulative) in slowest[:TABLE_SIZE]:
+            lines.append(f"{self_time:12.3f} | {cumulative:14.3f} | {module}")
+        return "\n".join(lines)
+
+
+_timer: Optional[_Timer] = None
+
+
+def install(budget: float) -> None:
+    """Start timing imports, failing any which takes longer than `budget`
+    seconds."""
+    global _timer
+    _timer = _Timer(budget)
+    sys.meta_path.insert(0, _timer)
+
+
+def uninstall() -> None:
+    """Stop timing imports."""
+    global _timer
+    if _timer is not None:
+        sys.meta_path.remove(_timer)
+        _timer = None
@@ -0,0 +1,35 @@
+"""Implements the `componentize-py:lifecycle/shutdown` interface.
+
+When a component is built with `--shutdown-hook`, `componentize-py` adds an
+export of that interface to the world and routes calls to it here rather than
+to the app.  The app registers callbacks using the `on_shutdown` function
+generated in its world module, which delegates to `on_shutdown` below.
+"""
+
+import sys
+import traceback
+
+from typing import Callable, List
+
+_CALLBACKS: List[Callable[[], None]] = []
+
+
+def on_shutdown(callback: Callable[[], None]) -> None:
+    _CALLBACKS.append(callback)
+
+
+class Shutdown:
+    def shutdown(self) -> None:
+        # As with `atexit`, run the most recently registered callbacks first,
+        # and don't let one failing callback prevent the others from running.
+        while _CALLBACKS:
+            callback = _CALLBACKS.pop()
+            try:
+                callback()
+            except Exception:
+                traceback.print_exc()
+
+        # The host is about to drop the instance, so make sure nothing the app
+        # printed is left sitting in a buffer.
+        sys.stdout.flush()
+        sys.stderr.flush()
@@ -0,0 +1,87 @@
+"""Make installed distributions' metadata available at runtime
+
+Packages which discover plugins using `importlib.metadata.entry_points` (e.g.
+`pluggy` and `click` plugins), or which look up their own version using
+`importlib.metadata.version`, read `*.dist-info` directories on `sys.path`.
+Those directories are only available during pre-initialization, though, so if
+the app (or one of its dependencies) has imported `importlib.metadata` by the
+time it has finished importing, the runtime calls `snapshot`, which copies the
+metadata of each distribution into this module and registers a finder which
+serves it from there.
+"""
+
+import re
+import sys
+from importlib import metadata
+from pathlib import PurePosixPath
+from typing import Any, Dict, Iterator, List, Optional, Tuple
+
+# Metadata files to copy for each distribution.  For `METADATA` and `PKG-INFO`
+# we keep only the headers, dropping the (often long) description.
+FILES: Tuple[str, ...] = ("METADATA", "PKG-INFO", "entry_points.txt", "top_level.txt")
+
+# The snapshot taken by `snapshot`: for each distribution, its normalized name,
+# the path of its metadata directory at build time, and the contents of each of
+# `FILES` it has.
+DISTRIBUTIONS: List[Tuple[str, str, Dict[str, str]]] = []
+
+
+def _normalize(name: str) -> str:
+    return re.sub(r"[-_.]+", "_", name).lower()
+
+
+class _Distribution(metadata.Distribution):
+    def __init__(self, location: str, files: Dict[str, str]):
+        self._location = location
+        self._files = files
+
+    def read_text(self, filename: str) -> Optional[str]:
+        return self._files.get(filename)
+
+    def locate_file(self, path: Any) -> PurePosixPath:
+        return PurePosixPath(self._location).parent / path
+
+
+class _Finder(metadata.DistributionFinder):
+    def find_spec(self, *args: Any) -> None:
+        return None
+
+    def find_distributions(
+        self, context: Any = metadata.DistributionFinder.Context()
+    ) -> Iterator[metadata.Distribution]:
+        wanted = None if context.name is None else _normalize(context.name)
+        for name, location, files in DISTRIBUTIONS:
+            if wanted is None or name == wanted:
+                yield _Distribution(location, files)
+
+
+def snapshot() -> None:
+    """Copy the metadata of each distribution visible to `importlib.metadata`
+    into this module, and arrange for `importlib.metadata` to find it there
+    from now on."""
+    seen = set()
+    for distribution in metadata.distributions():
+        name = distribution.metadata["Name"]
+        if name is None or _normalize(name) in seen:
+            continue
+        seen.add(_normalize(name))
+
+        files = {}
+        for filename in FILES:
+            text = distribution.read_text(filename)
+            if text is not None:
+                if filename in ("METADATA", "PKG-INFO"):
+                    text = text.split("\n\n", 1)[0].rstrip("\n") + "\n"
+                files[filename] = text
+
+        location = str(getattr(distribution, "_path", ""))
+        DISTRIBUTIONS.append((_normalize(name), location, files))
+
+    # Sort so that the snapshot doesn't depend on the order in which the
+    # filesystem lists directories.
+    DISTRIBUTIONS.sort(key=lambda distribution: distribution[0])
+
+    # Go first so that our copies take precedence over any distributions the
+    # path-based finder still finds (e.g. if the same directories happen to be
+    # available at runtime).
+    sys.meta_path.insert(0, _Finder())
@@ -0,0 +1,64 @@
+"""Run an app's pytest suite inside a component built by `componentize-py test`
+
+`componentize-py test` builds the app's world with `componentize-py:pytest/runner`
+added to its exports and `componentize-py:pytest/reporter` added to its
+imports.  The generated module it uses as the app calls `collect` while being
+imported, so that pytest, the tests, and everything they import are loaded
+during pre-initialization, and the host then calls `Runner.run`, which runs the
+collected tests and reports the outcome of each as it finishes.
+"""
+
+import os
+from typing import List, Optional
+
+import pytest
+
+from componentize_py_pytest_bindings import reporter
+
+# The arguments passed to pytest both when collecting and when running tests.
+# Output capturing uses `sys` rather than file descriptors, which WASI can't
+# duplicate, and the cache is disabled since it would only be written to the
+# host's copy of the project.
+ARGS: List[str] = ["--capture=sys", "-p", "no:cacheprovider", "-p", "no:faulthandler"]
+
+
+class _Reporter:
+    """A pytest plugin which reports test outcomes to the host."""
+
+    def pytest_runtest_logreport(self, report: pytest.TestReport) -> None:
+        # Report the call phase of each test which gets that far, plus any
+        # setup or teardown phase which fails or is skipped.
+        if report.when != "call" and report.passed:
+            return
+
+        message: Optional[str] = None
+        if report.passed:
+            outcome = reporter.Outcome.PASSED
+        elif report.skipped:
+            outcome = reporter.Outcome.SKIPPED
+            if isinstance(report.longrepr, tuple):
+                message = report.longrepr[2]
+        else:
+            outcome = reporter.Outcome.FAILED
+            message = report.longreprtext
+
+        reporter.report(
+            reporter.Report(report.nodeid, outcome, report.duration, message)
+        )
+
+
+def collect(directory: str, args: List[str]) -> None:
+    """Change to `directory` (the guest path of the directory `componentize-py
+    test` was run from) and collect the tests selected by `args`, failing the
+    build if pytest can't."""
+    os.chdir(directory)
+    ARGS.extend(args)
+
+    code = pytest.main(["--collect-only", "-qq", *ARGS])
+    if code not in (pytest.ExitCode.OK, pytest.ExitCode.NO_TESTS_COLLECTED):
+        raise RuntimeError(f"pytest failed to collect tests (exit code {int(code)})")
+
+
+class Runner:
+    def run(self) -> int:
+        return int(pytest.main(ARGS, plugins=[_Reporter()]))
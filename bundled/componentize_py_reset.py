@@ -0,0 +1,84 @@
+"""Reset state which stdlib modules cached during pre-initialization
+
+Modules imported while the component is built may cache values derived from
+the build environment, e.g. the locale, the time zone, or the directory used
+for temporary files, and those values are captured in the snapshot along with
+everything else.  The runtime calls `reset` the first time the host calls an
+export, just after it has populated `os.environ` and `sys.argv` and reseeded
+`random`, which runs each callback in `RESETS` in order.
+
+Only modules which were imported during pre-initialization are reset, since
+nothing else can have cached anything.  Apps and libraries may add callbacks
+of their own using `register`, or remove a built-in one by deleting it from
+`RESETS`, at the top level of a module.
+"""
+
+import sys
+import traceback
+
+from typing import Callable, Dict
+
+
+def _reset_locale() -> None:
+    # Python sets `LC_CTYPE` from the environment at startup, which during
+    # pre-initialization was the build environment.
+    locale = sys.modules.get("locale")
+    if locale is not None:
+        try:
+            locale.setlocale(locale.LC_CTYPE, "")
+        except locale.Error:
+            # The locale named by the environment isn't supported by the
+            # runtime, so keep the one we have.
+            pass
+
+
+def _reset_time() -> None:
+    # `time.timezone`, `time.tzname`, etc. are computed from `TZ` when the
+    # module is initialized.
+    time = sys.modules.get("time")
+    tzset = getattr(time, "tzset", None)
+    if tzset is not None:
+        tzset()
+
+
+def _reset_tempfile() -> None:
+    # `tempfile` caches the directory from `TMPDIR` et al. on first use, and
+    # each instance would otherwise generate the same sequence of names.
+    tempfile = sys.modules.get("tempfile")
+    if tempfile is not None:
+        tempfile.tempdir = None
+        tempfile._name_sequence = None
+
+
+def _reset_platform() -> None:
+    platform = sys.modules.get("platform")
+    if platform is not None:
+        platform._uname_cache = None
+        for name in ("_platform_cache", "_sys_version_cache"):
+            cache = getattr(platform, name, None)
+            if cache is not None:
+                cache.clear()
+
+
+RESETS: Dict[str, Callable[[], None]] = {
+    "locale": _reset_locale,
+    "time": _reset_time,
+    "tempfile": _reset_tempfile,
+    "platform": _reset_platform,
+}
+
+
+def register(name: str, callback: Callable[[], None]) -> None:
+    """Call `callback` the first time the host calls an export, replacing any
+    callback previously registered as `name`."""
+    RESETS[name] = callback
+
+
+def reset() -> None:
+    # As with shutdown callbacks, don't let one failing callback prevent the
+    # others from running.
+    for callback in list(RESETS.values()):
+        try:
+            callback()
+        except Exception:
+            traceback.print_exc()
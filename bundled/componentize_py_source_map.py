@@ -0,0 +1,46 @@
+"""Rewrite guest file paths in tracebacks to the host paths they were loaded from
+
+When an app is built with `--map-source-paths`, `componentize-py` passes a
+mapping from each guest directory (e.g. `/0`) to the corresponding host
+directory at build time.  This module uses that mapping so that tracebacks
+reference files on the developer's filesystem rather than in the component.
+Only the displayed path is mapped; source lines are still read from the guest
+path, which is where the files actually are during pre-initialization.
+"""
+
+import sys
+import traceback
+
+
+def install(source_map: str) -> None:
+    """Install the specified mapping, given as one tab-separated `<guest>` and `<host>` pair per line."""
+    mappings = [
+        tuple(line.split("\t", 1)) for line in source_map.splitlines() if "\t" in line
+    ]
+
+    def map_path(filename: str) -> str:
+        for guest, host in mappings:
+            if filename == guest or filename.startswith(guest + "/"):
+                return host + filename[len(guest) :]
+        return filename
+
+    original_init = traceback.FrameSummary.__init__
+
+    def init(self, filename, *args, **kwargs):
+        original_init(self, filename, *args, **kwargs)
+        mapped = map_path(filename)
+        if mapped != filename:
+            # The host path doesn't exist in the guest, so look up the source
+            # line (which `FrameSummary` otherwise does lazily, by filename)
+            # using the guest path before replacing it with the host one.
+            self.line
+            self.filename = mapped
+
+    traceback.FrameSummary.__init__ = init  # type: ignore
+
+    # The interpreter's built-in exception printer does not use the `traceback` module, so we replace it with one
+    # that does.
+    def excepthook(ty, value, tb):
+        traceback.print_exception(ty, value, tb)
+
+    sys.excepthook = excepthook
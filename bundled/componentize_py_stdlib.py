@@ -0,0 +1,105 @@
+"""Make the whole standard library importable at runtime
+
+The standard library directory is only available during pre-initialization,
+so normally a module which the app first imports at runtime (e.g. inside a
+function, to keep startup cheap) raises `ModuleNotFoundError`.  When the app is
+built with `--embed-stdlib`, the runtime calls `snapshot` once the app has
+finished importing, which copies the source of every standard library module
+not already imported into this module and registers a finder which compiles
+and serves them from there on demand.
+"""
+
+import importlib.abc
+import importlib.machinery
+import importlib.util
+import os
+import sys
+from types import CodeType
+from typing import Any, Dict, Optional, Sequence, Tuple
+
+# Directories under the standard library which are never worth embedding:
+# bytecode caches, third-party packages (those the app uses have already been
+# imported), and CPython's own test suite.
+SKIP: Tuple[str, ...] = ("__pycache__", "site-packages", "test")
+
+# The snapshot taken by `snapshot`: for each module, whether it is a package,
+# the path of its source file at build time, and the source itself.
+MODULES: Dict[str, Tuple[bool, str, bytes]] = {}
+
+
+class _Loader(importlib.abc.InspectLoader):
+    def is_package(self, fullname: str) -> bool:
+        return MODULES[fullname][0]
+
+    def get_source(self, fullname: str) -> str:
+        return importlib.util.decode_source(MODULES[fullname][2])
+
+    def get_code(self, fullname: str) -> CodeType:
+        _, path, source = MODULES[fullname]
+        return compile(source, path, "exec", dont_inherit=True)
+
+
+_LOADER = _Loader()
+
+
+class _Finder(importlib.abc.MetaPathFinder):
+    def find_spec(
+        self,
+        fullname: str,
+        path: Optional[Sequence[str]] = None,
+        target: Any = None,
+    ) -> Optional[importlib.machinery.ModuleSpec]:
+        module = MODULES.get(fullname)
+        if module is None:
+            return None
+
+        is_package, origin, _ = module
+        spec = importlib.util.spec_from_loader(
+            fullname, _LOADER, origin=origin, is_package=is_package
+        )
+        if spec is not None:
+            # Set `__file__`, since some modules use it to name themselves in
+            # messages.
+            spec.has_location = True
+        return spec
+
+
+def snapshot() -> None:
+    """Copy the source of each standard library module which hasn't been
+    imported yet into this module, and arrange for the import system to find
+    it there from now on."""
+    root = os.path.dirname(os.__file__)
+    for directory, subdirectories, filenames in os.walk(root):
+        # Sort so that the snapshot doesn't depend on the order in which the
+        # filesystem lists directories.
+        subdirectories[:] = sorted(
+            name
+            for name in subdirectories
+            if name not in SKIP
+            and name.isidentifier()
+            and os.path.isfile(os.path.join(directory, name, "__init__.py"))
+        )
+
+        relative = os.path.relpath(directory, root)
+        package = "" if relative == "." else relative.replace(os.sep, ".")
+
+        for filename in sorted(filenames):
+            stem, extension = os.path.splitext(filename)
+            if extension != ".py" or not stem.isidentifier():
+                continue
+
+            if stem == "__init__":
+                name, is_package = package, True
+            else:
+                name, is_package = f"{package}.{stem}" if package else stem, False
+
+            if not name or name in sys.modules:
+                continue
+
+            path = os.path.join(directory, filename)
+            with open(path, "rb") as file:
+                MODULES[name] = (is_package, path, file.read())
+
+    # Go last so that modules the path-based finder can still find (e.g. those
+    # in directories mounted at runtime) take precedence.
+    sys.meta_path.append(_Finder())
@@ -0,0 +1,353 @@
+"""A small, `requests`-like HTTP client backed by `wasi:http/outgoing-handler`.
+
+This is available to any app targeting a world which imports
+`wasi:http/outgoing-handler`, and hides the low-level resource juggling
+required to send a request and read the response:
+
+    import http_client
+
+    response = http_client.get("https://example.com/data.json")
+    response.raise_for_status()
+    print(response.json())
+
+Request bodies may be `bytes`, `str`, or any iterable of `bytes` chunks (which
+are written as they are produced), and response bodies may be consumed
+incrementally using `Response.iter_content`, so neither needs to be buffered in
+memory all at once.
+
+All calls block the calling task until they complete.  Use `poll_loop` if you
+need to make several requests concurrently.
+"""
+
+import json as _json
+
+from http_client_bindings import Ok, Err, types, outgoing_handler, streams
+from typing import Any, Dict, Iterable, Iterator, List, Optional, Tuple, Union
+from urllib import parse
+
+# Maximum number of bytes to read at a time
+READ_SIZE: int = 16 * 1024
+
+# Maximum number of bytes `output-stream.blocking-write-and-flush` accepts at a
+# time
+WRITE_SIZE: int = 4096
+
+Body = Union[None, bytes, str, Iterable[bytes]]
+
+
+class HTTPError(Exception):
+    """Raised when a request fails or, via `Response.raise_for_status`, when
+    the server responds with an error status.
+
+    For transport-level failures, `error_code` holds the `wasi:http/types`
+    `error-code` reported by the host.
+    """
+
+    def __init__(
+        self,
+        message: str,
+        error_code: Optional[Any] = None,
+        response: Optional["Response"] = None,
+    ):
+        super().__init__(message)
+        self.error_code = error_code
+        self.response = response
+
+
+class Headers:
+    """Case-insensitive, multi-valued view of HTTP headers."""
+
+    def __init__(self, entries: List[Tuple[str, bytes]]):
+        self.entries = entries
+
+    def get(self, name: str, default: Optional[str] = None) -> Optional[str]:
+        """Return the first value of the named header, or `default`."""
+        values = self.get_all(name)
+        return values[0] if values else default
+
+    def get_all(self, name: str) -> List[str]:
+        """Return all values of the named header."""
+        name = name.lower()
+        return [
+            str(value, "latin-1") for key, value in self.entries if key.lower() == name
+        ]
+
+    def __getitem__(self, name: str) -> str:
+        value = self.get(name)
+        if value is None:
+            raise KeyError(name)
+        return value
+
+    def __contains__(self, name: object) -> bool:
+        return isinstance(name, str) and bool(self.get_all(name))
+
+    def __iter__(self) -> Iterator[str]:
+        return iter(dict.fromkeys(key.lower() for key, _ in self.entries))
+
+    def __repr__(self) -> str:
+        return f"Headers({self.entries!r})"
+
+
+class Response:
+    """The response to an HTTP request.
+
+    Unless the request was made with `stream=True`, the body has already been
+    read in full and is available via `content`, `text`, and `json()`.
+    Otherwise, it may be read incrementally using `iter_content`.
+    """
+
+    def __init__(self, url: str, response: types.IncomingResponse, stream: bool):
+        self.url = url
+        self.status_code: int = response.status()
+        with response.headers() as headers:
+            self.headers = Headers(headers.entries())
+        self._response: Optional[types.IncomingResponse] = response
+        self._body: Optional[types.IncomingBody] = response.consume()
+        self._stream: Optional[streams.InputStream] = self._body.stream()
+        self._content: Optional[bytes] = None
+        if not stream:
+            self._content = b"".join(self.iter_content())
+
+    @property
+    def ok(self) -> bool:
+        """Whether the status code indicates success (i.e. is less than 400)."""
+        return self.status_code < 400
+
+    def iter_content(self, chunk_size: int = READ_SIZE) -> Iterator[bytes]:
+        """Yield the body in chunks of up to `chunk_size` bytes as they
+        arrive."""
+        if self._content is not None:
+            for offset in range(0, len(self._content), chunk_size):
+                yield self._content[offset : offset + chunk_size]
+            return
+
+        while self._stream is not None:
+            try:
+                chunk = self._stream.blocking_read(chunk_size)
+            except Err as e:
+                if isinstance(e.value, streams.StreamError_Closed):
+                    self.close()
+                    return
+                else:
+                    self.close()
+                    raise HTTPError(f"error reading response body: {e.value}")
+            if chunk:
+                yield chunk
+
+    @property
+    def content(self) -> bytes:
+        """The entire body, reading the remainder of it if necessary."""
+        if self._content is None:
+            self._content = b"".join(self.iter_content())
+        return self._content
+
+    @property
+    def text(self) -> str:
+        """The entire body, decoded according to the `content-type` charset
+        (defaulting to UTF-8)."""
+        charset = "utf-8"
+        for param in (self.headers.get("content-type") or "").split(";")[1:]:
+            key, _, value = param.strip().partition("=")
+            if key.lower() == "charset" and value:
+                charset = value.strip('"')
+        return str(self.content, charset, errors="replace")
+
+    def json(self) -> Any:
+        """Parse the entire body as JSON."""
+        return _json.loads(self.content)
+
+    def raise_for_status(self) -> None:
+        """Raise an `HTTPError` if the status code indicates failure."""
+        if not self.ok:
+            raise HTTPError(f"{self.status_code} error for {self.url}", response=self)
+
+    def close(self) -> None:
+        """Release the underlying resources, discarding any unread body."""
+        if self._stream is not None:
+            self._stream.__exit__(None, None, None)
+            self._stream = None
+        if self._body is not None:
+            types.IncomingBody.finish(self._body).__exit__(None, None, None)
+            self._body = None
+        if self._response is not None:
+            self._response.__exit__(None, None, None)
+            self._response = None
+
+    def __enter__(self) -> "Response":
+        return self
+
+    def __exit__(self, *args) -> None:
+        self.close()
+
+    def __repr__(self) -> str:
+        return f"<Response [{self.status_code}]>"
+
+
+def request(
+    method: str,
+    url: str,
+    *,
+    params: Optional[Dict[str, str]] = None,
+    headers: Optional[Dict[str, str]] = None,
+    data: Body = None,
+    json: Any = None,
+    timeout: Optional[float] = None,
+    stream: bool = False,
+) -> Response:
+    """Send an HTTP request and return the response.
+
+    `timeout`, if specified, is in seconds and applies to connecting, to
+    receiving the first byte of the response, and to each gap between bytes of
+    the response.  Hosts may not support all of these, in which case they are
+    ignored.
+    """
+    if json is not None:
+        data = _json.dumps(json).encode("utf-8")
+        headers = {"content-type": "application/json", **(headers or {})}
+
+    parsed = parse.urlsplit(url)
+    path = parsed.path or "/"
+    query = "&".join(filter(None, [parsed.query, parse.urlencode(params or {})]))
+    if query:
+        path = f"{path}?{query}"
+
+    try:
+        fields = types.Fields.from_list(
+            [(key, value.encode("latin-1")) for key, value in (headers or {}).items()]
+        )
+    except Err as e:
+        raise HTTPError(f"invalid header: {e.value}")
+
+    outgoing = types.OutgoingRequest(fields)
+    outgoing.set_method(_method(method))
+    outgoing.set_scheme(_scheme(parsed.scheme))
+    outgoing.set_authority(parsed.netloc)
+    outgoing.set_path_with_query(path)
+
+    options = None
+    if timeout is not None:
+        options = types.RequestOptions()
+        nanoseconds = int(timeout * 1_000_000_000)
+        for set_timeout in (
+            options.set_connect_timeout,
+            options.set_first_byte_timeout,
+            options.set_between_bytes_timeout,
+        ):
+            try:
+                set_timeout(nanoseconds)
+            except Err:
+                # The host doesn't support this timeout
+                pass
+
+    body = outgoing.body()
+
+    try:
+        future = outgoing_handler.handle(outgoing, options)
+    except Err as e:
+        raise HTTPError(f"request to {url} failed: {e.value}", error_code=e.value)
+
+    # Note that we write the body only after handing off the request so that
+    # large (or streamed) bodies aren't subject to buffering limits.
+    _write_body(body, data)
+
+    with future:
+        while True:
+            result = future.get()
+            if result is None:
+                with future.subscribe() as pollable:
+                    pollable.block()
+            elif isinstance(result, Ok):
+                if isinstance(result.value, Ok):
+                    return Response(url, result.value.value, stream)
+                else:
+                    raise HTTPError(
+                        f"request to {url} failed: {result.value.value}",
+                        error_code=result.value.value,
+                    )
+            else:
+                raise HTTPError(f"request to {url} failed: response already taken")
+
+
+def get(url: str, **kwargs) -> Response:
+    """Send a `GET` request.  See `request` for the available options."""
+    return request("GET", url, **kwargs)
+
+
+def head(url: str, **kwargs) -> Response:
+    """Send a `HEAD` request.  See `request` for the available options."""
+    return request("HEAD", url, **kwargs)
+
+
+def post(url: str, **kwargs) -> Response:
+    """Send a `POST` request.  See `request` for the available options."""
+    return request("POST", url, **kwargs)
+
+
+def put(url: str, **kwargs) -> Response:
+    """Send a `PUT` request.  See `request` for the available options."""
+    return request("PUT", url, **kwargs)
+
+
+def patch(url: str, **kwargs) -> Response:
+    """Send a `PATCH` request.  See `request` for the available options."""
+    return request("PATCH", url, **kwargs)
+
+
+def delete(url: str, **kwargs) -> Response:
+    """Send a `DELETE` request.  See `request` for the available options."""
+    return request("DELETE", url, **kwargs)
+
+
+def _method(method: str) -> Any:
+    match method.upper():
+        case "GET":
+            return types.Method_Get()
+        case "HEAD":
+            return types.Method_Head()
+        case "POST":
+            return types.Method_Post()
+        case "PUT":
+            return types.Method_Put()
+        case "DELETE":
+            return types.Method_Delete()
+        case "CONNECT":
+            return types.Method_Connect()
+        case "OPTIONS":
+            return types.Method_Options()
+        case "TRACE":
+            return types.Method_Trace()
+        case "PATCH":
+            return types.Method_Patch()
+        case other:
+            return types.Method_Other(other)
+
+
+def _scheme(scheme: str) -> Any:
+    match scheme.lower():
+        case "http":
+            return types.Scheme_Http()
+        case "https":
+            return types.Scheme_Https()
+        case other:
+            return types.Scheme_Other(other)
+
+
+def _write_body(body: types.OutgoingBody, data: Body) -> None:
+    if data is None:
+        chunks: Iterable[bytes] = []
+    elif isinstance(data, str):
+        chunks = [data.encode("utf-8")]
+    elif isinstance(data, (bytes, bytearray, memoryview)):
+        chunks = [bytes(data)]
+    else:
+        chunks = data
+
+    with body.write() as stream:
+        for chunk in chunks:
+            for offset in range(0, len(chunk), WRITE_SIZE):
+                try:
+                    stream.blocking_write_and_flush(chunk[offset : offset + WRITE_SIZE])
+                except Err as e:
+                    raise HTTPError(f"error writing request body: {e.value}")
+
+    types.OutgoingBody.finish(body, None)
@@ -0,0 +1,163 @@
+//! `serde_json`-backed replacements for `json.dumps` and `json.loads`, exposed as
+//! `componentize_py_runtime.json_dumps` and `componentize_py_runtime.json_loads`.
+//!
+//! These support only the subset of Python values which `to_dict` methods in generated bindings produce, i.e.
+//! `dict`s with `str` keys, `list`s, `tuple`s, `str`s, `int`s, `float`s, booleans, and `None`, and they produce
+//! compact output (i.e. without whitespace).  Generated code falls back to the `json` module when the runtime was
+//! built without the `json` feature.
+
+use {
+    pyo3::{
+        exceptions::PyValueError,
+        types::{
+            PyAnyMethods, PyBool, PyBoolMethods, PyDict, PyDictMethods, PyFloat, PyFloatMethods,
+            PyList, PyListMethods, PyLong, PyString, PyStringMethods, PyTuple, PyTupleMethods,
+        },
+        Bound, IntoPy, PyAny, PyObject, PyResult, Python,
+    },
+    serde::{
+        de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor},
+        ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer},
+    },
+    std::fmt,
+};
+
+/// Serialize `value` as JSON.
+#[pyo3::pyfunction]
+#[pyo3(name = "json_dumps")]
+pub fn dumps(value: &Bound<PyAny>) -> PyResult<String> {
+    serde_json::to_string(&Value(value)).map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+/// Deserialize a value from the JSON in `s`.
+#[pyo3::pyfunction]
+#[pyo3(name = "json_loads")]
+pub fn loads(py: Python, s: &str) -> PyResult<PyObject> {
+    let mut deserializer = serde_json::Deserializer::from_str(s);
+    Seed(py)
+        .deserialize(&mut deserializer)
+        .and_then(|value| deserializer.end().map(|()| value))
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+struct Value<'a, 'py>(&'a Bound<'py, PyAny>);
+
+impl Serialize for Value<'_, '_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let value = self.0;
+        if value.is_none() {
+            serializer.serialize_unit()
+        } else if let Ok(value) = value.downcast::<PyBool>() {
+            serializer.serialize_bool(value.is_true())
+        } else if let Ok(value) = value.downcast::<PyLong>() {
+            if let Ok(value) = value.extract::<i64>() {
+                serializer.serialize_i64(value)
+            } else if let Ok(value) = value.extract::<u64>() {
+                serializer.serialize_u64(value)
+            } else {
+                Err(ser::Error::custom(format!("integer out of range: {value}")))
+            }
+        } else if let Ok(value) = value.downcast::<PyFloat>() {
+            let value = value.value();
+            if value.is_finite() {
+                serializer.serialize_f64(value)
+            } else {
+                Err(ser::Error::custom(format!(
+                    "non-finite float not allowed: {value}"
+                )))
+            }
+        } else if let Ok(value) = value.downcast::<PyString>() {
+            serializer.serialize_str(&value.to_cow().map_err(ser::Error::custom)?)
+        } else if let Ok(value) = value.downcast::<PyList>() {
+            let mut seq = serializer.serialize_seq(Some(value.len()))?;
+            for item in value.iter() {
+                seq.serialize_element(&Value(&item))?;
+            }
+            seq.end()
+        } else if let Ok(value) = value.downcast::<PyTuple>() {
+            let mut seq = serializer.serialize_seq(Some(value.len()))?;
+            for item in value.iter() {
+                seq.serialize_element(&Value(&item))?;
+            }
+            seq.end()
+        } else if let Ok(value) = value.downcast::<PyDict>() {
+            let mut map = serializer.serialize_map(Some(value.len()))?;
+            for (key, item) in value.iter() {
+                let key = key
+                    .downcast::<PyString>()
+                    .map_err(|_| {
+                        ser::Error::custom(format!("keys must be str, not {}", key.get_type()))
+                    })?
+                    .to_cow()
+                    .map_err(ser::Error::custom)?;
+                map.serialize_entry(&key, &Value(&item))?;
+            }
+            map.end()
+        } else {
+            Err(ser::Error::custom(format!(
+                "object of type {} is not JSON serializable",
+                value.get_type()
+            )))
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Seed<'py>(Python<'py>);
+
+impl<'de> DeserializeSeed<'de> for Seed<'_> {
+    type Value = PyObject;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<PyObject, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Seed<'_> {
+    type Value = PyObject;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a JSON value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<PyObject, E> {
+        Ok(self.0.None())
+    }
+
+    fn visit_bool<E: de::Error>(self, value: bool) -> Result<PyObject, E> {
+        Ok(value.into_py(self.0))
+    }
+
+    fn visit_i64<E: de::Error>(self, value: i64) -> Result<PyObject, E> {
+        Ok(value.into_py(self.0))
+    }
+
+    fn visit_u64<E: de::Error>(self, value: u64) -> Result<PyObject, E> {
+        Ok(value.into_py(self.0))
+    }
+
+    fn visit_f64<E: de::Error>(self, value: f64) -> Result<PyObject, E> {
+        Ok(value.into_py(self.0))
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<PyObject, E> {
+        Ok(value.into_py(self.0))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<PyObject, A::Error> {
+        let list = PyList::empty_bound(self.0);
+        while let Some(item) = seq.next_element_seed(self)? {
+            list.append(item).map_err(de::Error::custom)?;
+        }
+        Ok(list.into_py(self.0))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<PyObject, A::Error> {
+        let dict = PyDict::new_bound(self.0);
+        while let Some(key) = map.next_key::<String>()? {
+            let item = map.next_value_seed(self)?;
+            dict.set_item(key, item).map_err(de::Error::custom)?;
+        }
+        Ok(dict.into_py(self.0))
+    }
+}
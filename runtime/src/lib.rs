@@ -2,7 +2,7 @@
 
 use {
     anyhow::{Error, Result},
-    componentize_py_shared::ReturnStyle,
+    componentize_py_shared::{NumberKind, ReturnStyle, INIT_VERSION},
     exports::exports::{
         self as exp, Bundled, Constructor, Function, FunctionExport, Guest, LocalResource,
         OwnedKind, OwnedType, RemoteResource, Resource, Static, Symbols,
@@ -10,25 +10,38 @@ use {
     num_bigint::BigUint,
     once_cell::sync::OnceCell,
     pyo3::{
-        exceptions::PyAssertionError,
-        intern,
+        buffer::PyBuffer,
+        exceptions::{PyAssertionError, PyAttributeError, PySystemExit, PyTypeError},
+        ffi, intern,
         types::{
-            PyAnyMethods, PyBool, PyBytes, PyBytesMethods, PyDict, PyList, PyListMethods,
-            PyMapping, PyMappingMethods, PyModule, PyModuleMethods, PyString, PyTuple,
+            PyAnyMethods, PyBool, PyBytes, PyBytesMethods, PyDict, PyDictMethods, PyList,
+            PyListMethods, PyMapping, PyMappingMethods, PyModule, PyModuleMethods, PyString,
+            PyTuple, PyTupleMethods,
         },
         AsPyPointer, Borrowed, Bound, Py, PyAny, PyErr, PyObject, PyResult, Python, ToPyObject,
     },
     std::{
         alloc::{self, Layout},
-        ffi::c_void,
+        collections::HashMap,
+        env,
+        ffi::{c_void, CStr},
         mem::{self, MaybeUninit},
         ops::DerefMut,
         ptr, slice, str,
-        sync::{Mutex, Once},
+        sync::{
+            atomic::{AtomicPtr, Ordering},
+            Mutex, Once,
+        },
     },
     wasi::cli::environment,
 };
 
+#[cfg(feature = "json")]
+mod json;
+mod strict;
+mod trace;
+mod verify;
+
 wit_bindgen::generate!({
     world: "init",
     path: "../wit",
@@ -38,16 +51,43 @@ wit_bindgen::generate!({
 export!(MyExports);
 
 static STUB_WASI: OnceCell<bool> = OnceCell::new();
+static WASI_INTERFACES: OnceCell<Option<Vec<String>>> = OnceCell::new();
+static VERSION: OnceCell<String> = OnceCell::new();
 static EXPORTS: OnceCell<Vec<Export>> = OnceCell::new();
 static TYPES: OnceCell<Vec<Type>> = OnceCell::new();
 static ENVIRON: OnceCell<Py<PyMapping>> = OnceCell::new();
 static SOME_CONSTRUCTOR: OnceCell<PyObject> = OnceCell::new();
 static OK_CONSTRUCTOR: OnceCell<PyObject> = OnceCell::new();
 static ERR_CONSTRUCTOR: OnceCell<PyObject> = OnceCell::new();
+static WIT_ERROR_CONSTRUCTOR: OnceCell<PyObject> = OnceCell::new();
 static FINALIZE: OnceCell<PyObject> = OnceCell::new();
 static DROP_RESOURCE: OnceCell<PyObject> = OnceCell::new();
 static SEED: OnceCell<PyObject> = OnceCell::new();
+static RESET: OnceCell<PyObject> = OnceCell::new();
+static IS_COROUTINE: OnceCell<PyObject> = OnceCell::new();
+static EXCEPTION_MAPPINGS: OnceCell<Vec<(PyObject, PyObject)>> = OnceCell::new();
 static ARGV: OnceCell<Py<PyList>> = OnceCell::new();
+static EXPORT_COUNTERS: Mutex<Vec<Counters>> = Mutex::new(Vec::new());
+static IMPORT_COUNTERS: Mutex<Vec<Counters>> = Mutex::new(Vec::new());
+
+/// Call and error counts for an exported or imported function, reported by `componentize_py_runtime.counters`.
+#[derive(Copy, Clone, Default)]
+struct Counters {
+    calls: u64,
+    errors: u64,
+}
+
+/// Record a call to the function at `index` in `counters`, growing it as necessary.
+fn count(counters: &Mutex<Vec<Counters>>, index: usize, error: bool) {
+    let mut counters = counters.lock().unwrap();
+    if counters.len() <= index {
+        counters.resize(index + 1, Counters::default());
+    }
+    counters[index].calls += 1;
+    if error {
+        counters[index].errors += 1;
+    }
+}
 
 struct Borrow {
     handle: i32,
@@ -59,6 +99,9 @@ static BORROWS: Mutex<Vec<Borrow>> = Mutex::new(Vec::new());
 const DISCRIMINANT_FIELD_INDEX: i32 = 0;
 const PAYLOAD_FIELD_INDEX: i32 = 1;
 
+/// Function called after importing the app during pre-initialization, if defined and not overridden at build time
+const DEFAULT_PRE_INIT_HOOK: &str = "__componentize_pre_init__";
+
 #[derive(Debug)]
 struct Case {
     constructor: PyObject,
@@ -143,6 +186,10 @@ fn call_import<'a>(
     params: Vec<Bound<'a, PyAny>>,
     result_count: usize,
 ) -> PyResult<Vec<&'a PyAny>> {
+    strict::check_import(module.py(), index, &params)?;
+
+    let call = trace::start(false, index.try_into().unwrap(), &params);
+
     let mut results = vec![MaybeUninit::<&PyAny>::uninit(); result_count];
     unsafe {
         componentize_py_call_indirect(
@@ -153,10 +200,110 @@ fn call_import<'a>(
         );
 
         // todo: is this sound, or do we need to `.into_iter().map(MaybeUninit::assume_init).collect()` instead?
-        Ok(mem::transmute::<Vec<MaybeUninit<&PyAny>>, Vec<&PyAny>>(
-            results,
-        ))
+        let results = mem::transmute::<Vec<MaybeUninit<&PyAny>>, Vec<&PyAny>>(results);
+
+        let py = module.py();
+        trace::finish(py, call);
+        count(
+            &IMPORT_COUNTERS,
+            index.try_into().unwrap(),
+            results
+                .first()
+                .is_some_and(|result| is_err(&Bound::from_borrowed_ptr(py, result.as_ptr()))),
+        );
+
+        Ok(results)
+    }
+}
+
+/// Return the call and error counts for each exported and imported function, indexed as described by the
+/// `COMPONENTIZE_PY_DIAGNOSTICS` environment variable at build time.
+///
+/// This is used by the bundled `componentize_py_diagnostics` module.
+#[pyo3::pyfunction]
+#[allow(clippy::type_complexity)]
+fn counters() -> (Vec<(u64, u64)>, Vec<(u64, u64)>) {
+    let get = |counters: &Mutex<Vec<Counters>>| {
+        counters
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|counters| (counters.calls, counters.errors))
+            .collect()
+    };
+
+    (get(&EXPORT_COUNTERS), get(&IMPORT_COUNTERS))
+}
+
+/// Return the size of linear memory in bytes, plus the number of live objects of each record, variant, and
+/// resource type (and zero for any other type), indexed as in the type table.
+///
+/// Objects are found using `gc.get_objects`, which is adequate since instances of the classes generated for those
+/// types are always tracked by the garbage collector.  This is used by the bundled `componentize_py_diagnostics`
+/// module.
+#[pyo3::pyfunction]
+fn memory_statistics(py: Python) -> PyResult<(u64, Vec<u64>)> {
+    let types = TYPES.get().unwrap();
+
+    let classes = PyDict::new_bound(py);
+    for (index, ty) in types.iter().enumerate() {
+        match ty {
+            Type::Record { constructor, .. } | Type::Resource { constructor, .. } => {
+                classes.set_item(constructor, index)?
+            }
+            Type::Variant { cases, .. } => {
+                for case in cases {
+                    classes.set_item(&case.constructor, index)?;
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let mut counts = vec![0; types.len()];
+    for object in py
+        .import_bound(intern!(py, "gc"))?
+        .call_method0(intern!(py, "get_objects"))?
+        .iter()?
+    {
+        if let Some(index) = classes.get_item(object?.get_type())? {
+            counts[index.extract::<usize>()?] += 1;
+        }
     }
+
+    let pages = u64::try_from(core::arch::wasm32::memory_size(0)).unwrap();
+
+    Ok((pages * 65536, counts))
+}
+
+/// Return the version of `componentize-py` which built this component.
+#[pyo3::pyfunction]
+fn version() -> &'static str {
+    VERSION.get().unwrap()
+}
+
+/// Return a `dict` describing how this component was built, so that libraries can adapt their behavior to (or
+/// report problems with) the runtime they find themselves in.
+///
+/// The keys are `version` (as returned by `version`), `async_abi` (whether the component-model async ABI is in
+/// use, which is never the case yet), `stub_wasi` (whether WASI imports were stubbed out), and `python` (the
+/// embedded interpreter's build string, as in `sys.version`).
+#[pyo3::pyfunction]
+fn build_info(py: Python) -> PyResult<Bound<PyDict>> {
+    let info = PyDict::new_bound(py);
+    info.set_item("version", version())?;
+    info.set_item("async_abi", false)?;
+    info.set_item("stub_wasi", STUB_WASI.get().copied().unwrap_or(false))?;
+    info.set_item("python", py.version())?;
+    Ok(info)
+}
+
+/// Return true if `value` is an instance of the `Err` class generated for the world (or a subclass such as
+/// `WitError`).
+fn is_err(value: &Bound<PyAny>) -> bool {
+    ERR_CONSTRUCTOR
+        .get()
+        .is_some_and(|err| value.is_instance(err.bind(value.py())).unwrap_or(false))
 }
 
 #[pyo3::pyfunction]
@@ -178,7 +325,19 @@ fn drop_resource(module: &Bound<PyModule>, index: u32, handle: usize) -> PyResul
 #[pyo3(name = "componentize_py_runtime")]
 fn componentize_py_module(_py: Python<'_>, module: &Bound<PyModule>) -> PyResult<()> {
     module.add_function(pyo3::wrap_pyfunction!(call_import, module)?)?;
-    module.add_function(pyo3::wrap_pyfunction!(drop_resource, module)?)
+    module.add_function(pyo3::wrap_pyfunction!(drop_resource, module)?)?;
+    module.add_function(pyo3::wrap_pyfunction!(counters, module)?)?;
+    module.add_function(pyo3::wrap_pyfunction!(memory_statistics, module)?)?;
+    module.add_function(pyo3::wrap_pyfunction!(version, module)?)?;
+    module.add_function(pyo3::wrap_pyfunction!(build_info, module)?)?;
+
+    #[cfg(feature = "json")]
+    {
+        module.add_function(pyo3::wrap_pyfunction!(json::dumps, module)?)?;
+        module.add_function(pyo3::wrap_pyfunction!(json::loads, module)?)?;
+    }
+
+    Ok(())
 }
 
 fn do_init(app_name: String, symbols: Symbols, stub_wasi: bool) -> Result<()> {
@@ -186,7 +345,39 @@ fn do_init(app_name: String, symbols: Symbols, stub_wasi: bool) -> Result<()> {
 
     pyo3::prepare_freethreaded_python();
 
+    install_panic_hook();
+
+    // Set these first so they're available to the app (e.g. via `build_info`) while it's being imported.
+    VERSION
+        .set(env::var("COMPONENTIZE_PY_VERSION").unwrap_or_else(|_| "unknown".to_owned()))
+        .unwrap();
+    STUB_WASI.set(stub_wasi).unwrap();
+    WASI_INTERFACES
+        .set(
+            env::var("COMPONENTIZE_PY_WASI_INTERFACES")
+                .ok()
+                .map(|interfaces| interfaces.split(',').map(str::to_owned).collect()),
+        )
+        .unwrap();
+
     Python::with_gil(|py| {
+        // If requested at build time, make tracebacks refer to the host paths the app was loaded from rather than
+        // to guest paths like `/0/app.py`.
+        if let Ok(source_map) = env::var("COMPONENTIZE_PY_SOURCE_MAP") {
+            py.import_bound("componentize_py_source_map")?
+                .call_method1("install", (source_map,))?;
+        }
+
+        // If requested at build time, time each module the app imports, failing if any takes too long.
+        let import_timer = env::var("COMPONENTIZE_PY_IMPORT_BUDGET")
+            .ok()
+            .map(|budget| {
+                let timer = py.import_bound("componentize_py_import_timer")?;
+                timer.call_method1("install", (budget.parse::<f64>()?,))?;
+                Ok::<_, Error>(timer)
+            })
+            .transpose()?;
+
         let app = match py.import_bound(app_name.as_str()) {
             Ok(app) => app,
             Err(e) => {
@@ -195,60 +386,79 @@ fn do_init(app_name: String, symbols: Symbols, stub_wasi: bool) -> Result<()> {
             }
         };
 
-        STUB_WASI.set(stub_wasi).unwrap();
+        if let Some(timer) = import_timer {
+            timer.call_method0("uninstall")?;
+        }
 
-        EXPORTS
+        // Give the app a chance to do expensive setup (e.g. compiling regexes or loading models) now, so the results
+        // are captured in the snapshot rather than recomputed by every instance.  A hook named at build time must
+        // exist, whereas the default one is optional.
+        let hook = match env::var("COMPONENTIZE_PY_PRE_INIT_HOOK") {
+            Ok(path) if path.contains(':') => Some(resolve_object(py, &path)),
+            Ok(name) => Some(app.getattr(name.as_str())),
+            Err(_) => app
+                .hasattr(DEFAULT_PRE_INIT_HOOK)?
+                .then(|| app.getattr(DEFAULT_PRE_INIT_HOOK)),
+        };
+
+        if let Some(hook) = hook {
+            if let Err(e) = hook.and_then(|hook| hook.call0()) {
+                e.print(py);
+                return Err(e.into());
+            }
+        }
+
+        // If the app (or one of its dependencies) uses `importlib.metadata`, e.g. to discover plugins via entry
+        // points, take a copy of the installed distributions' metadata now, since the directories it's read from
+        // won't be available at runtime.
+        if py
+            .import_bound("sys")?
+            .getattr("modules")?
+            .contains("importlib.metadata")?
+        {
+            py.import_bound("componentize_py_metadata")?
+                .call_method0("snapshot")?;
+        }
+
+        // If requested via `--embed-stdlib`, take a copy of the standard library modules the app hasn't imported
+        // yet, so it can still import them at runtime.
+        if env::var("COMPONENTIZE_PY_EMBED_STDLIB").is_ok() {
+            py.import_bound("componentize_py_stdlib")?
+                .call_method0("snapshot")?;
+        }
+
+        // Read these now, since we clear the environment below.
+        let deterministic = env::var("COMPONENTIZE_PY_DETERMINISTIC").is_ok();
+        let unbuffered = env::var("COMPONENTIZE_PY_UNBUFFERED").is_ok();
+
+        // This is populated at build time according to any exception mappings specified via CLI options or
+        // `componentize-py.toml` files, in order of precedence.
+        EXCEPTION_MAPPINGS
             .set(
-                symbols
-                    .exports
-                    .iter()
-                    .map(|export| {
-                        Ok(match export {
-                            FunctionExport::Bundled(Bundled {
-                                module,
-                                protocol,
-                                name,
-                            }) => Export::Freestanding {
-                                name: PyString::intern_bound(py, name).into(),
-                                instance: py
-                                    .import_bound(module.as_str())?
-                                    .getattr(protocol.as_str())?
-                                    .call0()?
-                                    .into(),
-                            },
-                            FunctionExport::Freestanding(Function { protocol, name }) => {
-                                Export::Freestanding {
-                                    name: PyString::intern_bound(py, name).into(),
-                                    instance: app.getattr(protocol.as_str())?.call0()?.into(),
-                                }
-                            }
-                            FunctionExport::Constructor(Constructor { module, protocol }) => {
-                                Export::Constructor(
-                                    py.import_bound(module.as_str())?
-                                        .getattr(protocol.as_str())?
-                                        .into(),
-                                )
-                            }
-                            FunctionExport::Method(name) => {
-                                Export::Method(PyString::intern_bound(py, name).into())
-                            }
-                            FunctionExport::Static(Static {
-                                module,
-                                protocol,
-                                name,
-                            }) => Export::Static {
-                                name: PyString::intern_bound(py, name).into(),
-                                class: py
-                                    .import_bound(module.as_str())?
-                                    .getattr(protocol.as_str())?
-                                    .into(),
-                            },
-                        })
+                env::var("COMPONENTIZE_PY_EXCEPTION_MAPPINGS")
+                    .unwrap_or_default()
+                    .lines()
+                    .map(|line| {
+                        let (exception, function) = line.split_once('\t').unwrap();
+                        Ok((
+                            resolve_object(py, exception)?.into(),
+                            resolve_object(py, function)?.into(),
+                        ))
                     })
                     .collect::<PyResult<_>>()?,
             )
             .unwrap();
 
+        // A world with no exports (e.g. an import-only library which does its work at import time) has nothing to
+        // resolve or verify, so don't bother importing the types package or reading export classes for it.
+        EXPORTS
+            .set(if symbols.exports.is_empty() {
+                Vec::new()
+            } else {
+                exports(py, &app, &symbols)?
+            })
+            .unwrap();
+
         TYPES
             .set(
                 symbols
@@ -333,6 +543,27 @@ fn do_init(app_name: String, symbols: Symbols, stub_wasi: bool) -> Result<()> {
         SOME_CONSTRUCTOR.set(types.getattr("Some")?.into()).unwrap();
         OK_CONSTRUCTOR.set(types.getattr("Ok")?.into()).unwrap();
         ERR_CONSTRUCTOR.set(types.getattr("Err")?.into()).unwrap();
+        // Bindings generated by older versions of `componentize-py` won't have `WitError`, in which case we
+        // fall back to `Err` for `string` error payloads, too.
+        WIT_ERROR_CONSTRUCTOR
+            .set(
+                types
+                    .getattr("WitError")
+                    .or_else(|_| types.getattr("Err"))?
+                    .into(),
+            )
+            .unwrap();
+
+        // If requested at build time, check the types of values passed to the host before lowering them.  This
+        // must be done after `TYPES` and the above are populated, since the checks depend on them.
+        if let Ok(manifest) = env::var("COMPONENTIZE_PY_STRICT") {
+            strict::init(&manifest);
+        }
+
+        // Likewise, log calls across the component boundary if requested.
+        if let Ok(manifest) = env::var("COMPONENTIZE_PY_TRACE") {
+            trace::init(&manifest);
+        }
 
         let environ = py
             .import_bound("os")?
@@ -363,6 +594,18 @@ fn do_init(app_name: String, symbols: Symbols, stub_wasi: bool) -> Result<()> {
         SEED.set(py.import_bound("random")?.getattr("seed")?.into())
             .unwrap();
 
+        RESET
+            .set(
+                py.import_bound("componentize_py_reset")?
+                    .getattr("reset")?
+                    .into(),
+            )
+            .unwrap();
+
+        IS_COROUTINE
+            .set(py.import_bound("inspect")?.getattr("iscoroutine")?.into())
+            .unwrap();
+
         let argv = py
             .import_bound("sys")?
             .getattr("argv")?
@@ -375,6 +618,35 @@ fn do_init(app_name: String, symbols: Symbols, stub_wasi: bool) -> Result<()> {
 
         ARGV.set(argv.into()).unwrap();
 
+        // Pre-initialization runs with `PYTHONUNBUFFERED` set, but that's not necessarily what the app wants at
+        // runtime, so set the policy explicitly: line-buffered by default, so that each complete line reaches the
+        // host promptly without a separate write for every `print` fragment, or unbuffered if requested at build
+        // time.  Either way, any partial line is flushed if the component traps; see `install_panic_hook`.
+        let sys = py.import_bound("sys")?;
+        for stream in ["stdout", "stderr"] {
+            // The app may have replaced the stream with something other than a `TextIOWrapper`, in which case we
+            // leave it alone.
+            let stream = sys.getattr(stream)?;
+            if stream.hasattr(intern!(py, "reconfigure"))? {
+                let options = PyDict::new_bound(py);
+                options.set_item("line_buffering", true)?;
+                options.set_item("write_through", unbuffered)?;
+                stream.call_method("reconfigure", (), Some(&options))?;
+            }
+        }
+
+        // Calls made during pre-initialization aren't of interest at runtime, so don't let them be baked into the
+        // snapshot.
+        IMPORT_COUNTERS.lock().unwrap().clear();
+
+        // For `--deterministic` builds, forget any filesystem metadata (e.g. the directory modification times
+        // cached by `importlib`'s path finders) so it doesn't vary from one build to the next.
+        if deterministic {
+            py.import_bound("importlib")?
+                .call_method0("invalidate_caches")?;
+            py.import_bound("linecache")?.call_method0("clearcache")?;
+        }
+
         Ok(())
     })
 }
@@ -382,6 +654,10 @@ fn do_init(app_name: String, symbols: Symbols, stub_wasi: bool) -> Result<()> {
 struct MyExports;
 
 impl Guest for MyExports {
+    fn version() -> u32 {
+        INIT_VERSION
+    }
+
     fn init(app_name: String, symbols: Symbols, stub_wasi: bool) -> Result<(), String> {
         let result = do_init(app_name, symbols, stub_wasi).map_err(|e| format!("{e:?}"));
 
@@ -410,6 +686,121 @@ impl Guest for MyExports {
     }
 }
 
+/// Resolve the Python object (and, where applicable, method name) to call for each of the world's exports, after
+/// checking that the app implements all of them.
+fn exports<'py>(
+    py: Python<'py>,
+    app: &Bound<'py, PyModule>,
+    symbols: &Symbols,
+) -> PyResult<Vec<Export>> {
+    // Classes named in the `exports` tables of `componentize-py.toml` files, keyed by the protocol they
+    // implement, which take the place of the app's own class (if any) of the same name as that protocol.
+    let export_classes = env::var("COMPONENTIZE_PY_EXPORT_CLASSES")
+        .unwrap_or_default()
+        .lines()
+        .map(|line| {
+            let (protocol, path) = line.split_once('\t').unwrap();
+            Ok((protocol.to_owned(), resolve_object(py, path)?))
+        })
+        .collect::<PyResult<HashMap<_, _>>>()?;
+
+    // Report any unimplemented protocol methods now, rather than when the host first tries to call them.
+    verify::check(py, app, &export_classes, symbols)?;
+
+    let types_package = py.import_bound(symbols.types_package.as_str())?;
+
+    symbols
+        .exports
+        .iter()
+        .map(|export| {
+            Ok(match export {
+                FunctionExport::Bundled(Bundled {
+                    module,
+                    protocol,
+                    name,
+                }) => Export::Freestanding {
+                    name: PyString::intern_bound(py, name).into(),
+                    instance: py
+                        .import_bound(module.as_str())?
+                        .getattr(protocol.as_str())?
+                        .call0()?
+                        .into(),
+                },
+                FunctionExport::Freestanding(Function { protocol, name }) => Export::Freestanding {
+                    name: PyString::intern_bound(py, name).into(),
+                    instance: export_instance(
+                        py,
+                        app,
+                        &export_classes,
+                        &types_package,
+                        protocol,
+                        name,
+                    )?
+                    .into(),
+                },
+                FunctionExport::Constructor(Constructor { module, protocol }) => {
+                    Export::Constructor(
+                        py.import_bound(module.as_str())?
+                            .getattr(protocol.as_str())?
+                            .into(),
+                    )
+                }
+                FunctionExport::Method(name) => {
+                    Export::Method(PyString::intern_bound(py, name).into())
+                }
+                FunctionExport::Static(Static {
+                    module,
+                    protocol,
+                    name,
+                }) => Export::Static {
+                    name: PyString::intern_bound(py, name).into(),
+                    class: py
+                        .import_bound(module.as_str())?
+                        .getattr(protocol.as_str())?
+                        .into(),
+                },
+            })
+        })
+        .collect()
+}
+
+/// Return a new instance of the class configured to implement the specified export protocol or, failing that, the
+/// app's class of the same name or, if the app doesn't define one, an object whose attributes are the functions
+/// registered for that protocol using the generated `export` decorators.
+fn export_instance<'a>(
+    py: Python<'a>,
+    app: &Bound<'a, PyModule>,
+    export_classes: &HashMap<String, Bound<'a, PyAny>>,
+    types_package: &Bound<'a, PyModule>,
+    protocol: &str,
+    name: &str,
+) -> PyResult<Bound<'a, PyAny>> {
+    if let Some(class) = export_classes.get(protocol) {
+        return class.call0();
+    }
+
+    match app.getattr(protocol) {
+        Ok(class) => class.call0(),
+        Err(error) => {
+            let Ok(registered) = types_package
+                .getattr(intern!(py, "_EXPORTS"))?
+                .get_item(protocol)
+            else {
+                return Err(error);
+            };
+
+            if registered.hasattr(name)? {
+                Ok(registered)
+            } else {
+                Err(PyAttributeError::new_err(format!(
+                    "app defines no `{protocol}` class, and no function named `{name}` was registered using the \
+                     `export` decorator for `{protocol}`"
+                )))
+            }
+        }
+    }
+}
+
 /// # Safety
 /// TODO
 #[export_name = "componentize-py#Dispatch"]
@@ -433,31 +824,44 @@ pub unsafe extern "C" fn componentize_py_dispatch(
         );
 
         // todo: is this sound, or do we need to `.into_iter().map(MaybeUninit::assume_init).collect()` instead?
-        let mut params_py = mem::transmute::<Vec<MaybeUninit<&PyAny>>, Vec<&PyAny>>(params_py)
+        let params_py = mem::transmute::<Vec<MaybeUninit<&PyAny>>, Vec<&PyAny>>(params_py)
             .into_iter()
-            .map(|p| Bound::from_borrowed_ptr(py, p.as_ptr()));
+            .map(|p| Bound::from_borrowed_ptr(py, p.as_ptr()))
+            .collect::<Vec<_>>();
 
         if !*STUB_WASI.get().unwrap() {
             static ONCE: Once = Once::new();
             ONCE.call_once(|| {
                 // We must call directly into the host to get the runtime environment since libc's version will only
-                // contain the build-time pre-init snapshot.
-                let environ = ENVIRON.get().unwrap().bind(py);
-                for (k, v) in environment::get_environment() {
-                    environ.set_item(k, v).unwrap();
-                }
+                // contain the build-time pre-init snapshot.  Note that the WASI profile may exclude it, in which
+                // case the runtime module's import of it has been replaced with a trapping stub.
+                if wasi_available("wasi:cli/environment") {
+                    let environ = ENVIRON.get().unwrap().bind(py);
+                    for (k, v) in environment::get_environment() {
+                        environ.set_item(k, v).unwrap();
+                    }
 
-                // Likewise for CLI arguments.
-                for arg in environment::get_arguments() {
-                    ARGV.get().unwrap().bind(py).append(arg).unwrap();
+                    // Likewise for CLI arguments.
+                    for arg in environment::get_arguments() {
+                        ARGV.get().unwrap().bind(py).append(arg).unwrap();
+                    }
                 }
 
                 // Call `random.seed()` to ensure we get a fresh seed rather than the one that got baked in during
                 // pre-init.
-                SEED.get().unwrap().call0(py).unwrap();
+                if wasi_available("wasi:random/random") {
+                    SEED.get().unwrap().call0(py).unwrap();
+                }
+
+                // Finally, let the bundled `componentize_py_reset` module clear any other state cached during
+                // pre-init (e.g. the locale and time zone), along with anything the app has registered.
+                RESET.get().unwrap().call0(py).unwrap();
             });
         }
 
+        let index = export;
+        let call = trace::start(true, index, &params_py);
+        let mut params_py = params_py.into_iter();
         let export = &EXPORTS.get().unwrap()[export];
         let result = match export {
             Export::Freestanding { instance, name } => {
@@ -475,33 +879,39 @@ pub unsafe extern "C" fn componentize_py_dispatch(
                 .and_then(|function| function.call1(py, PyTuple::new_bound(py, params_py))),
         };
 
-        let result = match return_style {
+        let result = result
+            .and_then(|result| reject_coroutine(py, result))
+            .and_then(|result| strict::check_export(py, index, result));
+
+        trace::finish(py, call);
+
+        let (result, error) = match return_style {
             ReturnStyle::Normal => match result {
-                Ok(result) => result,
-                Err(error) => {
-                    error.print(py);
-                    panic!("Python function threw an unexpected exception")
-                }
+                Ok(result) => (result, false),
+                Err(error) => exit_or_panic(py, error),
             },
             ReturnStyle::Result => match result {
-                Ok(result) => OK_CONSTRUCTOR.get().unwrap().call1(py, (result,)).unwrap(),
+                Ok(result) => (
+                    OK_CONSTRUCTOR.get().unwrap().call1(py, (result,)).unwrap(),
+                    false,
+                ),
                 Err(result) => {
-                    if ERR_CONSTRUCTOR
-                        .get()
-                        .unwrap()
-                        .bind(py)
-                        .eq(result.get_type_bound(py))
-                        .unwrap()
-                    {
-                        result.to_object(py)
+                    if is_err(result.value_bound(py)) {
+                        (result.to_object(py), true)
+                    } else if let Some(value) = map_exception(py, index, &result) {
+                        (
+                            ERR_CONSTRUCTOR.get().unwrap().call1(py, (value,)).unwrap(),
+                            true,
+                        )
                     } else {
-                        result.print(py);
-                        panic!("Python function threw an unexpected exception")
+                        exit_or_panic(py, result)
                     }
                 }
             },
         };
 
+        count(&EXPORT_COUNTERS, index, error);
+
         let result_array = [result];
 
         componentize_py_call_indirect(
@@ -526,6 +936,231 @@ pub unsafe extern "C" fn componentize_py_dispatch(
     });
 }
 
+/// Raise a `TypeError` if an exported function returned a coroutine (e.g. because it was defined using `async def`).
+///
+/// Exports are synchronous, so there's nothing to drive the coroutine to completion, and attempting to lower it
+/// as the function's result would fail obscurely.  We close the coroutine first so that CPython doesn't later
+/// complain that it was never awaited.
+fn reject_coroutine(py: Python, value: PyObject) -> PyResult<PyObject> {
+    if IS_COROUTINE
+        .get()
+        .unwrap()
+        .call1(py, (&value,))?
+        .is_truthy(py)?
+    {
+        value.call_method0(py, intern!(py, "close"))?;
+
+        Err(PyTypeError::new_err(
+            "exported function returned a coroutine; async exports are not supported, \
+             so please define it using `def` rather than `async def`",
+        ))
+    } else {
+        Ok(value)
+    }
+}
+
+/// Look up a Python object given a path of the form `module:attribute[.attribute...]`, or just `attribute` for
+/// builtins (e.g. `KeyError`).
+fn resolve_object<'py>(py: Python<'py>, path: &str) -> PyResult<Bound<'py, PyAny>> {
+    let (module, attributes) = path.split_once(':').unwrap_or(("builtins", path));
+    let mut object = py.import_bound(module)?.into_any();
+    for attribute in attributes.split('.') {
+        object = object.getattr(attribute)?;
+    }
+    Ok(object)
+}
+
+/// If an exception mapping applies to `error`, return the result of passing it to the mapping's function, for use
+/// as the payload of an `Err` result.
+///
+/// When more than one mapping applies, we use the one for the most derived exception type, as determined by the
+/// exception's method resolution order, or else the first one specified.
+fn map_exception(py: Python, export: usize, error: &PyErr) -> Option<PyObject> {
+    let mappings = EXCEPTION_MAPPINGS.get().unwrap();
+    if mappings.is_empty() {
+        return None;
+    }
+
+    let mro = error
+        .get_type_bound(py)
+        .getattr(intern!(py, "__mro__"))
+        .unwrap()
+        .downcast_into::<PyTuple>()
+        .unwrap();
+
+    let function = mro.iter().find_map(|class| {
+        mappings
+            .iter()
+            .find_map(|(exception, function)| exception.bind(py).is(&class).then_some(function))
+    })?;
+
+    // A mapping applies to every export which returns a `result`, whatever its error type, so check that the
+    // payload suits this one before we try to lower it.
+    match function
+        .call1(py, (error.value_bound(py),))
+        .and_then(|value| strict::check_error(py, export, value))
+    {
+        Ok(value) => Some(value),
+        Err(mapping_error) => {
+            mapping_error.set_cause(py, Some(error.clone_ref(py)));
+            exit_or_panic(py, mapping_error)
+        }
+    }
+}
+
+/// Install a panic hook which follows the default hook's output with the Python stack at the time of the panic, if
+/// any.
+///
+/// Most panics in the runtime are due to Python code passing a value of the wrong type to an import (or returning
+/// one from an export), which traps with a backtrace showing only Wasm frames.  Printing the Python stack
+/// alongside it tells the developer which Python code was responsible.  Exceptions escaping from exports are
+/// reported separately by `exit_or_panic`, by which point the Python stack has already unwound.
+///
+/// The hook runs in the middle of whatever panicked (e.g. lowering a value while holding the GIL), so it runs no
+/// Python code, since a second panic would abort without the original message.
+fn install_panic_hook() {
+    let default = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        // Print the panic message first, in case anything below fails.
+        default(info);
+
+        if let Some(stack) = python_stack() {
+            eprintln!("Python stack at the time of the trap (most recent call last):\n{stack}");
+        }
+    }));
+}
+
+/// Flush `sys.stdout` and `sys.stderr`, ignoring any errors since this is only called when we're about to exit or
+/// trap anyway.
+fn flush_stdio(py: Python) {
+    if let Ok(sys) = py.import_bound("sys") {
+        for stream in ["stdout", "stderr"] {
+            let _ = sys
+                .getattr(stream)
+                .and_then(|stream| stream.call_method0("flush"));
+        }
+    }
+}
+
+/// Format the current Python stack the same way `traceback.print_stack` would (minus source lines), or return
+/// `None` if no Python code is running.
+///
+/// This reads the frames using the C API rather than e.g. `sys._getframe`, since we may be in the middle of a failed
+/// call into Python, with objects half-constructed, and must avoid running any Python code.  For the same reason,
+/// it does nothing unless this thread already holds the GIL.
+fn python_stack() -> Option<String> {
+    extern "C" {
+        fn PyThreadState_GetFrame(state: *mut ffi::PyThreadState) -> *mut ffi::PyFrameObject;
+        fn PyFrame_GetCode(frame: *mut ffi::PyFrameObject) -> *mut ffi::PyCodeObject;
+        fn PyFrame_GetBack(frame: *mut ffi::PyFrameObject) -> *mut ffi::PyFrameObject;
+    }
+
+    // Return the specified `str` attribute of `code` (a builtin member, so no Python code runs), or `?` if it's
+    // unavailable.
+    unsafe fn attribute(code: *mut ffi::PyCodeObject, name: &CStr) -> String {
+        let value = ffi::PyObject_GetAttrString(code.cast(), name.as_ptr());
+        let mut size = 0;
+        let utf8 = if value.is_null() {
+            ptr::null()
+        } else {
+            ffi::PyUnicode_AsUTF8AndSize(value, &mut size)
+        };
+        let result = if utf8.is_null() {
+            ffi::PyErr_Clear();
+            "?".to_owned()
+        } else {
+            String::from_utf8_lossy(slice::from_raw_parts(utf8.cast(), size.try_into().unwrap()))
+                .into_owned()
+        };
+        if !value.is_null() {
+            ffi::Py_DecRef(value);
+        }
+        result
+    }
+
+    unsafe {
+        if ffi::Py_IsInitialized() == 0 || ffi::PyGILState_Check() == 0 {
+            return None;
+        }
+
+        // Set aside the exception being raised (if any) while we look up attributes, and restore it afterward.
+        let exception = ffi::PyErr_GetRaisedException();
+
+        let mut lines = Vec::new();
+        let mut frame = PyThreadState_GetFrame(ffi::PyGILState_GetThisThreadState());
+        while !frame.is_null() {
+            let code = PyFrame_GetCode(frame);
+            lines.push(format!(
+                "  File \"{}\", line {}, in {}",
+                attribute(code, c"co_filename"),
+                ffi::PyFrame_GetLineNumber(frame),
+                attribute(code, c"co_name")
+            ));
+            ffi::Py_DecRef(code.cast());
+
+            let back = PyFrame_GetBack(frame);
+            ffi::Py_DecRef(frame.cast());
+            frame = back;
+        }
+
+        ffi::PyErr_SetRaisedException(exception);
+
+        lines.reverse();
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+}
+
+/// Return whether the host provides the specified WASI interface (named without a version, e.g.
+/// `wasi:cli/environment`), i.e. whether it was neither stubbed out nor excluded by the component's WASI profile.
+fn wasi_available(interface: &str) -> bool {
+    !*STUB_WASI.get().unwrap()
+        && WASI_INTERFACES
+            .get()
+            .unwrap()
+            .as_ref()
+            .map_or(true, |interfaces| interfaces.iter().any(|i| i == interface))
+}
+
+/// Handle an exception which escaped from an exported function.
+///
+/// A `SystemExit` (e.g. raised by `sys.exit`) is translated into a call to the WASI Preview 1 adapter's `proc_exit`
+/// (and thus `wasi:cli/exit`) so that the exit status propagates to the host.  Any other exception is printed, followed by a trap.
+fn exit_or_panic(py: Python, error: PyErr) -> ! {
+    if error.is_instance_of::<PySystemExit>(py) && wasi_available("wasi:cli/exit") {
+        // Follow CPython's convention: `None` and zero indicate success, other integers indicate failure, and
+        // anything else is printed to `stderr` and indicates failure.
+        let code = error.value_bound(py).getattr(intern!(py, "code")).ok();
+        let success = match &code {
+            None => true,
+            Some(code) if code.is_none() => true,
+            Some(code) => match code.extract::<i64>() {
+                Ok(code) => code == 0,
+                Err(_) => {
+                    eprintln!("{code}");
+                    false
+                }
+            },
+        };
+
+        flush_stdio(py);
+
+        // We exit via the adapter rather than importing `wasi:cli/exit` ourselves so that the component only
+        // imports it if the adapter does, i.e. if the WASI profile allows it.
+        #[link(wasm_import_module = "wasi_snapshot_preview1")]
+        extern "C" {
+            #[cfg_attr(target_arch = "wasm32", link_name = "proc_exit")]
+            fn proc_exit(code: u32) -> !;
+        }
+
+        unsafe { proc_exit(if success { 0 } else { 1 }) }
+    }
+
+    error.print(py);
+    // Flush here rather than in the panic hook, where it wouldn't be safe to run Python code.
+    flush_stdio(py);
+    panic!("Python function threw an unexpected exception")
+}
+
 /// # Safety
 /// TODO
 #[export_name = "componentize-py#Allocate"]
@@ -666,8 +1301,19 @@ pub extern "C" fn componentize_py_get_field<'a>(
             PAYLOAD_FIELD_INDEX => {
                 if value.is_none() {
                     value.to_owned()
-                } else {
+                } else if value
+                    .is_instance(SOME_CONSTRUCTOR.get().unwrap().bind(*py))
+                    .unwrap()
+                {
                     value.getattr("value").unwrap()
+                } else {
+                    // We can't raise an exception from here, so the best we can do is explain what went wrong.
+                    panic!(
+                        "expected `None` or `Some(...)` for a nested `option`, got {}; wrap the value using \
+                         `Some` (see the generated `option` module), or build with `--strict` to have a \
+                         `TypeError` raised instead",
+                        value.get_type()
+                    )
                 }
             }
             _ => unreachable!(),
@@ -681,13 +1327,7 @@ pub extern "C" fn componentize_py_get_field<'a>(
                 .unwrap()
             {
                 0_i32
-            } else if ERR_CONSTRUCTOR
-                .get()
-                .unwrap()
-                .bind(*py)
-                .eq(value.get_type())
-                .unwrap()
-            {
+            } else if is_err(&value) {
                 1
             } else {
                 unreachable!()
@@ -709,12 +1349,58 @@ pub extern "C" fn componentize_py_get_field<'a>(
     }
 }
 
+/// Return `value` unchanged if it may be indexed by `componentize_py_get_list_element` (or copied by
+/// `componentize_py_get_bytes` or `componentize_py_get_numbers`), or else a `list` containing the items produced
+/// by iterating over it, e.g. for a generator or a `set`.  A `str` or a mapping raises `TypeError` instead.
+#[export_name = "componentize-py#AsSequence"]
+pub extern "C" fn componentize_py_as_sequence<'a>(
+    py: &Python<'a>,
+    value: Borrowed<'_, 'a, PyAny>,
+) -> Bound<'a, PyAny> {
+    if value.is_instance_of::<PyList>()
+        || value.is_instance_of::<PyTuple>()
+        || value.is_instance_of::<PyBytes>()
+        || unsafe { pyo3::ffi::PyObject_CheckBuffer(value.as_ptr()) != 0 }
+    {
+        value.to_owned()
+    } else if value.is_instance_of::<PyString>() || value.downcast::<PyMapping>().is_ok() {
+        // Iterating would silently split a `str` into characters or reduce a mapping to its keys, neither of
+        // which is likely to be what the caller meant.
+        exit_or_panic(
+            *py,
+            PyTypeError::new_err(format!(
+                "expected a list, tuple, buffer, or other iterable, got {}",
+                value
+                    .get_type()
+                    .qualname()
+                    .map(|name| name.to_string())
+                    .unwrap_or_else(|_| "<unknown>".to_owned())
+            )),
+        )
+    } else {
+        PyList::new_bound(
+            *py,
+            value.iter().unwrap().collect::<PyResult<Vec<_>>>().unwrap(),
+        )
+        .into_any()
+    }
+}
+
 #[export_name = "componentize-py#GetListLength"]
 pub extern "C" fn componentize_py_get_list_length(_py: &Python, value: Borrowed<PyAny>) -> usize {
     if let Ok(bytes) = value.downcast::<PyBytes>() {
         bytes.len().unwrap()
+    } else if let Ok(list) = value.downcast::<PyList>() {
+        list.len()
+    } else if let Ok(tuple) = value.downcast::<PyTuple>() {
+        tuple.len()
+    } else if let Ok(buffer) = PyBuffer::<u8>::get_bound(&value) {
+        // A `list<u8>` may also be passed as any object supporting the buffer protocol; see
+        // `componentize_py_get_bytes`.
+        buffer.item_count()
     } else {
-        value.downcast::<PyList>().unwrap().len()
+        // E.g. a one-dimensional NumPy array of some other element type.
+        value.len().unwrap()
     }
 }
 
@@ -724,7 +1410,55 @@ pub extern "C" fn componentize_py_get_list_element<'a>(
     value: Borrowed<'_, 'a, PyAny>,
     index: usize,
 ) -> Bound<'a, PyAny> {
-    value.downcast::<PyList>().unwrap().get_item(index).unwrap()
+    if let Ok(list) = value.downcast::<PyList>() {
+        list.get_item(index).unwrap()
+    } else if let Ok(tuple) = value.downcast::<PyTuple>() {
+        tuple.get_item(index).unwrap()
+    } else {
+        value.get_item(index).unwrap()
+    }
+}
+
+/// Copy the elements of `src` to `dst` without converting each to a Python object first, if `src` supports the
+/// buffer protocol with an element type matching `kind` and has exactly `len` elements.
+///
+/// Returns whether the elements were copied; if not, the caller should fall back to lowering them one at a time.
+///
+/// # Safety
+/// `dst` must be valid for writes of `len` elements of the type indicated by `kind`, and suitably aligned for it.
+#[export_name = "componentize-py#GetNumbers"]
+pub unsafe extern "C" fn componentize_py_get_numbers(
+    py: &Python,
+    src: Borrowed<PyAny>,
+    dst: *mut c_void,
+    len: usize,
+    kind: NumberKind,
+) -> bool {
+    unsafe fn copy<T: pyo3::buffer::Element>(
+        py: Python,
+        src: &Bound<PyAny>,
+        dst: *mut c_void,
+        len: usize,
+    ) -> bool {
+        let Ok(buffer) = PyBuffer::<T>::get_bound(src) else {
+            return false;
+        };
+        buffer.item_count() == len
+            && buffer
+                .copy_to_slice(py, slice::from_raw_parts_mut(dst as *mut T, len))
+                .is_ok()
+    }
+
+    match kind {
+        NumberKind::S16 => copy::<i16>(*py, &src, dst, len),
+        NumberKind::U16 => copy::<u16>(*py, &src, dst, len),
+        NumberKind::S32 => copy::<i32>(*py, &src, dst, len),
+        NumberKind::U32 => copy::<u32>(*py, &src, dst, len),
+        NumberKind::S64 => copy::<i64>(*py, &src, dst, len),
+        NumberKind::U64 => copy::<u64>(*py, &src, dst, len),
+        NumberKind::F32 => copy::<f32>(*py, &src, dst, len),
+        NumberKind::F64 => copy::<f64>(*py, &src, dst, len),
+    }
 }
 
 #[export_name = "componentize-py#FromCanonBool"]
@@ -767,7 +1501,11 @@ pub extern "C" fn componentize_py_from_canon_f64(py: &Python, value: f64) -> Py<
 
 #[export_name = "componentize-py#FromCanonChar"]
 pub extern "C" fn componentize_py_from_canon_char(py: &Python, value: u32) -> Py<PyAny> {
-    char::from_u32(value).unwrap().to_string().to_object(*py)
+    // Encode on the stack rather than allocating a `String`; CPython will return its cached object for Latin-1
+    // characters.
+    PyString::new_bound(*py, char::from_u32(value).unwrap().encode_utf8(&mut [0; 4]))
+        .into_any()
+        .unbind()
 }
 
 /// # Safety
@@ -917,18 +1655,21 @@ pub unsafe extern "C" fn componentize_py_init<'a>(
             )
             .extract::<u32>()
             .unwrap();
+            let payload = ptr::read(data.offset(isize::try_from(PAYLOAD_FIELD_INDEX).unwrap()));
 
             match discriminant {
                 0 => OK_CONSTRUCTOR.get().unwrap(),
+                // `string` error payloads get the more ergonomic `WitError`, which is also a `ValueError` whose
+                // `str()` is the message.
+                1 if Bound::from_borrowed_ptr(*py, payload.as_ptr())
+                    .is_instance_of::<PyString>() =>
+                {
+                    WIT_ERROR_CONSTRUCTOR.get().unwrap()
+                }
                 1 => ERR_CONSTRUCTOR.get().unwrap(),
                 _ => unreachable!(),
             }
-            .call1(
-                *py,
-                (ptr::read(
-                    data.offset(isize::try_from(PAYLOAD_FIELD_INDEX).unwrap()),
-                ),),
-            )
+            .call1(*py, (payload,))
             .unwrap()
             .into_bound(*py)
         }
@@ -943,18 +1684,36 @@ pub unsafe extern "C" fn componentize_py_init<'a>(
     }
 }
 
+/// Create a list with room for `len` elements, each of which must be set using `componentize_py_list_set` before
+/// the list is handed to Python code.
+///
+/// Since the length of a lifted list is always known in advance, this avoids the repeated reallocation we'd incur
+/// by appending to an empty list.
 #[export_name = "componentize-py#MakeList"]
-pub extern "C" fn componentize_py_make_list<'a>(py: &Python<'a>) -> Bound<'a, PyList> {
-    PyList::empty_bound(*py)
+pub extern "C" fn componentize_py_make_list<'a>(py: &Python<'a>, len: usize) -> Bound<'a, PyList> {
+    unsafe {
+        Bound::from_owned_ptr(*py, ffi::PyList_New(len.try_into().unwrap()))
+            .downcast_into_unchecked()
+    }
 }
 
-#[export_name = "componentize-py#ListAppend"]
-pub extern "C" fn componentize_py_list_append(
+#[export_name = "componentize-py#ListSet"]
+pub extern "C" fn componentize_py_list_set(
     _py: &Python,
     list: Borrowed<PyList>,
+    index: usize,
     element: Borrowed<PyAny>,
 ) {
-    list.append(element).unwrap();
+    // `PyList_SetItem` steals a reference, whereas `PyList::append` would have added one, so hand it a new one.
+    assert!(
+        unsafe {
+            ffi::PyList_SetItem(
+                list.as_ptr(),
+                index.try_into().unwrap(),
+                element.to_owned().into_ptr(),
+            )
+        } == 0
+    );
 }
 
 #[export_name = "componentize-py#None"]
@@ -966,13 +1725,28 @@ pub extern "C" fn componentize_py_none(py: &Python) -> Py<PyAny> {
 /// TODO
 #[export_name = "componentize-py#GetBytes"]
 pub unsafe extern "C" fn componentize_py_get_bytes(
-    _py: &Python,
-    src: Borrowed<PyBytes>,
+    py: &Python,
+    src: Borrowed<PyAny>,
     dst: *mut u8,
     len: usize,
 ) {
-    assert_eq!(len, src.len().unwrap());
-    slice::from_raw_parts_mut(dst, len).copy_from_slice(src.as_bytes())
+    let dst = slice::from_raw_parts_mut(dst, len);
+    if let Ok(bytes) = src.downcast::<PyBytes>() {
+        assert_eq!(len, bytes.len().unwrap());
+        dst.copy_from_slice(bytes.as_bytes())
+    } else if let Ok(buffer) = PyBuffer::<u8>::get_bound(&src) {
+        // E.g. a `bytearray`, `memoryview`, or NumPy `uint8` array, the last two of which may not be contiguous, in
+        // which case `copy_to_slice` gathers the elements for us.
+        assert_eq!(len, buffer.item_count());
+        buffer.copy_to_slice(*py, dst).unwrap()
+    } else {
+        // E.g. a `list` or `tuple` of `int`s, or the result of `componentize_py_as_sequence` for some other
+        // iterable.
+        assert_eq!(len, src.len().unwrap());
+        for (index, byte) in dst.iter_mut().enumerate() {
+            *byte = src.get_item(index).unwrap().extract().unwrap();
+        }
+    }
 }
 
 /// # Safety
@@ -1103,6 +1877,7 @@ pub extern "C" fn componentize_py_to_canon_handle(
     if local != 0 {
         let ty = &TYPES.get().unwrap()[usize::try_from(resource).unwrap()];
         let Type::Resource {
+            constructor,
             local: Some(LocalResource { new, drop, .. }),
             ..
         } = ty
@@ -1110,6 +1885,21 @@ pub extern "C" fn componentize_py_to_canon_handle(
             panic!("expected local resource, found {ty:?}");
         };
 
+        // Resources with no constructor or methods are generated as concrete token classes, which wrap any
+        // other value lowered in their place (e.g. a `str`, which wouldn't let us attach a handle to it).
+        let constructor = constructor.bind(*py);
+        let wrapped;
+        let value = if constructor
+            .hasattr(intern!(*py, "__componentize_py_token__"))
+            .unwrap()
+            && !value.is_instance(constructor).unwrap()
+        {
+            wrapped = constructor.call1((value.to_owned(),)).unwrap();
+            wrapped.as_borrowed()
+        } else {
+            value
+        };
+
         let name = intern!(*py, "__componentize_py_handle");
         if value.hasattr(name).unwrap() {
             value.getattr(name).unwrap().extract().unwrap()
@@ -1170,6 +1960,86 @@ pub extern "C" fn componentize_py_to_canon_handle(
     }
 }
 
+/// Called when the last handle to an instance of a resource defined by the app is dropped, whether by the host or
+/// the app itself.
+///
+/// `rep` is the pointer to the instance which `componentize_py_to_canon_handle` passed to `[resource-new]`, along
+/// with the reference it leaked in order to keep the instance alive while handles to it exist.  Here we notify the
+/// instance by calling its `__exit__` method (if any) and then release that reference, allowing the instance to
+/// be garbage collected (and thus its `__del__` method, if any, to be called).
+///
+/// # Safety
+/// `rep` must be a pointer previously passed to `[resource-new]` by `componentize_py_to_canon_handle`.
+#[export_name = "componentize-py#ResourceDtor"]
+pub unsafe extern "C" fn componentize_py_resource_dtor(rep: usize) {
+    // The host may drop a resource in the middle of a synchronous import call, while the GIL is released.
+    with_reacquired_gil(|| {
+        Python::with_gil(|py| {
+            let value = Bound::from_owned_ptr(py, rep as _);
+
+            // The handle is gone, so make sure we don't try to use or drop it again.
+            let name = intern!(py, "__componentize_py_handle");
+            if value.hasattr(name).unwrap() {
+                value.delattr(name).unwrap();
+            }
+            let name = intern!(py, "finalizer");
+            if value.hasattr(name).unwrap() {
+                value
+                    .getattr(name)
+                    .unwrap()
+                    .call_method0(intern!(py, "detach"))
+                    .unwrap();
+            }
+
+            let name = intern!(py, "__exit__");
+            if value.hasattr(name).unwrap() {
+                // As with `__del__`, there's nobody to propagate an exception to, so we just report it.
+                if let Err(error) = value.call_method1(name, (py.None(), py.None(), py.None())) {
+                    error.write_unraisable_bound(py, Some(&value));
+                }
+            }
+        })
+    });
+}
+
+/// The thread state saved by `componentize_py_release_gil` while a synchronous import call is in progress, if any
+static RELEASED_GIL: AtomicPtr<ffi::PyThreadState> = AtomicPtr::new(ptr::null_mut());
+
+/// Release the GIL once the parameters of a synchronous import call have been lowered, before calling the host.
+///
+/// Note that, since this happens in the middle of `call_import`, PyO3 still considers the GIL held, so anything
+/// the host may call in the meantime must use `with_reacquired_gil` before touching Python objects.
+///
+/// # Safety
+/// Must be paired with a call to `componentize_py_acquire_gil` once the host call returns.
+#[export_name = "componentize-py#ReleaseGil"]
+pub unsafe extern "C" fn componentize_py_release_gil(_py: &Python) {
+    RELEASED_GIL.store(ffi::PyEval_SaveThread(), Ordering::Relaxed);
+}
+
+/// Reacquire the GIL released by `componentize_py_release_gil` before lifting the results of an import call.
+///
+/// # Safety
+/// Must follow a call to `componentize_py_release_gil`.
+#[export_name = "componentize-py#AcquireGil"]
+pub unsafe extern "C" fn componentize_py_acquire_gil(_py: &Python) {
+    ffi::PyEval_RestoreThread(RELEASED_GIL.swap(ptr::null_mut(), Ordering::Relaxed));
+}
+
+/// Call `fun`, first reacquiring the GIL if it was released by `componentize_py_release_gil` and releasing it again
+/// afterward.
+unsafe fn with_reacquired_gil<T>(fun: impl FnOnce() -> T) -> T {
+    let state = RELEASED_GIL.swap(ptr::null_mut(), Ordering::Relaxed);
+    if state.is_null() {
+        fun()
+    } else {
+        ffi::PyEval_RestoreThread(state);
+        let result = fun();
+        RELEASED_GIL.store(ffi::PyEval_SaveThread(), Ordering::Relaxed);
+        result
+    }
+}
+
 // As of this writing, recent Rust `nightly` builds include a version of the `libc` crate that expects `wasi-libc`
 // to define the following global variables, but `wasi-libc` defines them as preprocessor constants which aren't
 // visible at link time, so we need to define them somewhere.  Ideally, we should fix this upstream, but for now we
@@ -0,0 +1,547 @@
+//! Validation of the values Python code passes to the host, for components built with `--strict`.
+//!
+//! Lowering a value assumes it has the Python type the bindings declare for it, and panics (or worse, for e.g. a
+//! `bytearray` passed as `list<u8>`) if it doesn't.  In strict mode we instead check each parameter passed to an
+//! imported function and each value returned from an exported one before lowering begins, raising a `TypeError`
+//! which names the function, the offending parameter (and, for nested values, where within it), and the expected
+//! type.
+//!
+//! The expected types are described by the `COMPONENTIZE_PY_STRICT` environment variable at build time; see
+//! `Summary::strict_manifest` in the `componentize-py` crate for the format.
+
+use {
+    crate::{is_err, Type, OK_CONSTRUCTOR, SOME_CONSTRUCTOR, TYPES},
+    once_cell::sync::OnceCell,
+    pyo3::{
+        buffer::PyBuffer,
+        exceptions::PyTypeError,
+        intern,
+        types::{
+            PyAnyMethods, PyBool, PyBytes, PyDictMethods, PyFloat, PyList, PyListMethods, PyLong,
+            PyMapping, PyString, PyStringMethods, PyTuple, PyTupleMethods, PyTypeMethods,
+        },
+        Bound, PyAny, PyErr, PyObject, PyResult, Python,
+    },
+    std::{collections::HashMap, fmt, vec},
+};
+
+static MANIFEST: OnceCell<Manifest> = OnceCell::new();
+
+enum Check {
+    Bool,
+    Int {
+        name: &'static str,
+        min: i128,
+        max: i128,
+    },
+    Float(&'static str),
+    Char,
+    String,
+    Bytes,
+    List(Box<Check>),
+    Option(Box<Check>),
+    NestingOption(Box<Check>),
+    Result(Option<Box<Check>>, Option<Box<Check>>),
+    Tuple(Vec<Check>),
+    /// A record, variant, enum, flags, or resource type, identified by its index in `TYPES`
+    Type {
+        index: usize,
+        name: String,
+    },
+    /// A type for which we have no checks
+    Any,
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let unit = |check: &Option<Box<Check>>| {
+            check
+                .as_ref()
+                .map_or_else(|| "_".to_owned(), |check| check.to_string())
+        };
+
+        match self {
+            Self::Bool => f.write_str("bool"),
+            Self::Int { name, .. } | Self::Float(name) => f.write_str(name),
+            Self::Char => f.write_str("char"),
+            Self::String => f.write_str("string"),
+            Self::Bytes => f.write_str("bytes"),
+            Self::List(check) => write!(f, "list<{check}>"),
+            Self::Option(check) | Self::NestingOption(check) => write!(f, "option<{check}>"),
+            Self::Result(ok, err) => write!(f, "result<{}, {}>", unit(ok), unit(err)),
+            Self::Tuple(checks) => write!(
+                f,
+                "tuple<{}>",
+                checks
+                    .iter()
+                    .map(|check| check.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Self::Type { name, .. } => f.write_str(name),
+            Self::Any => f.write_str("any"),
+        }
+    }
+}
+
+/// Describes where within a value a check failed, e.g. `[2].name`, along with what we expected to find there and
+/// what we found instead.
+struct Mismatch {
+    path: String,
+    expected: String,
+    actual: String,
+}
+
+impl Mismatch {
+    fn new(expected: &impl fmt::Display, value: &Bound<PyAny>) -> Self {
+        Self {
+            path: String::new(),
+            expected: expected.to_string(),
+            actual: type_name(value),
+        }
+    }
+
+    fn within(mut self, segment: &str) -> Self {
+        self.path.insert_str(0, segment);
+        self
+    }
+}
+
+enum TypeInfo {
+    Record(Vec<Check>),
+    Variant(Vec<Option<Check>>),
+    Other,
+}
+
+struct Function {
+    name: String,
+    checks: Vec<(String, Option<Check>)>,
+}
+
+struct Manifest {
+    types: HashMap<usize, TypeInfo>,
+    exports: HashMap<usize, Function>,
+    errors: HashMap<usize, Function>,
+    imports: HashMap<usize, Function>,
+}
+
+/// Parse the manifest generated by `Summary::strict_manifest`, enabling checks for subsequent calls to
+/// `check_import`, `check_export`, and `check_error`.
+pub fn init(manifest: &str) {
+    let lines = manifest
+        .lines()
+        .map(|line| line.split('\t').collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    // Collect the type names first, since a type may refer to one which appears later in the manifest.
+    let names = lines
+        .iter()
+        .filter(|line| matches!(line[0], "record" | "variant" | "type"))
+        .map(|line| (line[1].parse().unwrap(), line[2].to_owned()))
+        .collect::<HashMap<usize, _>>();
+
+    let parse_type = |ty: &str| {
+        let (check, rest) = parse(&names, ty);
+        assert!(rest.is_empty(), "unexpected trailing input in type: {ty}");
+        check
+    };
+
+    let mut types = HashMap::new();
+    let mut exports = HashMap::new();
+    let mut errors = HashMap::new();
+    let mut imports = HashMap::new();
+    for line in lines {
+        let index = line[1].parse::<usize>().unwrap();
+        match line[0] {
+            "record" => {
+                types.insert(
+                    index,
+                    TypeInfo::Record(line[3..].iter().map(|ty| parse_type(ty).unwrap()).collect()),
+                );
+            }
+            "variant" => {
+                types.insert(
+                    index,
+                    TypeInfo::Variant(line[3..].iter().map(|ty| parse_type(ty)).collect()),
+                );
+            }
+            "type" => {
+                types.insert(index, TypeInfo::Other);
+            }
+            "export" | "error" => {
+                let functions = if line[0] == "export" {
+                    &mut exports
+                } else {
+                    &mut errors
+                };
+                functions.insert(
+                    index,
+                    Function {
+                        name: line[2].to_owned(),
+                        checks: vec![(String::new(), parse_type(line[3]))],
+                    },
+                );
+            }
+            "import" => {
+                imports.insert(
+                    index,
+                    Function {
+                        name: line[2].to_owned(),
+                        checks: line[3..]
+                            .chunks(2)
+                            .map(|pair| (pair[0].to_owned(), parse_type(pair[1])))
+                            .collect(),
+                    },
+                );
+            }
+            kind => panic!("unexpected entry in strict manifest: {kind}"),
+        }
+    }
+
+    if MANIFEST
+        .set(Manifest {
+            types,
+            exports,
+            errors,
+            imports,
+        })
+        .is_err()
+    {
+        panic!("strict manifest already initialized")
+    }
+}
+
+/// Parse a type from the start of `input`, returning the rest.  `_` (i.e. no value) is parsed as `None`.
+fn parse<'a>(names: &HashMap<usize, String>, input: &'a str) -> (Option<Check>, &'a str) {
+    let end = input.find(['<', '>', ',']).unwrap_or(input.len());
+    let (name, mut rest) = input.split_at(end);
+
+    let mut args = Vec::new();
+    if let Some(tail) = rest.strip_prefix('<') {
+        rest = tail;
+        loop {
+            let (arg, tail) = parse(names, rest);
+            args.push(arg);
+            if let Some(tail) = tail.strip_prefix(", ") {
+                rest = tail;
+            } else {
+                rest = tail.strip_prefix('>').unwrap();
+                break;
+            }
+        }
+    }
+
+    let mut args = args.into_iter();
+    let arg = |args: &mut vec::IntoIter<Option<Check>>| args.next().unwrap().map(Box::new);
+    let int = |name, min, max| Check::Int { name, min, max };
+
+    let check = match name {
+        "_" => return (None, rest),
+        "bool" => Check::Bool,
+        "u8" => int("u8", u8::MIN.into(), u8::MAX.into()),
+        "s8" => int("s8", i8::MIN.into(), i8::MAX.into()),
+        "u16" => int("u16", u16::MIN.into(), u16::MAX.into()),
+        "s16" => int("s16", i16::MIN.into(), i16::MAX.into()),
+        "u32" => int("u32", u32::MIN.into(), u32::MAX.into()),
+        "s32" => int("s32", i32::MIN.into(), i32::MAX.into()),
+        "u64" => int("u64", u64::MIN.into(), u64::MAX.into()),
+        "s64" => int("s64", i64::MIN.into(), i64::MAX.into()),
+        "f32" => Check::Float("f32"),
+        "f64" => Check::Float("f64"),
+        "char" => Check::Char,
+        "string" => Check::String,
+        "bytes" => Check::Bytes,
+        "list" => Check::List(arg(&mut args).unwrap()),
+        "option" => Check::Option(arg(&mut args).unwrap()),
+        "nesting-option" => Check::NestingOption(arg(&mut args).unwrap()),
+        "result" => Check::Result(arg(&mut args), arg(&mut args)),
+        "tuple" => Check::Tuple(args.map(Option::unwrap).collect()),
+        "any" => Check::Any,
+        _ => {
+            let index = name.strip_prefix('#').unwrap().parse().unwrap();
+            Check::Type {
+                index,
+                name: names[&index].clone(),
+            }
+        }
+    };
+
+    (Some(check), rest)
+}
+
+/// If strict mode is enabled, raise a `TypeError` unless each of `params` has the type expected by the imported
+/// function with the specified dispatch index.
+pub fn check_import(py: Python, index: u32, params: &[Bound<PyAny>]) -> PyResult<()> {
+    let Some(manifest) = MANIFEST.get() else {
+        return Ok(());
+    };
+    let Some(function) = manifest.imports.get(&usize::try_from(index).unwrap()) else {
+        return Ok(());
+    };
+
+    for ((name, check), value) in function.checks.iter().zip(params) {
+        if let Some(check) = check {
+            manifest.check(py, check, value).map_err(|mismatch| {
+                error(
+                    &function.name,
+                    &format!("parameter `{name}{}`", mismatch.path),
+                    &mismatch,
+                )
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// If strict mode is enabled, raise a `TypeError` unless `value` has the type expected to be returned by the
+/// exported function with the specified index, otherwise returning `value` unchanged.
+pub fn check_export(py: Python, index: usize, value: PyObject) -> PyResult<PyObject> {
+    let Some(manifest) = MANIFEST.get() else {
+        return Ok(value);
+    };
+    let Some(Function { name, checks }) = manifest.exports.get(&index) else {
+        return Ok(value);
+    };
+
+    if let [(_, Some(check))] = checks.as_slice() {
+        manifest
+            .check(py, check, value.bind(py))
+            .map_err(|mismatch| {
+                let location = if mismatch.path.is_empty() {
+                    "return value".to_owned()
+                } else {
+                    format!("return value `{}`", mismatch.path)
+                };
+                error(name, &location, &mismatch)
+            })?;
+    }
+
+    Ok(value)
+}
+
+/// Raise a `TypeError` unless `value`, as returned by an exception mapping, has the type expected for the `error`
+/// payload of the exported function with the specified index, otherwise returning `value` unchanged.
+///
+/// Unlike the other checks, this one is made whenever exception mappings are in use, not just in strict mode.
+pub fn check_error(py: Python, index: usize, value: PyObject) -> PyResult<PyObject> {
+    let Some(manifest) = MANIFEST.get() else {
+        return Ok(value);
+    };
+    let Some(Function { name, checks }) = manifest.errors.get(&index) else {
+        return Ok(value);
+    };
+
+    if let [(_, Some(check))] = checks.as_slice() {
+        manifest
+            .check(py, check, value.bind(py))
+            .map_err(|mismatch| {
+                let location = if mismatch.path.is_empty() {
+                    "exception mapping result".to_owned()
+                } else {
+                    format!("exception mapping result `{}`", mismatch.path)
+                };
+                error(name, &location, &mismatch)
+            })?;
+    }
+
+    Ok(value)
+}
+
+fn error(function: &str, location: &str, mismatch: &Mismatch) -> PyErr {
+    PyTypeError::new_err(format!(
+        "{function}: {location}: expected {}, got {}",
+        mismatch.expected, mismatch.actual
+    ))
+}
+
+fn type_name(value: &Bound<PyAny>) -> String {
+    value
+        .get_type()
+        .qualname()
+        .map(|name| name.to_string())
+        .unwrap_or_else(|_| "<unknown>".to_owned())
+}
+
+/// Return true if `value`'s type is exactly `class` (i.e. not a subclass).
+fn is_exactly(value: &Bound<PyAny>, class: &OnceCell<PyObject>) -> bool {
+    value.get_type().is(class.get().unwrap().bind(value.py()))
+}
+
+impl Manifest {
+    fn check(&self, py: Python, check: &Check, value: &Bound<PyAny>) -> Result<(), Mismatch> {
+        let mismatch = || Err(Mismatch::new(check, value));
+        let payload = |check: &Check| {
+            self.check(py, check, &value.getattr(intern!(py, "value")).unwrap())
+                .map_err(|mismatch| mismatch.within(".value"))
+        };
+
+        match check {
+            Check::Bool => {
+                if !value.is_instance_of::<PyBool>() {
+                    return mismatch();
+                }
+            }
+            Check::Int { min, max, .. } => {
+                if !value.is_instance_of::<PyLong>() || value.is_instance_of::<PyBool>() {
+                    return mismatch();
+                }
+                if !value
+                    .extract::<i128>()
+                    .is_ok_and(|value| (*min..=*max).contains(&value))
+                {
+                    return Err(Mismatch {
+                        path: String::new(),
+                        expected: check.to_string(),
+                        actual: format!("{value} (out of range)"),
+                    });
+                }
+            }
+            Check::Float(_) => {
+                if !(value.is_instance_of::<PyFloat>()
+                    || (value.is_instance_of::<PyLong>() && !value.is_instance_of::<PyBool>()))
+                {
+                    return mismatch();
+                }
+            }
+            Check::Char => {
+                let Ok(string) = value.downcast::<PyString>() else {
+                    return mismatch();
+                };
+                let length = string.to_cow().map_or(0, |string| string.chars().count());
+                if length != 1 {
+                    return Err(Mismatch {
+                        path: String::new(),
+                        expected: check.to_string(),
+                        actual: format!("str of length {length}"),
+                    });
+                }
+            }
+            Check::String => {
+                if !value.is_instance_of::<PyString>() {
+                    return mismatch();
+                }
+            }
+            Check::Bytes => {
+                if !value.is_instance_of::<PyBytes>() && PyBuffer::<u8>::get_bound(value).is_err() {
+                    return mismatch();
+                }
+            }
+            Check::List(element) => {
+                let items = if let Ok(list) = value.downcast::<PyList>() {
+                    list.iter().collect::<Vec<_>>()
+                } else if let Ok(tuple) = value.downcast::<PyTuple>() {
+                    tuple.iter().collect()
+                } else if unsafe { pyo3::ffi::PyObject_CheckBuffer(value.as_ptr()) != 0 }
+                    || (value.hasattr(intern!(py, "__iter__")).unwrap_or(false)
+                        && !value.is_instance_of::<PyString>()
+                        && value.downcast::<PyMapping>().is_err())
+                {
+                    // We can't check the elements of a buffer or an arbitrary iterable without consuming it, so
+                    // we leave that to lowering.
+                    Vec::new()
+                } else {
+                    return mismatch();
+                };
+                for (index, item) in items.iter().enumerate() {
+                    self.check(py, element, item)
+                        .map_err(|mismatch| mismatch.within(&format!("[{index}]")))?;
+                }
+            }
+            Check::Option(some) => {
+                if !value.is_none() {
+                    self.check(py, some, value)?;
+                }
+            }
+            Check::NestingOption(some) => {
+                if !value.is_none() {
+                    if !is_exactly(value, &SOME_CONSTRUCTOR) {
+                        return mismatch();
+                    }
+                    payload(some)?;
+                }
+            }
+            Check::Result(ok, err) => {
+                let check = if is_exactly(value, &OK_CONSTRUCTOR) {
+                    ok
+                } else if is_err(value) {
+                    err
+                } else {
+                    return mismatch();
+                };
+                if let Some(check) = check {
+                    payload(check)?;
+                }
+            }
+            Check::Tuple(checks) => {
+                let Ok(tuple) = value.downcast::<PyTuple>() else {
+                    return mismatch();
+                };
+                if tuple.len() != checks.len() {
+                    return Err(Mismatch {
+                        path: String::new(),
+                        expected: check.to_string(),
+                        actual: format!("tuple of length {}", tuple.len()),
+                    });
+                }
+                for (index, (check, item)) in checks.iter().zip(tuple.iter()).enumerate() {
+                    self.check(py, check, &item)
+                        .map_err(|mismatch| mismatch.within(&format!("[{index}]")))?;
+                }
+            }
+            Check::Type { index, .. } => match &TYPES.get().unwrap()[*index] {
+                Type::Record {
+                    constructor,
+                    fields,
+                } => {
+                    if !value.is_instance(constructor.bind(py)).unwrap() {
+                        return mismatch();
+                    }
+                    let Some(TypeInfo::Record(checks)) = self.types.get(index) else {
+                        unreachable!()
+                    };
+                    for (field, check) in fields.iter().zip(checks) {
+                        self.check(py, check, &value.getattr(field.as_str()).unwrap())
+                            .map_err(|mismatch| mismatch.within(&format!(".{field}")))?;
+                    }
+                }
+                Type::Variant {
+                    types_to_discriminants,
+                    ..
+                } => {
+                    let Some(discriminant) = types_to_discriminants
+                        .bind(py)
+                        .get_item(value.get_type())
+                        .unwrap()
+                    else {
+                        return mismatch();
+                    };
+                    let Some(TypeInfo::Variant(cases)) = self.types.get(index) else {
+                        unreachable!()
+                    };
+                    if let Some(check) = &cases[discriminant.extract::<usize>().unwrap()] {
+                        payload(check)?;
+                    }
+                }
+                Type::Enum { constructor, .. } | Type::Flags { constructor, .. } => {
+                    if !value.is_instance(constructor.bind(py)).unwrap() {
+                        return mismatch();
+                    }
+                }
+                Type::Resource {
+                    constructor, local, ..
+                } => {
+                    // Resources defined by the app need only implement the protocol in the generated bindings
+                    // rather than subclass it, so we can only check instances of imported resources.
+                    if local.is_none() && !value.is_instance(constructor.bind(py)).unwrap() {
+                        return mismatch();
+                    }
+                }
+                ty => unreachable!("unexpected type in strict manifest: {ty:?}"),
+            },
+            Check::Any => {}
+        }
+
+        Ok(())
+    }
+}
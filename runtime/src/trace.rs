@@ -0,0 +1,133 @@
+//! Logging of calls across the component boundary, for components built with `--trace-imports`.
+//!
+//! Each call to an imported function and each call to an exported one is logged along with how long it took and
+//! the size of each of its parameters (i.e. the length of each string, `bytes`, list, tuple, or dict, with `-`
+//! standing for anything else), which is usually enough to tell which calls are chatty or unexpectedly large.
+//! Messages are sent to `wasi:logging/logging` at the `trace` level if the world imports it, or written to
+//! `stderr` otherwise.
+//!
+//! The function names are described by the `COMPONENTIZE_PY_TRACE` environment variable at build time; see
+//! `Summary::trace_manifest` in the `componentize-py` crate for the format.
+
+use {
+    crate::{componentize_py_call_indirect, Type, TYPES},
+    once_cell::sync::OnceCell,
+    pyo3::{
+        types::{PyAnyMethods, PyBytes, PyDict, PyList, PyString, PyTuple},
+        Bound, PyAny, Python, ToPyObject,
+    },
+    std::{collections::HashMap, ptr, time::Instant},
+};
+
+/// Context passed to `wasi:logging/logging#log`
+const CONTEXT: &str = "componentize-py";
+
+static MANIFEST: OnceCell<Manifest> = OnceCell::new();
+
+struct Manifest {
+    exports: HashMap<usize, String>,
+    imports: HashMap<usize, String>,
+    /// The index of the imported `wasi:logging/logging#log` function and of its `level` enum in `TYPES`, if any
+    logging: Option<(u32, usize)>,
+}
+
+/// A call in progress, returned by `start` and consumed by `finish`
+pub struct Call {
+    description: String,
+    start: Instant,
+}
+
+/// Parse the manifest generated by `Summary::trace_manifest`, enabling logging for subsequent calls to `start`.
+pub fn init(manifest: &str) {
+    let mut exports = HashMap::new();
+    let mut imports = HashMap::new();
+    let mut logging = None;
+    for line in manifest.lines() {
+        let line = line.split('\t').collect::<Vec<_>>();
+        let index = line[1].parse::<usize>().unwrap();
+        match line[0] {
+            "export" => {
+                exports.insert(index, line[2].to_owned());
+            }
+            "import" => {
+                imports.insert(index, line[2].to_owned());
+            }
+            "logging" => logging = Some((index.try_into().unwrap(), line[2].parse().unwrap())),
+            _ => unreachable!(),
+        }
+    }
+
+    MANIFEST
+        .set(Manifest {
+            exports,
+            imports,
+            logging,
+        })
+        .ok()
+        .unwrap();
+}
+
+/// If tracing is enabled, start timing a call to the exported (if `export` is true) or imported function at
+/// `index` with the specified parameters.
+pub fn start(export: bool, index: usize, params: &[Bound<PyAny>]) -> Option<Call> {
+    let manifest = MANIFEST.get()?;
+    let (direction, name) = if export {
+        ("export", manifest.exports.get(&index)?)
+    } else {
+        ("import", manifest.imports.get(&index)?)
+    };
+
+    let sizes = params
+        .iter()
+        .map(|param| {
+            if param.is_instance_of::<PyString>()
+                || param.is_instance_of::<PyBytes>()
+                || param.is_instance_of::<PyList>()
+                || param.is_instance_of::<PyTuple>()
+                || param.is_instance_of::<PyDict>()
+            {
+                param
+                    .len()
+                    .map_or_else(|_| "-".to_owned(), |len| len.to_string())
+            } else {
+                "-".to_owned()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Some(Call {
+        description: format!("{direction} {name}({})", sizes.join(", ")),
+        start: Instant::now(),
+    })
+}
+
+/// Log a call previously started using `start`, if any.
+pub fn finish(py: Python, call: Option<Call>) {
+    let Some(Call { description, start }) = call else {
+        return;
+    };
+    let message = format!("{description} took {:?}", start.elapsed());
+
+    match MANIFEST.get().unwrap().logging {
+        Some((log, level)) => {
+            let Type::Enum { constructor, .. } = &TYPES.get().unwrap()[level] else {
+                unreachable!()
+            };
+            // The `trace` level is the first case of the `level` enum.
+            let params = [
+                constructor.call1(py, (0,)).unwrap(),
+                CONTEXT.to_object(py),
+                message.to_object(py),
+            ];
+            unsafe {
+                componentize_py_call_indirect(
+                    &py as *const _ as _,
+                    params.as_ptr() as _,
+                    ptr::null_mut(),
+                    log,
+                );
+            }
+        }
+        None => eprintln!("{CONTEXT}: {message}"),
+    }
+}
@@ -0,0 +1,241 @@
+//! Verification of the app's implementations of the generated export protocols, performed once at build time.
+//!
+//! A class which subclasses a generated protocol but misspells one of its methods can still be defined, and for
+//! resources even instantiated lazily, so without this check the mistake would only show up as an obscure error
+//! (or trap) the first time the host calls the method.  Instead, we check each class the runtime will use for
+//! unimplemented abstract methods before the snapshot is taken, failing the build with a message which names each
+//! missing method along with any similarly named attribute the app defined instead.
+//!
+//! We also warn about classes in the app module which implement one of the world's export protocols under a
+//! different name, since the runtime looks classes up by name and will never use them (unless a
+//! `componentize-py.toml` file names them explicitly).
+
+use {
+    crate::exp::{Bundled, Constructor, Function, FunctionExport, Static, Symbols},
+    pyo3::{
+        exceptions::PyTypeError,
+        ffi, intern,
+        types::{PyAnyMethods, PyDictMethods, PyModule, PyModuleMethods, PyTuple, PyType},
+        Bound, PyAny, PyResult, Python,
+    },
+    std::collections::{HashMap, HashSet},
+};
+
+/// Check the classes implementing each of the exports in `symbols`, returning a `TypeError` describing every
+/// unimplemented method, if any.
+pub fn check(
+    py: Python,
+    app: &Bound<PyModule>,
+    export_classes: &HashMap<String, Bound<PyAny>>,
+    symbols: &Symbols,
+) -> PyResult<()> {
+    let mut protocols = HashSet::new();
+    let mut seen = HashSet::new();
+    let mut problems = Vec::new();
+
+    for export in &symbols.exports {
+        let class = match export {
+            FunctionExport::Freestanding(Function { protocol, .. }) => {
+                protocols.insert(protocol.as_str());
+                // If the app defines no such class, it may have registered free functions instead; see
+                // `export_instance`.
+                match export_classes.get(protocol.as_str()) {
+                    Some(class) => Some(class.clone()),
+                    None => app.getattr(protocol.as_str()).ok(),
+                }
+            }
+            FunctionExport::Bundled(Bundled {
+                module, protocol, ..
+            })
+            | FunctionExport::Constructor(Constructor { module, protocol })
+            | FunctionExport::Static(Static {
+                module, protocol, ..
+            }) => py
+                .import_bound(module.as_str())?
+                .getattr(protocol.as_str())
+                .ok(),
+            FunctionExport::Method(_) => None,
+        };
+
+        if let Some(class) = class {
+            if seen.insert(class.as_ptr()) {
+                problems.extend(missing_methods(py, &class)?);
+            }
+        }
+    }
+
+    warn_unused(py, app, &protocols, &seen)?;
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(PyTypeError::new_err(format!(
+            "app does not fully implement the exports of its world:\n{}",
+            problems
+                .iter()
+                .map(|problem| format!("  - {problem}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )))
+    }
+}
+
+/// Describe each abstract method `class` leaves unimplemented, suggesting a similarly named attribute if there is
+/// one.
+fn missing_methods(py: Python, class: &Bound<PyAny>) -> PyResult<Vec<String>> {
+    let Ok(abstract_methods) = class.getattr(intern!(py, "__abstractmethods__")) else {
+        return Ok(Vec::new());
+    };
+
+    let mut missing = abstract_methods
+        .iter()?
+        .map(|name| name?.extract::<String>())
+        .collect::<PyResult<Vec<_>>>()?;
+
+    if missing.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    missing.sort();
+
+    // Gather the names defined by the app's own classes (i.e. excluding the generated protocols), any of which
+    // might be a misspelling of a missing method.
+    let mut defined = Vec::new();
+    for base in class
+        .getattr(intern!(py, "__mro__"))?
+        .downcast_into::<PyTuple>()?
+        .iter()
+    {
+        if is_protocol(py, &base) {
+            continue;
+        }
+        for name in base
+            .getattr(intern!(py, "__dict__"))?
+            .call_method0(intern!(py, "keys"))?
+            .iter()?
+        {
+            let name = name?.extract::<String>()?;
+            if !name.starts_with("__") {
+                defined.push(name);
+            }
+        }
+    }
+
+    let class_name = qualified_name(py, class)?;
+
+    Ok(missing
+        .iter()
+        .map(|name| {
+            let mut problem = format!("`{class_name}` does not implement `{name}`");
+            if let Some(candidate) = defined
+                .iter()
+                .filter_map(|candidate| {
+                    let distance = distance(name, candidate)?;
+                    Some((distance, candidate))
+                })
+                .min()
+                .map(|(_, candidate)| candidate)
+            {
+                problem.push_str(&format!(" (did you mean to name `{candidate}` `{name}`?)"));
+            }
+            problem
+        })
+        .collect())
+}
+
+/// Warn about classes defined in the app module which implement one of the export `protocols` but aren't named
+/// after it (nor among the classes the runtime will otherwise `use`), and so will never be used.
+fn warn_unused(
+    py: Python,
+    app: &Bound<PyModule>,
+    protocols: &HashSet<&str>,
+    used: &HashSet<*mut ffi::PyObject>,
+) -> PyResult<()> {
+    let app_name = app.name()?.extract::<String>()?;
+    let type_type = py.get_type_bound::<PyType>();
+
+    for (name, value) in app.dict().iter() {
+        let name = name.extract::<String>()?;
+        if protocols.contains(name.as_str())
+            || used.contains(&value.as_ptr())
+            || !value.is_instance(&type_type)?
+            || value
+                .getattr(intern!(py, "__module__"))?
+                .extract::<String>()?
+                != app_name
+        {
+            continue;
+        }
+
+        for base in value
+            .getattr(intern!(py, "__mro__"))?
+            .downcast_into::<PyTuple>()?
+            .iter()
+        {
+            let base_name = base.getattr(intern!(py, "__name__"))?.extract::<String>()?;
+            if is_protocol(py, &base) && protocols.contains(base_name.as_str()) {
+                eprintln!(
+                    "warning: `{app_name}.{name}` implements the `{base_name}` protocol but will never be used; \
+                     rename it to `{base_name}` if it's meant to implement that export"
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Return whether `class` is a `typing.Protocol` itself rather than a concrete class implementing one.
+fn is_protocol(py: Python, class: &Bound<PyAny>) -> bool {
+    class
+        .getattr(intern!(py, "__dict__"))
+        .and_then(|dict| dict.get_item(intern!(py, "_is_protocol")))
+        .and_then(|value| value.is_truthy())
+        .unwrap_or(false)
+}
+
+fn qualified_name(py: Python, class: &Bound<PyAny>) -> PyResult<String> {
+    Ok(format!(
+        "{}.{}",
+        class.getattr(intern!(py, "__module__"))?,
+        class.getattr(intern!(py, "__qualname__"))?
+    ))
+}
+
+/// Return how different `candidate` is from `name`, or `None` if they're too different for `candidate` to be a
+/// plausible misspelling of `name`.
+///
+/// Names which only differ in case and underscores (e.g. `takeSnapshot` vs. `take_snapshot`) are considered
+/// closest, followed by those within a small edit distance of each other.
+fn distance(name: &str, candidate: &str) -> Option<usize> {
+    let normalize = |s: &str| {
+        s.chars()
+            .filter(|&c| c != '_')
+            .flat_map(char::to_lowercase)
+            .collect::<Vec<_>>()
+    };
+
+    if normalize(name) == normalize(candidate) {
+        return Some(0);
+    }
+
+    let name = name.chars().collect::<Vec<_>>();
+    let candidate = candidate.chars().collect::<Vec<_>>();
+
+    // Levenshtein distance, keeping only the previous row of the table.
+    let mut previous = (0..=candidate.len()).collect::<Vec<_>>();
+    for (i, a) in name.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in candidate.iter().enumerate() {
+            current.push(
+                (previous[j] + usize::from(a != b))
+                    .min(previous[j + 1] + 1)
+                    .min(current[j] + 1),
+            );
+        }
+        previous = current;
+    }
+
+    let distance = previous[candidate.len()];
+    (distance <= (name.len() / 4).max(1)).then_some(distance + 1)
+}
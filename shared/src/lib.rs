@@ -1,5 +1,27 @@
+/// Version of the `componentize-py:init` interface (see `wit/init.wit`) spoken by this build.
+///
+/// The CLI and the runtime must agree on this exactly.  Bump it whenever anything in the `exports` interface changes,
+/// including adding a field to `symbols` or a case to one of its variants.  The `version` function itself must never
+/// change, since it's how the CLI identifies which version a given runtime speaks.
+pub const INIT_VERSION: u32 = 1;
+
 #[repr(u8)]
 pub enum ReturnStyle {
     Normal,
     Result,
 }
+
+/// Element type of a numeric `list<T>` which may be lowered directly from an object supporting the buffer protocol
+///
+/// `list<u8>` and `list<s8>` are handled separately since they're always lowered as bytes.
+#[repr(u8)]
+pub enum NumberKind {
+    S16,
+    U16,
+    S32,
+    U32,
+    S64,
+    U64,
+    F32,
+    F64,
+}
@@ -0,0 +1,310 @@
+//! A structured description of the Python API generated for a world, as returned by `describe_world` and printed by
+//! the `summary` subcommand.
+//!
+//! This allows tools such as documentation generators and linters to inspect the bindings without generating and
+//! parsing the Python code.  Types are named as they would be in the generated code, qualified by their modules
+//! (e.g. `types.Fields`), and paths are relative to the world module (e.g. `imports.types.Fields.get`).
+
+use {
+    serde::Serialize,
+    std::fmt::{self, Display, Formatter},
+};
+
+/// How to print the description of a world's API
+#[derive(clap::ValueEnum, Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// One line per interface, type, and function, resembling the generated declarations.
+    #[default]
+    Text,
+    /// A single JSON object, as serialized from `WorldApi`.
+    Json,
+}
+
+/// The Python API generated for a world
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct WorldApi {
+    /// The world's fully qualified WIT name, e.g. `wasi:http/proxy@0.2.0`
+    pub world: String,
+    /// The name of the top-level module containing the bindings
+    pub module: String,
+    pub interfaces: Vec<InterfaceApi>,
+    /// Named types, in an order such that each appears after any it refers to
+    pub types: Vec<TypeApi>,
+    pub functions: Vec<FunctionApi>,
+}
+
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Direction {
+    Import,
+    Export,
+}
+
+/// An interface imported or exported by the world
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct InterfaceApi {
+    /// The interface's fully qualified WIT name, e.g. `wasi:http/types@0.2.0`
+    pub name: String,
+    pub direction: Direction,
+    /// The path of the module generated for the interface, e.g. `imports.types`
+    pub module: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docs: Option<String>,
+}
+
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TypeKind {
+    Record,
+    Variant,
+    Enum,
+    Flags,
+    Resource,
+    /// A name for another type, e.g. `type headers = list<tuple<string, string>>`
+    Alias,
+}
+
+/// A named type, which is generated as a class (or, for an alias, a variable)
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct TypeApi {
+    /// The path of the class, e.g. `imports.types.Fields`
+    pub path: String,
+    /// The type's name in WIT, e.g. `fields`
+    pub wit_name: String,
+    pub kind: TypeKind,
+    /// The fields of a record
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<Param>,
+    /// The cases of a variant, enum, or flags type
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub cases: Vec<Case>,
+    /// The type an alias refers to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docs: Option<String>,
+    /// The paths of the other named types this one refers to
+    pub uses: Vec<String>,
+}
+
+/// A parameter of a function or a field of a record
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Param {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+/// A case of a variant (in which case it's a class of its own), an enum, or a flags type
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Case {
+    pub name: String,
+    /// The type of the case's payload, if any
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub ty: Option<String>,
+}
+
+#[derive(Serialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum FunctionKind {
+    Freestanding,
+    Method,
+    Static,
+    Constructor,
+}
+
+/// A function or method, either called by the app (an import) or implemented by it (an export)
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct FunctionApi {
+    /// The path of the function, e.g. `imports.types.Fields.get` or `exports.Handler.handle`
+    pub path: String,
+    /// The function's name in WIT, e.g. `[method]fields.get`
+    pub wit_name: String,
+    pub direction: Direction,
+    pub kind: FunctionKind,
+    /// The parameters, not including `self` or `cls`
+    pub params: Vec<Param>,
+    pub returns: String,
+    /// The payload type of the `Err` raised by the function, if its WIT return type is a `result`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raises: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub docs: Option<String>,
+    /// The paths of the named types used by the parameters and return type
+    pub uses: Vec<String>,
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Import => "import",
+            Self::Export => "export",
+        })
+    }
+}
+
+impl Display for WorldApi {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let join = |items: Vec<String>| items.join(", ");
+
+        writeln!(f, "world {} (module {})", self.world, self.module)?;
+
+        for interface in &self.interfaces {
+            writeln!(
+                f,
+                "{} {}: {}",
+                interface.direction, interface.name, interface.module
+            )?;
+        }
+
+        for ty in &self.types {
+            let path = &ty.path;
+            match ty.kind {
+                TypeKind::Record => writeln!(
+                    f,
+                    "record {path}({})",
+                    join(
+                        ty.fields
+                            .iter()
+                            .map(|field| format!("{}: {}", field.name, field.ty))
+                            .collect()
+                    )
+                )?,
+                TypeKind::Variant | TypeKind::Enum | TypeKind::Flags => writeln!(
+                    f,
+                    "{} {path}({})",
+                    match ty.kind {
+                        TypeKind::Variant => "variant",
+                        TypeKind::Enum => "enum",
+                        _ => "flags",
+                    },
+                    join(
+                        ty.cases
+                            .iter()
+                            .map(|case| match &case.ty {
+                                Some(ty) => format!("{}({ty})", case.name),
+                                None => case.name.clone(),
+                            })
+                            .collect()
+                    )
+                )?,
+                TypeKind::Resource => writeln!(f, "resource {path}")?,
+                TypeKind::Alias => writeln!(
+                    f,
+                    "type {path} = {}",
+                    ty.target.as_deref().unwrap_or_default()
+                )?,
+            }
+        }
+
+        for function in &self.functions {
+            let receiver = match function.kind {
+                FunctionKind::Method | FunctionKind::Constructor => Some("self".to_owned()),
+                FunctionKind::Static => Some("cls".to_owned()),
+                FunctionKind::Freestanding => None,
+            };
+            writeln!(
+                f,
+                "{} def {}({}) -> {}{}",
+                function.direction,
+                function.path,
+                join(
+                    receiver
+                        .into_iter()
+                        .chain(
+                            function
+                                .params
+                                .iter()
+                                .map(|param| format!("{}: {}", param.name, param.ty))
+                        )
+                        .collect()
+                ),
+                function.returns,
+                function
+                    .raises
+                    .as_ref()
+                    .map(|raises| format!(" raises {raises}"))
+                    .unwrap_or_default()
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::NamingStyle, std::path::Path};
+
+    fn describe() -> WorldApi {
+        crate::describe_world(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("src/test/golden/wit/resources.wit"),
+            None,
+            None,
+            &[],
+            false,
+            None,
+            &Default::default(),
+            &Default::default(),
+            NamingStyle::default(),
+            Default::default(),
+            &[],
+        )
+        .unwrap()
+        .0
+    }
+
+    #[test]
+    fn describe_resources() {
+        let api = describe();
+        assert_eq!(api.world, "componentize-py:resources/resources");
+        assert_eq!(api.module, "resources");
+
+        let text = api.to_string();
+        assert!(text.contains("import componentize-py:resources/files: imports.files\n"));
+        assert!(text.contains("record imports.files.Metadata(size: int, readonly: bool)\n"));
+        assert!(text.contains("variant imports.files.Error(NotFound, Denied(str))\n"));
+        assert!(text.contains(
+            "import def imports.files.File.read(self, len: int) -> bytes raises files.Error\n"
+        ));
+        assert!(text.contains("import def imports.files.File.stat(cls, path: str)"));
+
+        let snapshot = api
+            .types
+            .iter()
+            .find(|ty| ty.path == "exports.sessions.Snapshot")
+            .unwrap();
+        assert!(snapshot
+            .uses
+            .iter()
+            .any(|path| path == "exports.sessions.Session"));
+
+        let take_snapshot = api
+            .functions
+            .iter()
+            .find(|function| function.wit_name == "take-snapshot")
+            .unwrap();
+        assert_eq!(take_snapshot.path, "exports.Sessions.take_snapshot");
+        assert_eq!(take_snapshot.direction, Direction::Export);
+    }
+
+    #[test]
+    fn json() {
+        let json = serde_json::to_value(describe()).unwrap();
+        let metadata = json["types"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|ty| ty["wit-name"] == "metadata")
+            .unwrap();
+        assert_eq!(metadata["kind"], "record");
+        assert_eq!(metadata["fields"][0]["name"], "size");
+        assert_eq!(metadata["fields"][0]["type"], "int");
+        assert_eq!(metadata["docs"], "Metadata describing a file.");
+        assert!(metadata.get("cases").is_none());
+    }
+}
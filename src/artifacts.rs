@@ -0,0 +1,305 @@
+//! The prebuilt artifacts from which components are built: the `componentize-py` runtime, CPython, and the WASI
+//! SDK libraries they link against, plus the Python standard library, helper utilities, and the WASI adapter.
+//!
+//! These are normally embedded in `componentize-py` at build time, which requires the WASI SDK and a nightly Rust
+//! toolchain.  Since the artifacts are the same regardless of the host platform, packagers for hosts where that's
+//! impractical (e.g. musl or ARM Linux) may instead build with the `slim` feature enabled (or, equivalently, with
+//! `COMPONENTIZE_PY_EXTERNAL_ARTIFACTS` set), in which case nothing is embedded and the artifacts are read at run
+//! time from a directory written by `export` (i.e. the `export-artifacts` subcommand) using another build.  This
+//! also makes for much faster builds when working on parts of `componentize-py` other than the runtime.  Unless
+//! told otherwise, such builds look for the directory in the artifact cache, i.e. `util::cache_dir()/artifacts/`
+//! followed by the version of `componentize-py`, where a distribution channel which fetches the artifacts
+//! separately may place them.  An artifact directory contains one file per artifact plus a
+//! `SHA256SUMS` file, against which each artifact is checked before use.  If `COMPONENTIZE_PY_ARTIFACT_CHECKSUMS`
+//! named a file in the same format at build time, the checksums it lists are used instead, pinning the artifacts
+//! the build accepts.
+//!
+//! Artifacts are only ever read from the local filesystem; fetching them (e.g. from a release) is left to the
+//! packager.
+
+use {
+    crate::util,
+    anyhow::{bail, Context, Result},
+    sha2::{Digest, Sha256},
+    std::{
+        borrow::Cow,
+        collections::HashMap,
+        env,
+        fmt::Write as _,
+        fs,
+        io::Cursor,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Name of the file in an artifact directory listing the SHA-256 digest of each artifact, in the format used by
+/// `sha256sum`
+pub const CHECKSUMS: &str = "SHA256SUMS";
+
+/// Environment variable naming an artifact directory, for use when none is specified explicitly
+const DIR_VAR: &str = "COMPONENTIZE_PY_ARTIFACT_DIR";
+
+/// Whether this build was made with the `slim` feature or `COMPONENTIZE_PY_EXTERNAL_ARTIFACTS` set, and thus has
+/// nothing embedded
+const EXTERNAL: bool =
+    cfg!(feature = "slim") || option_env!("COMPONENTIZE_PY_EXTERNAL_ARTIFACTS").is_some();
+
+/// Checksums pinned at build time using `COMPONENTIZE_PY_ARTIFACT_CHECKSUMS`, if any
+static PINNED_CHECKSUMS: &str = include_str!(concat!(env!("OUT_DIR"), "/artifact-checksums.txt"));
+
+pub const PYTHON_STANDARD_LIBRARY: &str = "python-lib.tar.zst";
+
+pub const HELPER_UTILS: &str = "bundled.tar.zst";
+
+pub const WASI_ADAPTER: &str = "wasi_snapshot_preview1.reactor.wasm.zst";
+
+/// The libraries linked into every component, in order
+static LIBRARIES: &[&str] = &[
+    "libcomponentize_py_runtime.so",
+    "libpython3.12.so",
+    "libc.so",
+    "libwasi-emulated-mman.so",
+    "libwasi-emulated-process-clocks.so",
+    "libwasi-emulated-getpid.so",
+    "libwasi-emulated-signal.so",
+    "libc++.so",
+    "libc++abi.so",
+];
+
+/// The file name and (zstd-compressed) contents of each embedded artifact
+static EMBEDDED: &[(&str, &[u8])] = &[
+    (
+        "libcomponentize_py_runtime.so.zst",
+        include_bytes!(concat!(
+            env!("OUT_DIR"),
+            "/libcomponentize_py_runtime.so.zst"
+        )),
+    ),
+    (
+        "libpython3.12.so.zst",
+        include_bytes!(concat!(env!("OUT_DIR"), "/libpython3.12.so.zst")),
+    ),
+    (
+        "libc.so.zst",
+        include_bytes!(concat!(env!("OUT_DIR"), "/libc.so.zst")),
+    ),
+    (
+        "libwasi-emulated-mman.so.zst",
+        include_bytes!(concat!(env!("OUT_DIR"), "/libwasi-emulated-mman.so.zst")),
+    ),
+    (
+        "libwasi-emulated-process-clocks.so.zst",
+        include_bytes!(concat!(
+            env!("OUT_DIR"),
+            "/libwasi-emulated-process-clocks.so.zst"
+        )),
+    ),
+    (
+        "libwasi-emulated-getpid.so.zst",
+        include_bytes!(concat!(env!("OUT_DIR"), "/libwasi-emulated-getpid.so.zst")),
+    ),
+    (
+        "libwasi-emulated-signal.so.zst",
+        include_bytes!(concat!(env!("OUT_DIR"), "/libwasi-emulated-signal.so.zst")),
+    ),
+    (
+        "libc++.so.zst",
+        include_bytes!(concat!(env!("OUT_DIR"), "/libc++.so.zst")),
+    ),
+    (
+        "libc++abi.so.zst",
+        include_bytes!(concat!(env!("OUT_DIR"), "/libc++abi.so.zst")),
+    ),
+    (
+        PYTHON_STANDARD_LIBRARY,
+        include_bytes!(concat!(env!("OUT_DIR"), "/python-lib.tar.zst")),
+    ),
+    (
+        HELPER_UTILS,
+        include_bytes!(concat!(env!("OUT_DIR"), "/bundled.tar.zst")),
+    ),
+    (
+        WASI_ADAPTER,
+        include_bytes!(concat!(
+            env!("OUT_DIR"),
+            "/wasi_snapshot_preview1.reactor.wasm.zst"
+        )),
+    ),
+];
+
+/// Return the directory in which builds without embedded artifacts look for them by default, if a cache directory
+/// could be determined.
+///
+/// This is versioned since the artifacts are only compatible with the version of `componentize-py` they came from.
+pub fn cache_dir() -> Option<PathBuf> {
+    util::cache_dir().map(|dir| dir.join("artifacts").join(env!("CARGO_PKG_VERSION")))
+}
+
+/// A complete set of artifacts, each in compressed form
+pub struct Artifacts {
+    files: HashMap<&'static str, Cow<'static, [u8]>>,
+}
+
+impl Artifacts {
+    /// Return the artifacts in `dir` if specified (or else in the directory named by `COMPONENTIZE_PY_ARTIFACT_DIR`,
+    /// if set), or else the embedded ones.
+    ///
+    /// If nothing is embedded, the artifact cache (see `cache_dir`) is used in place of the embedded artifacts.
+    pub fn new(dir: Option<&Path>) -> Result<Self> {
+        match dir
+            .map(Path::to_owned)
+            .or_else(|| env::var_os(DIR_VAR).map(PathBuf::from))
+            .or_else(|| cache_dir().filter(|dir| EXTERNAL && dir.is_dir()))
+        {
+            Some(dir) => Self::load(&dir),
+            None if EXTERNAL => bail!(
+                "this build of `componentize-py` does not embed its runtime artifacts; please specify a \
+                 directory containing them using `--artifact-dir` or the `{DIR_VAR}` environment variable, or \
+                 export them to {} using another build",
+                cache_dir().map_or_else(
+                    || "the artifact cache".to_owned(),
+                    |dir| dir.display().to_string()
+                )
+            ),
+            None => Ok(Self {
+                files: EMBEDDED
+                    .iter()
+                    .map(|(name, contents)| (*name, Cow::Borrowed(*contents)))
+                    .collect(),
+            }),
+        }
+    }
+
+    fn load(dir: &Path) -> Result<Self> {
+        let checksums = if PINNED_CHECKSUMS.trim().is_empty() {
+            let path = dir.join(CHECKSUMS);
+            parse_checksums(&fs::read_to_string(&path).with_context(|| {
+                format!("unable to read artifact checksums from {}", path.display())
+            })?)
+            .with_context(|| path.display().to_string())?
+        } else {
+            parse_checksums(PINNED_CHECKSUMS).context("invalid pinned artifact checksums")?
+        };
+
+        let files = EMBEDDED
+            .iter()
+            .map(|(name, _)| {
+                let path = dir.join(name);
+                let contents = fs::read(&path)
+                    .with_context(|| format!("unable to read artifact {}", path.display()))?;
+                let Some(expected) = checksums.get(name) else {
+                    bail!("no checksum found for artifact `{name}`");
+                };
+                let actual = format!("{:x}", Sha256::digest(&contents));
+                if actual != *expected {
+                    bail!(
+                        "checksum mismatch for artifact {}: expected {expected}, got {actual}",
+                        path.display()
+                    );
+                }
+                Ok((*name, Cow::Owned(contents)))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { files })
+    }
+
+    /// Return the compressed contents of the artifact with the specified file name.
+    pub fn get(&self, name: &str) -> &[u8] {
+        &self.files[name]
+    }
+
+    /// Return the decompressed contents of the artifact with the specified file name.
+    pub fn decompress(&self, name: &str) -> Result<Vec<u8>> {
+        zstd::decode_all(Cursor::new(self.get(name)))
+            .with_context(|| format!("unable to decompress artifact `{name}`"))
+    }
+
+    /// Return the name and compressed contents of each library to be linked into every component, in order.
+    pub fn libraries(&self) -> Vec<(&'static str, &[u8])> {
+        LIBRARIES
+            .iter()
+            .map(|name| (*name, self.get(&format!("{name}.zst"))))
+            .collect()
+    }
+
+    /// Feed every artifact into `hasher`, along with the version of `componentize-py`, so that cached build
+    /// artifacts are invalidated whenever any of them changes.
+    pub fn digest(&self, hasher: &mut Sha256) {
+        hasher.update(env!("CARGO_PKG_VERSION"));
+        for (name, _) in EMBEDDED {
+            hasher.update(name);
+            hasher.update(self.get(name));
+        }
+    }
+
+    /// Write each artifact to `dir` (creating it if necessary), along with a `SHA256SUMS` file listing their
+    /// digests, in the layout expected by `--artifact-dir`.
+    pub fn export(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir).with_context(|| dir.display().to_string())?;
+
+        let mut checksums = String::new();
+        for (name, _) in EMBEDDED {
+            let contents = self.get(name);
+            let path = dir.join(name);
+            fs::write(&path, contents).with_context(|| path.display().to_string())?;
+            writeln!(checksums, "{:x}  {name}", Sha256::digest(contents))?;
+        }
+
+        let path = dir.join(CHECKSUMS);
+        fs::write(&path, checksums).with_context(|| path.display().to_string())?;
+
+        Ok(())
+    }
+}
+
+/// Parse a file in the format written by `sha256sum`, returning the digest for each file name.
+fn parse_checksums(checksums: &str) -> Result<HashMap<&str, &str>> {
+    checksums
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let Some((digest, name)) = line.split_once(char::is_whitespace) else {
+                bail!("expected `<digest>  <file name>`; got `{line}`");
+            };
+            // `sha256sum` marks files read in binary mode with a `*`.
+            Ok((name.trim().trim_start_matches('*'), digest))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exported_artifacts_verified() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        Artifacts::new(None)?.export(dir.path())?;
+
+        let artifacts = Artifacts::load(dir.path())?;
+        assert_eq!(
+            Artifacts::new(None)?.get(WASI_ADAPTER),
+            artifacts.get(WASI_ADAPTER)
+        );
+
+        fs::write(dir.path().join(HELPER_UTILS), b"tampered")?;
+        let error = Artifacts::load(dir.path()).err().unwrap();
+        assert!(
+            format!("{error:?}").contains("checksum mismatch"),
+            "{error:?}"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn checksums_parsed() -> Result<()> {
+        let checksums = parse_checksums("abc123  libc.so.zst\ndef456 *bundled.tar.zst\n\n")?;
+        assert_eq!(Some(&"abc123"), checksums.get("libc.so.zst"));
+        assert_eq!(Some(&"def456"), checksums.get("bundled.tar.zst"));
+        assert!(parse_checksums("abc123").is_err());
+
+        Ok(())
+    }
+}
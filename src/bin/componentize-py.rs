@@ -1,6 +1,22 @@
-use {anyhow::Result, std::env};
+use {
+    componentize_py::report::Reported,
+    std::{env, process::ExitCode},
+};
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
     pretty_env_logger::init_timed();
-    componentize_py::command::run(env::args_os())
+
+    let result = componentize_py::command::install_interrupt_handler()
+        .and_then(|()| componentize_py::command::run(env::args_os()));
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            // `run` prints some errors itself, e.g. when asked to report them as JSON.
+            if !error.is::<Reported>() {
+                eprintln!("Error: {error:?}");
+            }
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -4,7 +4,7 @@ use {
         summary::{Direction, MyFunction, Summary},
         util::Types as _,
     },
-    componentize_py_shared::ReturnStyle,
+    componentize_py_shared::{NumberKind, ReturnStyle},
     indexmap::IndexSet,
     once_cell::sync::Lazy,
     std::collections::HashMap,
@@ -83,6 +83,11 @@ pub static IMPORT_SIGNATURES: &[(&str, &[ValType], &[ValType])] = &[
         &[ValType::I32; 4],
         &[ValType::I32],
     ),
+    (
+        "componentize-py#AsSequence",
+        &[ValType::I32; 2],
+        &[ValType::I32],
+    ),
     (
         "componentize-py#GetListLength",
         &[ValType::I32; 2],
@@ -138,11 +143,20 @@ pub static IMPORT_SIGNATURES: &[(&str, &[ValType], &[ValType])] = &[
         &[ValType::I32; 3],
         &[ValType::I32],
     ),
-    ("componentize-py#MakeList", &[ValType::I32], &[ValType::I32]),
-    ("componentize-py#ListAppend", &[ValType::I32; 3], &[]),
+    (
+        "componentize-py#MakeList",
+        &[ValType::I32; 2],
+        &[ValType::I32],
+    ),
+    ("componentize-py#ListSet", &[ValType::I32; 4], &[]),
     ("componentize-py#None", &[ValType::I32], &[ValType::I32]),
     ("componentize-py#Init", &[ValType::I32; 4], &[ValType::I32]),
     ("componentize-py#GetBytes", &[ValType::I32; 4], &[]),
+    (
+        "componentize-py#GetNumbers",
+        &[ValType::I32; 5],
+        &[ValType::I32],
+    ),
     (
         "componentize-py#MakeBytes",
         &[ValType::I32; 3],
@@ -158,6 +172,9 @@ pub static IMPORT_SIGNATURES: &[(&str, &[ValType], &[ValType])] = &[
         &[ValType::I32; 5],
         &[ValType::I32],
     ),
+    ("componentize-py#ResourceDtor", &[ValType::I32], &[]),
+    ("componentize-py#ReleaseGil", &[ValType::I32], &[]),
+    ("componentize-py#AcquireGil", &[ValType::I32], &[]),
     ("cabi_realloc", &[ValType::I32; 4], &[ValType::I32]),
 ];
 
@@ -317,7 +334,17 @@ impl<'a> FunctionBindgen<'a> {
             self.get_stack();
         }
 
+        // The parameters have been lowered, so release the GIL for the duration of the call (allowing e.g. signal
+        // handlers and other threads to run) and reacquire it before lifting the results.
+        self.push(Ins::LocalGet(context));
+        self.push(Ins::Call(
+            *IMPORTS.get("componentize-py#ReleaseGil").unwrap(),
+        ));
         self.push(Ins::Call(index));
+        self.push(Ins::LocalGet(context));
+        self.push(Ins::Call(
+            *IMPORTS.get("componentize-py#AcquireGil").unwrap(),
+        ));
 
         if self.results_abi.flattened.len() <= MAX_FLAT_RESULTS {
             let locals = self
@@ -732,11 +759,21 @@ impl<'a> FunctionBindgen<'a> {
                 TypeDefKind::List(ty) => {
                     let abi = abi::abi(self.resolve, *ty);
 
+                    let sequence = self.push_local(ValType::I32);
                     let length = self.push_local(ValType::I32);
                     let destination = self.push_local(ValType::I32);
 
+                    // Arbitrary iterables (e.g. generators) are collected into a `list` first so that we can
+                    // query their length and index them below.
                     self.push(Ins::LocalGet(context));
                     self.push(Ins::LocalGet(value));
+                    self.push(Ins::Call(
+                        *IMPORTS.get("componentize-py#AsSequence").unwrap(),
+                    ));
+                    self.push(Ins::LocalSet(sequence));
+
+                    self.push(Ins::LocalGet(context));
+                    self.push(Ins::LocalGet(sequence));
                     self.push(Ins::Call(
                         *IMPORTS.get("componentize-py#GetListLength").unwrap(),
                     ));
@@ -751,57 +788,27 @@ impl<'a> FunctionBindgen<'a> {
 
                     if let Type::U8 | Type::S8 = ty {
                         self.push(Ins::LocalGet(context));
-                        self.push(Ins::LocalGet(value));
+                        self.push(Ins::LocalGet(sequence));
                         self.push(Ins::LocalGet(destination));
                         self.push(Ins::LocalGet(length));
                         self.push(Ins::Call(*IMPORTS.get("componentize-py#GetBytes").unwrap()));
-                    } else {
-                        let index = self.push_local(ValType::I32);
-                        let element_value = self.push_local(ValType::I32);
-                        let element_destination = self.push_local(ValType::I32);
-
-                        self.push(Ins::I32Const(0));
-                        self.push(Ins::LocalSet(index));
-
-                        self.push(Ins::Loop(BlockType::Empty));
-
-                        self.push(Ins::LocalGet(index));
-                        self.push(Ins::LocalGet(length));
-                        self.push(Ins::I32Ne);
-
-                        self.push(Ins::If(BlockType::Empty));
-
+                    } else if let Some(kind) = number_kind(*ty) {
+                        // Try copying the elements directly from e.g. a NumPy array, falling back to lowering
+                        // them one at a time if that's not possible.
                         self.push(Ins::LocalGet(context));
-                        self.push(Ins::LocalGet(value));
-                        self.push(Ins::LocalGet(index));
+                        self.push(Ins::LocalGet(sequence));
+                        self.push(Ins::LocalGet(destination));
+                        self.push(Ins::LocalGet(length));
+                        self.push(Ins::I32Const(kind as _));
                         self.push(Ins::Call(
-                            *IMPORTS.get("componentize-py#GetListElement").unwrap(),
+                            *IMPORTS.get("componentize-py#GetNumbers").unwrap(),
                         ));
-                        self.push(Ins::LocalSet(element_value));
-
-                        self.push(Ins::LocalGet(destination));
-                        self.push(Ins::LocalGet(index));
-                        self.push(Ins::I32Const(abi.size.try_into().unwrap()));
-                        self.push(Ins::I32Mul);
-                        self.push(Ins::I32Add);
-                        self.push(Ins::LocalSet(element_destination));
-
-                        self.store(*ty, context, element_value, element_destination);
-
-                        self.push(Ins::LocalGet(index));
-                        self.push(Ins::I32Const(1));
-                        self.push(Ins::I32Add);
-                        self.push(Ins::LocalSet(index));
-
-                        self.push(Ins::Br(1));
-
-                        self.push(Ins::End);
-
+                        self.push(Ins::I32Eqz);
+                        self.push(Ins::If(BlockType::Empty));
+                        self.to_canon_list_elements(*ty, context, sequence, length, destination);
                         self.push(Ins::End);
-
-                        self.pop_local(element_destination, ValType::I32);
-                        self.pop_local(element_value, ValType::I32);
-                        self.pop_local(index, ValType::I32);
+                    } else {
+                        self.to_canon_list_elements(*ty, context, sequence, length, destination);
                     }
 
                     self.push(Ins::LocalGet(destination));
@@ -809,6 +816,7 @@ impl<'a> FunctionBindgen<'a> {
 
                     self.pop_local(destination, ValType::I32);
                     self.pop_local(length, ValType::I32);
+                    self.pop_local(sequence, ValType::I32);
                 }
                 TypeDefKind::Handle(handle) => {
                     self.marshal_handle(handle, context, value);
@@ -822,6 +830,64 @@ impl<'a> FunctionBindgen<'a> {
         }
     }
 
+    fn to_canon_list_elements(
+        &mut self,
+        ty: Type,
+        context: u32,
+        sequence: u32,
+        length: u32,
+        destination: u32,
+    ) {
+        let abi = abi::abi(self.resolve, ty);
+
+        let index = self.push_local(ValType::I32);
+        let element_value = self.push_local(ValType::I32);
+        let element_destination = self.push_local(ValType::I32);
+
+        self.push(Ins::I32Const(0));
+        self.push(Ins::LocalSet(index));
+
+        self.push(Ins::Loop(BlockType::Empty));
+
+        self.push(Ins::LocalGet(index));
+        self.push(Ins::LocalGet(length));
+        self.push(Ins::I32Ne);
+
+        self.push(Ins::If(BlockType::Empty));
+
+        self.push(Ins::LocalGet(context));
+        self.push(Ins::LocalGet(sequence));
+        self.push(Ins::LocalGet(index));
+        self.push(Ins::Call(
+            *IMPORTS.get("componentize-py#GetListElement").unwrap(),
+        ));
+        self.push(Ins::LocalSet(element_value));
+
+        self.push(Ins::LocalGet(destination));
+        self.push(Ins::LocalGet(index));
+        self.push(Ins::I32Const(abi.size.try_into().unwrap()));
+        self.push(Ins::I32Mul);
+        self.push(Ins::I32Add);
+        self.push(Ins::LocalSet(element_destination));
+
+        self.store(ty, context, element_value, element_destination);
+
+        self.push(Ins::LocalGet(index));
+        self.push(Ins::I32Const(1));
+        self.push(Ins::I32Add);
+        self.push(Ins::LocalSet(index));
+
+        self.push(Ins::Br(1));
+
+        self.push(Ins::End);
+
+        self.push(Ins::End);
+
+        self.pop_local(element_destination, ValType::I32);
+        self.pop_local(element_value, ValType::I32);
+        self.pop_local(index, ValType::I32);
+    }
+
     fn marshal_handle(&mut self, handle: &Handle, context: u32, value: u32) {
         let (borrow, resource) = match handle {
             Handle::Own(resource) => (0, resource),
@@ -1588,6 +1654,7 @@ impl<'a> FunctionBindgen<'a> {
                         let destination = self.push_local(ValType::I32);
 
                         self.push(Ins::LocalGet(context));
+                        self.push(Ins::LocalGet(length));
                         self.push(Ins::Call(*IMPORTS.get("componentize-py#MakeList").unwrap()));
                         self.push(Ins::LocalSet(destination));
 
@@ -1611,12 +1678,11 @@ impl<'a> FunctionBindgen<'a> {
 
                         self.push(Ins::LocalGet(context));
                         self.push(Ins::LocalGet(destination));
+                        self.push(Ins::LocalGet(index));
 
                         self.load(*ty, context, element_source);
 
-                        self.push(Ins::Call(
-                            *IMPORTS.get("componentize-py#ListAppend").unwrap(),
-                        ));
+                        self.push(Ins::Call(*IMPORTS.get("componentize-py#ListSet").unwrap()));
 
                         self.push(Ins::LocalGet(index));
                         self.push(Ins::I32Const(1));
@@ -2685,3 +2751,18 @@ pub fn dealias(resolve: &Resolve, mut id: TypeId) -> TypeId {
         }
     }
 }
+
+/// Return the `NumberKind` corresponding to `ty`, if it's a numeric type other than `u8` or `s8`.
+fn number_kind(ty: Type) -> Option<NumberKind> {
+    Some(match ty {
+        Type::S16 => NumberKind::S16,
+        Type::U16 => NumberKind::U16,
+        Type::S32 => NumberKind::S32,
+        Type::U32 => NumberKind::U32,
+        Type::S64 => NumberKind::S64,
+        Type::U64 => NumberKind::U64,
+        Type::F32 => NumberKind::F32,
+        Type::F64 => NumberKind::F64,
+        _ => return None,
+    })
+}
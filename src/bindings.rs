@@ -289,6 +289,37 @@ pub fn make_bindings(
         exports.export(name, ExportKind::Func, dispatch_offset);
     }
 
+    // Export a destructor for each resource defined by the app, which the host will call when the last handle to
+    // an instance is dropped.
+    for function in summary
+        .functions
+        .iter()
+        .filter(|function| matches!(function.kind, FunctionKind::ResourceNew))
+    {
+        let offset = types.len();
+        types.ty().function([ValType::I32], []);
+        functions.function(offset);
+        let interface = function.interface.as_ref().unwrap();
+        let name = format!(
+            "{}#[dtor]{}",
+            resolve
+                .id_of(interface.id)
+                .unwrap_or_else(|| interface.name.to_owned()),
+            function.name
+        );
+        function_names.push((offset, name.clone()));
+        let mut dtor = Function::new([]);
+        dtor.instruction(&Ins::LocalGet(0));
+        dtor.instruction(&Ins::Call(
+            *IMPORTS.get("componentize-py#ResourceDtor").unwrap(),
+        ));
+        dtor.instruction(&Ins::End);
+
+        code.function(&dtor);
+
+        exports.export(&name, ExportKind::Func, offset);
+    }
+
     exports.export(
         "cabi_import_realloc",
         ExportKind::Func,
@@ -0,0 +1,262 @@
+use {
+    crate::{prelink, NamingStyle, Profile, VersionCollisions},
+    anyhow::{Context, Result},
+    serde::Serialize,
+    sha2::{Digest, Sha256},
+    std::{collections::HashMap, fs, path::Path},
+    wit_component::DecodedWasm,
+};
+
+/// Inputs used to build the component in a bundle, recorded in its metadata
+pub struct Inputs<'a> {
+    pub app_name: &'a str,
+    pub wit_path: Option<&'a Path>,
+    pub world: Option<&'a str>,
+    pub primary_package: Option<&'a str>,
+    pub features: &'a [String],
+    pub all_features: bool,
+    pub python_path: &'a [&'a str],
+    pub stub_wasi: bool,
+    pub profile: Profile,
+    pub import_interface_names: &'a HashMap<&'a str, &'a str>,
+    pub export_interface_names: &'a HashMap<&'a str, &'a str>,
+    pub naming_style: NamingStyle,
+    pub version_collisions: VersionCollisions,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Metadata<'a> {
+    componentize_py_version: &'a str,
+    component: ComponentMetadata,
+    build: BuildMetadata<'a>,
+    python_packages: Vec<PythonPackage>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct ComponentMetadata {
+    file: String,
+    sha256: String,
+    size: usize,
+    imports: Vec<String>,
+    exports: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct BuildMetadata<'a> {
+    app_name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    wit_path: Option<&'a Path>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    world: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    primary_package: Option<&'a str>,
+    features: &'a [String],
+    all_features: bool,
+    python_path: &'a [&'a str],
+    stub_wasi: bool,
+    profile: String,
+}
+
+#[derive(Serialize)]
+struct PythonPackage {
+    name: String,
+    version: String,
+}
+
+/// Populate `dir` with supporting files for the component at `component_path`, which is expected to be located in
+/// `dir` already.
+///
+/// This includes Python bindings for the targeted world (if a WIT path was specified or `./wit` exists), a
+/// `metadata.toml` file describing the component and how it was built, plus a `README.md` and (for worlds
+/// `wasmtime` knows how to run) a `run.sh` script.
+pub fn write(dir: &Path, component_path: &Path, inputs: &Inputs) -> Result<()> {
+    let component = fs::read(component_path)?;
+    let file = component_path
+        .file_name()
+        .unwrap()
+        .to_str()
+        .context("non-UTF-8 component file name")?
+        .to_owned();
+
+    let DecodedWasm::Component(resolve, world) = wit_component::decode(&component)? else {
+        unreachable!("expected a component, not a WIT package");
+    };
+    let world = &resolve.worlds[world];
+    let imports = world
+        .imports
+        .keys()
+        .map(|key| resolve.name_world_key(key))
+        .collect();
+    let exports = world
+        .exports
+        .keys()
+        .map(|key| resolve.name_world_key(key))
+        .collect::<Vec<_>>();
+
+    let wit_path = inputs.wit_path.unwrap_or(Path::new("wit"));
+    let bindings = if wit_path.exists() {
+        crate::generate_bindings(
+            wit_path,
+            inputs.world,
+            inputs.primary_package,
+            inputs.features,
+            inputs.all_features,
+            None,
+            &dir.join("bindings"),
+            inputs.import_interface_names,
+            inputs.export_interface_names,
+            Default::default(),
+            inputs.naming_style,
+            inputs.version_collisions,
+            Default::default(),
+        )?;
+        true
+    } else {
+        false
+    };
+
+    let metadata = Metadata {
+        componentize_py_version: env!("CARGO_PKG_VERSION"),
+        component: ComponentMetadata {
+            file: file.clone(),
+            sha256: format!("{:x}", Sha256::digest(&component)),
+            size: component.len(),
+            imports,
+            exports: exports.clone(),
+        },
+        build: BuildMetadata {
+            app_name: inputs.app_name,
+            wit_path: inputs.wit_path,
+            world: inputs.world,
+            primary_package: inputs.primary_package,
+            features: inputs.features,
+            all_features: inputs.all_features,
+            python_path: inputs.python_path,
+            stub_wasi: inputs.stub_wasi,
+            profile: inputs.profile.to_string(),
+        },
+        python_packages: find_python_packages(inputs.python_path)?,
+    };
+
+    fs::write(
+        dir.join("metadata.toml"),
+        toml::to_string_pretty(&metadata)?,
+    )?;
+
+    let exports_interface = |name: &str| {
+        exports
+            .iter()
+            .any(|export| export.split_once('@').map_or(export.as_str(), |(a, _)| a) == name)
+    };
+
+    let command = if exports_interface("wasi:http/incoming-handler") {
+        Some(format!("wasmtime serve -Scli \"$dir/{file}\" \"$@\""))
+    } else if exports_interface("wasi:cli/run") {
+        Some(format!("wasmtime run \"$dir/{file}\" \"$@\""))
+    } else {
+        None
+    };
+
+    let mut readme = format!(
+        "# {app_name}\n\n\
+         This directory was generated by `componentize-py` {version} and contains:\n\n\
+         - `{file}`: the component\n",
+        app_name = inputs.app_name,
+        version = env!("CARGO_PKG_VERSION"),
+    );
+    if bindings {
+        readme.push_str(
+            "- `bindings/`: Python bindings for the world, for use by host-side tooling\n",
+        );
+    }
+    readme.push_str("- `metadata.toml`: the component's imports, exports, and build inputs\n");
+
+    if let Some(command) = &command {
+        let script = dir.join("run.sh");
+        fs::write(
+            &script,
+            format!("#!/bin/sh\nset -e\ndir=\"$(dirname \"$0\")\"\nexec {command}\n"),
+        )?;
+        make_executable(&script)?;
+
+        readme.push_str(&format!(
+            "- `run.sh`: runs the component using `wasmtime`\n\n\
+             ## Running\n\n\
+             ```shell\n\
+             ./run.sh\n\
+             ```\n\n\
+             which is equivalent to:\n\n\
+             ```shell\n\
+             {}\n\
+             ```\n",
+            command.replace("$dir/", "").replace(" \"$@\"", "")
+        ));
+    } else {
+        readme.push_str(
+            "\n## Running\n\n\
+             This component's world is not one `wasmtime` knows how to run directly, so it must be \
+             instantiated by a host which implements its imports, e.g. using `wasmtime-py` or the \
+             `wasmtime` crate.\n",
+        );
+    }
+
+    fs::write(dir.join("README.md"), readme)?;
+
+    Ok(())
+}
+
+/// Find the names and versions of installed distributions (i.e. `*.dist-info` directories) in each `python_path`
+/// directory.
+fn find_python_packages(python_path: &[&str]) -> Result<Vec<PythonPackage>> {
+    let mut packages = Vec::new();
+    for dir in prelink::extract_archives(python_path)? {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("dist-info") {
+                continue;
+            }
+
+            let Ok(metadata) = fs::read_to_string(path.join("METADATA")) else {
+                continue;
+            };
+
+            let field = |name: &str| {
+                metadata.lines().find_map(|line| {
+                    line.strip_prefix(name)
+                        .and_then(|rest| rest.strip_prefix(':'))
+                        .map(|value| value.trim().to_owned())
+                })
+            };
+
+            if let (Some(name), Some(version)) = (field("Name"), field("Version")) {
+                packages.push(PythonPackage { name, version });
+            }
+        }
+    }
+
+    packages.sort_by(|a, b| (&a.name, &a.version).cmp(&(&b.name, &b.version)));
+    packages.dedup_by(|a, b| a.name == b.name && a.version == b.version);
+
+    Ok(packages)
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_mode(permissions.mode() | 0o111);
+    Ok(fs::set_permissions(path, permissions)?)
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
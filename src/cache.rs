@@ -0,0 +1,516 @@
+use {
+    crate::{
+        artifacts::Artifacts, notices, optimize, prelink, util, NamingStyle, Notices, Profile,
+        VersionCollisions,
+    },
+    anyhow::{Context, Result},
+    rayon::prelude::*,
+    sha2::{Digest, Sha256},
+    std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        env, fs, iter,
+        path::{Path, PathBuf},
+        time::{Duration, SystemTime},
+    },
+};
+
+/// Name of the subdirectory of `util::cache_dir()` containing cached components
+pub const COMPONENTS: &str = "components";
+
+/// Extensions of the files holding the output captured while pre-initializing each cached component
+pub const OUTPUT_EXTENSIONS: [&str; 2] = ["stdout", "stderr"];
+
+/// Maximum total size of the cached components, beyond which the least recently used are evicted
+const MAX_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Everything which may affect the result of pre-initializing a component, used to derive a cache key.
+pub struct Inputs<'a> {
+    pub artifacts: &'a Artifacts,
+    pub wit_path: &'a Path,
+    pub world: Option<&'a str>,
+    pub primary_package: Option<&'a str>,
+    pub features: &'a [String],
+    pub all_features: bool,
+    pub python_path: &'a [&'a str],
+    pub excluded_paths: &'a prelink::ExcludedPaths,
+    /// Where the component will be written, which is ignored if it's within `python_path`
+    pub output_path: &'a Path,
+    pub static_libraries: &'a [&'a str],
+    pub dynamic_libraries: &'a [&'a str],
+    pub module_worlds: &'a [(&'a str, &'a str)],
+    pub app_name: &'a str,
+    pub stub_wasi: bool,
+    pub profile: Profile,
+    pub import_interface_names: &'a HashMap<&'a str, &'a str>,
+    pub export_interface_names: &'a HashMap<&'a str, &'a str>,
+    pub naming_style: NamingStyle,
+    pub version_collisions: VersionCollisions,
+    pub map_source_paths: bool,
+    pub optimize: &'a optimize::Options<'a>,
+    pub exception_mappings: &'a [(&'a str, &'a str)],
+    pub diagnostics: bool,
+    pub strict: bool,
+    pub trace_imports: bool,
+    pub shutdown_hook: bool,
+    pub unbuffered: bool,
+    pub embed_stdlib: bool,
+    pub compose: &'a [&'a Path],
+    pub wasi_adapter: Option<&'a Path>,
+    pub import_time_budget: Option<Duration>,
+    pub pre_init_hook: Option<&'a str>,
+    pub metadata: &'a [(&'a str, &'a str)],
+    pub notices: Option<Notices>,
+    pub deterministic: bool,
+}
+
+/// Persistent, content-addressed cache of pre-initialized components.
+///
+/// Entries live under `util::cache_dir()/components` and are keyed by a SHA-256 digest of the build's `Inputs`,
+/// including the full contents of each WIT and `python_path` directory (less the output files and any virtual
+/// environments or Git repositories within them), plus the runtime artifacts (whether embedded in this build of
+/// `componentize-py` or read from an artifact directory).  Each entry also records what the app (including any
+/// pre-init hook) printed while being pre-initialized, so it can be replayed when the entry is reused.
+pub struct BuildCache {
+    path: PathBuf,
+}
+
+/// A component found in the cache, along with the output captured while pre-initializing it
+pub struct Entry {
+    pub component: Vec<u8>,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl BuildCache {
+    /// Return the cache entry for the specified inputs, or `None` if no cache directory could be determined.
+    pub fn new(inputs: &Inputs) -> Result<Option<Self>> {
+        let Some(dir) = util::cache_dir() else {
+            return Ok(None);
+        };
+
+        let mut hasher = Sha256::new();
+        inputs.artifacts.digest(&mut hasher);
+
+        update_str(&mut hasher, inputs.world.unwrap_or(""));
+        update_str(&mut hasher, inputs.primary_package.unwrap_or(""));
+        for feature in inputs.features {
+            update_str(&mut hasher, feature);
+        }
+        update_str(&mut hasher, if inputs.all_features { "all" } else { "" });
+        for (module, world) in inputs.module_worlds {
+            update_str(&mut hasher, module);
+            update_str(&mut hasher, world);
+        }
+        update_str(&mut hasher, inputs.app_name);
+        update_str(&mut hasher, if inputs.stub_wasi { "stub" } else { "" });
+        update_str(&mut hasher, &inputs.profile.to_string());
+        update_str(
+            &mut hasher,
+            if inputs.map_source_paths { "map" } else { "" },
+        );
+        update_str(
+            &mut hasher,
+            if inputs.optimize.strip_debug {
+                "strip"
+            } else {
+                ""
+            },
+        );
+        for names in [
+            inputs.static_libraries,
+            inputs.dynamic_libraries,
+            inputs.optimize.exclude_modules,
+            inputs.optimize.include_modules,
+        ] {
+            update_str(&mut hasher, &names.len().to_string());
+            for name in names {
+                update_str(&mut hasher, name);
+            }
+        }
+        update_str(&mut hasher, &inputs.exception_mappings.len().to_string());
+        for (exception, function) in inputs.exception_mappings {
+            update_str(&mut hasher, exception);
+            update_str(&mut hasher, function);
+        }
+        update_str(
+            &mut hasher,
+            if inputs.diagnostics {
+                "diagnostics"
+            } else {
+                ""
+            },
+        );
+        update_str(&mut hasher, if inputs.strict { "strict" } else { "" });
+        update_str(&mut hasher, if inputs.trace_imports { "trace" } else { "" });
+        update_str(&mut hasher, &inputs.metadata.len().to_string());
+        for (key, value) in inputs.metadata {
+            update_str(&mut hasher, key);
+            update_str(&mut hasher, value);
+        }
+        update_str(
+            &mut hasher,
+            if inputs.notices == Some(Notices::Embed) {
+                "notices"
+            } else {
+                ""
+            },
+        );
+        update_str(
+            &mut hasher,
+            if inputs.shutdown_hook {
+                "shutdown-hook"
+            } else {
+                ""
+            },
+        );
+        update_str(
+            &mut hasher,
+            if inputs.unbuffered { "unbuffered" } else { "" },
+        );
+        update_str(
+            &mut hasher,
+            if inputs.embed_stdlib {
+                "embed-stdlib"
+            } else {
+                ""
+            },
+        );
+        update_str(
+            &mut hasher,
+            if inputs.deterministic {
+                "deterministic"
+            } else {
+                ""
+            },
+        );
+        update_str(&mut hasher, &inputs.naming_style.to_string());
+        update_str(&mut hasher, &inputs.version_collisions.to_string());
+        update_str(
+            &mut hasher,
+            &inputs
+                .import_time_budget
+                .map(|budget| budget.as_secs_f64().to_string())
+                .unwrap_or_default(),
+        );
+        update_str(&mut hasher, inputs.pre_init_hook.unwrap_or(""));
+        for names in [inputs.import_interface_names, inputs.export_interface_names] {
+            for (a, b) in names.iter().collect::<BTreeMap<_, _>>() {
+                update_str(&mut hasher, a);
+                update_str(&mut hasher, b);
+            }
+        }
+
+        update_str(&mut hasher, &inputs.compose.len().to_string());
+        update_str(
+            &mut hasher,
+            if inputs.wasi_adapter.is_some() {
+                "wasi-adapter"
+            } else {
+                ""
+            },
+        );
+        // Note that any WIT directories referenced by `componentize-py.toml` files must be located within the
+        // corresponding package, and thus are covered by the `python_path` digests.  The output (and notices) file
+        // may be too, in which case we ignore it, or else the key would change after every build.
+        let outputs = [
+            inputs.output_path.to_owned(),
+            notices::path(inputs.output_path),
+        ]
+        .iter()
+        .filter_map(|path| {
+            let parent = path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty());
+            Some(
+                parent
+                    .unwrap_or(Path::new("."))
+                    .canonicalize()
+                    .ok()?
+                    .join(path.file_name()?),
+            )
+        })
+        .collect::<Vec<_>>();
+        let none = HashSet::new();
+        for (path, excluded) in iter::once((inputs.wit_path, &none))
+            .chain(
+                inputs
+                    .python_path
+                    .iter()
+                    .map(Path::new)
+                    .zip(inputs.excluded_paths),
+            )
+            .chain(inputs.compose.iter().map(|path| (*path, &none)))
+            .chain(inputs.wasi_adapter.map(|path| (path, &none)))
+        {
+            // Use the canonical path, since that's what `map_source_paths` will embed in the component.
+            let canonical = path.canonicalize().unwrap_or_else(|_| path.to_owned());
+            update_str(&mut hasher, &canonical.display().to_string());
+            hasher.update(digest_tree_excluding(path, excluded, &outputs)?);
+        }
+
+        Ok(Some(Self {
+            path: dir
+                .join(COMPONENTS)
+                .join(format!("{:x}.wasm", hasher.finalize())),
+        }))
+    }
+
+    /// Return the cached component and its output, if present.
+    pub fn load(&self) -> Option<Entry> {
+        let component = fs::read(&self.path).ok()?;
+        // Mark the entry as recently used so `evict` keeps it.
+        let _ = fs::File::options()
+            .write(true)
+            .open(&self.path)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        let [stdout, stderr] = OUTPUT_EXTENSIONS
+            .map(|ext| fs::read(self.path.with_extension(ext)).unwrap_or_default());
+        Some(Entry {
+            component,
+            stdout,
+            stderr,
+        })
+    }
+
+    /// Add the specified component and the output captured while pre-initializing it to the cache, evicting the
+    /// least recently used entries if the cache has grown too large.
+    ///
+    /// Failing to write to the cache is not fatal, so any errors are ignored.
+    pub fn store(&self, component: &[u8], stdout: &[u8], stderr: &[u8]) {
+        let dir = self.path.parent().unwrap();
+        let _ = fs::create_dir_all(dir).and_then(|()| {
+            // Write to a temporary file first and then rename it so that concurrent builds never observe a
+            // partially-written entry.  The component is written last, since its presence marks the entry as
+            // complete.
+            let write = |path: PathBuf, contents: &[u8]| {
+                let temp = path.with_extension(format!(
+                    "{}.{}.tmp",
+                    path.extension().unwrap().to_str().unwrap(),
+                    std::process::id()
+                ));
+                fs::write(&temp, contents).and_then(|()| fs::rename(&temp, &path))
+            };
+            for (ext, contents) in OUTPUT_EXTENSIONS.iter().zip([stdout, stderr]) {
+                write(self.path.with_extension(ext), contents)?;
+            }
+            write(self.path.clone(), component)
+        });
+        let _ = evict(dir, MAX_SIZE);
+    }
+}
+
+/// Remove the least recently used components (and their output) from `dir` until their total size is at most
+/// `max_size`.
+fn evict(dir: &Path, max_size: u64) -> Result<()> {
+    let mut entries = fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let metadata = path.metadata().ok()?;
+            (path.extension()? == "wasm").then(|| (metadata.modified().ok(), metadata.len(), path))
+        })
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    let mut size = entries.iter().map(|(_, len, _)| len).sum::<u64>();
+    for (_, len, path) in entries {
+        if size <= max_size {
+            break;
+        }
+        fs::remove_file(&path)?;
+        for ext in OUTPUT_EXTENSIONS {
+            let _ = fs::remove_file(path.with_extension(ext));
+        }
+        size -= len;
+    }
+
+    Ok(())
+}
+
+/// Remove everything `componentize-py` has cached (i.e. built components and extracted archives), returning the
+/// paths removed.
+///
+/// Runtime artifacts placed in the cache for builds which don't embed them (see `artifacts::cache_dir`) are left
+/// alone, since those can't be recreated by `componentize-py` itself.
+pub fn clean() -> Result<Vec<PathBuf>> {
+    let mut removed = Vec::new();
+    let mut remove = |dir: PathBuf| {
+        if dir.is_dir() {
+            fs::remove_dir_all(&dir).with_context(|| dir.display().to_string())?;
+            removed.push(dir);
+        } else if dir.exists() {
+            fs::remove_file(&dir).with_context(|| dir.display().to_string())?;
+            removed.push(dir);
+        }
+        Ok::<_, anyhow::Error>(())
+    };
+
+    if let Some(dir) = util::cache_dir().filter(|dir| dir.is_dir()) {
+        for entry in fs::read_dir(&dir).with_context(|| dir.display().to_string())? {
+            let path = entry?.path();
+            if path.file_name() != Some("artifacts".as_ref()) {
+                remove(path)?;
+            }
+        }
+    }
+
+    // Note that archives are extracted under the system temporary directory if no cache directory is available.
+    remove(env::temp_dir().join("componentize-py"))?;
+
+    Ok(removed)
+}
+
+/// Feed `s` into `hasher`, prefixed by its length so that adjacent strings can't be confused with one another.
+fn update_str(hasher: &mut Sha256, s: &str) {
+    hasher.update((s.len() as u64).to_le_bytes());
+    hasher.update(s);
+}
+
+/// Calculate a digest of the file or directory at `root`, covering the relative path and contents of each file.
+///
+/// Bytecode caches (i.e. `__pycache__` directories) are ignored since they are derived from the source files, as
+/// are Git repositories and virtual environments nested within `root`, which can't be imported from and would
+/// otherwise make hashing a project directory needlessly slow.
+pub fn digest_tree(root: &Path) -> Result<[u8; 32]> {
+    digest_tree_excluding(root, &HashSet::new(), &[])
+}
+
+/// Return the path, size, and modification time of each file `digest_tree` would cover, sorted by path.
+///
+/// This only reads metadata, so it's much cheaper than `digest_tree` for deciding whether a tree might have changed
+/// since it was last digested.
+pub fn stamp_tree(root: &Path) -> Result<Vec<(PathBuf, u64, Option<SystemTime>)>> {
+    list_files(root, &HashSet::new(), &[])?
+        .into_iter()
+        .map(|path| {
+            let metadata = fs::metadata(&path).with_context(|| path.display().to_string())?;
+            Ok((path, metadata.len(), metadata.modified().ok()))
+        })
+        .collect()
+}
+
+/// Like `digest_tree`, but also ignoring the `excluded` paths (e.g. those hidden from the app by
+/// `prelink::find_excluded_paths`) and the files whose canonical paths are in `outputs`.
+fn digest_tree_excluding(
+    root: &Path,
+    excluded: &HashSet<PathBuf>,
+    outputs: &[PathBuf],
+) -> Result<[u8; 32]> {
+    let files = list_files(root, excluded, outputs)?;
+
+    let digests = files
+        .par_iter()
+        .map(|path| {
+            let mut hasher = Sha256::new();
+            hasher.update(
+                path.strip_prefix(root)
+                    .unwrap_or(path)
+                    .to_string_lossy()
+                    .as_bytes(),
+            );
+            hasher.update([0]);
+            hasher.update(fs::read(path).with_context(|| path.display().to_string())?);
+            Ok(hasher.finalize())
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut hasher = Sha256::new();
+    for digest in digests {
+        hasher.update(digest);
+    }
+    Ok(hasher.finalize().into())
+}
+
+/// List the files beneath `root` covered by `digest_tree_excluding`, sorted by path.
+fn list_files(
+    root: &Path,
+    excluded: &HashSet<PathBuf>,
+    outputs: &[PathBuf],
+) -> Result<Vec<PathBuf>> {
+    fn visit(
+        root: &Path,
+        path: &Path,
+        excluded: &HashSet<PathBuf>,
+        outputs: &[PathBuf],
+        files: &mut Vec<PathBuf>,
+    ) -> Result<()> {
+        if excluded.contains(path) {
+            return Ok(());
+        }
+
+        if path.is_dir() {
+            let name = path.file_name().and_then(|name| name.to_str());
+            let skip = matches!(name, Some("__pycache__" | ".git"))
+                || (path != root && path.join("pyvenv.cfg").exists());
+            if !skip {
+                for entry in fs::read_dir(path).with_context(|| path.display().to_string())? {
+                    visit(root, &entry?.path(), excluded, outputs, files)?;
+                }
+            }
+        } else if path.exists() {
+            let is_output = outputs.iter().any(|output| {
+                output.file_name() == path.file_name()
+                    && path.canonicalize().is_ok_and(|path| path == *output)
+            });
+            if !is_output {
+                files.push(path.to_owned());
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    visit(root, root, excluded, outputs, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::time::UNIX_EPOCH};
+
+    #[test]
+    fn digest_ignores_outputs_and_environments() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path();
+        fs::write(root.join("app.py"), "print('hello')")?;
+        let output = root.canonicalize()?.join("app.wasm");
+        let digest = || digest_tree_excluding(root, &HashSet::new(), &[output.clone()]);
+        let before = digest()?;
+
+        fs::write(&output, b"\0asm")?;
+        fs::create_dir_all(root.join(".venv/lib"))?;
+        fs::write(root.join(".venv/pyvenv.cfg"), "version = 3.12.4")?;
+        fs::write(root.join(".venv/lib/module.py"), "")?;
+        fs::create_dir_all(root.join(".git"))?;
+        fs::write(root.join(".git/HEAD"), "ref: refs/heads/main")?;
+        assert_eq!(before, digest()?);
+
+        fs::write(root.join("app.py"), "print('goodbye')")?;
+        assert_ne!(before, digest()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn evict_least_recently_used() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for (index, name) in ["old", "new"].iter().enumerate() {
+            let path = dir.path().join(format!("{name}.wasm"));
+            fs::write(&path, [0; 16])?;
+            fs::write(path.with_extension("stdout"), b"output")?;
+            fs::File::options()
+                .write(true)
+                .open(&path)?
+                .set_modified(UNIX_EPOCH + Duration::from_secs(index as u64 + 1))?;
+        }
+
+        evict(dir.path(), 16)?;
+
+        assert!(!dir.path().join("old.wasm").exists());
+        assert!(!dir.path().join("old.stdout").exists());
+        assert!(dir.path().join("new.wasm").exists());
+
+        Ok(())
+    }
+}
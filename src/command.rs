@@ -1,17 +1,39 @@
 use {
-    anyhow::{Context, Result},
-    clap::Parser as _,
+    crate::{
+        api,
+        artifacts::Artifacts,
+        bundle, cache, doctor, optimize,
+        output::{Callbacks, OutputCallback, Phase},
+        report::{self, ErrorFormat, Reported},
+        temp, BindingsFormat, HostLanguage, NamingStyle, Notices, Profile, TypingStyle,
+        VersionCollisions,
+    },
+    anyhow::{anyhow, bail, Context, Result},
+    clap::{parser::ValueSource, ArgMatches, CommandFactory as _, FromArgMatches as _},
+    serde::Deserialize,
     std::{
+        collections::{BTreeSet, HashMap},
         env,
         ffi::OsString,
         fs,
+        io::{self, Read, Write},
         path::{Path, PathBuf},
         process, str,
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
     },
     tokio::runtime::Runtime,
 };
 
+/// Name of the optional project configuration file, which is read from the current directory
+const PROJECT_CONFIG: &str = "componentize-py.toml";
+
 /// A utility to convert Python apps into Wasm components
+///
+/// Defaults for most options may be specified in a `componentize-py.toml` file in the current directory, e.g.
+/// `wit_path`, `world`, `features`, `python_path`, `output`, and `import_interface_names`/`export_interface_names`
+/// tables.  Options specified on the command line take precedence.
 #[derive(clap::Parser, Debug)]
 #[command(author, version, about)]
 pub struct Options {
@@ -32,6 +54,13 @@ pub struct Common {
     #[arg(short = 'w', long)]
     pub world: Option<String>,
 
+    /// Package in which to look up an unqualified (or unspecified) world name
+    ///
+    /// By default, this is the package defined by the WIT file or directory itself rather than any of its
+    /// dependencies.  Use e.g. `my:app` or `my:app@1.0.0`.
+    #[arg(long)]
+    pub primary_package: Option<String>,
+
     /// Disable non-error output
     #[arg(short = 'q', long)]
     pub quiet: bool,
@@ -39,7 +68,8 @@ pub struct Common {
     /// Comma-separated list of features that should be enabled when processing
     /// WIT files.
     ///
-    /// This enables using `@unstable` annotations in WIT files.
+    /// This enables using `@unstable` annotations in WIT files.  Packages may also request features for their own
+    /// WIT files using a `features` list in their `componentize-py.toml` file.
     #[clap(long)]
     features: Vec<String>,
 
@@ -55,6 +85,11 @@ pub struct Common {
     /// interface name, possibly qualified with the package name and namespace and/or version if that name would
     /// otherwise clash with another interface.  With this option, you may override that name with your own, unique
     /// name.
+    ///
+    /// The interface may be given as a pattern in which each `*` matches any sequence of characters, in which case
+    /// each `*` in the name is replaced by what the corresponding `*` in the pattern matched, e.g.
+    /// `wasi:http/*@0.2.*=http-*`.  An exact match takes precedence over a pattern, and a longer pattern over a
+    /// shorter one.
     #[arg(long, value_parser = parse_key_value)]
     pub import_interface_name: Vec<(String, String)>,
 
@@ -64,8 +99,38 @@ pub struct Common {
     /// interface name, possibly qualified with the package name and namespace and/or version if that name would
     /// otherwise clash with another interface.  With this option, you may override that name with your own, unique
     /// name.
+    ///
+    /// The interface may be given as a pattern in which each `*` matches any sequence of characters, in which case
+    /// each `*` in the name is replaced by what the corresponding `*` in the pattern matched, e.g.
+    /// `wasi:http/*@0.2.*=http-*`.  An exact match takes precedence over a pattern, and a longer pattern over a
+    /// shorter one.
     #[arg(long, value_parser = parse_key_value)]
     pub export_interface_name: Vec<(String, String)>,
+
+    /// How to name generated functions, methods, and parameters: `snake` (the default), `camel`, or `preserve`.
+    ///
+    /// `snake` converts WIT names to snake case (e.g. `get-value` becomes `get_value`), `camel` converts them to
+    /// lower camel case (e.g. `getValue`), and `preserve` keeps them as written apart from replacing dashes with
+    /// underscores.  Type and module names are unaffected.
+    #[arg(long, value_enum, default_value_t)]
+    pub naming_style: NamingStyle,
+
+    /// How to name the modules for interfaces of which more than one version is present: `qualify` (the default) or
+    /// `duplicate-if-necessary`.
+    ///
+    /// `qualify` names each version after its package and version (e.g. `wasi_http_types_0_2_0` and
+    /// `wasi_http_types_0_2_1`), whereas `duplicate-if-necessary` names the latest version as though it were the
+    /// only one (e.g. `types`) and qualifies only the older ones with their versions (e.g. `types_0_2_0`).
+    #[arg(long, value_enum, default_value_t)]
+    pub version_collisions: VersionCollisions,
+
+    /// How to report errors: `human` (the default) or `json`.
+    ///
+    /// With `json`, each error is printed to `stderr` as a single JSON object containing the phase of the build
+    /// during which it occurred, the message and its causes, the Python traceback (if the app raised an exception
+    /// during pre-initialization), and the WIT file location (if any) it refers to.
+    #[arg(long, value_enum, default_value_t)]
+    pub error_format: ErrorFormat,
 }
 
 #[derive(clap::Subcommand, Debug)]
@@ -75,6 +140,62 @@ pub enum Command {
 
     /// Generate Python bindings for the world and write them to the specified directory.
     Bindings(Bindings),
+
+    /// List the packages and worlds available in the WIT path, along with each world's imports and exports.
+    ListWorlds,
+
+    /// Generate a `pytest` module containing property-based (Hypothesis) contract tests for each of the world's
+    /// exported functions, as implemented by the specified Python app.
+    GenProptests(GenProptests),
+
+    /// Compare the bindings for the world with those generated from an older version of its WIT files and describe
+    /// each change which may require an app written against the older bindings to be updated (e.g. renamed
+    /// methods, changed signatures, and removed types).
+    MigrateBindings(MigrateBindings),
+
+    /// Describe the Python API generated for the world: the module for each of its interfaces, plus each named type
+    /// and each function the app may call or must implement.
+    ///
+    /// With `--format json`, the description is printed as a JSON object (see `componentize_py::api::WorldApi`),
+    /// allowing documentation generators, linters, etc. to build on it without parsing the generated code.
+    Summary(Summary),
+
+    /// Generate a minimal host project for running components which target the world, with a stub implementation
+    /// of each of its imports to be filled in and examples of calling its exports.
+    ///
+    /// The project uses either `wasmtime`'s Rust API (a Cargo project using `wasmtime::component::bindgen!`) or
+    /// `wasmtime-py` (a Python script using the bindings generated by `python -m wasmtime.bindgen`).
+    ScaffoldHost(ScaffoldHost),
+
+    /// Build a component from the world with a test runner added, then run the app's pytest suite inside it,
+    /// optionally writing the outcome of each test as JUnit XML.
+    ///
+    /// The tests are collected from the current directory during pre-initialization and run by `wasmtime` with
+    /// the Python path mounted as it was then, so they exercise the app under the same interpreter and WASI
+    /// environment as the real component.  Calls to the world's imports (other than WASI) trap.
+    Test(Test),
+
+    /// Write the runtime artifacts embedded in this build to the specified directory, along with a `SHA256SUMS`
+    /// file listing their checksums, for use with `componentize --artifact-dir`.
+    ///
+    /// Since the artifacts are the same on every platform, this allows `componentize-py` to be packaged for
+    /// platforms on which building them is impractical.
+    ExportArtifacts(ExportArtifacts),
+
+    /// Check whether this environment is able to build components, reporting each problem found along with a
+    /// suggested fix.
+    ///
+    /// This checks for writable temporary space, intact runtime artifacts, sufficient memory, a sane virtual
+    /// environment layout (if any), a healthy build cache, and the optional tools `componentize-py` can make use
+    /// of.  The command fails if any check does.
+    Doctor(Doctor),
+
+    /// Remove all cached components and extracted archives, plus any temporary directories left behind by builds
+    /// which were killed before they could clean up.
+    ///
+    /// Temporary directories modified within the last day are left alone in case they belong to a build which is
+    /// still running.
+    CleanCache,
 }
 
 #[derive(clap::Args, Debug)]
@@ -88,14 +209,60 @@ pub struct Componentize {
 
     /// Specify a directory containing the app and/or its dependencies.  May be specified more than once.
     ///
-    /// If a `VIRTUAL_ENV` environment variable is set, it will be interpreted as a directory name, and that
-    /// directory will be searched for a `site-packages` subdirectory, which will be appended to the path as a
-    /// convenience for `venv` users.  Alternatively, if `pipenv` is in `$PATH` and `pipenv --venv` produces a
-    /// non-empty result, it will be searched for a `site-packages` subdirectory, which will likewise be appended.
-    /// If the previous options fail, the `site` module in python will be used to get the `site-packages`
+    /// A `.whl`, `.zip`, or `.pyz` file may be specified instead of a directory, in which case it is extracted (to a
+    /// subdirectory of the build cache directory) and used as if it were a directory, e.g. to build using wheels
+    /// downloaded with `pip download` without needing to install them first, or from a zipapp produced by an existing
+    /// build (in which case `__main__.py` is ignored, and the app module must be named explicitly).  Specify `-` to
+    /// read a tar stream from `stdin` instead, which is extracted to a temporary directory, e.g. for build systems
+    /// which prefer pipes to shared directories.
+    ///
+    /// Unless `--site-packages` is specified, the `site-packages` directories of the app's virtual environment
+    /// are appended to the path as a convenience.  The environment is the first of the following to be found:
+    /// the one named by the `VIRTUAL_ENV` environment variable (i.e. an activated environment), the one named by
+    /// `UV_PROJECT_ENVIRONMENT`, a `.venv` directory in the current directory (as created by `python -m venv`,
+    /// `uv`, and, if so configured, `pipenv` and Poetry), and the ones reported by `pipenv --venv` and `poetry
+    /// env info --path`.  If no environment is found, the `site` module of the `python3` in `$PATH` is used to
+    /// locate `site-packages` directories instead.
     #[arg(short = 'p', long, default_value = ".")]
     pub python_path: Vec<String>,
 
+    /// Append the specified `site-packages` directory to the Python path instead of searching for a virtual
+    /// environment.  May be specified more than once.
+    #[arg(long, value_name = "DIR")]
+    pub site_packages: Vec<PathBuf>,
+
+    /// Hide files and directories matching the specified pattern in the Python path from the app.  May be
+    /// specified more than once.
+    ///
+    /// Patterns use a subset of `.gitignore` syntax and are matched within each Python path directory: `*.log`
+    /// matches any file named e.g. `debug.log`, `/docs/` matches only a top-level `docs` directory, and
+    /// `!/tests/` re-includes something excluded earlier.  Patterns listed in a `.componentizeignore` file at the
+    /// top of a Python path directory apply to that directory as well.  By default, `__pycache__`, version control
+    /// and tool cache directories (e.g. `.git` and `.pytest_cache`) are excluded.  Excluded paths are not mounted
+    /// during pre-initialization, searched for native extensions and package configuration, or considered by the
+    /// build cache.
+    #[arg(long, value_name = "PATTERN")]
+    pub exclude_path: Vec<String>,
+
+    /// Link libraries matching the specified pattern statically, i.e. such that they satisfy other libraries'
+    /// imports but can't be loaded using `dlopen`.  May be specified more than once.
+    ///
+    /// Patterns are matched against the names of embedded libraries (e.g. `libc++.so`) and the paths of shared
+    /// libraries within each Python path directory (e.g. `numpy.libs/libopenblas.so`); patterns without a `/` need
+    /// only match the file name.  Native extensions are always found and made `dlopen`-able, since that's how
+    /// Python loads them, but other shared libraries (e.g. those vendored alongside an extension) are only linked
+    /// if they match this option or `--dynamic-library`.  Static libraries add nothing to the `dlopen` lookup
+    /// table, making the component smaller, but must be imported from by some other library.
+    #[arg(long, value_name = "PATTERN")]
+    pub static_library: Vec<String>,
+
+    /// Make libraries matching the specified pattern loadable using `dlopen` (e.g. via `ctypes.CDLL`).  May be
+    /// specified more than once.
+    ///
+    /// See `--static-library` for the pattern syntax.
+    #[arg(long, value_name = "PATTERN")]
+    pub dynamic_library: Vec<String>,
+
     /// Specify which world to use with which Python module.  May be specified more than once.
     ///
     /// Some Python modules (e.g. SDK wrappers around WIT APIs) may contain `componentize-py.toml` files which
@@ -104,13 +271,28 @@ pub struct Componentize {
     ///
     /// Note that these must be specified in topological order (i.e. if a module containing WIT files depends on
     /// other modules containing WIT files, it must be listed after all its dependencies).
+    ///
+    /// The module may be given as a glob pattern (e.g. `*_service=my:world`), in which case each matching module
+    /// without an entry of its own is assigned the world, in alphabetical order.  It is an error for a module to
+    /// match patterns specifying different worlds.
     #[arg(short = 'm', long, value_parser = parse_key_value)]
     pub module_worlds: Vec<(String, String)>,
 
-    /// Output file to which to write the resulting component
+    /// Output file to which to write the resulting component, or `-` to write it to `stdout`
     #[arg(short = 'o', long, default_value = "index.wasm")]
     pub output: PathBuf,
 
+    /// If set, write a bundle to the specified directory instead of writing a single file to `--output`.
+    ///
+    /// The bundle contains the component (named `<app_name>.wasm`), Python bindings for the targeted world (if
+    /// available), a `metadata.toml` file describing the component's imports, exports, build options, and the
+    /// Python packages it includes, plus a `README.md` and (for `wasi:cli` and `wasi:http` apps) a `run.sh`
+    /// script for running it with `wasmtime`.
+    ///
+    /// This directory will be created if it does not already exist.
+    #[arg(long, conflicts_with = "output")]
+    pub output_dir: Option<PathBuf>,
+
     /// If set, replace all WASI imports with trapping stubs.
     ///
     /// PLEASE NOTE: This has the effect of baking whatever PRNG seed is generated at build time into the
@@ -118,6 +300,207 @@ pub struct Componentize {
     /// run.  Do *not* use this option in situations where a secure source of randomness is required.
     #[arg(short = 's', long)]
     pub stub_wasi: bool,
+
+    /// Which WASI interfaces the component may import.
+    ///
+    /// `cli` (the default) allows everything Python's standard library may use, including the filesystem,
+    /// clocks, randomness, environment variables, and sockets.  `http` allows only the WASI interfaces included
+    /// in the `wasi:http/proxy` world (streams, clocks, randomness, and standard input, output, and error), and
+    /// `minimal` allows only streams plus standard output and error, e.g. for embedded hosts.  Any other WASI
+    /// function the app calls at runtime will trap.  Imports of the targeted world itself are unaffected.
+    #[arg(long, value_enum, default_value_t = Profile::Cli, conflicts_with = "stub_wasi")]
+    pub profile: Profile,
+
+    /// If set, make Python tracebacks produced by the component refer to the host paths of the app's source files
+    /// (e.g. `/home/me/app/app.py`) rather than guest paths (e.g. `/0/app.py`).
+    ///
+    /// The mapping from guest to host paths is also embedded in the component as a `componentize-py-source-map`
+    /// custom section.  Note that this reveals the layout of the build machine's filesystem to anyone with access
+    /// to the component.
+    #[arg(long)]
+    pub map_source_paths: bool,
+
+    /// Make the specified Python module (and any submodules) unavailable to the app.  May be specified more than
+    /// once.
+    ///
+    /// This removes the module from the standard library (and omits any matching native extension found in the
+    /// Python path) during pre-initialization, which can significantly reduce the size of the component when
+    /// modules are only imported opportunistically.  Note that excluded modules cannot be imported at all, so the
+    /// build will fail if the app (or one of its dependencies) requires them.
+    #[arg(long, value_name = "MODULE")]
+    pub exclude_module: Vec<String>,
+
+    /// Keep the specified Python module (and its parent packages) available even if it matches an
+    /// `--exclude-module` option.  May be specified more than once.
+    #[arg(long, value_name = "MODULE")]
+    pub include_module: Vec<String>,
+
+    /// Strip debug names and DWARF sections from all libraries before linking them into the component.
+    ///
+    /// This makes the component smaller at the expense of less informative stack traces for Wasm-level traps.
+    #[arg(long)]
+    pub strip_debug: bool,
+
+    /// Also write a `zstd`-compressed copy of the component to `<output>.zst`, optionally specifying the
+    /// compression level (default: 19).
+    #[arg(long, value_name = "LEVEL", num_args = 0..=1, default_missing_value = "19")]
+    pub compression: Option<i32>,
+
+    /// Print a report of the component's size, broken down by core module (i.e. library) and section.
+    #[arg(long)]
+    pub size_report: bool,
+
+    /// Map an exception type to a function which converts it to the error payload of a `result`-returning export.
+    /// May be specified more than once.
+    ///
+    /// Both sides are specified as `module:name` (or just `name` for builtins), e.g.
+    /// `--map-exception KeyError=app:not_found`.  When an exported function whose WIT return type is a `result`
+    /// raises an exception (other than `Err`) matching the type, the function is called with the exception and
+    /// its return value is used as the error, which must match that export's error type.  If more than one mapping
+    /// applies, the one for the most derived type wins, or else the one specified first.  Mappings may also be
+    /// specified in a `componentize-py.toml` file using an `exception_mappings` table.
+    #[arg(long, value_parser = parse_key_value)]
+    pub map_exception: Vec<(String, String)>,
+
+    /// Add exports of the `componentize-py:diagnostics/counters` and `componentize-py:diagnostics/memory`
+    /// interfaces to the world, which hosts may call to retrieve per-function call and error counts maintained by
+    /// the runtime and statistics about the app's memory usage, respectively.
+    #[arg(long)]
+    pub diagnostics: bool,
+
+    /// Check that each value passed from Python to the host (i.e. each parameter of an imported function and each
+    /// value returned from an exported one) has the type declared in the generated bindings before lowering it,
+    /// raising a `TypeError` naming the function, parameter, and expected type if not.
+    ///
+    /// Without this, a value of the wrong type usually causes a panic with an unhelpful message.  The checks add
+    /// some overhead to each call, so this is mainly intended for development and testing.
+    #[arg(long)]
+    pub strict: bool,
+
+    /// Log each call to an imported function and each call to an exported one, including the function's name,
+    /// the size of each parameter (for strings, bytes, lists, tuples, and dicts), and how long the call took.
+    ///
+    /// Messages are sent to `wasi:logging/logging` (at the `trace` level) if the world imports it, or written to
+    /// `stderr` otherwise.  This adds overhead to each call, so it is intended for performance debugging.
+    #[arg(long)]
+    pub trace_imports: bool,
+
+    /// Add an export of the `componentize-py:lifecycle/shutdown` interface to the world, which hosts may call
+    /// before dropping an instance to run any callbacks the app registered using the `on_shutdown` function
+    /// generated in its world module.
+    #[arg(long)]
+    pub shutdown_hook: bool,
+
+    /// Make the component's `sys.stdout` and `sys.stderr` unbuffered rather than line-buffered, so that each write
+    /// reaches the host immediately.
+    ///
+    /// This is slower for components which print a lot, but ensures no output is lost if the component traps
+    /// partway through a line.
+    #[arg(long)]
+    pub unbuffered: bool,
+
+    /// Make the whole Python standard library available to the app at runtime, rather than only the modules it
+    /// imported during pre-initialization.
+    ///
+    /// The source of each standard library module (minus any excluded using `--exclude-module`) is embedded in the
+    /// component, and compiled the first time it's imported.  This allows the app to import modules lazily, e.g.
+    /// within rarely-taken branches, at the cost of a larger component.
+    #[arg(long)]
+    pub embed_stdlib: bool,
+
+    /// Satisfy imports of the component using the exports of the specified component, fusing the two into a
+    /// single component.  May be specified more than once.
+    ///
+    /// Each import whose name matches an export of one of these components (e.g. an interface implemented by a
+    /// Rust component) is connected to that export; any other imports are left as is.  Note that the composed
+    /// components are not available during pre-initialization, so the app should not call the corresponding
+    /// imports at the top level.
+    #[arg(long, value_name = "COMPONENT")]
+    pub compose: Vec<PathBuf>,
+
+    /// Use the specified `wasi_snapshot_preview1` adapter module rather than the built-in one, e.g. to target a
+    /// host which only supports an older release of WASI 0.2.
+    ///
+    /// The adapter must be a reactor (rather than command) build, since the component has no `_start` function
+    /// for a command adapter to call.
+    #[arg(long, value_name = "ADAPTER")]
+    pub wasi_adapter: Option<PathBuf>,
+
+    /// Read the runtime artifacts (i.e. the runtime and CPython libraries, standard library, and WASI adapter)
+    /// from the specified directory, as written by `export-artifacts`, rather than using those embedded in this
+    /// build.
+    ///
+    /// Each artifact is checked against the directory's `SHA256SUMS` file (or, if this build pinned them, the
+    /// checksums specified at build time) before use.  Defaults to `$COMPONENTIZE_PY_ARTIFACT_DIR`, if set, which
+    /// is required for builds made with `COMPONENTIZE_PY_EXTERNAL_ARTIFACTS` set.
+    #[arg(long, value_name = "DIR")]
+    pub artifact_dir: Option<PathBuf>,
+
+    /// Fail the build if any module takes longer than this many seconds to import during pre-initialization.
+    ///
+    /// Each module's time excludes that spent importing other modules in turn.  On failure, the slowest imports
+    /// are listed along with their cumulative times.
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    pub import_time_budget: Option<Duration>,
+
+    /// Abort pre-initialization if it takes longer than this many seconds in total, e.g. because the app hangs
+    /// while being imported.
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    pub pre_init_timeout: Option<Duration>,
+
+    /// Call this function once the app has been imported during pre-initialization, e.g. to warm caches or load
+    /// models so the results are included in the snapshot.
+    ///
+    /// The function is given as either the name of an attribute of the app module or a `module:attribute` path.
+    /// By default, `__componentize_pre_init__` is called if the app module defines it.
+    #[arg(long, value_name = "FUNCTION")]
+    pub pre_init_hook: Option<String>,
+
+    /// Retain at most this many bytes of each of `stdout` and `stderr` from pre-initialization for error reporting
+    /// (default: 1 MiB).
+    ///
+    /// Beyond that, the start and end of the output are kept, with a marker noting how much was omitted in between.
+    /// Output echoed to the terminal is never truncated.
+    #[arg(long, value_name = "BYTES")]
+    pub output_capture_limit: Option<usize>,
+
+    /// Embed descriptive metadata in the component, e.g. `--metadata name=my-app,version=1.2.3`.
+    ///
+    /// Supported keys are `name`, `version`, `authors`, `description`, `license`, `homepage`, `repository`,
+    /// `documentation`, and `categories`; `authors` and `categories` may be given more than once.  The name is
+    /// recorded in the component's name section, the version in a `version` custom section, and the rest in a
+    /// `registry-metadata` custom section, so that registries and tools like `wasm-tools metadata show` can
+    /// describe the component.
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_key_value, value_delimiter = ',')]
+    pub metadata: Vec<(String, String)>,
+
+    /// Record the license texts and notices of the third-party code the component contains, either by embedding
+    /// them in the component or by writing them to a file alongside it.
+    ///
+    /// This covers CPython, the WASI SDK's libraries, the Rust crates linked into the runtime, and each
+    /// distribution (i.e. `*.dist-info` directory) found in the Python path, including any files in its `licenses`
+    /// subdirectory or named e.g. `LICENSE` or `COPYING`.
+    #[arg(long, value_name = "MODE")]
+    pub notices: Option<Notices>,
+
+    /// Make the output byte-for-byte reproducible given identical inputs.
+    ///
+    /// This runs pre-initialization with fixed clocks and random number generators, pins Python's hash secret,
+    /// discards cached filesystem metadata from the snapshot, and embeds host paths (e.g. for
+    /// `--map-source-paths`) relative to the current directory.  Note that the pinned hash secret remains in
+    /// effect at runtime, although `random` is still reseeded from the host on first use.
+    #[arg(long)]
+    pub deterministic: bool,
+
+    /// Leave temporary directories (e.g. the unpacked standard library and generated bindings) in place rather
+    /// than removing them, printing the path of each, for debugging.
+    #[arg(long)]
+    pub keep_temp: bool,
+
+    /// Always pre-initialize the component rather than reusing one built from identical inputs, and don't add the
+    /// result to the build cache.
+    #[arg(long)]
+    pub no_cache: bool,
 }
 
 #[derive(clap::Args, Debug)]
@@ -129,11 +512,317 @@ pub struct Bindings {
 
     /// Optional name of top-level module to use for bindings.
     ///
-    /// If this is not specified, the module name will be derived from the world name.
+    /// If this is not specified, the module name will be derived from the world name.  If it's `auto`, the name
+    /// is derived from the world's namespace, package, and name instead (e.g. `wasi_cli_command`), with a
+    /// `_bindings` suffix added if that would collide with a module in the output directory or in the app's
+    /// virtual environment.
+    #[arg(long)]
+    pub world_module: Option<String>,
+
+    /// Which typing constructs to use in the generated code.
+    ///
+    /// `modern` emits PEP 604 unions, builtin generics, and PEP 695 `type` aliases, which require Python 3.12 or
+    /// later; `legacy` uses their `typing` module equivalents for compatibility with older tooling.
+    #[arg(long, value_enum, default_value_t)]
+    pub typing_style: TypingStyle,
+
+    /// How to write the bindings.
+    ///
+    /// `directory` writes the Python package directly to the output directory.  `wheel` instead writes a wheel
+    /// (e.g. `my_world-1.2.3-py3-none-any.whl`) containing the package plus a `py.typed` marker, which may be
+    /// installed using `pip` or published to a package index.  The wheel's version is derived from that of the
+    /// WIT package containing the world (or `0.0.0` if it has none), and its name from the top-level module.
+    #[arg(long, value_enum, default_value_t)]
+    pub format: BindingsFormat,
+
+    /// Keep running after generating the bindings, regenerating them whenever the contents of the WIT path change.
+    ///
+    /// Only files whose contents actually changed are rewritten, so editors and language servers watching the
+    /// output directory won't reload the others.  Errors (e.g. due to a WIT file being saved in an incomplete
+    /// state) are reported without exiting.
+    #[arg(long)]
+    pub watch: bool,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct GenProptests {
+    /// The name of a Python module containing the app to test.
+    pub app_name: String,
+
+    /// File to which the tests should be written.
+    #[arg(short = 'o', long, default_value = "test_contract.py")]
+    pub output: PathBuf,
+
+    /// Name of the top-level module containing the bindings the app uses, if it isn't derived from the world name
+    /// (i.e. if `--world-module` was passed to the `bindings` subcommand).  As for `bindings`, `auto` derives it
+    /// from the world's fully qualified name, checking the current directory for collisions.
+    #[arg(long)]
+    pub world_module: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct MigrateBindings {
+    /// File or directory containing the WIT files from which the existing bindings were generated.
+    pub old_wit_path: PathBuf,
+
+    /// Name of the world from which the existing bindings were generated, if it differs from the one specified
+    /// using `--world`.
+    #[arg(long)]
+    pub old_world: Option<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct Summary {
+    /// How to print the description: `text` (the default) or `json`.
+    #[arg(long, value_enum, default_value_t)]
+    pub format: api::Format,
+
+    /// Name of the top-level module containing the bindings, if it isn't derived from the world name (i.e. if
+    /// `--world-module` was passed to the `bindings` subcommand).
     #[arg(long)]
     pub world_module: Option<String>,
 }
 
+#[derive(clap::Args, Debug)]
+pub struct ScaffoldHost {
+    /// Directory to write the project to, which is created if necessary.
+    #[arg(short = 'o', long, default_value = "host")]
+    pub output_dir: PathBuf,
+
+    /// Language (and `wasmtime` API) to use for the host.
+    #[arg(long, value_enum, default_value_t)]
+    pub language: HostLanguage,
+
+    /// Path of the component the host should run by default.
+    #[arg(long, default_value = "app.wasm")]
+    pub component: String,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct Test {
+    /// The name of a Python module containing the app implementing the world's exports, if it has any.
+    #[arg(long)]
+    pub app_name: Option<String>,
+
+    /// Specify a directory containing the app, its tests, and/or its dependencies.  May be specified more than
+    /// once.
+    ///
+    /// As for `componentize`, the `site-packages` directories of the app's virtual environment (which must
+    /// include `pytest`) are appended unless `--site-packages` is specified.  The current directory is appended
+    /// if it isn't already present, since that's where pytest looks for tests.
+    #[arg(short = 'p', long, default_value = ".")]
+    pub python_path: Vec<String>,
+
+    /// Append the specified `site-packages` directory to the Python path instead of searching for a virtual
+    /// environment.  May be specified more than once.
+    #[arg(long, value_name = "DIR")]
+    pub site_packages: Vec<PathBuf>,
+
+    /// Hide files and directories matching the specified pattern in the Python path from the app, as for
+    /// `componentize`.  May be specified more than once.
+    #[arg(long, value_name = "PATTERN")]
+    pub exclude_path: Vec<String>,
+
+    /// File to which to write the outcome of each test as JUnit XML.
+    #[arg(long, value_name = "FILE")]
+    pub junit_xml: Option<PathBuf>,
+
+    /// Arguments to pass to pytest, e.g. `-- -k add tests/test_math.py`.
+    #[arg(last = true)]
+    pub pytest_args: Vec<String>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct ExportArtifacts {
+    /// Directory to write the artifacts to, which is created if necessary.
+    pub output_dir: PathBuf,
+
+    /// Export the artifacts in the specified directory (after checking them) rather than those embedded in this
+    /// build.
+    #[arg(long, value_name = "DIR")]
+    pub artifact_dir: Option<PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct Doctor {
+    /// Check the runtime artifacts in the specified directory rather than those embedded in this build.
+    #[arg(long, value_name = "DIR")]
+    pub artifact_dir: Option<PathBuf>,
+
+    /// Also check that `wasm-opt` is installed.
+    #[arg(long)]
+    pub wasm_opt: bool,
+}
+
+/// Build configuration read from a `componentize-py.toml` file at the root of a project.
+///
+/// Unlike the `componentize-py.toml` files found in Python packages, which describe the WIT files and bindings
+/// belonging to those packages, this supplies defaults for the command line options of the project as a whole.
+/// Options specified on the command line take precedence over those specified here, which in turn take precedence
+/// over any package configuration.  Relative paths are interpreted relative to the directory containing the file.
+#[derive(Deserialize, Default, Debug)]
+struct ProjectConfig {
+    wit_path: Option<PathBuf>,
+    world: Option<String>,
+    primary_package: Option<String>,
+    #[serde(default)]
+    features: Vec<String>,
+    #[serde(default)]
+    all_features: bool,
+    #[serde(default)]
+    import_interface_names: HashMap<String, String>,
+    #[serde(default)]
+    export_interface_names: HashMap<String, String>,
+    #[serde(default)]
+    python_path: Vec<String>,
+    #[serde(default)]
+    exclude_paths: Vec<String>,
+    #[serde(default)]
+    static_libraries: Vec<String>,
+    #[serde(default)]
+    dynamic_libraries: Vec<String>,
+    #[serde(default)]
+    module_worlds: Vec<String>,
+    pre_init_hook: Option<String>,
+    output: Option<PathBuf>,
+}
+
+impl ProjectConfig {
+    /// Read the configuration file in `dir`, if present.
+    fn load(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(PROJECT_CONFIG);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let mut config = toml::from_str::<Self>(
+            &fs::read_to_string(&path).with_context(|| path.display().to_string())?,
+        )
+        .with_context(|| path.display().to_string())?;
+
+        for entry in &config.module_worlds {
+            parse_key_value(entry)
+                .map_err(|e| anyhow!("{e} in `module_worlds`"))
+                .with_context(|| path.display().to_string())?;
+        }
+
+        config.wit_path = config.wit_path.map(|p| dir.join(p));
+        config.output = config.output.map(|p| dir.join(p));
+        config.python_path = config
+            .python_path
+            .into_iter()
+            .map(|p| {
+                Ok(dir
+                    .join(p)
+                    .to_str()
+                    .context("non-UTF-8 `python_path` entry")?
+                    .to_owned())
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Some(config))
+    }
+
+    /// Fill in any options in `options` which were not specified on the command line (according to `matches`)
+    /// using this configuration.
+    ///
+    /// Scalar options are only taken from the configuration if absent from the command line, as is the
+    /// `python_path` list, since the order of its entries matters.  `features`, `exclude_paths`, the library
+    /// linking patterns, and the interface name tables are merged with those from the command line, which take
+    /// precedence in the event of a conflict.
+    fn apply(self, options: &mut Options, matches: &ArgMatches) {
+        let common = &mut options.common;
+        common.wit_path = common.wit_path.take().or(self.wit_path);
+        common.world = common.world.take().or(self.world);
+        common.primary_package = common.primary_package.take().or(self.primary_package);
+        common.all_features |= self.all_features;
+        for feature in self.features {
+            if !common.features.contains(&feature) {
+                common.features.push(feature);
+            }
+        }
+        merge_names(
+            &mut common.import_interface_name,
+            self.import_interface_names,
+        );
+        merge_names(
+            &mut common.export_interface_name,
+            self.export_interface_names,
+        );
+
+        if let (Command::Componentize(componentize), Some(matches)) = (
+            &mut options.command,
+            matches.subcommand_matches("componentize"),
+        ) {
+            let from_cli = |id| matches.value_source(id) == Some(ValueSource::CommandLine);
+            if !from_cli("python_path") && !self.python_path.is_empty() {
+                componentize.python_path = self.python_path;
+            }
+            // As with `python_path`, the order of these matters, so they're replaced rather than merged.
+            if !from_cli("module_worlds") && !self.module_worlds.is_empty() {
+                componentize.module_worlds = self
+                    .module_worlds
+                    .iter()
+                    .map(|entry| parse_key_value(entry).unwrap())
+                    .collect();
+            }
+            // Later patterns take precedence, so put those from the command line last.
+            componentize.exclude_path.splice(0..0, self.exclude_paths);
+            componentize
+                .static_library
+                .splice(0..0, self.static_libraries);
+            componentize
+                .dynamic_library
+                .splice(0..0, self.dynamic_libraries);
+            componentize.pre_init_hook = componentize.pre_init_hook.take().or(self.pre_init_hook);
+            if let Some(output) = self.output.filter(|_| !from_cli("output")) {
+                componentize.output = output;
+            }
+        } else if let (Command::Test(test), Some(matches)) =
+            (&mut options.command, matches.subcommand_matches("test"))
+        {
+            if matches.value_source("python_path") != Some(ValueSource::CommandLine)
+                && !self.python_path.is_empty()
+            {
+                test.python_path = self.python_path;
+            }
+            test.exclude_path.splice(0..0, self.exclude_paths);
+        }
+    }
+}
+
+/// Add each of `names` to `list` unless an entry with the same key is already present.
+fn merge_names(list: &mut Vec<(String, String)>, names: HashMap<String, String>) {
+    let mut names = names.into_iter().collect::<Vec<_>>();
+    names.sort();
+    for (key, value) in names {
+        if !list.iter().any(|(k, _)| *k == key) {
+            list.push((key, value));
+        }
+    }
+}
+
+/// Parse `args`, filling in any options not specified there using the project configuration file in `dir` (if
+/// any).
+fn parse_options<T: Into<OsString> + Clone, I: IntoIterator<Item = T>>(
+    args: I,
+    dir: &Path,
+) -> Result<Options> {
+    let matches = Options::command().get_matches_from(args);
+    let mut options = Options::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Some(config) = ProjectConfig::load(dir)? {
+        config.apply(&mut options, &matches);
+    }
+    Ok(options)
+}
+
+fn parse_seconds(s: &str) -> Result<Duration, String> {
+    s.parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("expected a non-negative number of seconds; got `{s}`"))
+}
+
 fn parse_key_value(s: &str) -> Result<(String, String), String> {
     let (k, v) = s
         .split_once('=')
@@ -141,24 +830,145 @@ fn parse_key_value(s: &str) -> Result<(String, String), String> {
     Ok((k.to_string(), v.to_string()))
 }
 
+/// Remove any temporary directories (see `run`) before exiting if the process is interrupted (e.g. by Ctrl-C).
+///
+/// Temporary directories are normally removed when dropped, but that won't happen if we're interrupted.  This
+/// takes over the process's `SIGINT` handling, so it's for use by the `componentize-py` binary rather than
+/// embedders such as the Python module, and may only be called once per process.
+pub fn install_interrupt_handler() -> Result<()> {
+    ctrlc::set_handler(|| {
+        temp::remove_all();
+        process::exit(130);
+    })?;
+    Ok(())
+}
+
+/// Run the command line interface with the specified arguments (including the program name).
+///
+/// Errors are returned for the caller to report, except for those which this has already printed (i.e. when
+/// using `ErrorFormat::Json`), which are wrapped in `report::Reported`.
 pub fn run<T: Into<OsString> + Clone, I: IntoIterator<Item = T>>(args: I) -> Result<()> {
-    let options = Options::parse_from(args);
-    match options.command {
-        Command::Componentize(opts) => componentize(options.common, opts),
-        Command::Bindings(opts) => generate_bindings(options.common, opts),
+    let options = parse_options(args, Path::new(""))?;
+    let error_format = options.common.error_format;
+
+    // The most recent phase of the build to begin, for use in error reports
+    let phase = Arc::new(Mutex::new(None));
+
+    let result = match options.command {
+        Command::Componentize(opts) => {
+            let phase = phase.clone();
+            let callbacks = Callbacks {
+                progress: Some(Arc::new(move |current| {
+                    *phase.lock().unwrap() = Some(current)
+                })),
+                ..Default::default()
+            };
+            componentize(options.common, opts, &callbacks)
+        }
+        Command::Bindings(opts) => {
+            *phase.lock().unwrap() = Some(Phase::GeneratingBindings);
+            generate_bindings(options.common, opts)
+        }
+        Command::ListWorlds => list_worlds(options.common),
+        Command::GenProptests(opts) => gen_proptests(options.common, opts),
+        Command::MigrateBindings(opts) => migrate_bindings(options.common, opts),
+        Command::Summary(opts) => summary(options.common, opts),
+        Command::ScaffoldHost(opts) => scaffold_host(options.common, opts),
+        Command::Test(opts) => test(options.common, opts),
+        Command::ExportArtifacts(opts) => export_artifacts(options.common, opts),
+        Command::Doctor(opts) => doctor(options.common, opts),
+        Command::CleanCache => clean_cache(options.common),
+    };
+
+    match (result, error_format) {
+        (Err(error), ErrorFormat::Json) => {
+            report::print(&error, error_format, *phase.lock().unwrap());
+            Err(Reported(error).into())
+        }
+        (result, _) => result,
     }
 }
 
-fn generate_bindings(common: Common, bindings: Bindings) -> Result<()> {
-    crate::generate_bindings(
+fn export_artifacts(common: Common, export: ExportArtifacts) -> Result<()> {
+    Artifacts::new(export.artifact_dir.as_deref())?.export(&export.output_dir)?;
+
+    if !common.quiet {
+        println!("Artifacts written to {}", export.output_dir.display());
+    }
+
+    Ok(())
+}
+
+fn doctor(common: Common, options: Doctor) -> Result<()> {
+    let checks = doctor::run(options.artifact_dir.as_deref(), options.wasm_opt);
+
+    for check in &checks {
+        if common.quiet && check.status == doctor::Status::Pass {
+            continue;
+        }
+        println!("[{}] {}: {}", check.status, check.name, check.detail);
+        if let Some(fix) = &check.fix {
+            println!("       fix: {fix}");
+        }
+    }
+
+    let failed = checks
+        .iter()
+        .filter(|check| check.status == doctor::Status::Fail)
+        .count();
+    if failed > 0 {
+        bail!("{failed} check(s) failed");
+    }
+
+    Ok(())
+}
+
+fn clean_cache(common: Common) -> Result<()> {
+    let removed = cache::clean()?
+        .into_iter()
+        .chain(temp::remove_stale()?)
+        .collect::<Vec<_>>();
+
+    if !common.quiet {
+        if removed.is_empty() {
+            println!("Nothing to remove");
+        }
+        for path in removed {
+            println!("Removed {}", path.display());
+        }
+    }
+
+    Ok(())
+}
+
+fn list_worlds(common: Common) -> Result<()> {
+    print!(
+        "{}",
+        crate::list_worlds(
+            &common
+                .wit_path
+                .unwrap_or_else(|| Path::new("wit").to_owned()),
+            common.primary_package.as_deref(),
+            &common.features,
+            common.all_features,
+        )?
+    );
+
+    Ok(())
+}
+
+fn gen_proptests(common: Common, proptests: GenProptests) -> Result<()> {
+    let world_module = crate::generate_proptests(
         &common
             .wit_path
             .unwrap_or_else(|| Path::new("wit").to_owned()),
         common.world.as_deref(),
+        common.primary_package.as_deref(),
         &common.features,
         common.all_features,
-        bindings.world_module.as_deref(),
-        &bindings.output_dir,
+        proptests.world_module.as_deref(),
+        &proptests.app_name,
+        &proptests.output,
         &common
             .import_interface_name
             .iter()
@@ -169,13 +979,45 @@ fn generate_bindings(common: Common, bindings: Bindings) -> Result<()> {
             .iter()
             .map(|(a, b)| (a.as_str(), b.as_str()))
             .collect(),
-    )
+        common.naming_style,
+        common.version_collisions,
+        &world_module_site_packages(proptests.world_module.as_deref())?,
+    )?;
+    report_world_module(&world_module, common.quiet);
+
+    if !common.quiet {
+        println!("Wrote contract tests to {}", proptests.output.display());
+    }
+
+    Ok(())
 }
 
-fn componentize(common: Common, componentize: Componentize) -> Result<()> {
-    let mut python_path = componentize.python_path;
+fn scaffold_host(common: Common, scaffold: ScaffoldHost) -> Result<()> {
+    let files = crate::scaffold_host(
+        &common
+            .wit_path
+            .unwrap_or_else(|| Path::new("wit").to_owned()),
+        common.world.as_deref(),
+        common.primary_package.as_deref(),
+        &common.features,
+        common.all_features,
+        scaffold.language,
+        &scaffold.output_dir,
+        &scaffold.component,
+    )?;
+
+    if !common.quiet {
+        for file in files {
+            println!("Wrote {}", file.display());
+        }
+    }
+
+    Ok(())
+}
 
-    for site_packages in find_site_packages()? {
+fn test(common: Common, test: Test) -> Result<()> {
+    let mut python_path = test.python_path;
+    for site_packages in find_site_packages(&test.site_packages)? {
         python_path.push(
             site_packages
                 .to_str()
@@ -183,25 +1025,83 @@ fn componentize(common: Common, componentize: Componentize) -> Result<()> {
                 .to_owned(),
         );
     }
+    let python_path = python_path.iter().map(|s| s.as_str()).collect::<Vec<_>>();
 
-    Runtime::new()?.block_on(crate::componentize(
-        common.wit_path.as_deref(),
-        common.world.as_deref(),
-        &common.features,
-        common.all_features,
-        &python_path.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
-        &componentize
-            .module_worlds
-            .iter()
-            .map(|(k, v)| (k.as_str(), v.as_str()))
-            .collect::<Vec<_>>(),
-        &componentize.app_name,
-        &componentize.output,
-        None,
-        componentize.stub_wasi,
-        &common
-            .import_interface_name
-            .iter()
+    let exclude_paths = test
+        .exclude_path
+        .iter()
+        .map(|s| s.as_str())
+        .collect::<Vec<_>>();
+
+    let mut builder = crate::ComponentizeBuilder::new()
+        .features(&common.features)
+        .all_features(common.all_features)
+        .python_path(&python_path)
+        .exclude_paths(&exclude_paths)
+        .import_interface_names(
+            common
+                .import_interface_name
+                .iter()
+                .map(|(a, b)| (a.as_str(), b.as_str()))
+                .collect(),
+        )
+        .export_interface_names(
+            common
+                .export_interface_name
+                .iter()
+                .map(|(a, b)| (a.as_str(), b.as_str()))
+                .collect(),
+        )
+        .naming_style(common.naming_style)
+        .version_collisions(common.version_collisions)
+        .quiet(common.quiet);
+    if let Some(wit_path) = &common.wit_path {
+        builder = builder.wit_path(wit_path);
+    }
+    if let Some(world) = &common.world {
+        builder = builder.world(world);
+    }
+    if let Some(primary_package) = &common.primary_package {
+        builder = builder.primary_package(primary_package);
+    }
+
+    let code = Runtime::new()?.block_on(
+        builder.test(
+            test.app_name.as_deref(),
+            &test
+                .pytest_args
+                .iter()
+                .map(|s| s.as_str())
+                .collect::<Vec<_>>(),
+            test.junit_xml.as_deref(),
+        ),
+    )?;
+
+    if let (Some(path), false) = (&test.junit_xml, common.quiet) {
+        println!("Wrote test results to {}", path.display());
+    }
+
+    if code != 0 {
+        bail!("pytest exited with code {code}");
+    }
+
+    Ok(())
+}
+
+fn migrate_bindings(common: Common, migrate: MigrateBindings) -> Result<()> {
+    let notes = crate::migrate_bindings(
+        &migrate.old_wit_path,
+        migrate.old_world.as_deref().or(common.world.as_deref()),
+        &common
+            .wit_path
+            .unwrap_or_else(|| Path::new("wit").to_owned()),
+        common.world.as_deref(),
+        common.primary_package.as_deref(),
+        &common.features,
+        common.all_features,
+        &common
+            .import_interface_name
+            .iter()
             .map(|(a, b)| (a.as_str(), b.as_str()))
             .collect(),
         &common
@@ -209,80 +1109,508 @@ fn componentize(common: Common, componentize: Componentize) -> Result<()> {
             .iter()
             .map(|(a, b)| (a.as_str(), b.as_str()))
             .collect(),
-    ))?;
+        common.naming_style,
+        common.version_collisions,
+    )?;
 
-    if !common.quiet {
-        println!("Component built successfully");
+    for note in &notes {
+        println!("- {note}");
+    }
+
+    if notes.is_empty() && !common.quiet {
+        println!("No changes require the app to be updated");
+    }
+
+    Ok(())
+}
+
+fn summary(common: Common, summary: Summary) -> Result<()> {
+    let (api, world_module) = crate::describe_world(
+        &common
+            .wit_path
+            .unwrap_or_else(|| Path::new("wit").to_owned()),
+        common.world.as_deref(),
+        common.primary_package.as_deref(),
+        &common.features,
+        common.all_features,
+        summary.world_module.as_deref(),
+        &common
+            .import_interface_name
+            .iter()
+            .map(|(a, b)| (a.as_str(), b.as_str()))
+            .collect(),
+        &common
+            .export_interface_name
+            .iter()
+            .map(|(a, b)| (a.as_str(), b.as_str()))
+            .collect(),
+        common.naming_style,
+        common.version_collisions,
+        &world_module_site_packages(summary.world_module.as_deref())?,
+    )?;
+    report_world_module(&world_module, common.quiet);
+
+    match summary.format {
+        api::Format::Text => print!("{api}"),
+        api::Format::Json => println!("{}", serde_json::to_string_pretty(&api)?),
     }
 
     Ok(())
 }
 
-fn find_site_packages() -> Result<Vec<PathBuf>> {
-    Ok(if let Ok(env) = env::var("VIRTUAL_ENV") {
-        let dir = Path::new(&env).join("lib");
+fn generate_bindings(common: Common, bindings: Bindings) -> Result<()> {
+    let wit_path = common
+        .wit_path
+        .clone()
+        .unwrap_or_else(|| Path::new("wit").to_owned());
+    let site_packages = world_module_site_packages(bindings.world_module.as_deref())?;
+
+    let generate = || {
+        let mut builder = crate::BindingsBuilder::new()
+            .wit_path(&wit_path)
+            .features(&common.features)
+            .all_features(common.all_features)
+            .site_packages(&site_packages)
+            .output_dir(&bindings.output_dir)
+            .import_interface_names(
+                common
+                    .import_interface_name
+                    .iter()
+                    .map(|(a, b)| (a.as_str(), b.as_str()))
+                    .collect(),
+            )
+            .export_interface_names(
+                common
+                    .export_interface_name
+                    .iter()
+                    .map(|(a, b)| (a.as_str(), b.as_str()))
+                    .collect(),
+            )
+            .typing_style(bindings.typing_style)
+            .naming_style(common.naming_style)
+            .version_collisions(common.version_collisions)
+            .format(bindings.format);
 
-        if let Some(site_packages) = find_dir("site-packages", &dir)? {
-            vec![site_packages]
-        } else {
-            eprintln!(
-                "warning: site-packages directory not found under {}",
-                dir.display()
-            );
-            Vec::new()
+        if let Some(world) = &common.world {
+            builder = builder.world(world);
+        }
+        if let Some(primary_package) = &common.primary_package {
+            builder = builder.primary_package(primary_package);
         }
+        if let Some(world_module) = &bindings.world_module {
+            builder = builder.world_module(world_module);
+        }
+
+        let (paths, world_module) = builder.generate()?;
+        report_world_module(&world_module, common.quiet);
+        Ok(paths)
+    };
+
+    if bindings.watch {
+        watch(&wit_path, common.quiet, common.error_format, generate)
     } else {
-        let pipenv_packages = match process::Command::new("pipenv").arg("--venv").output() {
-            Ok(output) => {
-                if output.status.success() {
-                    let dir = Path::new(str::from_utf8(&output.stdout)?.trim()).join("lib");
-
-                    if let Some(site_packages) = find_dir("site-packages", &dir)? {
-                        vec![site_packages]
-                    } else {
-                        eprintln!(
-                            "warning: site-packages directory not found under {}",
-                            dir.display()
-                        );
-                        Vec::new()
+        generate().map(drop)
+    }
+}
+
+/// Return the directories, other than the one the bindings are written to, in which `AUTO_WORLD_MODULE` should
+/// check for collisions, i.e. the `site-packages` directories of the app's virtual environment, if any.
+///
+/// Finding the environment may involve running `pipenv` or `poetry`, so we only look for it if `world_module` is
+/// `AUTO_WORLD_MODULE`.
+fn world_module_site_packages(world_module: Option<&str>) -> Result<Vec<PathBuf>> {
+    if world_module == Some(crate::AUTO_WORLD_MODULE) {
+        if let Some(venv) = find_venv() {
+            return venv_site_packages(&venv);
+        }
+    }
+    Ok(Vec::new())
+}
+
+/// Print the reason a name other than the one derived from the world was chosen for the bindings module, if any.
+fn report_world_module(world_module: &crate::WorldModule, quiet: bool) {
+    if let Some(note) = &world_module.note {
+        if !quiet {
+            eprintln!("note: {note}");
+        }
+    }
+}
+
+/// How often `watch` checks whether the WIT path has changed
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Call `generate` immediately and then again each time the contents of `wit_path` change, never returning.
+///
+/// `generate` returns the paths of the files it generated, and any file generated last time but not this time (e.g.
+/// the module for an interface which has since been removed from the WIT) is deleted.
+fn watch(
+    wit_path: &Path,
+    quiet: bool,
+    error_format: ErrorFormat,
+    generate: impl Fn() -> Result<BTreeSet<PathBuf>>,
+) -> Result<()> {
+    let mut stamp = None;
+    let mut digest = None;
+    let mut generated = BTreeSet::new();
+    loop {
+        // Only hash the contents if a file has been added, removed, or modified (according to its size and
+        // modification time) since last time.  If reading the directory fails (e.g. because an editor is in the
+        // middle of replacing a file), just try again next time.
+        if let Ok(current) = cache::stamp_tree(wit_path) {
+            if stamp.as_ref() != Some(&current) {
+                if let Ok(current_digest) = cache::digest_tree(wit_path) {
+                    stamp = Some(current);
+                    if digest != Some(current_digest) {
+                        digest = Some(current_digest);
+                        match generate() {
+                            Ok(paths) => {
+                                for stale in generated.difference(&paths) {
+                                    remove_generated(stale);
+                                }
+                                generated = paths;
+
+                                if !quiet {
+                                    eprintln!("Generated bindings for {}", wit_path.display());
+                                }
+                            }
+                            Err(e) => {
+                                report::print(&e, error_format, Some(Phase::GeneratingBindings))
+                            }
+                        }
                     }
-                } else {
-                    // `pipenv` is in `$PATH`, but this app does not appear to be using it
-                    Vec::new()
                 }
             }
-            Err(_) => {
-                // `pipenv` is not in `$PATH -- assume this app isn't using it
-                Vec::new()
-            }
-        };
+        }
+
+        thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+/// Delete the previously generated file at `path`, along with any directories left empty as a result (e.g. a
+/// sharded module's package), ignoring errors since the user may already have removed or replaced them.
+fn remove_generated(path: &Path) {
+    let _ = fs::remove_file(path);
+    let mut dir = path.parent();
+    while let Some(parent) = dir {
+        if fs::remove_dir(parent).is_err() {
+            break;
+        }
+        dir = parent.parent();
+    }
+}
+
+fn componentize(common: Common, componentize: Componentize, callbacks: &Callbacks) -> Result<()> {
+    let mut python_path = componentize.python_path;
+    let _stdin_tree =
+        unpack_stdin_tree(&mut python_path, io::stdin().lock(), componentize.keep_temp)?;
+
+    for site_packages in find_site_packages(&componentize.site_packages)? {
+        python_path.push(
+            site_packages
+                .to_str()
+                .context("non-UTF-8 site-packages name")?
+                .to_owned(),
+        );
+    }
+
+    let to_stdout = componentize.output_dir.is_none() && componentize.output == Path::new("-");
+    if to_stdout {
+        if componentize.notices == Some(Notices::File) {
+            bail!("`--notices file` requires `--output` to name a file rather than `-`");
+        }
+        if componentize.size_report {
+            bail!(
+                "`--size-report` cannot be combined with `--output -` since both write to `stdout`"
+            );
+        }
+    }
 
-        if !pipenv_packages.is_empty() {
-            pipenv_packages
+    // When writing to `stdout`, build to a temporary file first, and send anything the app prints during
+    // pre-initialization to `stderr` so it doesn't corrupt the component.
+    let stdout_dir = to_stdout
+        .then(|| temp::dir(componentize.keep_temp))
+        .transpose()?;
+    let callbacks = if to_stdout {
+        Callbacks {
+            stdout: callbacks.stdout.clone().or_else(|| {
+                (!common.quiet).then(|| {
+                    Arc::new(|bytes: &[u8]| drop(io::stderr().write_all(bytes))) as OutputCallback
+                })
+            }),
+            ..callbacks.clone()
+        }
+    } else {
+        callbacks.clone()
+    };
+
+    let output = if let Some(dir) = &componentize.output_dir {
+        fs::create_dir_all(dir)?;
+        dir.join(format!("{}.wasm", componentize.app_name))
+    } else if let Some(dir) = &stdout_dir {
+        dir.path().join(format!("{}.wasm", componentize.app_name))
+    } else {
+        componentize.output
+    };
+
+    let python_path = python_path.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+    let import_interface_names = common
+        .import_interface_name
+        .iter()
+        .map(|(a, b)| (a.as_str(), b.as_str()))
+        .collect();
+    let export_interface_names = common
+        .export_interface_name
+        .iter()
+        .map(|(a, b)| (a.as_str(), b.as_str()))
+        .collect();
+
+    Runtime::new()?.block_on(
+        crate::ComponentizeBuilder {
+            wit_path: common.wit_path.as_deref(),
+            world: common.world.as_deref(),
+            primary_package: common.primary_package.as_deref(),
+            features: &common.features,
+            all_features: common.all_features,
+            python_path: &python_path,
+            exclude_paths: &componentize
+                .exclude_path
+                .iter()
+                .map(|s| s.as_str())
+                .collect::<Vec<_>>(),
+            static_libraries: &componentize
+                .static_library
+                .iter()
+                .map(|s| s.as_str())
+                .collect::<Vec<_>>(),
+            dynamic_libraries: &componentize
+                .dynamic_library
+                .iter()
+                .map(|s| s.as_str())
+                .collect::<Vec<_>>(),
+            module_worlds: &componentize
+                .module_worlds
+                .iter()
+                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .collect::<Vec<_>>(),
+            app_name: &componentize.app_name,
+            output_path: &output,
+            add_to_linker: None,
+            stub_wasi: componentize.stub_wasi,
+            profile: componentize.profile,
+            import_interface_names,
+            export_interface_names,
+            naming_style: common.naming_style,
+            version_collisions: common.version_collisions,
+            map_source_paths: componentize.map_source_paths,
+            quiet: common.quiet,
+            optimize: optimize::Options {
+                exclude_modules: &componentize
+                    .exclude_module
+                    .iter()
+                    .map(|s| s.as_str())
+                    .collect::<Vec<_>>(),
+                include_modules: &componentize
+                    .include_module
+                    .iter()
+                    .map(|s| s.as_str())
+                    .collect::<Vec<_>>(),
+                strip_debug: componentize.strip_debug,
+                compression: componentize.compression,
+                size_report: componentize.size_report,
+            },
+            exception_mappings: &componentize
+                .map_exception
+                .iter()
+                .map(|(a, b)| (a.as_str(), b.as_str()))
+                .collect::<Vec<_>>(),
+            diagnostics: componentize.diagnostics,
+            strict: componentize.strict,
+            trace_imports: componentize.trace_imports,
+            shutdown_hook: componentize.shutdown_hook,
+            unbuffered: componentize.unbuffered,
+            embed_stdlib: componentize.embed_stdlib,
+            compose: &componentize
+                .compose
+                .iter()
+                .map(|path| path.as_path())
+                .collect::<Vec<_>>(),
+            wasi_adapter: componentize.wasi_adapter.as_deref(),
+            artifact_dir: componentize.artifact_dir.as_deref(),
+            import_time_budget: componentize.import_time_budget,
+            pre_init_timeout: componentize.pre_init_timeout,
+            pre_init_hook: componentize.pre_init_hook.as_deref(),
+            output_capture_limit: componentize.output_capture_limit,
+            metadata: &componentize
+                .metadata
+                .iter()
+                .map(|(a, b)| (a.as_str(), b.as_str()))
+                .collect::<Vec<_>>(),
+            notices: componentize.notices,
+            deterministic: componentize.deterministic,
+            keep_temp: componentize.keep_temp,
+            cache: !componentize.no_cache,
+            callbacks,
+            ..crate::ComponentizeBuilder::new()
+        }
+        .build(),
+    )?;
+
+    if let Some(dir) = &componentize.output_dir {
+        bundle::write(
+            dir,
+            &output,
+            &bundle::Inputs {
+                app_name: &componentize.app_name,
+                wit_path: common.wit_path.as_deref(),
+                world: common.world.as_deref(),
+                primary_package: common.primary_package.as_deref(),
+                features: &common.features,
+                all_features: common.all_features,
+                python_path: &python_path,
+                stub_wasi: componentize.stub_wasi,
+                profile: componentize.profile,
+                import_interface_names: &import_interface_names,
+                export_interface_names: &export_interface_names,
+                naming_style: common.naming_style,
+                version_collisions: common.version_collisions,
+            },
+        )?;
+    }
+
+    if to_stdout {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(&fs::read(&output).with_context(|| output.display().to_string())?)?;
+        stdout.flush()?;
+    }
+
+    if !common.quiet {
+        if to_stdout {
+            eprintln!("Component built successfully");
         } else {
-            // Get site packages location using the `site` module in python
-            match process::Command::new("python3")
-                .args([
-                    "-c",
-                    "import site; \
-                     list = site.getsitepackages(); \
-                     list.insert(0, site.getusersitepackages()); \
-                     print(';'.join(list))",
-                ])
-                .output()
-            {
-                Ok(output) => str::from_utf8(&output.stdout)?
-                    .trim()
-                    .split(';')
-                    .map(|p| Path::new(p).to_path_buf())
-                    .collect(),
-                Err(_) => Vec::new(),
+            println!("Component built successfully");
+        }
+    }
+
+    Ok(())
+}
+
+/// If `python_path` contains `-`, extract the tar stream read from `stdin` to a temporary directory and replace
+/// the `-` with that directory's path, returning the directory so the caller can keep it alive until the build has
+/// finished.
+fn unpack_stdin_tree(
+    python_path: &mut [String],
+    stdin: impl Read,
+    keep_temp: bool,
+) -> Result<Option<temp::TempDir>> {
+    let mut paths = python_path.iter_mut().filter(|path| *path == "-");
+    let Some(path) = paths.next() else {
+        return Ok(None);
+    };
+    if paths.next().is_some() {
+        bail!("`-` may be specified at most once in the Python path");
+    }
+
+    let dir = temp::dir(keep_temp)?;
+    tar::Archive::new(stdin)
+        .unpack(dir.path())
+        .context("failed to extract app source tree from stdin")?;
+    *path = dir
+        .path()
+        .to_str()
+        .context("non-UTF-8 temporary directory name")?
+        .to_owned();
+
+    Ok(Some(dir))
+}
+
+/// Return the `site-packages` directories to append to the Python path, as described for
+/// `Componentize::python_path`.
+fn find_site_packages(explicit: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    if !explicit.is_empty() {
+        return explicit
+            .iter()
+            .map(|dir| {
+                if dir.is_dir() {
+                    Ok(dir.canonicalize()?)
+                } else {
+                    bail!("site-packages directory {} not found", dir.display())
+                }
+            })
+            .collect();
+    }
+
+    if let Some(venv) = find_venv() {
+        return venv_site_packages(&venv);
+    }
+
+    // Get site packages location using the `site` module in python
+    Ok(
+        match process::Command::new("python3")
+            .args([
+                "-c",
+                "import site; \
+                 list = site.getsitepackages(); \
+                 list.insert(0, site.getusersitepackages()); \
+                 print(';'.join(list))",
+            ])
+            .output()
+        {
+            Ok(output) => str::from_utf8(&output.stdout)?
+                .trim()
+                .split(';')
+                .map(|p| Path::new(p).to_path_buf())
+                .collect(),
+            Err(_) => Vec::new(),
+        },
+    )
+}
+
+/// Return the path of the app's virtual environment, if any, i.e. `$VIRTUAL_ENV`, `$UV_PROJECT_ENVIRONMENT`, a
+/// `.venv` directory in the current directory, or the environment reported by `pipenv` or `poetry`.
+pub(crate) fn find_venv() -> Option<PathBuf> {
+    env::var_os("VIRTUAL_ENV")
+        .or_else(|| env::var_os("UV_PROJECT_ENVIRONMENT"))
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(".venv")).filter(|dir| dir.join("pyvenv.cfg").exists()))
+        .or_else(|| tool_venv("pipenv", &["--venv"]))
+        .or_else(|| tool_venv("poetry", &["env", "info", "--path"]))
+}
+
+/// Run the specified environment manager (e.g. `pipenv`) to ask it for the path of the app's virtual environment,
+/// if any.
+fn tool_venv(tool: &str, args: &[&str]) -> Option<PathBuf> {
+    // If the tool isn't in `$PATH`, or it is but this app does not appear to be using it, there's no environment.
+    let output = process::Command::new(tool).args(args).output().ok()?;
+    let path = str::from_utf8(&output.stdout).ok()?.trim();
+    (output.status.success() && !path.is_empty()).then(|| PathBuf::from(path))
+}
+
+/// Return the `site-packages` directories of the virtual environment at `venv`.
+///
+/// On Windows, these are found under `Lib`; elsewhere, pure Python packages (i.e. "purelib") are found under `lib`,
+/// while those containing native code (i.e. "platlib") may instead be found under `lib64`, which may or may not be
+/// a symlink to `lib`.
+fn venv_site_packages(venv: &Path) -> Result<Vec<PathBuf>> {
+    let mut dirs = Vec::new();
+    for lib in ["lib", "lib64", "Lib"] {
+        if let Some(dir) = find_dir("site-packages", &venv.join(lib))? {
+            if !dirs.contains(&dir) {
+                dirs.push(dir);
             }
         }
-    })
+    }
+
+    if dirs.is_empty() {
+        eprintln!(
+            "warning: site-packages directory not found under {}",
+            venv.display()
+        );
+    }
+
+    Ok(dirs)
 }
 
-fn find_dir(name: &str, path: &Path) -> Result<Option<PathBuf>> {
+pub(crate) fn find_dir(name: &str, path: &Path) -> Result<Option<PathBuf>> {
     if path.is_dir() {
         match path.file_name().and_then(|name| name.to_str()) {
             Some(this_name) if this_name == name => {
@@ -303,10 +1631,83 @@ fn find_dir(name: &str, path: &Path) -> Result<Option<PathBuf>> {
 
 #[cfg(test)]
 mod tests {
-    use std::io::Write;
+    use std::{collections::HashMap, io::Write, time::SystemTime};
 
     use super::*;
 
+    /// Return the options for generating bindings for the WIT file at `wit_path` (or, if `None`, the WIT
+    /// directories listed in `componentize-py.toml` files), leaving everything else at its default.
+    fn common_options(wit_path: Option<&Path>) -> Common {
+        Common {
+            wit_path: wit_path.map(Path::to_owned),
+            world: None,
+            primary_package: None,
+            quiet: false,
+            features: Vec::new(),
+            all_features: false,
+            import_interface_name: Vec::new(),
+            export_interface_name: Vec::new(),
+            error_format: ErrorFormat::Human,
+            naming_style: NamingStyle::Snake,
+            version_collisions: VersionCollisions::Qualify,
+        }
+    }
+
+    /// Return the options for writing bindings to `output_dir`, leaving everything else at its default.
+    fn bindings_options(output_dir: &Path) -> Bindings {
+        Bindings {
+            output_dir: output_dir.to_owned(),
+            world_module: None,
+            typing_style: TypingStyle::Legacy,
+            format: BindingsFormat::Directory,
+            watch: false,
+        }
+    }
+
+    /// Return the options for building a component from the `app` module in `dir` and writing it to
+    /// `dir/app.wasm`, leaving everything else at its default except for bypassing the build cache, which tests
+    /// mustn't share with the user (or pass by reusing an earlier build).
+    fn componentize_options(dir: &Path) -> Componentize {
+        Componentize {
+            app_name: "app".to_owned(),
+            python_path: vec![dir.to_string_lossy().into()],
+            site_packages: Vec::new(),
+            exclude_path: Vec::new(),
+            static_library: Vec::new(),
+            dynamic_library: Vec::new(),
+            module_worlds: Vec::new(),
+            output: dir.join("app.wasm"),
+            output_dir: None,
+            stub_wasi: false,
+            profile: Profile::Cli,
+            map_source_paths: false,
+            exclude_module: Vec::new(),
+            include_module: Vec::new(),
+            strip_debug: false,
+            compression: None,
+            size_report: false,
+            map_exception: Vec::new(),
+            diagnostics: false,
+            strict: false,
+            trace_imports: false,
+            shutdown_hook: false,
+            unbuffered: false,
+            embed_stdlib: false,
+            compose: Vec::new(),
+            wasi_adapter: None,
+            artifact_dir: None,
+            import_time_budget: None,
+            pre_init_timeout: None,
+            pre_init_hook: None,
+            output_capture_limit: None,
+            metadata: Vec::new(),
+            notices: None,
+            deterministic: false,
+            keep_temp: false,
+            no_cache: true,
+        }
+    }
+
     /// Generates a WIT file which has unstable feature "x"
     fn gated_x_wit_file() -> Result<tempfile::NamedTempFile, anyhow::Error> {
         let mut wit = tempfile::Builder::new()
@@ -336,19 +1737,8 @@ mod tests {
         let out_dir = tempfile::tempdir()?;
 
         // When generating the bindings for this WIT world
-        let common = Common {
-            wit_path: Some(wit.path().into()),
-            world: None,
-            quiet: false,
-            features: vec![],
-            all_features: false,
-            import_interface_name: Vec::new(),
-            export_interface_name: Vec::new(),
-        };
-        let bindings = Bindings {
-            output_dir: out_dir.path().into(),
-            world_module: None,
-        };
+        let common = common_options(Some(wit.path()));
+        let bindings = bindings_options(out_dir.path());
         generate_bindings(common, bindings)?;
 
         // Then the gated feature doesn't appear
@@ -359,6 +1749,60 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn bindings_regenerated_in_place() -> Result<()> {
+        // Given bindings already generated for a WIT world
+        let wit = gated_x_wit_file()?;
+        let out_dir = tempfile::tempdir()?;
+        let mut common = common_options(Some(wit.path()));
+        let bindings = || bindings_options(out_dir.path());
+        generate_bindings(common.clone(), bindings())?;
+        let types = out_dir.path().join("bindings/types.py");
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(86400);
+        fs::File::options()
+            .write(true)
+            .open(&types)?
+            .set_modified(old)?;
+
+        // When regenerating them into the same directory after the world has changed
+        common.features = vec!["x".to_owned()];
+        generate_bindings(common, bindings())?;
+
+        // Then the changed module is rewritten, but the unchanged one is left alone
+        let generated = fs::read_to_string(out_dir.path().join("bindings/__init__.py"))?;
+        assert!(generated.contains("def x() -> None:"));
+        assert_eq!(old, fs::metadata(&types)?.modified()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn auto_world_module_avoids_collisions() -> Result<()> {
+        // Given a WIT world and an output directory containing an app module whose name matches the one `auto`
+        // would derive for that world
+        let wit = gated_x_wit_file()?;
+        let out_dir = tempfile::tempdir()?;
+        fs::write(out_dir.path().join("foo_bar_bindings.py"), "")?;
+        let common = common_options(Some(wit.path()));
+        let bindings = || Bindings {
+            world_module: Some(crate::AUTO_WORLD_MODULE.to_owned()),
+            ..bindings_options(out_dir.path())
+        };
+
+        // When generating (and then regenerating) bindings with `--world-module auto`
+        generate_bindings(common.clone(), bindings())?;
+        generate_bindings(common, bindings())?;
+
+        // Then the bindings are written to a suffixed module, and regenerating them reuses it
+        assert!(out_dir
+            .path()
+            .join("foo_bar_bindings_bindings/__init__.py")
+            .exists());
+        assert!(!out_dir.path().join("foo_bar_bindings_bindings2").exists());
+
+        Ok(())
+    }
+
     #[test]
     fn unstable_bindings_generated_with_feature_flag() -> Result<()> {
         // Given a WIT file with gated features
@@ -367,18 +1811,10 @@ mod tests {
 
         // When generating the bindings for this WIT world
         let common = Common {
-            wit_path: Some(wit.path().into()),
-            world: None,
-            quiet: false,
             features: vec!["x".to_owned()],
-            all_features: false,
-            import_interface_name: Vec::new(),
-            export_interface_name: Vec::new(),
-        };
-        let bindings = Bindings {
-            output_dir: out_dir.path().into(),
-            world_module: None,
+            ..common_options(Some(wit.path()))
         };
+        let bindings = bindings_options(out_dir.path());
         generate_bindings(common, bindings)?;
 
         // Then the gated feature doesn't appear
@@ -397,18 +1833,10 @@ mod tests {
 
         // When generating the bindings for this WIT world
         let common = Common {
-            wit_path: Some(wit.path().into()),
-            world: None,
-            quiet: false,
-            features: vec![],
             all_features: true,
-            import_interface_name: Vec::new(),
-            export_interface_name: Vec::new(),
-        };
-        let bindings = Bindings {
-            output_dir: out_dir.path().into(),
-            world_module: None,
+            ..common_options(Some(wit.path()))
         };
+        let bindings = bindings_options(out_dir.path());
         generate_bindings(common, bindings)?;
 
         // Then the gated feature doesn't appear
@@ -420,44 +1848,1340 @@ mod tests {
     }
 
     #[test]
-    fn unstable_features_used_in_componentize() -> Result<()> {
-        // Given bindings to a WIT file with gated features and a Python file that uses them
+    fn worlds_listed_with_features() -> Result<()> {
+        // Given a WIT file with gated features
         let wit = gated_x_wit_file()?;
+
+        // When listing its worlds with and without the feature enabled
+        let without = crate::list_worlds(wit.path(), None, &[], false)?;
+        let with = crate::list_worlds(wit.path(), None, &["x".to_owned()], false)?;
+
+        // Then the gated import is only listed when the feature is enabled
+        assert_eq!(
+            "package foo:bar@1.2.3\n  world bindings (default)\n    export y: func\n",
+            without
+        );
+        assert_eq!(
+            "package foo:bar@1.2.3\n  world bindings (default)\n    import x: func\n    export y: func\n",
+            with
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn worlds_listed_with_primary_package() -> Result<()> {
+        // Given a WIT file whose main package has two worlds and which also defines another package
+        let mut wit = tempfile::Builder::new().suffix(".wit").tempfile()?;
+        write!(
+            wit,
+            r#"
+            package foo:app;
+
+            world a {{}}
+            world b {{}}
+
+            package foo:dep {{
+                world only {{}}
+            }}
+        "#,
+        )?;
+
+        // When listing its worlds with and without a primary package
+        let without = crate::list_worlds(wit.path(), None, &[], false)?;
+        let with = crate::list_worlds(wit.path(), Some("foo:dep"), &[], false)?;
+
+        // Then no default is chosen from the main package, but the primary package's sole world is the default
+        assert_eq!(
+            "package foo:app
+  world a
+  world b
+package foo:dep
+  world foo:dep/only
+",
+            without
+        );
+        assert_eq!(
+            "package foo:dep
+  world only (default)
+package foo:app
+  world foo:app/a
+  world foo:app/b
+",
+            with
+        );
+
+        // And generating bindings without specifying a world is an error only in the ambiguous case
         let out_dir = tempfile::tempdir()?;
-        let common = Common {
-            wit_path: Some(wit.path().into()),
-            world: None,
-            quiet: false,
-            features: vec!["x".to_owned()],
-            all_features: false,
-            import_interface_name: Vec::new(),
-            export_interface_name: Vec::new(),
-        };
-        let bindings = Bindings {
-            output_dir: out_dir.path().into(),
-            world_module: None,
+        let generate = |primary_package: Option<&str>| {
+            crate::generate_bindings(
+                wit.path(),
+                None,
+                primary_package,
+                &[],
+                false,
+                None,
+                out_dir.path(),
+                &HashMap::new(),
+                &HashMap::new(),
+                TypingStyle::Legacy,
+                NamingStyle::Snake,
+                VersionCollisions::Qualify,
+                BindingsFormat::Directory,
+            )
         };
-        generate_bindings(common.clone(), bindings)?;
+        let error = generate(None).unwrap_err();
+        assert!(format!("{error:?}").contains("--primary-package"));
+        generate(Some("foo:dep"))?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn project_config_supplies_defaults() -> Result<()> {
+        // Given a project configuration file
+        let dir = tempfile::tempdir()?;
         fs::write(
-            out_dir.path().join("app.py"),
+            dir.path().join(PROJECT_CONFIG),
             r#"
-import bindings
-from bindings import x
+wit_path = "wit"
+world = "app"
+features = ["x"]
+python_path = ["src", "deps"]
+exclude_paths = ["/docs/"]
+static_libraries = ["pkg.libs/*.so"]
+module_worlds = ["*_service=my:world"]
+pre_init_hook = "warm_caches"
+output = "build/app.wasm"
 
-class Bindings(bindings.Bindings):
-    def y(self) -> None:
-        x()
+[import_interface_names]
+"foo:bar/baz" = "baz"
+"foo:bar/qux" = "qux"
 "#,
         )?;
 
-        // Building the component succeeds
-        let componentize_opts = Componentize {
-            app_name: "app".to_owned(),
-            python_path: vec![out_dir.path().to_string_lossy().into()],
-            module_worlds: vec![],
-            output: out_dir.path().join("app.wasm"),
-            stub_wasi: false,
+        // When parsing a command line which specifies only some options
+        let options = parse_options(
+            [
+                "componentize-py",
+                "--features",
+                "y",
+                "--import-interface-name",
+                "foo:bar/baz=my_baz",
+                "componentize",
+                "--output",
+                "other.wasm",
+                "--exclude-path",
+                "*.log",
+                "--static-library",
+                "libfoo.so",
+                "my_app",
+            ],
+            dir.path(),
+        )?;
+
+        // Then the rest are taken from the file, with list and table entries merged
+        assert_eq!(Some(dir.path().join("wit")), options.common.wit_path);
+        assert_eq!(Some("app"), options.common.world.as_deref());
+        assert_eq!(vec!["y", "x"], options.common.features);
+        assert_eq!(
+            vec![
+                ("foo:bar/baz".to_owned(), "my_baz".to_owned()),
+                ("foo:bar/qux".to_owned(), "qux".to_owned())
+            ],
+            options.common.import_interface_name
+        );
+        let Command::Componentize(componentize) = options.command else {
+            panic!("expected `componentize` subcommand");
         };
-        componentize(common, componentize_opts)
+        assert_eq!(
+            vec![
+                dir.path().join("src").to_string_lossy(),
+                dir.path().join("deps").to_string_lossy()
+            ],
+            componentize.python_path
+        );
+        assert_eq!(vec!["/docs/", "*.log"], componentize.exclude_path);
+        assert_eq!(
+            vec!["pkg.libs/*.so", "libfoo.so"],
+            componentize.static_library
+        );
+        assert_eq!(
+            vec![("*_service".to_owned(), "my:world".to_owned())],
+            componentize.module_worlds
+        );
+        assert_eq!(Some("warm_caches"), componentize.pre_init_hook.as_deref());
+        assert_eq!(Path::new("other.wasm"), componentize.output);
+
+        Ok(())
+    }
+
+    #[test]
+    fn venv_site_packages_include_purelib_and_platlib() -> Result<()> {
+        // Given a virtual environment with separate purelib and platlib directories
+        let venv = tempfile::tempdir()?;
+        let purelib = venv.path().join("lib/python3.12/site-packages");
+        let platlib = venv.path().join("lib64/python3.12/site-packages");
+        fs::create_dir_all(&purelib)?;
+        fs::create_dir_all(&platlib)?;
+
+        // When searching it for `site-packages` directories
+        let dirs = venv_site_packages(venv.path())?;
+
+        // Then both are found
+        assert_eq!(vec![purelib.canonicalize()?, platlib.canonicalize()?], dirs);
+
+        Ok(())
+    }
+
+    #[test]
+    fn stale_bindings_removed() -> Result<()> {
+        // Given bindings generated for a world which imports two interfaces
+        let dir = tempfile::tempdir()?;
+        let wit = dir.path().join("world.wit");
+        let out_dir = dir.path().join("out");
+        let generate = |interfaces: &[&str]| {
+            fs::write(
+                &wit,
+                format!(
+                    "package foo:bar;\n{}\nworld bindings {{\n{}\n}}\n",
+                    interfaces
+                        .iter()
+                        .map(|name| format!("interface {name} {{ f: func(); }}"))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    interfaces
+                        .iter()
+                        .map(|name| format!("import {name};"))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ),
+            )?;
+            crate::generate_bindings(
+                &wit,
+                None,
+                None,
+                &[],
+                false,
+                None,
+                &out_dir,
+                &HashMap::new(),
+                &HashMap::new(),
+                TypingStyle::Legacy,
+                NamingStyle::Snake,
+                VersionCollisions::Qualify,
+                BindingsFormat::Directory,
+            )
+        };
+        let before = generate(&["a", "b"])?;
+
+        // When one of them is removed and the bindings are regenerated
+        let after = generate(&["a"])?;
+        for stale in before.difference(&after) {
+            remove_generated(stale);
+        }
+
+        // Then only the module for the removed interface is deleted
+        let imports = out_dir.join("bindings/imports");
+        assert!(imports.join("a.py").exists());
+        assert!(!imports.join("b.py").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn bindings_use_naming_style() -> Result<()> {
+        // Given a WIT file with multi-word function and parameter names
+        let mut wit = tempfile::Builder::new().suffix(".wit").tempfile()?;
+        write!(
+            wit,
+            r#"
+            package foo:bar;
+
+            world bindings {{
+                export get-value: func(max-count: u32) -> u32;
+            }}
+        "#,
+        )?;
+        let out_dir = tempfile::tempdir()?;
+        let generate = |naming_style| {
+            crate::generate_bindings(
+                wit.path(),
+                None,
+                None,
+                &[],
+                false,
+                None,
+                out_dir.path(),
+                &HashMap::new(),
+                &HashMap::new(),
+                TypingStyle::Legacy,
+                naming_style,
+                VersionCollisions::Qualify,
+                BindingsFormat::Directory,
+            )?;
+            Ok::<_, anyhow::Error>(fs::read_to_string(
+                out_dir.path().join("bindings/__init__.py"),
+            )?)
+        };
+
+        // When generating bindings using each naming style
+        // Then the names are converted accordingly
+        assert!(
+            generate(NamingStyle::Snake)?.contains("def get_value(self, max_count: int) -> int:")
+        );
+        assert!(generate(NamingStyle::Camel)?.contains("def getValue(self, maxCount: int) -> int:"));
+        assert!(generate(NamingStyle::Preserve)?
+            .contains("def get_value(self, max_count: int) -> int:"));
+
+        // And names which would collide after conversion are reported as errors
+        let mut wit = tempfile::Builder::new().suffix(".wit").tempfile()?;
+        write!(
+            wit,
+            r#"
+            package foo:bar;
+
+            world bindings {{
+                export get-value: func();
+                export get-VALUE: func();
+            }}
+        "#,
+        )?;
+        let error = crate::generate_bindings(
+            wit.path(),
+            None,
+            None,
+            &[],
+            false,
+            None,
+            out_dir.path(),
+            &HashMap::new(),
+            &HashMap::new(),
+            TypingStyle::Legacy,
+            NamingStyle::Snake,
+            VersionCollisions::Qualify,
+            BindingsFormat::Directory,
+        )
+        .unwrap_err();
+        assert!(format!("{error:?}").contains("would both be named `get_value`"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn interface_module_name_clashes_reported() -> Result<()> {
+        // Given a WIT file which imports two interfaces
+        let mut wit = tempfile::Builder::new().suffix(".wit").tempfile()?;
+        write!(
+            wit,
+            r#"
+            package foo:bar;
+
+            interface first {{
+                get: func() -> u32;
+            }}
+
+            interface second {{
+                get: func() -> u32;
+            }}
+
+            world bindings {{
+                import first;
+                import second;
+            }}
+        "#,
+        )?;
+        let out_dir = tempfile::tempdir()?;
+
+        // When generating bindings using a pattern which gives both the same module name
+        let error = crate::generate_bindings(
+            wit.path(),
+            None,
+            None,
+            &[],
+            false,
+            None,
+            out_dir.path(),
+            &[("foo:bar/*", "bar")].into(),
+            &HashMap::new(),
+            TypingStyle::Legacy,
+            NamingStyle::Snake,
+            VersionCollisions::Qualify,
+            BindingsFormat::Directory,
+        )
+        .unwrap_err();
+
+        // Then the clash is reported as an error
+        assert!(format!("{error:?}").contains(
+            "interfaces `foo:bar/first`, `foo:bar/second` would all use the Python module name `bar`"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn wide_flags_reported() -> Result<()> {
+        // Given a WIT file declaring a flags type with more than 32 flags
+        let flags = (0..33)
+            .map(|index| format!("f{index}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut wit = tempfile::Builder::new().suffix(".wit").tempfile()?;
+        write!(
+            wit,
+            r#"
+            package foo:bar;
+
+            interface types {{
+                flags wide {{ {flags} }}
+                get: func() -> wide;
+            }}
+
+            world bindings {{
+                import types;
+            }}
+        "#,
+        )?;
+        let out_dir = tempfile::tempdir()?;
+
+        // When generating bindings
+        let error = crate::generate_bindings(
+            wit.path(),
+            None,
+            None,
+            &[],
+            false,
+            None,
+            out_dir.path(),
+            &HashMap::new(),
+            &HashMap::new(),
+            TypingStyle::Legacy,
+            NamingStyle::Snake,
+            VersionCollisions::Qualify,
+            BindingsFormat::Directory,
+        )
+        .unwrap_err();
+
+        // Then the type is reported as an error rather than silently generating unusable bindings
+        assert!(format!("{error:?}")
+            .contains("flags type `wide` has 33 flags, but the component model allows at most 32"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn bindings_migration_described() -> Result<()> {
+        // Given two versions of a world
+        let mut old_wit = tempfile::Builder::new().suffix(".wit").tempfile()?;
+        write!(
+            old_wit,
+            r#"
+            package foo:bar;
+
+            interface handler {{
+                handle: func(request: string) -> string;
+                close: func();
+            }}
+
+            world bindings {{
+                export handler;
+            }}
+        "#,
+        )?;
+        let mut new_wit = tempfile::Builder::new().suffix(".wit").tempfile()?;
+        write!(
+            new_wit,
+            r#"
+            package foo:bar;
+
+            interface handler {{
+                handle: func(request: list<u8>) -> string;
+                shutdown: func();
+            }}
+
+            world bindings {{
+                export handler;
+            }}
+        "#,
+        )?;
+
+        // When comparing the bindings for each
+        let notes = crate::migrate_bindings(
+            old_wit.path(),
+            None,
+            new_wit.path(),
+            None,
+            None,
+            &[],
+            false,
+            &HashMap::new(),
+            &HashMap::new(),
+            NamingStyle::Snake,
+            VersionCollisions::Qualify,
+        )?;
+
+        // Then each change the app needs to make is described
+        assert_eq!(
+            vec![
+                "`exports.Handler.close` was renamed to `exports.Handler.shutdown`; rename your implementation",
+                "`exports.Handler.handle` changed from `(request: str) -> str` to `(request: bytes) -> str`; \
+                 update your implementation to match",
+            ],
+            notes
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn unstable_features_used_in_componentize() -> Result<()> {
+        // Given bindings to a WIT file with gated features and a Python file that uses them
+        let wit = gated_x_wit_file()?;
+        let out_dir = tempfile::tempdir()?;
+        let common = Common {
+            features: vec!["x".to_owned()],
+            ..common_options(Some(wit.path()))
+        };
+        let bindings = bindings_options(out_dir.path());
+        generate_bindings(common.clone(), bindings)?;
+        fs::write(
+            out_dir.path().join("app.py"),
+            r#"
+import bindings
+from bindings import x
+
+class Bindings(bindings.Bindings):
+    def y(self) -> None:
+        x()
+"#,
+        )?;
+
+        // Building the component succeeds
+        let componentize_opts = componentize_options(out_dir.path());
+        componentize(common, componentize_opts, &Callbacks::default())
+    }
+
+    #[test]
+    fn package_features_used_in_componentize() -> Result<()> {
+        // Given a package whose `componentize-py.toml` requests a WIT feature, and an app that uses the gated item
+        let wit = gated_x_wit_file()?;
+        let out_dir = tempfile::tempdir()?;
+        let sdk = out_dir.path().join("gated_sdk");
+        fs::create_dir_all(sdk.join("wit"))?;
+        fs::copy(wit.path(), sdk.join("wit/world.wit"))?;
+        fs::write(sdk.join("__init__.py"), "")?;
+        fs::write(
+            sdk.join("componentize-py.toml"),
+            r#"
+wit_directory = "wit"
+bindings = "wit"
+features = ["x"]
+"#,
+        )?;
+        fs::write(
+            out_dir.path().join("app.py"),
+            r#"
+from gated_sdk import wit
+
+class Bindings(wit.Bindings):
+    def y(self) -> None:
+        wit.x()
+"#,
+        )?;
+
+        // Building the component without `--features` succeeds
+        let common = common_options(None);
+        let componentize_opts = componentize_options(out_dir.path());
+        componentize(common, componentize_opts, &Callbacks::default())
+    }
+
+    #[test]
+    fn source_map_embedded_when_requested() -> Result<()> {
+        // Given bindings to a WIT file and a Python file that uses them
+        let wit = gated_x_wit_file()?;
+        let out_dir = tempfile::tempdir()?;
+        let common = Common {
+            features: vec!["x".to_owned()],
+            ..common_options(Some(wit.path()))
+        };
+        let bindings = bindings_options(out_dir.path());
+        generate_bindings(common.clone(), bindings)?;
+        fs::write(
+            out_dir.path().join("app.py"),
+            r#"
+import bindings
+from bindings import x
+
+class Bindings(bindings.Bindings):
+    def y(self) -> None:
+        x()
+"#,
+        )?;
+
+        // When building the component with `--map-source-paths`
+        let componentize_opts = Componentize {
+            map_source_paths: true,
+            ..componentize_options(out_dir.path())
+        };
+        componentize(common, componentize_opts, &Callbacks::default())?;
+
+        // Then the component contains the mapping from guest to host paths
+        let component = fs::read(out_dir.path().join("app.wasm"))?;
+        let host_path = out_dir.path().canonicalize()?;
+        let host_path = host_path.to_string_lossy().replace('\\', "/");
+        let contains = |needle: &[u8]| component.windows(needle.len()).any(|w| w == needle);
+
+        assert!(contains(b"componentize-py-source-map"));
+        assert!(contains(format!("/0\t{host_path}").as_bytes()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn bundle_written_to_output_dir() -> Result<()> {
+        // Given bindings to a WIT file and a Python file that uses them
+        let wit = gated_x_wit_file()?;
+        let out_dir = tempfile::tempdir()?;
+        let bundle_dir = tempfile::tempdir()?;
+        let common = Common {
+            features: vec!["x".to_owned()],
+            ..common_options(Some(wit.path()))
+        };
+        let bindings = bindings_options(out_dir.path());
+        generate_bindings(common.clone(), bindings)?;
+        fs::write(
+            out_dir.path().join("app.py"),
+            r#"
+import bindings
+from bindings import x
+
+class Bindings(bindings.Bindings):
+    def y(self) -> None:
+        x()
+"#,
+        )?;
+
+        // When building the component with `--output-dir`
+        let componentize_opts = Componentize {
+            output: PathBuf::from("index.wasm"),
+            output_dir: Some(bundle_dir.path().join("bundle")),
+            ..componentize_options(out_dir.path())
+        };
+        componentize(common, componentize_opts, &Callbacks::default())?;
+
+        // Then the bundle contains the component, bindings, and metadata
+        let bundle = bundle_dir.path().join("bundle");
+        let component = fs::read(bundle.join("app.wasm"))?;
+        let metadata = fs::read_to_string(bundle.join("metadata.toml"))?.parse::<toml::Table>()?;
+
+        assert!(bundle.join("bindings/__init__.py").exists());
+        assert!(bundle.join("README.md").exists());
+        // This world exports neither `wasi:cli/run` nor `wasi:http/incoming-handler`
+        assert!(!bundle.join("run.sh").exists());
+        assert_eq!(
+            Some(component.len() as i64),
+            metadata["component"]["size"].as_integer()
+        );
+        assert!(metadata["component"]["exports"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|export| export.as_str() == Some("y")));
+        assert_eq!(
+            Some(env!("CARGO_PKG_VERSION")),
+            metadata["componentize-py-version"].as_str()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn compressed_component_written_when_requested() -> Result<()> {
+        // Given bindings to a WIT file and a Python file that uses them
+        let wit = gated_x_wit_file()?;
+        let out_dir = tempfile::tempdir()?;
+        let common = Common {
+            features: vec!["x".to_owned()],
+            ..common_options(Some(wit.path()))
+        };
+        let bindings = bindings_options(out_dir.path());
+        generate_bindings(common.clone(), bindings)?;
+        fs::write(
+            out_dir.path().join("app.py"),
+            r#"
+import bindings
+from bindings import x
+
+class Bindings(bindings.Bindings):
+    def y(self) -> None:
+        x()
+"#,
+        )?;
+
+        // When building the component with debug info stripped and compression enabled
+        let componentize_opts = Componentize {
+            exclude_module: vec!["tkinter".to_owned()],
+            strip_debug: true,
+            compression: Some(3),
+            size_report: true,
+            ..componentize_options(out_dir.path())
+        };
+        componentize(common, componentize_opts, &Callbacks::default())?;
+
+        // Then the compressed copy decompresses to the component
+        let component = fs::read(out_dir.path().join("app.wasm"))?;
+        let compressed = fs::read(out_dir.path().join("app.wasm.zst"))?;
+
+        assert!(compressed.len() < component.len());
+        assert_eq!(component, zstd::decode_all(compressed.as_slice())?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn app_loaded_from_wheel() -> Result<()> {
+        // Given bindings to a WIT file and a wheel containing a Python file that uses them
+        let wit = gated_x_wit_file()?;
+        let out_dir = tempfile::tempdir()?;
+        let common = Common {
+            features: vec!["x".to_owned()],
+            ..common_options(Some(wit.path()))
+        };
+        let bindings = bindings_options(&out_dir.path().join("bindings"));
+        generate_bindings(common.clone(), bindings)?;
+        let wheel = out_dir.path().join("app-1.0-py3-none-any.whl");
+        let mut writer = zip::ZipWriter::new(fs::File::create(&wheel)?);
+        writer.start_file("app.py", zip::write::SimpleFileOptions::default())?;
+        writer.write_all(
+            br#"
+import bindings
+from bindings import x
+
+class Bindings(bindings.Bindings):
+    def y(self) -> None:
+        x()
+"#,
+        )?;
+        writer.finish()?;
+
+        // When building the component with the wheel in the Python path
+        let componentize_opts = Componentize {
+            python_path: vec![
+                out_dir.path().join("bindings").to_string_lossy().into(),
+                wheel.to_string_lossy().into(),
+            ],
+            ..componentize_options(out_dir.path())
+        };
+
+        // Then the app is found in the wheel and the component is built
+        componentize(common, componentize_opts, &Callbacks::default())?;
+        assert!(out_dir.path().join("app.wasm").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn diagnostics_exported_when_requested() -> Result<()> {
+        // Given bindings to a WIT file and a Python file that uses them
+        let wit = gated_x_wit_file()?;
+        let out_dir = tempfile::tempdir()?;
+        let common = Common {
+            features: vec!["x".to_owned()],
+            ..common_options(Some(wit.path()))
+        };
+        let bindings = bindings_options(out_dir.path());
+        generate_bindings(common.clone(), bindings)?;
+        fs::write(
+            out_dir.path().join("app.py"),
+            r#"
+import bindings
+from bindings import x
+
+class Bindings(bindings.Bindings):
+    def y(self) -> None:
+        x()
+"#,
+        )?;
+
+        // When building the component with `--diagnostics`
+        let componentize_opts = Componentize {
+            diagnostics: true,
+            ..componentize_options(out_dir.path())
+        };
+        componentize(common, componentize_opts, &Callbacks::default())?;
+
+        // Then the component exports the diagnostics interfaces alongside the world's own exports
+        let component = fs::read(out_dir.path().join("app.wasm"))?;
+        let wit_component::DecodedWasm::Component(resolve, world) =
+            wit_component::decode(&component)?
+        else {
+            panic!("expected a component");
+        };
+        let exports = resolve.worlds[world]
+            .exports
+            .keys()
+            .map(|key| resolve.name_world_key(key))
+            .collect::<Vec<_>>();
+
+        assert!(exports.contains(&"y".to_owned()));
+        assert!(exports.contains(&"componentize-py:diagnostics/counters".to_owned()));
+        assert!(exports.contains(&"componentize-py:diagnostics/memory".to_owned()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn profile_limits_component_imports() -> Result<()> {
+        // Given bindings to a WIT file and a Python file that uses them
+        let wit = gated_x_wit_file()?;
+        let out_dir = tempfile::tempdir()?;
+        let common = Common {
+            features: vec!["x".to_owned()],
+            ..common_options(Some(wit.path()))
+        };
+        let bindings = bindings_options(out_dir.path());
+        generate_bindings(common.clone(), bindings)?;
+        fs::write(
+            out_dir.path().join("app.py"),
+            r#"
+import bindings
+from bindings import x
+
+class Bindings(bindings.Bindings):
+    def y(self) -> None:
+        x()
+"#,
+        )?;
+
+        // When building the component with `--profile http`
+        let componentize_opts = Componentize {
+            profile: Profile::Http,
+            ..componentize_options(out_dir.path())
+        };
+        componentize(common, componentize_opts, &Callbacks::default())?;
+
+        // Then the component imports only WASI interfaces in the profile (including those the runtime would
+        // otherwise import itself), plus the world's own imports
+        let component = fs::read(out_dir.path().join("app.wasm"))?;
+        let wit_component::DecodedWasm::Component(resolve, world) =
+            wit_component::decode(&component)?
+        else {
+            panic!("expected a component");
+        };
+        let imports = resolve.worlds[world]
+            .imports
+            .keys()
+            .map(|key| resolve.name_world_key(key))
+            .collect::<Vec<_>>();
+        let allowed = Profile::Http.interfaces().unwrap();
+
+        assert!(imports.contains(&"x".to_owned()));
+        assert!(imports
+            .iter()
+            .any(|name| name.starts_with("wasi:cli/stdout@")));
+        for name in imports.iter().filter(|name| name.starts_with("wasi:")) {
+            let interface = name.split('@').next().unwrap();
+            assert!(allowed.contains(&interface), "unexpected import {name}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn distribution_metadata_snapshotted() -> Result<()> {
+        // Given bindings to a WIT file, a distribution which provides a plugin via an entry point, and an app which
+        // uses `importlib.metadata`
+        let wit = gated_x_wit_file()?;
+        let out_dir = tempfile::tempdir()?;
+        let common = Common {
+            features: vec!["x".to_owned()],
+            ..common_options(Some(wit.path()))
+        };
+        let bindings = bindings_options(out_dir.path());
+        generate_bindings(common.clone(), bindings)?;
+        let dist_info = out_dir.path().join("foo_plugin-1.0.dist-info");
+        fs::create_dir_all(&dist_info)?;
+        fs::write(
+            dist_info.join("METADATA"),
+            "Metadata-Version: 2.1\nName: foo-plugin\nVersion: 1.0\n",
+        )?;
+        fs::write(
+            dist_info.join("entry_points.txt"),
+            "[app.plugins]\nfoo = foo_plugin:hook\n",
+        )?;
+        fs::write(
+            out_dir.path().join("foo_plugin.py"),
+            "def hook() -> None:\n    pass\n",
+        )?;
+        fs::write(
+            out_dir.path().join("app.py"),
+            r#"
+import bindings
+import importlib.metadata
+import foo_plugin  # so that it can be loaded via its entry point at runtime
+from bindings import x
+
+class Bindings(bindings.Bindings):
+    def y(self) -> None:
+        for plugin in importlib.metadata.entry_points(group="app.plugins"):
+            plugin.load()()
+        x()
+"#,
+        )?;
+
+        // When building a component from them
+        let componentize_opts = componentize_options(out_dir.path());
+
+        // Then the build succeeds, with the distribution's metadata having been snapshotted during
+        // pre-initialization
+        componentize(common, componentize_opts, &Callbacks::default())?;
+        assert!(out_dir.path().join("app.wasm").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn proptests_generated_for_exports() -> Result<()> {
+        // Given a WIT file with an exported function
+        let wit = gated_x_wit_file()?;
+        let out_dir = tempfile::tempdir()?;
+        let common = common_options(Some(wit.path()));
+
+        // When generating contract tests for it
+        let proptests = GenProptests {
+            app_name: "app".to_owned(),
+            output: out_dir.path().join("test_contract.py"),
+            world_module: None,
+        };
+        gen_proptests(common, proptests)?;
+
+        // Then there's a test which calls the app's implementation of the export
+        let generated = fs::read_to_string(out_dir.path().join("test_contract.py"))?;
+
+        assert!(generated.contains("def test_y():"));
+        assert!(generated.contains("result = app.Bindings().y()"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn shutdown_hook_exported_when_requested() -> Result<()> {
+        // Given a WIT file and a Python file which registers a shutdown callback at the top level
+        let wit = gated_x_wit_file()?;
+        let out_dir = tempfile::tempdir()?;
+        let common = Common {
+            features: vec!["x".to_owned()],
+            ..common_options(Some(wit.path()))
+        };
+        fs::write(
+            out_dir.path().join("app.py"),
+            r#"
+import bindings
+from bindings import x
+
+bindings.on_shutdown(lambda: print("shutting down"))
+
+class Bindings(bindings.Bindings):
+    def y(self) -> None:
+        x()
+"#,
+        )?;
+
+        // When building the component with `--shutdown-hook`
+        let componentize_opts = Componentize {
+            shutdown_hook: true,
+            ..componentize_options(out_dir.path())
+        };
+        componentize(common, componentize_opts, &Callbacks::default())?;
+
+        // Then the component exports the shutdown interface alongside the world's own exports
+        let component = fs::read(out_dir.path().join("app.wasm"))?;
+        let wit_component::DecodedWasm::Component(resolve, world) =
+            wit_component::decode(&component)?
+        else {
+            panic!("expected a component");
+        };
+        let exports = resolve.worlds[world]
+            .exports
+            .keys()
+            .map(|key| resolve.name_world_key(key))
+            .collect::<Vec<_>>();
+
+        assert!(exports.contains(&"y".to_owned()));
+        assert!(exports.contains(&"componentize-py:lifecycle/shutdown".to_owned()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn slow_import_exceeds_budget() -> Result<()> {
+        // Given a WIT file and a Python app which imports a module that takes half a second to import
+        let wit = gated_x_wit_file()?;
+        let out_dir = tempfile::tempdir()?;
+        let common = Common {
+            quiet: true,
+            features: vec!["x".to_owned()],
+            ..common_options(Some(wit.path()))
+        };
+        fs::write(
+            out_dir.path().join("slow.py"),
+            "import time\ntime.sleep(0.5)\n",
+        )?;
+        fs::write(
+            out_dir.path().join("app.py"),
+            r#"
+import bindings
+import slow
+from bindings import x
+
+class Bindings(bindings.Bindings):
+    def y(self) -> None:
+        x()
+"#,
+        )?;
+
+        // When building the component with an import time budget of a tenth of a second
+        let componentize_opts = Componentize {
+            import_time_budget: Some(Duration::from_millis(100)),
+            ..componentize_options(out_dir.path())
+        };
+        let error = componentize(common, componentize_opts, &Callbacks::default()).unwrap_err();
+
+        // Then the build fails, naming the slow module
+        let message = format!("{error:?}");
+        assert!(message.contains("importing `slow` took"), "{message}");
+        assert!(
+            message.contains("exceeding the import time budget"),
+            "{message}"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn pre_init_hook_failure_reported() -> Result<()> {
+        // Given a WIT file and a Python app whose pre-init hook raises an exception
+        let wit = gated_x_wit_file()?;
+        let out_dir = tempfile::tempdir()?;
+        let common = || Common {
+            quiet: true,
+            features: vec!["x".to_owned()],
+            ..common_options(Some(wit.path()))
+        };
+        fs::write(
+            out_dir.path().join("app.py"),
+            r#"
+import bindings
+from bindings import x
+
+def __componentize_pre_init__():
+    raise RuntimeError("unable to warm caches")
+
+class Bindings(bindings.Bindings):
+    def y(self) -> None:
+        x()
+"#,
+        )?;
+        let componentize_opts = |pre_init_hook: Option<&str>| Componentize {
+            pre_init_hook: pre_init_hook.map(str::to_owned),
+            ..componentize_options(out_dir.path())
+        };
+
+        // When building the component, the build fails with the hook's exception
+        let error =
+            componentize(common(), componentize_opts(None), &Callbacks::default()).unwrap_err();
+        let message = format!("{error:?}");
+        assert!(message.contains("unable to warm caches"), "{message}");
+
+        // And when a hook which the app doesn't define is named explicitly, the build fails too
+        let error = componentize(
+            common(),
+            componentize_opts(Some("warm_caches")),
+            &Callbacks::default(),
+        )
+        .unwrap_err();
+        let message = format!("{error:?}");
+        assert!(message.contains("warm_caches"), "{message}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn import_only_world_componentized() -> Result<()> {
+        // Given a WIT world with imports but no exports, and an app which only runs code at the top level
+        let mut wit = tempfile::Builder::new().suffix(".wit").tempfile()?;
+        write!(
+            wit,
+            r#"
+            package foo:bar;
+
+            world imports-only {{
+                import log: func(message: string);
+            }}
+            "#
+        )?;
+        let out_dir = tempfile::tempdir()?;
+        let common = Common {
+            quiet: true,
+            ..common_options(Some(wit.path()))
+        };
+        fs::write(
+            out_dir.path().join("app.py"),
+            r#"
+import imports_only
+
+config = {"initialized": True}
+"#,
+        )?;
+
+        // When generating bindings for it
+        let bindings = bindings_options(out_dir.path());
+        generate_bindings(common.clone(), bindings)?;
+
+        // Then no protocol is generated for the app to implement
+        let module = fs::read_to_string(out_dir.path().join("imports_only/__init__.py"))?;
+        assert!(module.contains("def log(message: str) -> None:"));
+        assert!(!module.contains("class ImportsOnly"));
+
+        // And when building a component from it
+        let componentize_opts = componentize_options(out_dir.path());
+        componentize(common, componentize_opts, &Callbacks::default())?;
+
+        // Then the component has the world's import and no exports
+        let component = fs::read(out_dir.path().join("app.wasm"))?;
+        let wit_component::DecodedWasm::Component(resolve, world) =
+            wit_component::decode(&component)?
+        else {
+            panic!("expected a component");
+        };
+        let world = &resolve.worlds[world];
+        assert!(world
+            .imports
+            .keys()
+            .any(|key| resolve.name_world_key(key) == "log"));
+        assert!(world.exports.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn strict_componentized() -> Result<()> {
+        // Given a WIT world whose functions use a variety of types
+        let mut wit = tempfile::Builder::new().suffix(".wit").tempfile()?;
+        write!(
+            wit,
+            r#"
+            package foo:bar;
+
+            interface things {{
+                record point {{ x: s32, y: option<option<u8>>, label: string }}
+                variant shape {{ circle(f64), square(point), empty }}
+                enum color {{ red, green }}
+                flags permissions {{ read, write }}
+                resource blob {{
+                    constructor(data: list<u8>);
+                    size: func() -> u64;
+                }}
+
+                draw: func(shapes: list<shape>, color: color, blob: borrow<blob>) -> result<u32, string>;
+                check: func(permissions: permissions, pair: tuple<char, bool>) -> bool;
+            }}
+
+            world strict {{
+                import things;
+                use things.{{point}};
+
+                export centroid: func(points: list<point>) -> result<point>;
+                export count: func() -> tuple<s8, f32>;
+            }}
+            "#
+        )?;
+        let out_dir = tempfile::tempdir()?;
+        let common = Common {
+            quiet: true,
+            ..common_options(Some(wit.path()))
+        };
+        let bindings = bindings_options(out_dir.path());
+        generate_bindings(common.clone(), bindings)?;
+        fs::write(
+            out_dir.path().join("app.py"),
+            r#"
+import strict
+from strict.imports.things import Point
+from typing import List, Tuple
+
+class Strict(strict.Strict):
+    def centroid(self, points: List[Point]) -> Point:
+        return Point(0, None, "centroid")
+
+    def count(self) -> Tuple[int, float]:
+        return (0, 0.0)
+"#,
+        )?;
+
+        // When building a component from it with `--strict`
+        let componentize_opts = Componentize {
+            strict: true,
+            ..componentize_options(out_dir.path())
+        };
+
+        // Then the runtime accepts the description of the world's types, and the build succeeds
+        componentize(common, componentize_opts, &Callbacks::default())?;
+        assert!(out_dir.path().join("app.wasm").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn deterministic_builds_are_reproducible() -> Result<()> {
+        // Given a WIT file and a Python file which captures the time and a random number at the top level
+        let wit = gated_x_wit_file()?;
+        let build = || -> Result<Vec<u8>> {
+            // (using a fresh directory each time, as separate checkouts of the app would)
+            let out_dir = tempfile::tempdir()?;
+            let common = Common {
+                quiet: true,
+                features: vec!["x".to_owned()],
+                ..common_options(Some(wit.path()))
+            };
+            fs::write(
+                out_dir.path().join("app.py"),
+                r#"
+import random
+import time
+import bindings
+
+STARTED = time.time()
+SEED = random.random()
+NAMES = {"a", "b", "c"}
+
+class Bindings(bindings.Bindings):
+    def y(self) -> None:
+        pass
+"#,
+            )?;
+
+            let componentize_opts = Componentize {
+                deterministic: true,
+                ..componentize_options(out_dir.path())
+            };
+            componentize(common, componentize_opts, &Callbacks::default())?;
+
+            Ok(fs::read(out_dir.path().join("app.wasm"))?)
+        };
+
+        // When building it twice with `--deterministic` from different directories
+        let first = build()?;
+        let second = build()?;
+
+        // Then the results are identical
+        assert!(first == second);
+
+        Ok(())
+    }
+
+    #[test]
+    fn callbacks_receive_output_and_progress() -> Result<()> {
+        // Given bindings to a WIT file and a Python file that prints at the top level
+        let wit = gated_x_wit_file()?;
+        let out_dir = tempfile::tempdir()?;
+        let common = Common {
+            features: vec!["x".to_owned()],
+            ..common_options(Some(wit.path()))
+        };
+        let bindings = bindings_options(out_dir.path());
+        generate_bindings(common.clone(), bindings)?;
+        let greeting = format!("hello from {}", out_dir.path().display());
+        fs::write(
+            out_dir.path().join("app.py"),
+            format!(
+                r#"
+import bindings
+from bindings import x
+
+print({greeting:?})
+
+class Bindings(bindings.Bindings):
+    def y(self) -> None:
+        x()
+"#
+            ),
+        )?;
+
+        // When building the component with output and progress callbacks
+        let stdout = Arc::new(Mutex::new(Vec::new()));
+        let phases = Arc::new(Mutex::new(Vec::new()));
+        let callbacks = Callbacks {
+            stdout: Some({
+                let stdout = stdout.clone();
+                Arc::new(move |bytes: &[u8]| stdout.lock().unwrap().extend_from_slice(bytes))
+            }),
+            stderr: None,
+            progress: Some({
+                let phases = phases.clone();
+                Arc::new(move |phase: Phase| phases.lock().unwrap().push(phase))
+            }),
+        };
+        let python_path = out_dir.path().to_string_lossy().into_owned();
+        let python_path = [python_path.as_str()];
+        let output_path = out_dir.path().join("app.wasm");
+        Runtime::new()?.block_on(
+            crate::ComponentizeBuilder::new()
+                .wit_path(wit.path())
+                .features(&common.features)
+                .python_path(&python_path)
+                .output_path(&output_path)
+                .callbacks(callbacks)
+                .build(),
+        )?;
+
+        // Then the app's output is passed to the callback, and each phase is reported in order
+        assert_eq!(
+            format!("{greeting}\n"),
+            String::from_utf8(stdout.lock().unwrap().clone())?
+        );
+        assert_eq!(
+            vec![
+                Phase::Prelinking,
+                Phase::GeneratingBindings,
+                Phase::Linking,
+                Phase::PreInitializing,
+                Phase::Writing,
+            ],
+            *phases.lock().unwrap()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn python_path_read_from_stdin() -> Result<()> {
+        // Given a tar stream containing an app
+        let contents = b"def run():\n    pass\n";
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        let mut builder = tar::Builder::new(Vec::new());
+        builder.append_data(&mut header, "app/__init__.py", &contents[..])?;
+        let archive = builder.into_inner()?;
+
+        // When `-` appears in the Python path
+        let mut python_path = vec!["-".to_owned(), "lib".to_owned()];
+        let dir = unpack_stdin_tree(&mut python_path, &archive[..], false)?;
+
+        // Then it is replaced by a directory containing the extracted app
+        assert!(dir.is_some());
+        assert_eq!(
+            contents.as_slice(),
+            fs::read(Path::new(&python_path[0]).join("app/__init__.py"))?
+        );
+        assert_eq!("lib", python_path[1]);
+
+        // And `-` may only appear once, since `stdin` can only be read once
+        let mut python_path = vec!["-".to_owned(), "-".to_owned()];
+        assert!(unpack_stdin_tree(&mut python_path, &archive[..], false).is_err());
+
+        Ok(())
     }
 }
@@ -0,0 +1,44 @@
+use {
+    crate::temp,
+    anyhow::{Context, Result},
+    std::{fs, path::Path},
+    wasm_compose::{composer::ComponentComposer, config::Config},
+};
+
+/// Compose `component` with the components at `paths`, using the exports of the latter to satisfy any imports of
+/// the former with matching names, and return the resulting component.
+///
+/// The components at `paths` are embedded in the result rather than imported.  Any imports they don't satisfy are
+/// left as imports of the result.
+pub fn compose(component: Vec<u8>, paths: &[&Path], keep_temp: bool) -> Result<Vec<u8>> {
+    let dir = temp::dir(keep_temp)?;
+    let root = dir.path().join("root.wasm");
+    fs::write(&root, &component)?;
+    // The composer reads the component back from disk, so there's no need to keep it in memory meanwhile.
+    drop(component);
+
+    let config = Config {
+        dir: dir.path().to_owned(),
+        definitions: paths
+            .iter()
+            .map(|path| {
+                path.canonicalize()
+                    .with_context(|| format!("unable to find {}", path.display()))
+            })
+            .collect::<Result<_>>()?,
+        ..Default::default()
+    };
+
+    ComponentComposer::new(&root, &config)
+        .compose()
+        .with_context(|| {
+            format!(
+                "failed to compose component with {}",
+                paths
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+}
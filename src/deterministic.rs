@@ -0,0 +1,83 @@
+//! Support for `--deterministic` builds, i.e. ones which produce byte-for-byte identical components given
+//! identical inputs.
+//!
+//! Most of the nondeterminism in a build comes from pre-initialization, during which the app can observe the host's
+//! clocks and random number generators (directly or, e.g., via `random`'s default seed and Python's string hash
+//! secret), and the results may be baked into the snapshot.  Here we replace those with fixed sources.
+
+use {
+    std::{
+        env,
+        path::{Path, PathBuf},
+        sync::atomic::{AtomicU64, Ordering::Relaxed},
+        time::Duration,
+    },
+    wasmtime_wasi::{Deterministic, HostMonotonicClock, HostWallClock, WasiCtxBuilder},
+};
+
+/// A clock which starts at zero (i.e. the Unix epoch, for wall clock time) and advances by one nanosecond each
+/// time it is read.
+///
+/// We advance it rather than holding it constant so that code which waits for time to pass (e.g. using a
+/// busy loop) still terminates.
+#[derive(Default)]
+struct Clock {
+    nanoseconds: AtomicU64,
+}
+
+impl Clock {
+    fn tick(&self) -> u64 {
+        self.nanoseconds.fetch_add(1, Relaxed)
+    }
+}
+
+impl HostWallClock for Clock {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(1)
+    }
+
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.tick())
+    }
+}
+
+impl HostMonotonicClock for Clock {
+    fn resolution(&self) -> u64 {
+        1
+    }
+
+    fn now(&self) -> u64 {
+        self.tick()
+    }
+}
+
+/// Configure `wasi` to use fixed clocks and random number generators, and to pin Python's hash secret.
+///
+/// This also tells the runtime to discard any cached filesystem metadata (e.g. directory modification times) at
+/// the end of pre-initialization, since that would otherwise vary from one checkout of the same sources to the
+/// next.
+pub fn configure(wasi: &mut WasiCtxBuilder) {
+    wasi.env("PYTHONHASHSEED", "0")
+        .env("COMPONENTIZE_PY_DETERMINISTIC", "1")
+        .secure_random(Deterministic::new(vec![0]))
+        .insecure_random(Deterministic::new(vec![0]))
+        .insecure_random_seed(0)
+        .wall_clock(Clock::default())
+        .monotonic_clock(Clock::default());
+}
+
+/// Return `path` relative to the current directory if it is inside that directory, or else unchanged.
+///
+/// This is used to keep host paths embedded in the component (e.g. in the source map) independent of where the
+/// project happens to be checked out.
+pub fn normalize_path(path: &Path) -> PathBuf {
+    match env::current_dir()
+        .and_then(|dir| dir.canonicalize())
+        .ok()
+        .and_then(|dir| Some(path.strip_prefix(dir).ok()?.to_owned()))
+    {
+        Some(relative) if relative.as_os_str().is_empty() => PathBuf::from("."),
+        Some(relative) => relative,
+        None => path.to_owned(),
+    }
+}
@@ -0,0 +1,391 @@
+//! Support for `componentize-py doctor`, which checks whether the host environment is able to build components
+//! (e.g. that there is writable temporary space, the runtime artifacts are intact, and the app's virtual
+//! environment looks sane) and suggests a fix for each problem found.
+
+use {
+    crate::{artifacts::Artifacts, cache, command, optimize, temp, util},
+    std::{env, fmt, fs, path::Path, process},
+};
+
+/// Amount of available memory below which builds of larger apps (e.g. those using NumPy) may fail
+const RECOMMENDED_MEMORY: usize = 1024 * 1024 * 1024;
+
+/// The first four bytes of every Wasm module and component
+const WASM_MAGIC: &[u8] = b"\0asm";
+
+/// Outcome of a single check
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Status {
+    Pass,
+    /// Builds may still succeed, but something is missing or degraded
+    Warn,
+    /// Builds are expected to fail
+    Fail,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Pass => "ok",
+            Self::Warn => "warn",
+            Self::Fail => "FAIL",
+        })
+    }
+}
+
+pub struct Check {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+    /// What the user might do about a warning or failure
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Pass,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: Status::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+/// Run every check, using the runtime artifacts in `artifact_dir` (if specified) and also checking for `wasm-opt`
+/// if `wasm_opt` is true.
+pub fn run(artifact_dir: Option<&Path>, wasm_opt: bool) -> Vec<Check> {
+    let mut checks = vec![temp_space(), artifacts(artifact_dir)];
+    checks.extend(memory());
+    checks.push(venv(command::find_venv().as_deref()));
+    checks.push(match util::cache_dir() {
+        Some(dir) => cache_health(&dir),
+        None => Check::warn(
+            "cache",
+            "unable to determine a cache directory, so components will be rebuilt from scratch every time",
+            "set `COMPONENTIZE_PY_CACHE_DIR` to a writable directory",
+        ),
+    });
+    checks.push(tool(
+        "rustfmt",
+        Status::Warn,
+        "install it using `rustup component add rustfmt` (optional)",
+    ));
+    if wasm_opt {
+        checks.push(tool(
+            "wasm-opt",
+            Status::Fail,
+            "install Binaryen (e.g. using your package manager or `cargo install wasm-opt`)",
+        ));
+    }
+    checks
+}
+
+fn temp_space() -> Check {
+    const NAME: &str = "temporary directory";
+
+    let result =
+        temp::dir(false).and_then(|dir| Ok(fs::write(dir.path().join("probe"), b"probe")?));
+
+    if let Err(e) = result {
+        return Check::fail(
+            NAME,
+            format!("unable to write to {}: {e:#}", env::temp_dir().display()),
+            "set `TMPDIR` (or `TEMP` on Windows) to a writable directory with at least a few hundred MiB free",
+        );
+    }
+
+    match temp::find_stale() {
+        Ok(stale) if !stale.is_empty() => Check::warn(
+            NAME,
+            format!(
+                "found {} temporary director(ies) left behind by builds which were killed, e.g. {}",
+                stale.len(),
+                stale[0].display()
+            ),
+            "run `componentize-py clean-cache`",
+        ),
+        _ => Check::pass(NAME, format!("{} is writable", env::temp_dir().display())),
+    }
+}
+
+fn artifacts(artifact_dir: Option<&Path>) -> Check {
+    const NAME: &str = "runtime artifacts";
+
+    match Artifacts::new(artifact_dir) {
+        Ok(_) => Check::pass(
+            NAME,
+            match artifact_dir {
+                Some(dir) => format!("artifacts in {} match their checksums", dir.display()),
+                None => "available".to_owned(),
+            },
+        ),
+        Err(e) => Check::fail(
+            NAME,
+            format!("{e:#}"),
+            "use `componentize-py export-artifacts` with a complete build to write a fresh artifact directory",
+        ),
+    }
+}
+
+/// Check the available memory, if the platform makes it easy to determine.
+fn memory() -> Option<Check> {
+    const NAME: &str = "memory";
+
+    let available = available_memory()?;
+    let detail = format!("{} available", optimize::human(available));
+    Some(if available < RECOMMENDED_MEMORY {
+        Check::warn(
+            NAME,
+            detail,
+            format!(
+                "close other programs or add swap; at least {} is recommended",
+                optimize::human(RECOMMENDED_MEMORY)
+            ),
+        )
+    } else {
+        Check::pass(NAME, detail)
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn available_memory() -> Option<usize> {
+    fs::read_to_string("/proc/meminfo")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("MemAvailable:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .map(|kib| kib * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn available_memory() -> Option<usize> {
+    None
+}
+
+/// Check the layout of the virtual environment (if any) from which `componentize` will take `site-packages`.
+fn venv(venv: Option<&Path>) -> Check {
+    const NAME: &str = "virtual environment";
+
+    let Some(venv) = venv else {
+        return Check::pass(
+            NAME,
+            "none found, so the system Python's `site-packages` directories will be used",
+        );
+    };
+
+    if !venv.is_dir() {
+        return Check::fail(
+            NAME,
+            format!("{} does not exist", venv.display()),
+            "unset `VIRTUAL_ENV` (or `UV_PROJECT_ENVIRONMENT`), or recreate the environment",
+        );
+    }
+
+    let site_packages = ["lib", "lib64", "Lib"].iter().find_map(|lib| {
+        command::find_dir("site-packages", &venv.join(lib))
+            .ok()
+            .flatten()
+    });
+
+    let Some(site_packages) = site_packages else {
+        return Check::fail(
+            NAME,
+            format!(
+                "no `site-packages` directory found under {}",
+                venv.display()
+            ),
+            format!(
+                "recreate the environment, e.g. using `python3.12 -m venv {}`",
+                venv.display()
+            ),
+        );
+    };
+
+    match fs::read_to_string(venv.join("pyvenv.cfg"))
+        .ok()
+        .and_then(|config| python_version(&config))
+    {
+        Some(version) if version == "3.12" || version.starts_with("3.12.") => {
+            Check::pass(NAME, format!("using {}", site_packages.display()))
+        }
+        Some(version) => Check::warn(
+            NAME,
+            format!(
+                "{} was created using Python {version}, but components embed CPython 3.12, so packages \
+                 installed there may not be compatible",
+                venv.display()
+            ),
+            format!("recreate the environment using `python3.12 -m venv {}`", venv.display()),
+        ),
+        None => Check::warn(
+            NAME,
+            format!("unable to determine the Python version of {}", venv.display()),
+            format!(
+                "make sure {} contains a `pyvenv.cfg` file, e.g. by recreating the environment using \
+                 `python3.12 -m venv`",
+                venv.display()
+            ),
+        ),
+    }
+}
+
+/// Return the Python version recorded in the specified `pyvenv.cfg` file contents.
+fn python_version(config: &str) -> Option<&str> {
+    config.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        matches!(key.trim(), "version" | "version_info").then_some(value.trim())
+    })
+}
+
+/// Check that the cache directory is writable and contains no partially-written components.
+fn cache_health(dir: &Path) -> Check {
+    const NAME: &str = "cache";
+
+    let probe = dir.join(format!("probe.{}", process::id()));
+    if let Err(e) = fs::create_dir_all(dir)
+        .and_then(|()| fs::write(&probe, b"probe"))
+        .and_then(|()| fs::remove_file(&probe))
+    {
+        return Check::fail(
+            NAME,
+            format!("unable to write to {}: {e}", dir.display()),
+            "fix the directory's permissions, or set `COMPONENTIZE_PY_CACHE_DIR` to a writable directory",
+        );
+    }
+
+    let mut count = 0;
+    let mut size = 0;
+    let mut broken = Vec::new();
+    for entry in fs::read_dir(dir.join(cache::COMPONENTS))
+        .into_iter()
+        .flatten()
+        .flatten()
+    {
+        let path = entry.path();
+        if path
+            .extension()
+            .is_some_and(|ext| cache::OUTPUT_EXTENSIONS.iter().any(|output| ext == *output))
+        {
+            continue;
+        }
+        let contents = fs::read(&path).unwrap_or_default();
+        if path.extension().is_some_and(|ext| ext == "wasm") && contents.starts_with(WASM_MAGIC) {
+            count += 1;
+            size += contents.len();
+        } else {
+            broken.push(path);
+        }
+    }
+
+    if broken.is_empty() {
+        Check::pass(
+            NAME,
+            format!(
+                "{} contains {count} cached component(s) ({})",
+                dir.display(),
+                optimize::human(size)
+            ),
+        )
+    } else {
+        Check::warn(
+            NAME,
+            format!(
+                "found {} partially-written or corrupt cache entries, e.g. {}",
+                broken.len(),
+                broken[0].display()
+            ),
+            "run `componentize-py clean-cache`",
+        )
+    }
+}
+
+/// Check whether `name` is in `$PATH`, reporting `status` if not.
+fn tool(name: &'static str, status: Status, fix: &str) -> Check {
+    match process::Command::new(name).arg("--version").output() {
+        Ok(output) if output.status.success() => Check::pass(
+            name,
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_owned(),
+        ),
+        _ => Check {
+            name,
+            status,
+            detail: "not found in `PATH`".to_owned(),
+            fix: Some(fix.to_owned()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, anyhow::Result};
+
+    #[test]
+    fn venv_python_version() {
+        assert_eq!(
+            Some("3.12.4"),
+            python_version(
+                "home = /usr/bin\ninclude-system-site-packages = false\nversion = 3.12.4\n"
+            )
+        );
+        assert_eq!(
+            Some("3.11.9"),
+            python_version("version_info = 3.11.9.final.0")
+        );
+        assert_eq!(None, python_version("home = /usr/bin"));
+    }
+
+    #[test]
+    fn cache_health_flags_partial_entries() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let components = dir.path().join(cache::COMPONENTS);
+        fs::create_dir_all(&components)?;
+        fs::write(components.join("abc.wasm"), b"\0asm\x0d\0\x01\0")?;
+
+        let check = cache_health(dir.path());
+        assert_eq!(Status::Pass, check.status, "{}", check.detail);
+        assert!(
+            check.detail.contains("1 cached component"),
+            "{}",
+            check.detail
+        );
+
+        fs::write(components.join("def.1234.tmp"), b"\0as")?;
+
+        let check = cache_health(dir.path());
+        assert_eq!(Status::Warn, check.status, "{}", check.detail);
+        assert_eq!(
+            Some("run `componentize-py clean-cache`"),
+            check.fix.as_deref()
+        );
+
+        Ok(())
+    }
+}
@@ -2,45 +2,71 @@
 
 use {
     anyhow::{anyhow, bail, ensure, Context, Error, Result},
+    artifacts::Artifacts,
     async_trait::async_trait,
     bytes::Bytes,
+    cache::BuildCache,
     component_init::Invoker,
+    componentize_py_shared::INIT_VERSION,
     futures::future::FutureExt,
     heck::ToSnakeCase,
     indexmap::{IndexMap, IndexSet},
+    output::{Callbacks, InitFailure, Phase, TeeOutputPipe},
     serde::Deserialize,
     std::{
-        collections::HashMap,
-        fs, iter,
+        collections::{BTreeSet, HashMap},
+        fmt, fs, iter,
         ops::Deref,
         path::{Path, PathBuf},
         str,
+        sync::mpsc::{self, RecvTimeoutError},
+        thread,
+        time::Duration,
     },
     summary::{Escape, Locations, Summary},
     wasmtime::{
         component::{Component, Instance, Linker, ResourceTable, ResourceType},
-        Config, Engine, Store,
+        Config, Engine, Store, Trap,
     },
     wasmtime_wasi::{
-        pipe::{MemoryInputPipe, MemoryOutputPipe},
-        DirPerms, FilePerms, WasiCtx, WasiCtxBuilder, WasiView,
+        pipe::MemoryInputPipe, DirPerms, FilePerms, WasiCtx, WasiCtxBuilder, WasiView,
+    },
+    wit_parser::{
+        PackageId, Resolve, TypeDefKind, UnresolvedPackageGroup, WorldId, WorldItem, WorldKey,
     },
-    wit_parser::{Resolve, TypeDefKind, UnresolvedPackageGroup, WorldId, WorldItem, WorldKey},
 };
 
 mod abi;
+pub mod api;
+mod artifacts;
 mod bindgen;
 mod bindings;
+mod bundle;
+mod cache;
 pub mod command;
+mod compose;
+mod deterministic;
+mod doctor;
 mod link;
+mod metadata;
+mod migrate;
+mod notices;
+pub mod optimize;
+pub mod output;
 mod prelink;
+mod pytest;
 #[cfg(feature = "pyo3")]
 mod python;
+pub mod report;
+mod scaffold;
+mod source;
 mod stubwasi;
 mod summary;
+mod temp;
 #[cfg(test)]
 mod test;
 mod util;
+mod wheel;
 
 wasmtime::component::bindgen!({
     path: "wit",
@@ -53,6 +79,159 @@ pub struct Ctx {
     table: ResourceTable,
 }
 
+/// Which typing constructs to use in generated Python bindings
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum TypingStyle {
+    /// Use `typing.Union`, `typing.Optional`, `typing.List`, etc., for compatibility with older tooling.
+    #[default]
+    Legacy,
+    /// Use PEP 604 unions (`X | None`), builtin generics (`list[X]`), and PEP 695 `type` aliases for variants,
+    /// which require Python 3.12 or later.
+    Modern,
+}
+
+/// How to write generated Python bindings
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum BindingsFormat {
+    /// Write the Python package directly to the output directory.
+    #[default]
+    Directory,
+    /// Write a wheel containing the package (plus a `py.typed` marker) to the output directory, versioned using
+    /// the version of the WIT package containing the world (or `0.0.0` if it has none).
+    Wheel,
+}
+
+/// How to derive Python names for WIT functions and parameters in generated bindings
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum NamingStyle {
+    /// Convert to `snake_case`, e.g. `get-http-header` becomes `get_http_header`.
+    #[default]
+    Snake,
+    /// Convert to `lowerCamelCase`, e.g. `get-http-header` becomes `getHttpHeader`.
+    Camel,
+    /// Keep the WIT name as is, replacing hyphens with underscores, e.g. `get-HTTP-header` becomes
+    /// `get_HTTP_header`.
+    Preserve,
+}
+
+impl fmt::Display for NamingStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Snake => "snake",
+            Self::Camel => "camel",
+            Self::Preserve => "preserve",
+        })
+    }
+}
+
+/// How to name the Python modules for interfaces of which more than one version is present, e.g. when two
+/// dependencies target different versions of `wasi:http`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum VersionCollisions {
+    /// Qualify the name of every version with its package and version, e.g. `wasi-http-types-0-2-0` and
+    /// `wasi-http-types-0-2-1`.
+    #[default]
+    Qualify,
+    /// Name the latest version (or the unversioned one, if any) as though it were the only one and qualify only the
+    /// others with their versions, e.g. `types-0-2-0` and `types`.
+    DuplicateIfNecessary,
+}
+
+impl fmt::Display for VersionCollisions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Qualify => "qualify",
+            Self::DuplicateIfNecessary => "duplicate-if-necessary",
+        })
+    }
+}
+
+/// Which language (and `wasmtime` API) a host project generated by `scaffold_host` should use
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum HostLanguage {
+    /// A Cargo project using `wasmtime::component::bindgen!` and `wasmtime-wasi`.
+    #[default]
+    Rust,
+    /// A Python script using the bindings generated by `wasmtime-py`'s `python -m wasmtime.bindgen`.
+    Python,
+}
+
+impl fmt::Display for HostLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Rust => "rust",
+            Self::Python => "python",
+        })
+    }
+}
+
+/// How to record the license texts and notices of the third-party code a component contains
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Notices {
+    /// Embed them in a `componentize-py-notices` custom section of the component.
+    Embed,
+    /// Write them to a file alongside the component, named by appending `.NOTICES` to its path.
+    File,
+}
+
+/// Which WASI interfaces the component may import, for targeting hosts which provide only a subset of them
+///
+/// Python's standard library reaches the host via WASI Preview 1, which the `wasi_snapshot_preview1` adapter
+/// implements using whichever WASI 0.2 interfaces it needs, while the runtime itself imports
+/// `wasi:cli/environment` directly.  Profiles other than `cli` replace all imports from any interface outside the
+/// profile with trapping stubs, so the component doesn't import them, and the runtime avoids calling those it
+/// would otherwise call itself (e.g. to read environment variables at runtime).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum Profile {
+    /// Import only `wasi:io` and `wasi:cli/stdout` and `wasi:cli/stderr`, e.g. for embedded hosts.
+    Minimal,
+    /// Import everything the adapter uses from the `wasi:cli/command` world, including the filesystem, clocks,
+    /// randomness, environment variables, and sockets.
+    #[default]
+    Cli,
+    /// Import only the WASI interfaces included in the `wasi:http/proxy` world, i.e. `wasi:io`, `wasi:clocks`,
+    /// `wasi:random/random`, and standard input, output, and error.
+    Http,
+}
+
+impl Profile {
+    /// Return the names (without versions) of the WASI interfaces this profile allows the component to import, or
+    /// `None` if it allows all of them.
+    fn interfaces(self) -> Option<&'static [&'static str]> {
+        match self {
+            Self::Minimal => Some(&[
+                "wasi:io/error",
+                "wasi:io/poll",
+                "wasi:io/streams",
+                "wasi:cli/stdout",
+                "wasi:cli/stderr",
+            ]),
+            Self::Cli => None,
+            Self::Http => Some(&[
+                "wasi:io/error",
+                "wasi:io/poll",
+                "wasi:io/streams",
+                "wasi:cli/stdin",
+                "wasi:cli/stdout",
+                "wasi:cli/stderr",
+                "wasi:clocks/wall-clock",
+                "wasi:clocks/monotonic-clock",
+                "wasi:random/random",
+            ]),
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Minimal => "minimal",
+            Self::Cli => "cli",
+            Self::Http => "http",
+        })
+    }
+}
+
 pub struct Library {
     name: String,
     module: Vec<u8>,
@@ -76,6 +255,12 @@ struct RawComponentizePyConfig {
     import_interface_names: HashMap<String, String>,
     #[serde(default)]
     export_interface_names: HashMap<String, String>,
+    #[serde(default)]
+    exception_mappings: IndexMap<String, String>,
+    #[serde(default)]
+    exports: HashMap<String, String>,
+    #[serde(default)]
+    features: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -84,6 +269,9 @@ struct ComponentizePyConfig {
     wit_directory: Option<PathBuf>,
     import_interface_names: HashMap<String, String>,
     export_interface_names: HashMap<String, String>,
+    exception_mappings: IndexMap<String, String>,
+    exports: HashMap<String, String>,
+    features: Vec<String>,
 }
 
 impl TryFrom<(&Path, RawComponentizePyConfig)> for ComponentizePyConfig {
@@ -104,6 +292,9 @@ impl TryFrom<(&Path, RawComponentizePyConfig)> for ComponentizePyConfig {
             wit_directory: raw.wit_directory.map(convert).transpose()?,
             import_interface_names: raw.import_interface_names,
             export_interface_names: raw.export_interface_names,
+            exception_mappings: raw.exception_mappings,
+            exports: raw.exports,
+            features: raw.features,
         })
     }
 }
@@ -116,6 +307,65 @@ pub struct ConfigContext<T> {
     config: T,
 }
 
+/// Error indicating that the embedded runtime implements a different version of the `componentize-py:init`
+/// interface than this build of `componentize-py` expects.
+///
+/// This usually means the runtime was built from a different source tree than the CLI (e.g. a stale build
+/// artifact).
+#[derive(Debug)]
+pub struct InitVersionMismatch {
+    /// The version expected by this build of `componentize-py`
+    pub expected: u32,
+    /// The version implemented by the runtime, or `None` if it predates version reporting
+    pub actual: Option<u32>,
+}
+
+impl fmt::Display for InitVersionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "componentize-py expected version {} of the init interface, but the embedded runtime ",
+            self.expected
+        )?;
+        if let Some(actual) = self.actual {
+            write!(f, "implements version {actual}")?;
+        } else {
+            write!(f, "does not report a version")?;
+        }
+        write!(f, "; please rebuild componentize-py from a clean tree")
+    }
+}
+
+impl std::error::Error for InitVersionMismatch {}
+
+/// Verify that `instance` implements the same version of the `componentize-py:init` interface as this build.
+///
+/// Note that we look up the `version` function dynamically rather than via the generated bindings, since the
+/// latter would fail to type-check a mismatched runtime before we had a chance to report a useful error.
+async fn check_init_version(store: &mut Store<Ctx>, instance: &Instance) -> Result<()> {
+    let actual = if let Some(index) = instance
+        .get_export(&mut *store, None, "exports")
+        .and_then(|exports| instance.get_export(&mut *store, Some(&exports), "version"))
+    {
+        let func = instance.get_typed_func::<(), (u32,)>(&mut *store, index)?;
+        let version = func.call_async(&mut *store, ()).await?.0;
+        func.post_return_async(&mut *store).await?;
+        Some(version)
+    } else {
+        None
+    };
+
+    if actual != Some(INIT_VERSION) {
+        return Err(InitVersionMismatch {
+            expected: INIT_VERSION,
+            actual,
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
 struct MyInvoker {
     store: Store<Ctx>,
     instance: Instance,
@@ -169,386 +419,1877 @@ impl Invoker for MyInvoker {
     }
 }
 
+/// Value of the `world_module` option which asks for the module name to be derived from the world's fully qualified
+/// name; see `world_module_name`.
+pub const AUTO_WORLD_MODULE: &str = "auto";
+
+/// Determine the name of the top-level module for the bindings of `world`.
+///
+/// If `world_module` is `None`, this is the world's name in snake case, and if it's anything other than
+/// `AUTO_WORLD_MODULE`, it's used as is.  Otherwise, the name is derived from the world's namespace, package, and
+/// name (e.g. `wasi_cli_command`), so that teams sharing WIT packages needn't coordinate module names by hand.  If
+/// a module of that name already exists in `dir` (e.g. one of the app's own modules) or in any of `site_packages`
+/// (e.g. those of the app's virtual environment), we add a `_bindings` suffix (plus a number, if that's taken too).
+/// Packages which look like bindings generated previously (i.e. those containing a `types` module) don't count, so
+/// regenerating bindings in place (or generating contract tests for them) yields the same name each time.
+fn world_module_name(
+    resolve: &Resolve,
+    world: WorldId,
+    world_module: Option<&str>,
+    dir: &Path,
+    site_packages: &[PathBuf],
+) -> WorldModule {
+    let world_name = resolve.worlds[world].name.to_snake_case().escape();
+    match world_module {
+        None => return WorldModule::new(world_name),
+        Some(name) if name != AUTO_WORLD_MODULE => return WorldModule::new(name.to_owned()),
+        Some(_) => (),
+    }
+
+    let base = if let Some(package) = resolve.worlds[world].package {
+        let name = &resolve.packages[package].name;
+        format!(
+            "{}_{}_{}",
+            name.namespace.to_snake_case(),
+            name.name.to_snake_case(),
+            resolve.worlds[world].name.to_snake_case()
+        )
+        .escape()
+    } else {
+        world_name
+    };
+
+    let taken = |name: &str| {
+        iter::once(dir)
+            .chain(site_packages.iter().map(PathBuf::as_path))
+            .find(|dir| {
+                let package = dir.join(name);
+                dir.join(format!("{name}.py")).exists()
+                    || (package.is_dir() && !package.join("types.py").exists())
+            })
+    };
+
+    let mut name = base.clone();
+    let mut reasons = Vec::new();
+    for suffix in 1.. {
+        let Some(dir) = taken(&name) else {
+            break;
+        };
+        let next = if suffix == 1 {
+            format!("{base}_bindings")
+        } else {
+            format!("{base}_bindings{suffix}")
+        };
+        reasons.push(format!("`{name}` already exists in {}", dir.display()));
+        name = next;
+    }
+
+    let note = (!reasons.is_empty()).then(|| {
+        format!(
+            "using `{name}` as the world module name since {}",
+            reasons.join(" and ")
+        )
+    });
+    WorldModule { name, note }
+}
+
+/// Top-level module name chosen for the bindings of a world; see `world_module_name`.
+pub struct WorldModule {
+    pub name: String,
+    /// If `AUTO_WORLD_MODULE` was requested and the name derived from the world was already taken, a note
+    /// explaining which name was used instead, for the caller to report
+    pub note: Option<String>,
+}
+
+impl WorldModule {
+    fn new(name: String) -> Self {
+        Self { name, note: None }
+    }
+}
+
+/// Builder for calls to `generate_bindings`, for embedders which only need to set a few of its options.
+///
+/// Unless otherwise specified, the WIT path is `./wit`, the bindings are written as modules to the current
+/// directory, and all other options take their defaults, matching the defaults of the `bindings` subcommand.  Call
+/// `generate` to write the bindings.
+pub struct BindingsBuilder<'a> {
+    wit_path: &'a Path,
+    world: Option<&'a str>,
+    primary_package: Option<&'a str>,
+    features: &'a [String],
+    all_features: bool,
+    world_module: Option<&'a str>,
+    site_packages: &'a [PathBuf],
+    output_dir: &'a Path,
+    import_interface_names: HashMap<&'a str, &'a str>,
+    export_interface_names: HashMap<&'a str, &'a str>,
+    typing_style: TypingStyle,
+    naming_style: NamingStyle,
+    version_collisions: VersionCollisions,
+    format: BindingsFormat,
+}
+
+impl Default for BindingsBuilder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> BindingsBuilder<'a> {
+    pub fn new() -> Self {
+        Self {
+            wit_path: Path::new("wit"),
+            world: None,
+            primary_package: None,
+            features: &[],
+            all_features: false,
+            world_module: None,
+            site_packages: &[],
+            output_dir: Path::new("."),
+            import_interface_names: HashMap::new(),
+            export_interface_names: HashMap::new(),
+            typing_style: TypingStyle::default(),
+            naming_style: NamingStyle::default(),
+            version_collisions: VersionCollisions::default(),
+            format: BindingsFormat::default(),
+        }
+    }
+
+    /// File or directory containing the WIT document(s) to target
+    pub fn wit_path(mut self, wit_path: &'a Path) -> Self {
+        self.wit_path = wit_path;
+        self
+    }
+
+    /// Name of the world to target (by default, the only world in the primary package)
+    pub fn world(mut self, world: &'a str) -> Self {
+        self.world = Some(world);
+        self
+    }
+
+    /// Package in which to look up an unqualified (or unspecified) world name
+    pub fn primary_package(mut self, primary_package: &'a str) -> Self {
+        self.primary_package = Some(primary_package);
+        self
+    }
+
+    /// WIT features to enable, each entry being a comma- or whitespace-separated list
+    pub fn features(mut self, features: &'a [String]) -> Self {
+        self.features = features;
+        self
+    }
+
+    /// Whether to enable all WIT features
+    pub fn all_features(mut self, all_features: bool) -> Self {
+        self.all_features = all_features;
+        self
+    }
+
+    /// Name of the top-level module for the bindings (by default, derived from the world name; see
+    /// `AUTO_WORLD_MODULE`)
+    pub fn world_module(mut self, world_module: &'a str) -> Self {
+        self.world_module = Some(world_module);
+        self
+    }
+
+    /// Directories other than `output_dir` (e.g. the `site-packages` directories of the app's virtual environment)
+    /// in which to check for modules whose names `AUTO_WORLD_MODULE` should avoid
+    pub fn site_packages(mut self, site_packages: &'a [PathBuf]) -> Self {
+        self.site_packages = site_packages;
+        self
+    }
+
+    /// Directory to which to write the bindings
+    pub fn output_dir(mut self, output_dir: &'a Path) -> Self {
+        self.output_dir = output_dir;
+        self
+    }
+
+    /// Python module names to use for imported interfaces, keyed by interface name
+    pub fn import_interface_names(mut self, names: HashMap<&'a str, &'a str>) -> Self {
+        self.import_interface_names = names;
+        self
+    }
+
+    /// Python module names to use for exported interfaces, keyed by interface name
+    pub fn export_interface_names(mut self, names: HashMap<&'a str, &'a str>) -> Self {
+        self.export_interface_names = names;
+        self
+    }
+
+    /// Which typing syntax to use in the generated code
+    pub fn typing_style(mut self, typing_style: TypingStyle) -> Self {
+        self.typing_style = typing_style;
+        self
+    }
+
+    /// How to derive Python names for WIT functions and parameters
+    pub fn naming_style(mut self, naming_style: NamingStyle) -> Self {
+        self.naming_style = naming_style;
+        self
+    }
+
+    /// How to name the modules for interfaces of which more than one version is present
+    pub fn version_collisions(mut self, version_collisions: VersionCollisions) -> Self {
+        self.version_collisions = version_collisions;
+        self
+    }
+
+    /// Whether to write the bindings as modules or as a wheel
+    pub fn format(mut self, format: BindingsFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Generate the bindings, returning the paths of the files generated (i.e. the modules, or the wheel
+    /// containing them) along with the name of the top-level module.
+    pub fn generate(self) -> Result<(BTreeSet<PathBuf>, WorldModule)> {
+        let Self {
+            wit_path,
+            world,
+            primary_package,
+            features,
+            all_features,
+            world_module,
+            site_packages,
+            output_dir,
+            import_interface_names,
+            export_interface_names,
+            typing_style,
+            naming_style,
+            version_collisions,
+            format,
+        } = self;
+
+        // TODO: Split out and reuse the code responsible for finding and using componentize-py.toml files in the
+        // `componentize` function below, since that can affect the bindings we should be generating.
+
+        let (resolve, world) = parse_wit(wit_path, world, primary_package, features, all_features)?;
+        let mut summary = Summary::try_new(
+            &resolve,
+            &iter::once(world).collect(),
+            &import_interface_names,
+            &export_interface_names,
+            naming_style,
+            version_collisions,
+        )?;
+        summary.typing_style = typing_style;
+        let world_module =
+            world_module_name(&resolve, world, world_module, output_dir, site_packages);
+        let name = &world_module.name;
+
+        let write = |output_dir: &Path| {
+            let world_dir = output_dir.join(name.replace('.', "/"));
+            fs::create_dir_all(&world_dir)?;
+            summary.generate_code(&world_dir, world, name, &mut Locations::default(), true)
+        };
+
+        let paths = match format {
+            BindingsFormat::Directory => write(output_dir)?,
+            BindingsFormat::Wheel => {
+                let dir = temp::dir(false)?;
+                write(dir.path())?;
+
+                let package = resolve.worlds[world]
+                    .package
+                    .map(|id| &resolve.packages[id].name);
+                wheel::write(
+                    dir.path(),
+                    output_dir,
+                    &wheel::Distribution {
+                        name: &name.replace(['.', '_'], "-"),
+                        version: &package
+                            .and_then(|name| name.version.as_ref())
+                            .map(wheel::pep440_version)
+                            .unwrap_or_else(|| "0.0.0".to_owned()),
+                        summary: &format!(
+                            "Python bindings for the `{}{}` WIT world",
+                            package
+                                .map(|name| format!("{}:{}/", name.namespace, name.name))
+                                .unwrap_or_default(),
+                            resolve.worlds[world].name
+                        ),
+                        requires_python: match typing_style {
+                            // `typing.Self` requires 3.11.
+                            TypingStyle::Legacy => ">=3.11",
+                            TypingStyle::Modern => ">=3.12",
+                        },
+                    },
+                )
+                .map(|wheel| iter::once(wheel).collect())?
+            }
+        };
+
+        Ok((paths, world_module))
+    }
+}
+
+/// Generate Python bindings for the specified world in `output_dir`, returning the paths of the files generated
+/// (i.e. the modules, or the wheel containing them).
+///
+/// This is a thin wrapper around `BindingsBuilder`, kept for compatibility with existing callers.  Options added
+/// since are available only via the builder, so this signature doesn't change as new ones are added.
 #[allow(clippy::too_many_arguments)]
 pub fn generate_bindings(
     wit_path: &Path,
     world: Option<&str>,
+    primary_package: Option<&str>,
     features: &[String],
     all_features: bool,
     world_module: Option<&str>,
     output_dir: &Path,
     import_interface_names: &HashMap<&str, &str>,
     export_interface_names: &HashMap<&str, &str>,
-) -> Result<()> {
-    // TODO: Split out and reuse the code responsible for finding and using componentize-py.toml files in the
-    // `componentize` function below, since that can affect the bindings we should be generating.
+    typing_style: TypingStyle,
+    naming_style: NamingStyle,
+    version_collisions: VersionCollisions,
+    format: BindingsFormat,
+) -> Result<BTreeSet<PathBuf>> {
+    BindingsBuilder {
+        wit_path,
+        world,
+        primary_package,
+        features,
+        all_features,
+        world_module,
+        site_packages: &[],
+        output_dir,
+        import_interface_names: import_interface_names.clone(),
+        export_interface_names: export_interface_names.clone(),
+        typing_style,
+        naming_style,
+        version_collisions,
+        format,
+    }
+    .generate()
+    .map(|(paths, _)| paths)
+}
 
-    let (resolve, world) = parse_wit(wit_path, world, features, all_features)?;
+/// Generate a `pytest` module containing property-based contract tests for the exports of the specified world, as
+/// implemented by the `app_name` module, and write it to `output_path`, returning the name of the bindings module
+/// the tests import.
+///
+/// `site_packages` is as described for `BindingsBuilder::site_packages`.  See `Summary::generate_proptests` for
+/// details.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_proptests(
+    wit_path: &Path,
+    world: Option<&str>,
+    primary_package: Option<&str>,
+    features: &[String],
+    all_features: bool,
+    world_module: Option<&str>,
+    app_name: &str,
+    output_path: &Path,
+    import_interface_names: &HashMap<&str, &str>,
+    export_interface_names: &HashMap<&str, &str>,
+    naming_style: NamingStyle,
+    version_collisions: VersionCollisions,
+    site_packages: &[PathBuf],
+) -> Result<WorldModule> {
+    let (resolve, world) = parse_wit(wit_path, world, primary_package, features, all_features)?;
     let summary = Summary::try_new(
         &resolve,
         &iter::once(world).collect(),
         import_interface_names,
         export_interface_names,
+        naming_style,
+        version_collisions,
     )?;
-    let world_name = resolve.worlds[world].name.to_snake_case().escape();
-    let world_module = world_module.unwrap_or(&world_name);
-    let world_dir = output_dir.join(world_module.replace('.', "/"));
-    fs::create_dir_all(&world_dir)?;
-    summary.generate_code(
-        &world_dir,
-        world,
-        world_module,
-        &mut Locations::default(),
-        true,
+    let world_module =
+        world_module_name(&resolve, world, world_module, Path::new("."), site_packages);
+    fs::write(
+        output_path,
+        summary.generate_proptests(world, &world_module.name, app_name),
     )?;
 
-    Ok(())
+    Ok(world_module)
 }
 
-#[allow(clippy::type_complexity, clippy::too_many_arguments)]
-pub async fn componentize(
-    wit_path: Option<&Path>,
+/// Describe the Python API of the bindings generated for the specified world (i.e. its interfaces, types, and
+/// functions) as structured data which may be serialized as JSON, along with the name of the top-level module.
+///
+/// `site_packages` is as described for `BindingsBuilder::site_packages`.  See `Summary::describe` for details.
+#[allow(clippy::too_many_arguments)]
+pub fn describe_world(
+    wit_path: &Path,
     world: Option<&str>,
+    primary_package: Option<&str>,
     features: &[String],
     all_features: bool,
-    python_path: &[&str],
-    module_worlds: &[(&str, &str)],
-    app_name: &str,
-    output_path: &Path,
-    add_to_linker: Option<&dyn Fn(&mut Linker<Ctx>) -> Result<()>>,
-    stub_wasi: bool,
+    world_module: Option<&str>,
     import_interface_names: &HashMap<&str, &str>,
     export_interface_names: &HashMap<&str, &str>,
-) -> Result<()> {
-    // Remove non-existent elements from `python_path` so we don't choke on them later:
-    let python_path = &python_path
-        .iter()
-        .filter_map(|&s| Path::new(s).exists().then_some(s))
-        .collect::<Vec<_>>();
-
-    let embedded_python_standard_lib = prelink::embedded_python_standard_library()?;
-    let embedded_helper_utils = prelink::embedded_helper_utils()?;
+    naming_style: NamingStyle,
+    version_collisions: VersionCollisions,
+    site_packages: &[PathBuf],
+) -> Result<(api::WorldApi, WorldModule)> {
+    let (resolve, world) = parse_wit(wit_path, world, primary_package, features, all_features)?;
+    let summary = Summary::try_new(
+        &resolve,
+        &iter::once(world).collect(),
+        import_interface_names,
+        export_interface_names,
+        naming_style,
+        version_collisions,
+    )?;
+    let world_module =
+        world_module_name(&resolve, world, world_module, Path::new("."), site_packages);
 
-    let (configs, mut libraries) =
-        prelink::search_for_libraries_and_configs(python_path, module_worlds, world)?;
+    Ok((summary.describe(world, &world_module.name), world_module))
+}
 
-    // Next, iterate over all the WIT directories, merging them into a single `Resolve`, and matching Python
-    // packages to `WorldId`s.
-    let (mut resolve, mut main_world) = if let Some(path) = wit_path {
-        let (resolve, world) = parse_wit(path, world, features, all_features)?;
-        (Some(resolve), Some(world))
-    } else {
-        (None, None)
+/// Compare the bindings for `old_world` in the WIT file or directory at `old_wit_path` (i.e. those an app was
+/// written against) with the bindings for `world` in `wit_path`, returning a note for each change which may
+/// require the app to be updated.
+///
+/// See `migrate::notes` for details.
+#[allow(clippy::too_many_arguments)]
+pub fn migrate_bindings(
+    old_wit_path: &Path,
+    old_world: Option<&str>,
+    wit_path: &Path,
+    world: Option<&str>,
+    primary_package: Option<&str>,
+    features: &[String],
+    all_features: bool,
+    import_interface_names: &HashMap<&str, &str>,
+    export_interface_names: &HashMap<&str, &str>,
+    naming_style: NamingStyle,
+    version_collisions: VersionCollisions,
+) -> Result<Vec<String>> {
+    let api = |wit_path: &Path, world: Option<&str>| {
+        let (resolve, world) = parse_wit(wit_path, world, primary_package, features, all_features)?;
+        let summary = Summary::try_new(
+            &resolve,
+            &iter::once(world).collect(),
+            import_interface_names,
+            export_interface_names,
+            naming_style,
+            version_collisions,
+        )?;
+        Ok::<_, anyhow::Error>(summary.api(world))
     };
 
-    let import_interface_names = import_interface_names
-        .iter()
-        .map(|(a, b)| (*a, *b))
-        .chain(configs.iter().flat_map(|(_, (config, _))| {
-            config
-                .config
-                .import_interface_names
-                .iter()
-                .map(|(a, b)| (a.as_str(), b.as_str()))
-        }))
-        .collect();
+    let old = api(old_wit_path, old_world)
+        .with_context(|| format!("failed to process {}", old_wit_path.display()))?;
+    let new = api(wit_path, world)?;
 
-    let export_interface_names = export_interface_names
-        .iter()
-        .map(|(a, b)| (*a, *b))
-        .chain(configs.iter().flat_map(|(_, (config, _))| {
-            config
-                .config
-                .export_interface_names
-                .iter()
-                .map(|(a, b)| (a.as_str(), b.as_str()))
-        }))
-        .collect();
+    Ok(migrate::notes(&old, &new))
+}
 
-    let configs = configs
-        .iter()
-        .map(|(module, (config, world))| {
-            Ok((module, match (world, config.config.wit_directory.as_deref()) {
-                (_, Some(wit_path)) => {
-                    let (my_resolve, mut world) = parse_wit(&config.path.join(wit_path), *world, features, all_features)?;
-
-                    if let Some(resolve) = &mut resolve {
-                        let remap = resolve.merge(my_resolve)?;
-                        world = remap.worlds[world.index()].expect("missing world");
-                    } else {
-                        resolve = Some(my_resolve);
-                    }
+/// Generate a minimal host project for running components which target `world`, writing it to `output_dir` and
+/// returning the paths of the files written.
+///
+/// The host runs the component at `component` (by default) and provides a stub implementation of each of the
+/// world's imports for the user to fill in.  See `scaffold::write` for details.
+#[allow(clippy::too_many_arguments)]
+pub fn scaffold_host(
+    wit_path: &Path,
+    world: Option<&str>,
+    primary_package: Option<&str>,
+    features: &[String],
+    all_features: bool,
+    language: HostLanguage,
+    output_dir: &Path,
+    component: &str,
+) -> Result<Vec<PathBuf>> {
+    let (resolve, world) = parse_wit(wit_path, world, primary_package, features, all_features)?;
+    // The host's names come from `wasmtime`'s bindings generators rather than ours, so the naming options don't
+    // apply here.
+    let summary = Summary::try_new(
+        &resolve,
+        &iter::once(world).collect(),
+        &HashMap::new(),
+        &HashMap::new(),
+        NamingStyle::default(),
+        VersionCollisions::default(),
+    )?;
+
+    scaffold::write(&summary, world, language, wit_path, output_dir, component)
+}
+
+/// Describe the packages found in the WIT file or directory at `wit_path` (including any dependencies), along
+/// with each of their worlds and those worlds' imports and exports.
+///
+/// Each world is listed using the name which may be passed as the `world` parameter to e.g. `componentize` to
+/// select it given the same `primary_package`.  See `parse_wit` for how worlds are resolved.
+pub fn list_worlds(
+    wit_path: &Path,
+    primary_package: Option<&str>,
+    features: &[String],
+    all_features: bool,
+) -> Result<String> {
+    let (resolve, main) = load_wit(wit_path, features, all_features)?;
+    let main = primary(&resolve, main, primary_package)?;
+    let default = resolve.select_world(main, None).ok();
+
+    let mut list = String::new();
+    // List the primary package (by default, the one defined by `wit_path` itself) first, followed by the rest.
+    for (id, package) in iter::once((main, &resolve.packages[main]))
+        .chain(resolve.packages.iter().filter(|(id, _)| *id != main))
+    {
+        list.push_str(&format!("package {}\n", package.name));
 
-                    (config, Some(world))
+        for &world in package.worlds.values() {
+            let name = if id == main {
+                resolve.worlds[world].name.clone()
+            } else {
+                resolve.id_of_name(id, &resolve.worlds[world].name)
+            };
+            list.push_str(&format!(
+                "  world {name}{}\n",
+                if Some(world) == default {
+                    " (default)"
+                } else {
+                    ""
                 }
-                (None, None) => (config, None),
-                (Some(_), None) => {
-                    bail!("no `wit-directory` specified in `componentize-py.toml` for module `{module}`");
+            ));
+
+            for (direction, items) in [
+                ("import", &resolve.worlds[world].imports),
+                ("export", &resolve.worlds[world].exports),
+            ] {
+                for (key, item) in items {
+                    let name = resolve.name_world_key(key);
+                    list.push_str(&match item {
+                        WorldItem::Interface { .. } if matches!(key, WorldKey::Name(_)) => {
+                            format!("    {direction} {name}: interface\n")
+                        }
+                        WorldItem::Interface { .. } => format!("    {direction} {name}\n"),
+                        WorldItem::Function(_) => format!("    {direction} {name}: func\n"),
+                        WorldItem::Type(_) => format!("    {direction} {name}: type\n"),
+                    });
                 }
-            }))
-        })
-        .collect::<Result<IndexMap<_, _>>>()?;
+            }
+        }
+    }
 
-    let resolve = if let Some(resolve) = resolve {
-        resolve
-    } else {
-        // If no WIT directory was provided as a parameter and none were referenced by Python packages, use ./wit
-        // by default.
-        let (my_resolve, world) = parse_wit(Path::new("wit"), world, features, all_features)
-            .context(
-                "no WIT files found; please specify the directory or file \
-                 containing the WIT world you wish to target",
-            )?;
-        main_world = Some(world);
-        my_resolve
-    };
+    Ok(list)
+}
+
+/// Builder for calls to `componentize`, for embedders which only need to set a few of its many options.
+///
+/// Unless otherwise specified, the app is named `app`, the Python path is the current directory, the component
+/// is written to `index.wasm`, and all other options are disabled or empty, matching the defaults of the
+/// `componentize` subcommand.  Call `build` to produce the component.
+pub struct ComponentizeBuilder<'a> {
+    wit_path: Option<&'a Path>,
+    world: Option<&'a str>,
+    primary_package: Option<&'a str>,
+    features: &'a [String],
+    all_features: bool,
+    python_path: &'a [&'a str],
+    exclude_paths: &'a [&'a str],
+    static_libraries: &'a [&'a str],
+    dynamic_libraries: &'a [&'a str],
+    module_worlds: &'a [(&'a str, &'a str)],
+    app_name: &'a str,
+    output_path: &'a Path,
+    add_to_linker: Option<&'a dyn Fn(&mut Linker<Ctx>) -> Result<()>>,
+    transform_resolve: Option<&'a dyn Fn(&mut Resolve, &[WorldId]) -> Result<()>>,
+    stub_wasi: bool,
+    profile: Profile,
+    import_interface_names: HashMap<&'a str, &'a str>,
+    export_interface_names: HashMap<&'a str, &'a str>,
+    naming_style: NamingStyle,
+    version_collisions: VersionCollisions,
+    map_source_paths: bool,
+    quiet: bool,
+    optimize: optimize::Options<'a>,
+    exception_mappings: &'a [(&'a str, &'a str)],
+    diagnostics: bool,
+    strict: bool,
+    trace_imports: bool,
+    shutdown_hook: bool,
+    unbuffered: bool,
+    embed_stdlib: bool,
+    compose: &'a [&'a Path],
+    wasi_adapter: Option<&'a Path>,
+    artifact_dir: Option<&'a Path>,
+    import_time_budget: Option<Duration>,
+    pre_init_timeout: Option<Duration>,
+    pre_init_hook: Option<&'a str>,
+    output_capture_limit: Option<usize>,
+    metadata: &'a [(&'a str, &'a str)],
+    notices: Option<Notices>,
+    deterministic: bool,
+    keep_temp: bool,
+    cache: bool,
+    callbacks: Callbacks,
+    pytest: bool,
+}
 
-    // Extract relevant metadata from the `Resolve` into a `Summary` instance, which we'll use to generate Wasm-
-    // and Python-level bindings.
+impl Default for ComponentizeBuilder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-    let worlds = configs
-        .values()
-        .filter_map(|(_, world)| *world)
-        .chain(main_world)
-        .collect::<IndexSet<_>>();
+impl<'a> ComponentizeBuilder<'a> {
+    pub fn new() -> Self {
+        Self {
+            wit_path: None,
+            world: None,
+            primary_package: None,
+            features: &[],
+            all_features: false,
+            python_path: &["."],
+            exclude_paths: &[],
+            static_libraries: &[],
+            dynamic_libraries: &[],
+            module_worlds: &[],
+            app_name: "app",
+            output_path: Path::new("index.wasm"),
+            add_to_linker: None,
+            transform_resolve: None,
+            stub_wasi: false,
+            profile: Profile::Cli,
+            import_interface_names: HashMap::new(),
+            export_interface_names: HashMap::new(),
+            naming_style: NamingStyle::default(),
+            version_collisions: VersionCollisions::default(),
+            map_source_paths: false,
+            quiet: false,
+            optimize: optimize::Options::default(),
+            exception_mappings: &[],
+            diagnostics: false,
+            strict: false,
+            trace_imports: false,
+            shutdown_hook: false,
+            unbuffered: false,
+            embed_stdlib: false,
+            compose: &[],
+            wasi_adapter: None,
+            artifact_dir: None,
+            import_time_budget: None,
+            pre_init_timeout: None,
+            pre_init_hook: None,
+            output_capture_limit: None,
+            metadata: &[],
+            notices: None,
+            deterministic: false,
+            keep_temp: false,
+            cache: true,
+            callbacks: Callbacks::default(),
+            pytest: false,
+        }
+    }
 
-    if worlds
-        .iter()
-        .any(|&id| app_name == resolve.worlds[id].name.to_snake_case().escape())
-    {
-        bail!("App name `{app_name}` conflicts with world name; please rename your application module.");
+    /// File or directory containing the WIT document(s) to target (default: `./wit`, unless Python packages
+    /// provide their own)
+    pub fn wit_path(mut self, wit_path: &'a Path) -> Self {
+        self.wit_path = Some(wit_path);
+        self
     }
 
-    let summary = Summary::try_new(
-        &resolve,
-        &worlds,
-        &import_interface_names,
-        &export_interface_names,
-    )?;
+    /// Name of the world to target (by default, the only world in the primary package)
+    pub fn world(mut self, world: &'a str) -> Self {
+        self.world = Some(world);
+        self
+    }
 
-    libraries.push(Library {
-        name: "libcomponentize_py_bindings.so".into(),
-        module: bindings::make_bindings(&resolve, &worlds, &summary)?,
-        dl_openable: false,
-    });
+    /// Package in which to look up an unqualified (or unspecified) world name
+    pub fn primary_package(mut self, primary_package: &'a str) -> Self {
+        self.primary_package = Some(primary_package);
+        self
+    }
+
+    /// WIT features to enable, each entry being a comma- or whitespace-separated list
+    pub fn features(mut self, features: &'a [String]) -> Self {
+        self.features = features;
+        self
+    }
 
-    let component = link::link_libraries(&libraries)?;
+    /// Whether to enable all WIT features
+    pub fn all_features(mut self, all_features: bool) -> Self {
+        self.all_features = all_features;
+        self
+    }
 
-    let stubbed_component = if stub_wasi {
-        stubwasi::link_stub_modules(libraries)?
-    } else {
-        None
-    };
+    /// Directories (or `.whl`/`.zip` archives) containing the app and its dependencies
+    pub fn python_path(mut self, python_path: &'a [&'a str]) -> Self {
+        self.python_path = python_path;
+        self
+    }
 
-    // Pre-initialize the component by running it through `component_init::initialize`.  Currently, this is the
-    // application's first and only chance to load any standard or third-party modules since we do not yet include
-    // a virtual filesystem in the component to make those modules available at runtime.
-
-    let stdout = MemoryOutputPipe::new(10000);
-    let stderr = MemoryOutputPipe::new(10000);
-
-    let mut wasi = WasiCtxBuilder::new();
-    wasi.stdin(MemoryInputPipe::new(Bytes::new()))
-        .stdout(stdout.clone())
-        .stderr(stderr.clone())
-        .env("PYTHONUNBUFFERED", "1")
-        .env("COMPONENTIZE_PY_APP_NAME", app_name)
-        .env("PYTHONHOME", "/python")
-        .preopened_dir(
-            embedded_python_standard_lib.path(),
-            "python",
-            DirPerms::all(),
-            FilePerms::all(),
-        )?
-        .preopened_dir(
-            embedded_helper_utils.path(),
-            "bundled",
-            DirPerms::all(),
-            FilePerms::all(),
-        )?;
+    /// Patterns matching files and directories in `python_path` to hide from the app (in addition to defaults such
+    /// as `__pycache__/` and `.git/`), using a subset of `.gitignore` syntax
+    pub fn exclude_paths(mut self, exclude_paths: &'a [&'a str]) -> Self {
+        self.exclude_paths = exclude_paths;
+        self
+    }
 
-    // Generate guest mounts for each host directory in `python_path`.
-    for (index, path) in python_path.iter().enumerate() {
-        wasi.preopened_dir(path, index.to_string(), DirPerms::all(), FilePerms::all())?;
+    /// Patterns matching libraries to link statically, i.e. such that they can satisfy other libraries' imports
+    /// but can't be loaded using `dlopen`
+    pub fn static_libraries(mut self, static_libraries: &'a [&'a str]) -> Self {
+        self.static_libraries = static_libraries;
+        self
     }
 
-    // For each Python package with a `componentize-py.toml` file that specifies where generated bindings for that
-    // package should be placed, generate the bindings and place them as indicated.
+    /// Patterns matching libraries to make loadable using `dlopen`, including embedded libraries and shared
+    /// libraries in `python_path` which aren't native extensions
+    pub fn dynamic_libraries(mut self, dynamic_libraries: &'a [&'a str]) -> Self {
+        self.dynamic_libraries = dynamic_libraries;
+        self
+    }
 
-    let mut world_dir_mounts = Vec::new();
-    let mut locations = Locations::default();
-    let mut saw_main_world = false;
+    /// Which world to use with which Python module, in topological order
+    pub fn module_worlds(mut self, module_worlds: &'a [(&'a str, &'a str)]) -> Self {
+        self.module_worlds = module_worlds;
+        self
+    }
 
-    for (config, world, binding_path) in configs
-        .values()
-        .filter_map(|(config, world)| Some((config, world, config.config.bindings.as_deref()?)))
-    {
-        if *world == main_world {
-            saw_main_world = true;
+    /// Name of the Python module containing the app
+    pub fn app_name(mut self, app_name: &'a str) -> Self {
+        self.app_name = app_name;
+        self
+    }
+
+    /// File to which to write the component
+    pub fn output_path(mut self, output_path: &'a Path) -> Self {
+        self.output_path = output_path;
+        self
+    }
+
+    /// Function to add host implementations of the world's imports to the linker used for pre-initialization
+    ///
+    /// Note that the build cache is bypassed when this is set.
+    pub fn add_to_linker(
+        mut self,
+        add_to_linker: &'a dyn Fn(&mut Linker<Ctx>) -> Result<()>,
+    ) -> Self {
+        self.add_to_linker = Some(add_to_linker);
+        self
+    }
+
+    /// Function to modify the `Resolve` (e.g. rename interfaces or remove unwanted functions) after all WIT files
+    /// have been parsed and merged but before any bindings are generated
+    ///
+    /// The function is passed the worlds being targeted, i.e. the main world followed by any referenced by Python
+    /// packages.  Note that the build cache is bypassed when this is set.
+    pub fn transform_resolve(
+        mut self,
+        transform_resolve: &'a dyn Fn(&mut Resolve, &[WorldId]) -> Result<()>,
+    ) -> Self {
+        self.transform_resolve = Some(transform_resolve);
+        self
+    }
+
+    /// Whether to replace all WASI imports with trapping stubs
+    pub fn stub_wasi(mut self, stub_wasi: bool) -> Self {
+        self.stub_wasi = stub_wasi;
+        self
+    }
+
+    /// Which WASI interfaces the component may import (others are replaced with trapping stubs)
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Python module names to use for imported interfaces, keyed by interface name
+    pub fn import_interface_names(mut self, names: HashMap<&'a str, &'a str>) -> Self {
+        self.import_interface_names = names;
+        self
+    }
+
+    /// Python module names to use for exported interfaces, keyed by interface name
+    pub fn export_interface_names(mut self, names: HashMap<&'a str, &'a str>) -> Self {
+        self.export_interface_names = names;
+        self
+    }
+
+    /// How to derive Python names for WIT functions and parameters (this must match the bindings the app uses)
+    pub fn naming_style(mut self, naming_style: NamingStyle) -> Self {
+        self.naming_style = naming_style;
+        self
+    }
+
+    /// How to name the modules for interfaces of which more than one version is present (this must match the
+    /// bindings the app uses)
+    pub fn version_collisions(mut self, version_collisions: VersionCollisions) -> Self {
+        self.version_collisions = version_collisions;
+        self
+    }
+
+    /// Whether to make tracebacks refer to host rather than guest paths
+    pub fn map_source_paths(mut self, map_source_paths: bool) -> Self {
+        self.map_source_paths = map_source_paths;
+        self
+    }
+
+    /// Whether to suppress output (other than that sent to `callbacks`)
+    pub fn quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Size optimizations to apply, and what to produce alongside the component
+    pub fn optimize(mut self, optimize: optimize::Options<'a>) -> Self {
+        self.optimize = optimize;
+        self
+    }
+
+    /// Exception types to map to functions which produce `Err` payloads, as `(type, function)` pairs
+    pub fn exception_mappings(mut self, exception_mappings: &'a [(&'a str, &'a str)]) -> Self {
+        self.exception_mappings = exception_mappings;
+        self
+    }
+
+    /// Whether to export `componentize-py:diagnostics/counters` and `componentize-py:diagnostics/memory`
+    pub fn diagnostics(mut self, diagnostics: bool) -> Self {
+        self.diagnostics = diagnostics;
+        self
+    }
+
+    /// Whether to check the types of values passed to the host before lowering them
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Whether to log each call across the component boundary, with its duration and parameter sizes
+    pub fn trace_imports(mut self, trace_imports: bool) -> Self {
+        self.trace_imports = trace_imports;
+        self
+    }
+
+    /// Whether to export `componentize-py:lifecycle/shutdown`
+    pub fn shutdown_hook(mut self, shutdown_hook: bool) -> Self {
+        self.shutdown_hook = shutdown_hook;
+        self
+    }
+
+    /// Whether to make `sys.stdout` and `sys.stderr` unbuffered rather than line-buffered at runtime
+    pub fn unbuffered(mut self, unbuffered: bool) -> Self {
+        self.unbuffered = unbuffered;
+        self
+    }
+
+    /// Whether to make the whole Python standard library importable at runtime, rather than only the modules
+    /// imported during pre-initialization
+    pub fn embed_stdlib(mut self, embed_stdlib: bool) -> Self {
+        self.embed_stdlib = embed_stdlib;
+        self
+    }
+
+    /// Components whose exports should satisfy the component's imports
+    pub fn compose(mut self, compose: &'a [&'a Path]) -> Self {
+        self.compose = compose;
+        self
+    }
+
+    /// `wasi_snapshot_preview1` adapter module to use in place of the built-in reactor adapter
+    pub fn wasi_adapter(mut self, wasi_adapter: &'a Path) -> Self {
+        self.wasi_adapter = Some(wasi_adapter);
+        self
+    }
+
+    /// Directory containing the runtime artifacts (see `componentize-py export-artifacts`) to use in place of
+    /// those embedded in this build (default: `$COMPONENTIZE_PY_ARTIFACT_DIR`, if set)
+    pub fn artifact_dir(mut self, artifact_dir: &'a Path) -> Self {
+        self.artifact_dir = Some(artifact_dir);
+        self
+    }
+
+    /// Maximum time any one module may spend importing during pre-initialization, not counting the modules it
+    /// imports in turn
+    pub fn import_time_budget(mut self, import_time_budget: Duration) -> Self {
+        self.import_time_budget = Some(import_time_budget);
+        self
+    }
+
+    /// Maximum time pre-initialization as a whole may take
+    pub fn pre_init_timeout(mut self, pre_init_timeout: Duration) -> Self {
+        self.pre_init_timeout = Some(pre_init_timeout);
+        self
+    }
+
+    /// Function to call once the app has been imported during pre-initialization, given as either the name of an
+    /// attribute of the app module or a `module:attribute` path (default: `__componentize_pre_init__`, if the app
+    /// defines it)
+    pub fn pre_init_hook(mut self, pre_init_hook: &'a str) -> Self {
+        self.pre_init_hook = Some(pre_init_hook);
+        self
+    }
+
+    /// Maximum number of bytes of each of `stdout` and `stderr` to retain from pre-initialization for error
+    /// reporting (default: `output::DEFAULT_CAPTURE_LIMIT`)
+    pub fn output_capture_limit(mut self, output_capture_limit: usize) -> Self {
+        self.output_capture_limit = Some(output_capture_limit);
+        self
+    }
+
+    /// Descriptive metadata to embed in the component, as `(key, value)` pairs (e.g. `("version", "1.2.3")`)
+    pub fn metadata(mut self, metadata: &'a [(&'a str, &'a str)]) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// How to record the license texts and notices of the third-party code the component contains, if at all
+    pub fn notices(mut self, notices: Notices) -> Self {
+        self.notices = Some(notices);
+        self
+    }
+
+    /// Whether to make the output reproducible
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Whether to leave temporary directories in place (and print their paths) rather than removing them
+    pub fn keep_temp(mut self, keep_temp: bool) -> Self {
+        self.keep_temp = keep_temp;
+        self
+    }
+
+    /// Whether to reuse a component from the build cache if one was built from identical inputs, and to add the
+    /// result to the cache otherwise (default: true)
+    pub fn cache(mut self, cache: bool) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Callbacks through which to report output and progress
+    pub fn callbacks(mut self, callbacks: Callbacks) -> Self {
+        self.callbacks = callbacks;
+        self
+    }
+
+    /// Build a component from the app's world with the `componentize-py:pytest` interfaces added, run the pytest
+    /// suite collected from the current directory (which is added to the Python path if necessary) inside it, and
+    /// return pytest's exit code.
+    ///
+    /// `app`, if specified, names the module implementing the world's exports, `args` are passed to pytest, and
+    /// the outcome of each test is written as JUnit XML to `junit_xml`, if specified.  The output path is ignored.
+    pub async fn test(
+        self,
+        app: Option<&str>,
+        args: &[&str],
+        junit_xml: Option<&Path>,
+    ) -> Result<i32> {
+        pytest::run(self, app, args, junit_xml).await
+    }
+
+    /// Build the component and write it to the output path.
+    pub async fn build(self) -> Result<()> {
+        let Self {
+            wit_path,
+            world,
+            primary_package,
+            features,
+            all_features,
+            python_path,
+            exclude_paths,
+            static_libraries,
+            dynamic_libraries,
+            module_worlds,
+            app_name,
+            output_path,
+            add_to_linker,
+            transform_resolve,
+            stub_wasi,
+            profile,
+            import_interface_names,
+            export_interface_names,
+            naming_style,
+            version_collisions,
+            map_source_paths,
+            quiet,
+            optimize,
+            exception_mappings,
+            diagnostics,
+            strict,
+            trace_imports,
+            shutdown_hook,
+            unbuffered,
+            embed_stdlib,
+            compose,
+            wasi_adapter,
+            artifact_dir,
+            import_time_budget,
+            pre_init_timeout,
+            pre_init_hook,
+            output_capture_limit,
+            metadata,
+            notices,
+            deterministic,
+            keep_temp,
+            cache,
+            callbacks,
+            pytest,
+        } = self;
+        let import_interface_names = &import_interface_names;
+        let export_interface_names = &export_interface_names;
+        let optimize = &optimize;
+        let callbacks = &callbacks;
+
+        metadata::validate(metadata)?;
+
+        let artifacts = &Artifacts::new(artifact_dir)?;
+
+        if stub_wasi && profile != Profile::Cli {
+            bail!("`stub_wasi` replaces all WASI imports with stubs and cannot be combined with the `{profile}` profile");
         }
 
-        let Some(world) = *world else {
-            bail!("please specify a world for module `{}`", config.module);
-        };
+        if trace_imports && (stub_wasi || profile == Profile::Minimal) {
+            bail!("`trace_imports` times calls using `wasi:clocks/monotonic-clock`, which is unavailable with `stub_wasi` or the `minimal` profile");
+        }
 
-        let paths = python_path
+        // Extract any archives in `python_path` and remove non-existent elements so we don't choke on them later:
+        let python_path = prelink::extract_archives(python_path)?;
+        let python_path = &python_path
             .iter()
-            .enumerate()
-            .map(|(index, dir)| {
-                let dir = Path::new(dir).canonicalize()?;
-                Ok(if config.root == dir {
-                    config
-                        .path
-                        .join(binding_path)
-                        .strip_prefix(dir)
-                        .ok()
-                        .map(|p| (index, p.to_str().unwrap().replace('\\', "/")))
-                } else {
-                    None
-                })
-            })
-            .filter_map(Result::transpose)
-            .collect::<Result<Vec<_>>>()?;
+            .filter_map(|s| Path::new(s).exists().then_some(s.as_str()))
+            .collect::<Vec<_>>();
 
-        let binding_module = paths.first().unwrap().1.replace('/', ".");
+        callbacks.report(Phase::Prelinking);
 
-        let world_dir = tempfile::tempdir()?;
+        let excluded_paths = &prelink::find_excluded_paths(python_path, exclude_paths)?;
 
-        summary.generate_code(
-            world_dir.path(),
+        let (configs, library_path) = prelink::search_for_libraries_and_configs(
+            python_path,
+            excluded_paths,
+            module_worlds,
             world,
-            &binding_module,
-            &mut locations,
-            false,
         )?;
 
-        world_dir_mounts.push((
-            paths
-                .iter()
-                .map(|(index, p)| format!("{index}/{p}"))
-                .collect(),
-            world_dir,
-        ));
-    }
-
-    // If the caller specified a world and we haven't already generated bindings for it above, do so now.
-    if let (Some(world), false) = (main_world, saw_main_world) {
-        let module = resolve.worlds[world].name.to_snake_case();
-        let world_dir = tempfile::tempdir()?;
-        let module_path = world_dir.path().join(&module);
-        fs::create_dir_all(&module_path)?;
-        summary.generate_code(&module_path, world, &module, &mut locations, false)?;
-        world_dir_mounts.push((vec!["world".to_owned()], world_dir));
-
-        // The helper utilities are hard-coded to assume the world module is named `proxy`.  Here we replace that
-        // with the actual world name.
-        fn replace(path: &Path, pattern: &str, replacement: &str) -> Result<()> {
-            if path.is_dir() {
-                for entry in fs::read_dir(path)? {
-                    replace(&entry?.path(), pattern, replacement)?;
+        // If we've already built a component from identical inputs, reuse it rather than pre-initializing it again.
+        // We can't account for the behavior of a caller-supplied linker or `Resolve` transformation, though, so we
+        // bypass the cache in those cases, as well as for test builds, whose Python path includes a temporary directory.
+        let cache = if cache && add_to_linker.is_none() && transform_resolve.is_none() && !pytest {
+            BuildCache::new(&cache::Inputs {
+                artifacts,
+                wit_path: wit_path.unwrap_or(Path::new("wit")),
+                world,
+                primary_package,
+                features,
+                all_features,
+                python_path,
+                excluded_paths,
+                output_path,
+                static_libraries,
+                dynamic_libraries,
+                module_worlds,
+                app_name,
+                stub_wasi,
+                profile,
+                import_interface_names,
+                export_interface_names,
+                naming_style,
+                version_collisions,
+                map_source_paths,
+                optimize,
+                exception_mappings,
+                diagnostics,
+                strict,
+                trace_imports,
+                shutdown_hook,
+                unbuffered,
+                embed_stdlib,
+                compose,
+                wasi_adapter,
+                import_time_budget,
+                pre_init_hook,
+                metadata,
+                notices,
+                deterministic,
+            })?
+        } else {
+            None
+        };
+
+        let write_notices = || {
+            if notices == Some(Notices::File) {
+                let path = notices::path(output_path);
+                fs::write(&path, notices::collect(artifacts, python_path)?)
+                    .with_context(|| path.display().to_string())?;
+            }
+            Ok::<_, Error>(())
+        };
+
+        if let Some(entry) = cache.as_ref().and_then(BuildCache::load) {
+            // Replay whatever the app (including any pre-init hook) printed when the component was built, as if
+            // it had just been pre-initialized.
+            let _ = callbacks.stdout_echo(quiet).write(&entry.stdout);
+            let _ = callbacks.stderr_echo(quiet).write(&entry.stderr);
+            callbacks.report(Phase::Writing);
+            write_notices()?;
+            return optimize.write_output(output_path, &entry.component);
+        }
+
+        let embedded_python_standard_lib =
+            prelink::embedded_python_standard_library(artifacts, keep_temp)?;
+        optimize.prune_standard_library(embedded_python_standard_lib.path())?;
+        let embedded_helper_utils = prelink::embedded_helper_utils(artifacts, keep_temp)?;
+
+        let mut libraries = prelink::bundle_libraries(artifacts, library_path)?;
+        prelink::override_linking(
+            &mut libraries,
+            python_path,
+            excluded_paths,
+            static_libraries,
+            dynamic_libraries,
+        )?;
+        optimize.prune_libraries(&mut libraries)?;
+        prelink::check_abi(&libraries, python_path)?;
+
+        callbacks.report(Phase::GeneratingBindings);
+
+        // Next, iterate over all the WIT directories, merging them into a single `Resolve`, and matching Python
+        // packages to `WorldId`s.
+        let (mut resolve, mut main_world) = if let Some(path) = wit_path {
+            let (resolve, world) = parse_wit(path, world, primary_package, features, all_features)?;
+            (Some(resolve), Some(world))
+        } else {
+            (None, None)
+        };
+
+        let import_interface_names = import_interface_names
+            .iter()
+            .map(|(a, b)| (*a, *b))
+            .chain(configs.iter().flat_map(|(_, (config, _))| {
+                config
+                    .config
+                    .import_interface_names
+                    .iter()
+                    .map(|(a, b)| (a.as_str(), b.as_str()))
+            }))
+            .collect();
+
+        let export_interface_names = export_interface_names
+            .iter()
+            .map(|(a, b)| (*a, *b))
+            .chain(configs.iter().flat_map(|(_, (config, _))| {
+                config
+                    .config
+                    .export_interface_names
+                    .iter()
+                    .map(|(a, b)| (a.as_str(), b.as_str()))
+            }))
+            .collect();
+
+        let configs = configs
+            .iter()
+            .map(|(module, (config, world))| {
+                Ok((module, match (world, config.config.wit_directory.as_deref()) {
+                    (_, Some(wit_path)) => {
+                        // Enable any features the package needs in addition to those specified by the caller.  Note
+                        // that `primary_package` applies only to the main WIT path; a module's own WIT directory
+                        // always defines its primary package.
+                        let my_features = features.iter().chain(&config.config.features).cloned().collect::<Vec<_>>();
+                        let (my_resolve, mut world) = parse_wit(&config.path.join(wit_path), *world, None, &my_features, all_features)?;
+
+                        if let Some(resolve) = &mut resolve {
+                            let remap = match resolve.merge(my_resolve) {
+                                // If the package requested extra features, the same WIT package may have been parsed
+                                // elsewhere without them, in which case its contents won't match.
+                                Err(e) if !(config.config.features.is_empty() || all_features) => {
+                                    return Err(e.context(format!(
+                                        "WIT features requested by module `{module}` ({}) may conflict with those used \
+                                         elsewhere in this build; try enabling them for all WIT files using `--features`",
+                                        config.config.features.join(", ")
+                                    )));
+                                }
+                                result => result?,
+                            };
+                            world = remap.worlds[world.index()].expect("missing world");
+                        } else {
+                            resolve = Some(my_resolve);
+                        }
+
+                        (config, Some(world))
+                    }
+                    (None, None) => (config, None),
+                    (Some(_), None) => {
+                        bail!("no `wit-directory` specified in `componentize-py.toml` for module `{module}`");
+                    }
+                }))
+            })
+            .collect::<Result<IndexMap<_, _>>>()?;
+
+        let mut resolve = if let Some(resolve) = resolve {
+            resolve
+        } else {
+            // If no WIT directory was provided as a parameter and none were referenced by Python packages, use ./wit
+            // by default.  Only a missing directory is reported as such; any other error (e.g. an ambiguous default
+            // world) is passed along as-is.
+            let path = Path::new("wit");
+            if !path.exists() {
+                bail!(
+                    "no WIT files found; please specify the directory or file \
+                     containing the WIT world you wish to target"
+                );
+            }
+            let (my_resolve, world) =
+                parse_wit(path, world, primary_package, features, all_features)?;
+            main_world = Some(world);
+            my_resolve
+        };
+
+        // If requested, add exports of `componentize-py:diagnostics/counters` and `componentize-py:diagnostics/memory`
+        // and/or `componentize-py:lifecycle/shutdown` to the main world.  The bundled `componentize_py_diagnostics` and
+        // `componentize_py_lifecycle` modules implement them, so the app needn't do anything (other than register any
+        // shutdown callbacks).
+        for (enabled, option, path, wit, names) in [
+            (
+                diagnostics,
+                "--diagnostics",
+                "diagnostics.wit",
+                include_str!("diagnostics.wit"),
+                &["counters", "memory"][..],
+            ),
+            (
+                shutdown_hook,
+                "--shutdown-hook",
+                "lifecycle.wit",
+                include_str!("lifecycle.wit"),
+                &["shutdown"],
+            ),
+        ] {
+            if enabled {
+                let Some(world) = main_world else {
+                    bail!("`{option}` requires a WIT world to be specified");
+                };
+                let package = resolve.push_str(path, wit)?;
+                for name in names {
+                    let interface = resolve.packages[package].interfaces[*name];
+                    resolve.worlds[world].exports.insert(
+                        WorldKey::Interface(interface),
+                        WorldItem::Interface {
+                            id: interface,
+                            stability: Default::default(),
+                        },
+                    );
                 }
-            } else {
-                fs::write(
-                    path,
-                    fs::read_to_string(path)?
-                        .replace(pattern, replacement)
-                        .as_bytes(),
+            }
+        }
+
+        // When running the app's tests (see `ComponentizeBuilder::test`), also import the interface through which the
+        // bundled `componentize_py_pytest` module reports each test's outcome, and export the one through which the
+        // host starts the tests.
+        if pytest {
+            let Some(world) = main_world else {
+                bail!("running tests requires a WIT world to be specified");
+            };
+            let package = resolve.push_str("pytest.wit", include_str!("pytest.wit"))?;
+            let interfaces = &resolve.packages[package].interfaces;
+            let (reporter, runner) = (interfaces["reporter"], interfaces["runner"]);
+            let world = &mut resolve.worlds[world];
+            for (items, id) in [(&mut world.imports, reporter), (&mut world.exports, runner)] {
+                items.insert(
+                    WorldKey::Interface(id),
+                    WorldItem::Interface {
+                        id,
+                        stability: Default::default(),
+                    },
+                );
+            }
+        }
+
+        // Extract relevant metadata from the `Resolve` into a `Summary` instance, which we'll use to generate Wasm-
+        // and Python-level bindings.
+
+        let worlds = configs
+            .values()
+            .filter_map(|(_, world)| *world)
+            .chain(main_world)
+            .collect::<IndexSet<_>>();
+
+        if worlds
+            .iter()
+            .any(|&id| app_name == resolve.worlds[id].name.to_snake_case().escape())
+        {
+            bail!("App name `{app_name}` conflicts with world name; please rename your application module.");
+        }
+
+        if let Some(transform_resolve) = transform_resolve {
+            transform_resolve(&mut resolve, &worlds.iter().copied().collect::<Vec<_>>())?;
+        }
+
+        let summary = Summary::try_new(
+            &resolve,
+            &worlds,
+            &import_interface_names,
+            &export_interface_names,
+            naming_style,
+            version_collisions,
+        )?;
+
+        libraries.push(Library {
+            name: "libcomponentize_py_bindings.so".into(),
+            module: bindings::make_bindings(&resolve, &worlds, &summary)?,
+            dl_openable: false,
+        });
+
+        callbacks.report(Phase::Linking);
+
+        let wasi_adapter = if let Some(path) = wasi_adapter {
+            fs::read(path)
+                .with_context(|| format!("failed to read WASI adapter {}", path.display()))?
+        } else {
+            artifacts.decompress(artifacts::WASI_ADAPTER)?
+        };
+
+        let linked_component = link::link_libraries(&libraries, &wasi_adapter)?;
+
+        let stubbed_component = if stub_wasi {
+            stubwasi::link_stub_modules(&libraries)?
+        } else if let Some(interfaces) = profile.interfaces() {
+            stubwasi::link_with_profile(&mut libraries, &wasi_adapter, interfaces)?
+        } else {
+            None
+        };
+
+        // Each library has been copied into the linked component(s) by now, so free them before pre-initialization,
+        // which is when memory use peaks.  Likewise, we free each intermediate version of the component below as
+        // soon as we've derived the next one from it.
+        drop(libraries);
+
+        // Pre-initialize the component by running it through `component_init::initialize`.  Currently, this is the
+        // application's first and only chance to load any standard or third-party modules since we do not yet include
+        // a virtual filesystem in the component to make those modules available at runtime.
+
+        // Stream any output from pre-initialization to the user (or the caller's callbacks) as it happens, unless
+        // asked not to, while also retaining it in case we need to report an error.
+        let output_capture_limit = output_capture_limit.unwrap_or(output::DEFAULT_CAPTURE_LIMIT);
+        let stdout = TeeOutputPipe::new(callbacks.stdout_echo(quiet), output_capture_limit);
+        let stderr = TeeOutputPipe::new(callbacks.stderr_echo(quiet), output_capture_limit);
+
+        let mut wasi = WasiCtxBuilder::new();
+        wasi.stdin(MemoryInputPipe::new(Bytes::new()))
+            .stdout(stdout.clone())
+            .stderr(stderr.clone())
+            .env("PYTHONUNBUFFERED", "1")
+            .env("COMPONENTIZE_PY_APP_NAME", app_name)
+            .env("COMPONENTIZE_PY_VERSION", env!("CARGO_PKG_VERSION"))
+            .env("PYTHONHOME", "/python")
+            .preopened_dir(
+                embedded_python_standard_lib.path(),
+                "python",
+                DirPerms::all(),
+                FilePerms::all(),
+            )?
+            .preopened_dir(
+                embedded_helper_utils.path(),
+                "bundled",
+                DirPerms::all(),
+                FilePerms::all(),
+            )?;
+
+        // Generate guest mounts for each host directory in `python_path`, leaving out any excluded paths.
+        let mut staged = Vec::new();
+        for (index, (path, excluded)) in python_path.iter().zip(excluded_paths).enumerate() {
+            let (mounts, dir) = prelink::filtered_view(path, excluded, keep_temp)?;
+            for (host, guest) in mounts {
+                wasi.preopened_dir(
+                    host,
+                    format!("{index}{guest}"),
+                    DirPerms::all(),
+                    FilePerms::all(),
                 )?;
             }
+            staged.extend(dir);
+        }
 
-            Ok(())
+        if deterministic {
+            deterministic::configure(&mut wasi);
         }
-        replace(embedded_helper_utils.path(), "proxy", &module)?;
-    };
 
-    for (mounts, world_dir) in world_dir_mounts.iter() {
-        for mount in mounts {
-            wasi.preopened_dir(world_dir.path(), mount, DirPerms::all(), FilePerms::all())?;
+        // If requested, tell the runtime how to map guest paths in tracebacks back to host paths.  We also embed the
+        // mapping in the component itself so that other tools can use it.
+        let source_map = if map_source_paths {
+            let source_map = python_path
+                .iter()
+                .enumerate()
+                .map(|(index, path)| {
+                    let mut path = Path::new(path)
+                        .canonicalize()
+                        .with_context(|| path.to_string())?;
+                    if deterministic {
+                        path = deterministic::normalize_path(&path);
+                    }
+                    let path = path.to_str().context("non-UTF-8 path")?.replace('\\', "/");
+                    Ok(format!("/{index}\t{path}"))
+                })
+                .collect::<Result<Vec<_>>>()?
+                .join("\n");
+
+            wasi.env("COMPONENTIZE_PY_SOURCE_MAP", &source_map);
+
+            Some(source_map)
+        } else {
+            None
+        };
+
+        // Pre-initialization itself always runs unbuffered (see `PYTHONUNBUFFERED` above) so the user sees output as
+        // it happens; this tells the runtime which policy to switch to for the component itself.
+        if unbuffered {
+            wasi.env("COMPONENTIZE_PY_UNBUFFERED", "1");
+        }
+
+        // Tell the runtime which WASI interfaces it may call directly once the component is running, since the
+        // profile may have stubbed out some of those it imports itself.
+        if let Some(interfaces) = profile.interfaces() {
+            wasi.env("COMPONENTIZE_PY_WASI_INTERFACES", interfaces.join(","));
+        }
+
+        // The standard library is only mounted during pre-initialization, so the runtime reads it into memory then
+        // (see `bundled/componentize_py_stdlib.py`), pruned according to `exclude_modules` like the rest of it.
+        if embed_stdlib {
+            wasi.env("COMPONENTIZE_PY_EMBED_STDLIB", "1");
         }
-    }
 
-    // Generate a `Symbols` object containing metadata to be passed to the pre-init function.  The runtime library
-    // will use this to look up types and functions that will later be referenced by the generated Wasm code.
-    let symbols = summary.collect_symbols(&locations);
+        // Tell the runtime how to map exceptions escaping from exports to `Err` results.  Mappings specified by the
+        // caller take precedence over those from `componentize-py.toml` files (each listed in the order it appears in
+        // its file), since the runtime uses the first one it finds for a given exception type.
+        let exception_mappings = exception_mappings
+            .iter()
+            .copied()
+            .chain(configs.values().flat_map(|(config, _)| {
+                config
+                    .config
+                    .exception_mappings
+                    .iter()
+                    .map(|(a, b)| (a.as_str(), b.as_str()))
+            }))
+            .map(|(exception, function)| format!("{exception}\t{function}"))
+            .collect::<Vec<_>>();
+
+        if !exception_mappings.is_empty() {
+            wasi.env(
+                "COMPONENTIZE_PY_EXCEPTION_MAPPINGS",
+                exception_mappings.join("\n"),
+            );
+        }
 
-    // Finally, pre-initialize the component, writing the result to `output_path`.
+        // Classes which implement exported interfaces in place of the ones the runtime would otherwise look up in
+        // the app module by protocol name.
+        let export_classes = configs
+            .values()
+            .flat_map(|(config, _)| {
+                config
+                    .config
+                    .exports
+                    .iter()
+                    .map(|(a, b)| (a.as_str(), b.as_str()))
+            })
+            .collect::<HashMap<_, _>>();
 
-    let python_path = (0..python_path.len())
-        .map(|index| format!("/{index}"))
-        .collect::<Vec<_>>()
-        .join(":");
+        if !export_classes.is_empty() {
+            wasi.env(
+                "COMPONENTIZE_PY_EXPORT_CLASSES",
+                summary.export_classes(&export_classes)?,
+            );
+        }
 
-    let table = ResourceTable::new();
-    let wasi = wasi
-        .env(
-            "PYTHONPATH",
-            format!("/python:/world:{python_path}:/bundled"),
-        )
-        .build();
+        if diagnostics {
+            wasi.env(
+                "COMPONENTIZE_PY_DIAGNOSTICS",
+                summary.diagnostics_manifest(),
+            );
+        }
 
-    let mut config = Config::new();
-    config.wasm_component_model(true);
-    config.async_support(true);
+        // The runtime also uses the manifest to check that exception mappings produce payloads of the right type,
+        // so we pass a partial one if there are any mappings but strict mode is off.
+        if strict || !exception_mappings.is_empty() {
+            wasi.env("COMPONENTIZE_PY_STRICT", summary.strict_manifest(strict));
+        }
 
-    let engine = Engine::new(&config)?;
+        if trace_imports {
+            wasi.env("COMPONENTIZE_PY_TRACE", summary.trace_manifest());
+        }
 
-    let mut linker = Linker::new(&engine);
-    let added_to_linker = if let Some(add_to_linker) = add_to_linker {
-        add_to_linker(&mut linker)?;
-        true
-    } else {
-        false
-    };
+        if let Some(budget) = import_time_budget {
+            wasi.env(
+                "COMPONENTIZE_PY_IMPORT_BUDGET",
+                budget.as_secs_f64().to_string(),
+            );
+        }
+
+        if let Some(hook) = pre_init_hook {
+            wasi.env("COMPONENTIZE_PY_PRE_INIT_HOOK", hook);
+        }
+
+        // For each Python package with a `componentize-py.toml` file that specifies where generated bindings for that
+        // package should be placed, generate the bindings and place them as indicated.
 
-    let mut store = Store::new(&engine, Ctx { wasi, table });
-
-    let app_name = app_name.to_owned();
-    let component = component_init::initialize_staged(
-        &component,
-        stubbed_component
-            .as_ref()
-            .map(|(component, map)| (component.deref(), map as &dyn Fn(u32) -> u32)),
-        move |instrumented| {
-            async move {
-                let component = &Component::new(&engine, instrumented)?;
-                if !added_to_linker {
-                    add_wasi_and_stubs(&resolve, &worlds, &mut linker)?;
+        let mut world_dir_mounts = Vec::new();
+        let mut locations = Locations::default();
+        let mut saw_main_world = false;
+        let mut helper_bindings = IndexMap::new();
+
+        for (config, world, binding_path) in configs
+            .values()
+            .filter_map(|(config, world)| Some((config, world, config.config.bindings.as_deref()?)))
+        {
+            if *world == main_world {
+                saw_main_world = true;
+            }
+
+            let Some(world) = *world else {
+                bail!("please specify a world for module `{}`", config.module);
+            };
+
+            let paths = python_path
+                .iter()
+                .enumerate()
+                .map(|(index, dir)| {
+                    let dir = Path::new(dir).canonicalize()?;
+                    Ok(if config.root == dir {
+                        config
+                            .path
+                            .join(binding_path)
+                            .strip_prefix(dir)
+                            .ok()
+                            .map(|p| (index, p.to_str().unwrap().replace('\\', "/")))
+                    } else {
+                        None
+                    })
+                })
+                .filter_map(Result::transpose)
+                .collect::<Result<Vec<_>>>()?;
+
+            let binding_module = paths.first().unwrap().1.replace('/', ".");
+
+            let world_dir = temp::dir(keep_temp)?;
+
+            summary.generate_code(
+                world_dir.path(),
+                world,
+                &binding_module,
+                &mut locations,
+                false,
+            )?;
+            for (name, code) in summary.generate_helper_bindings(world, &binding_module) {
+                helper_bindings.entry(name).or_insert(code);
+            }
+
+            world_dir_mounts.push((
+                paths
+                    .iter()
+                    .map(|(index, p)| format!("{index}/{p}"))
+                    .collect(),
+                world_dir,
+            ));
+        }
+
+        // If the caller specified a world and we haven't already generated bindings for it above, do so now.
+        if let (Some(world), false) = (main_world, saw_main_world) {
+            let module = resolve.worlds[world].name.to_snake_case();
+            let world_dir = temp::dir(keep_temp)?;
+            let module_path = world_dir.path().join(&module);
+            fs::create_dir_all(&module_path)?;
+            summary.generate_code(&module_path, world, &module, &mut locations, false)?;
+            for (name, code) in summary.generate_helper_bindings(world, &module) {
+                helper_bindings.entry(name).or_insert(code);
+            }
+            world_dir_mounts.push((vec!["world".to_owned()], world_dir));
+
+            // The helper utilities are hard-coded to assume the world module is named `proxy`.  Here we replace that
+            // with the actual world name.
+            fn replace(path: &Path, pattern: &str, replacement: &str) -> Result<()> {
+                if path.is_dir() {
+                    for entry in fs::read_dir(path)? {
+                        replace(&entry?.path(), pattern, replacement)?;
+                    }
+                } else {
+                    fs::write(
+                        path,
+                        fs::read_to_string(path)?
+                            .replace(pattern, replacement)
+                            .as_bytes(),
+                    )?;
                 }
 
-                let pre = InitPre::new(linker.instantiate_pre(component)?)?;
-                let instance = pre.instance_pre.instantiate_async(&mut store).await?;
-                let guest = pre.indices.interface0.load(&mut store, &instance)?;
+                Ok(())
+            }
+            replace(embedded_helper_utils.path(), "proxy", &module)?;
+        };
 
-                guest
-                    .call_init(&mut store, &app_name, &symbols, stub_wasi)
-                    .await?
-                    .map_err(|e| anyhow!("{e}"))?;
+        // If any of the worlds import the interfaces used by a bundled helper (e.g. `wasi:http/outgoing-handler`
+        // for `http_client`), generate the bindings module used by that helper.  Note that we do this after the
+        // above substitution so it won't affect the generated code.
+        for (name, code) in helper_bindings {
+            fs::write(
+                embedded_helper_utils.path().join(format!("{name}.py")),
+                code,
+            )?;
+        }
 
-                Ok(Box::new(MyInvoker { store, instance }) as Box<dyn Invoker>)
+        for (mounts, world_dir) in world_dir_mounts.iter() {
+            for mount in mounts {
+                wasi.preopened_dir(world_dir.path(), mount, DirPerms::all(), FilePerms::all())?;
             }
-            .boxed()
-        },
-    )
-    .await
-    .with_context(move || {
-        format!(
-            "{}{}",
-            String::from_utf8_lossy(&stdout.try_into_inner().unwrap()),
-            String::from_utf8_lossy(&stderr.try_into_inner().unwrap())
+        }
+
+        // Generate a `Symbols` object containing metadata to be passed to the pre-init function.  The runtime library
+        // will use this to look up types and functions that will later be referenced by the generated Wasm code.
+        let symbols = summary.collect_symbols(&locations);
+
+        // Finally, pre-initialize the component, writing the result to `output_path`.
+
+        let python_path = (0..python_path.len())
+            .map(|index| format!("/{index}"))
+            .collect::<Vec<_>>()
+            .join(":");
+
+        let table = ResourceTable::new();
+        let wasi = wasi
+            .env(
+                "PYTHONPATH",
+                format!("/python:/world:{python_path}:/bundled"),
+            )
+            .build();
+
+        let mut config = Config::new();
+        config.wasm_component_model(true);
+        config.async_support(true);
+        // If requested, use epoch interruption to abort pre-initialization if it takes too long, e.g. because the
+        // app hangs while being imported.
+        config.epoch_interruption(pre_init_timeout.is_some());
+
+        let engine = Engine::new(&config)?;
+
+        let mut linker = Linker::new(&engine);
+        let added_to_linker = if let Some(add_to_linker) = add_to_linker {
+            add_to_linker(&mut linker)?;
+            true
+        } else {
+            false
+        };
+
+        let mut store = Store::new(&engine, Ctx { wasi, table });
+
+        // If pre-initialization doesn't finish within the timeout, this thread interrupts it; otherwise, it exits
+        // as soon as the sender is dropped below, rather than lingering (with a clone of the engine) until then.
+        let watchdog = pre_init_timeout.map(|timeout| {
+            store.set_epoch_deadline(1);
+            let engine = engine.clone();
+            let (done, done_rx) = mpsc::channel::<()>();
+            let thread = thread::spawn(move || {
+                if let Err(RecvTimeoutError::Timeout) = done_rx.recv_timeout(timeout) {
+                    engine.increment_epoch();
+                }
+            });
+            (done, thread)
+        });
+
+        let app_name = app_name.to_owned();
+        callbacks.report(Phase::PreInitializing);
+
+        let result = component_init::initialize_staged(
+            &linked_component,
+            stubbed_component
+                .as_ref()
+                .map(|(component, map)| (component.deref(), map as &dyn Fn(u32) -> u32)),
+            move |instrumented| {
+                async move {
+                    let component = &Component::new(&engine, instrumented)?;
+                    if !added_to_linker {
+                        add_wasi_and_stubs(&resolve, &worlds, &mut linker)?;
+                    }
+
+                    let instance_pre = linker.instantiate_pre(component)?;
+                    let instance = instance_pre.instantiate_async(&mut store).await?;
+                    check_init_version(&mut store, &instance).await?;
+
+                    let pre = InitPre::new(instance_pre)?;
+                    let guest = pre.indices.interface0.load(&mut store, &instance)?;
+
+                    guest
+                        .call_init(&mut store, &app_name, &symbols, stub_wasi)
+                        .await?
+                        .map_err(|e| anyhow!("{e}"))?;
+
+                    Ok(Box::new(MyInvoker { store, instance }) as Box<dyn Invoker>)
+                }
+                .boxed()
+            },
         )
-    })?;
+        .await;
 
-    fs::write(output_path, component)?;
+        if let Some((done, thread)) = watchdog {
+            drop(done);
+            let _ = thread.join();
+        }
 
-    Ok(())
+        let mut component = result
+            .map_err(|e| match (pre_init_timeout, e.downcast_ref::<Trap>()) {
+                (Some(timeout), Some(Trap::Interrupt)) => {
+                    e.context(format!("pre-initialization timed out after {timeout:?}"))
+                }
+                _ => e,
+            })
+            .with_context(|| InitFailure {
+                stdout: stdout.contents(),
+                stderr: stderr.contents(),
+            })?;
+
+        drop((linked_component, stubbed_component));
+
+        if let Some(source_map) = source_map {
+            metadata::append_custom_section(
+                &mut component,
+                "componentize-py-source-map",
+                source_map.as_bytes(),
+            );
+        }
+
+        // If requested, satisfy some of the component's imports using other components, fusing them into a single
+        // component.  Note that we do this after pre-initialization, since the composed components won't have been
+        // available to the app at that point anyway.
+        if !compose.is_empty() {
+            component = compose::compose(component, compose, keep_temp)?;
+        }
+
+        // If requested, embed the licenses and notices of the third-party code the component contains.
+        if notices == Some(Notices::Embed) {
+            metadata::append_custom_section(
+                &mut component,
+                notices::SECTION,
+                notices::collect(artifacts, python_path)?.as_bytes(),
+            );
+        }
+
+        let component = metadata::add(component, metadata)?;
+
+        if let Some(cache) = &cache {
+            cache.store(&component, &stdout.contents(), &stderr.contents());
+        }
+
+        callbacks.report(Phase::Writing);
+
+        write_notices()?;
+
+        optimize.write_output(output_path, &component)
+    }
 }
 
+/// Build a component from the specified options.
+///
+/// This is a thin wrapper around `ComponentizeBuilder`, kept for compatibility with existing callers.  Options added
+/// since (e.g. `exclude_paths` or `profile`) are available only via the builder, so this signature doesn't change
+/// as new ones are added.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub async fn componentize(
+    wit_path: Option<&Path>,
+    world: Option<&str>,
+    features: &[String],
+    all_features: bool,
+    python_path: &[&str],
+    module_worlds: &[(&str, &str)],
+    app_name: &str,
+    output_path: &Path,
+    add_to_linker: Option<&dyn Fn(&mut Linker<Ctx>) -> Result<()>>,
+    stub_wasi: bool,
+    import_interface_names: &HashMap<&str, &str>,
+    export_interface_names: &HashMap<&str, &str>,
+) -> Result<()> {
+    ComponentizeBuilder {
+        wit_path,
+        world,
+        features,
+        all_features,
+        python_path,
+        module_worlds,
+        app_name,
+        output_path,
+        add_to_linker,
+        stub_wasi,
+        import_interface_names: import_interface_names.clone(),
+        export_interface_names: export_interface_names.clone(),
+        ..ComponentizeBuilder::new()
+    }
+    .build()
+    .await
+}
+
+/// Parse the WIT file or directory at `path` with the specified features enabled and select a world from it.
+///
+/// The world is chosen as follows, regardless of whether the request came from the CLI, a `componentize-py.toml`
+/// file, or a library caller:
+///
+/// 1. If `world` is fully qualified (e.g. `wasi:http/proxy`), exactly that world is used.
+/// 2. Otherwise, the world is looked up in the "primary" package, which is `primary_package` if specified (e.g.
+///    `my:app` or `my:app@1.0.0`), or else the package defined by `path` itself (never one of its dependencies).
+/// 3. If `world` is `None`, the primary package must contain exactly one world; if it contains several, that's an
+///    error rather than an arbitrary choice.
 fn parse_wit(
     path: &Path,
     world: Option<&str>,
+    primary_package: Option<&str>,
     features: &[String],
     all_features: bool,
 ) -> Result<(Resolve, WorldId)> {
+    let (resolve, pkg) = load_wit(path, features, all_features)?;
+    let pkg = primary(&resolve, pkg, primary_package)?;
+    let world = resolve.select_world(pkg, world).with_context(|| {
+        if let Some(world) = world {
+            format!(
+                "unable to find world `{world}` in package `{}`",
+                resolve.packages[pkg].name
+            )
+        } else {
+            format!(
+                "no world specified and unable to choose a default from package `{}`; \
+                 please specify one using e.g. `--world`, or choose another package using `--primary-package`",
+                resolve.packages[pkg].name
+            )
+        }
+    })?;
+    Ok((resolve, world))
+}
+
+/// Return the package named by `primary_package`, if specified, or else `main`.
+///
+/// A name without a version matches a package with any version as long as only one such version is present.
+fn primary(resolve: &Resolve, main: PackageId, primary_package: Option<&str>) -> Result<PackageId> {
+    let Some(name) = primary_package else {
+        return Ok(main);
+    };
+
+    if let Some((_, &id)) = resolve
+        .package_names
+        .iter()
+        .find(|(candidate, _)| candidate.to_string() == name)
+    {
+        return Ok(id);
+    }
+
+    let candidates = resolve
+        .package_names
+        .iter()
+        .filter(|(candidate, _)| format!("{}:{}", candidate.namespace, candidate.name) == name)
+        .collect::<Vec<_>>();
+
+    match &candidates[..] {
+        [(_, &id)] => Ok(id),
+        [] => bail!(
+            "unknown package `{name}`; available packages are: {}",
+            resolve
+                .package_names
+                .keys()
+                .map(|name| format!("`{name}`"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        _ => bail!(
+            "package `{name}` is available at multiple versions ({}); please specify one",
+            candidates
+                .iter()
+                .map(|(candidate, _)| candidate.version.as_ref().unwrap().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Parse the WIT file or directory at `path` with the specified features enabled, returning the resulting
+/// `Resolve` and the ID of the package `path` itself defines.
+fn load_wit(path: &Path, features: &[String], all_features: bool) -> Result<(Resolve, PackageId)> {
     let mut resolve = Resolve {
         all_features,
         ..Default::default()
@@ -568,8 +2309,7 @@ fn parse_wit(
         let pkg = UnresolvedPackageGroup::parse_file(path)?;
         resolve.push_group(pkg)?
     };
-    let world = resolve.select_world(pkg, world)?;
-    Ok((resolve, world))
+    Ok((resolve, pkg))
 }
 
 fn add_wasi_and_stubs(
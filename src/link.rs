@@ -1,10 +1,16 @@
-use std::io::Cursor;
-
-use anyhow::Result;
+use anyhow::{Context, Result};
 
 use crate::Library;
 
-pub fn link_libraries(libraries: &[Library]) -> Result<Vec<u8>> {
+/// Link the specified libraries into a component, using `wasi_adapter` to adapt any `wasi_snapshot_preview1`
+/// imports.
+///
+/// Note that this runs on a single thread and caches nothing per library.  `wit_component::Linker` resolves
+/// symbols across all the libraries and encodes the component in one pass, so there is no per-library artifact
+/// we could produce in parallel or reuse, and skipping its validation of libraries we've seen before would let a
+/// corrupt cache entry through.  Instead, `prelink::bundle_libraries` reads and decompresses the libraries in
+/// parallel, and the build cache (see `cache::BuildCache`) skips linking altogether when nothing has changed.
+pub fn link_libraries(libraries: &[Library], wasi_adapter: &[u8]) -> Result<Vec<u8>> {
     let mut linker = wit_component::Linker::default()
         .validate(true)
         .use_built_in_libdl(true);
@@ -18,13 +24,9 @@ pub fn link_libraries(libraries: &[Library]) -> Result<Vec<u8>> {
         linker = linker.library(name, module, *dl_openable)?;
     }
 
-    linker = linker.adapter(
-        "wasi_snapshot_preview1",
-        &zstd::decode_all(Cursor::new(include_bytes!(concat!(
-            env!("OUT_DIR"),
-            "/wasi_snapshot_preview1.reactor.wasm.zst"
-        ))))?,
-    )?;
+    linker = linker
+        .adapter("wasi_snapshot_preview1", wasi_adapter)
+        .context("failed to load WASI adapter")?;
 
     linker.encode().map_err(|e| anyhow::anyhow!(e))
 }
@@ -0,0 +1,166 @@
+//! Support for embedding descriptive metadata (e.g. the app's name, version, and authors) in the component, for
+//! the benefit of registries and tools like `wasm-tools metadata show`.
+
+use {
+    anyhow::{bail, Result},
+    wasm_encoder::{ComponentSectionId, CustomSection, Encode},
+    wasm_metadata::{AddMetadata, Link, LinkType, RegistryMetadata},
+};
+
+/// Keys accepted by `add`, for use in error messages
+const KEYS: &str =
+    "name, version, authors, description, license, homepage, repository, documentation, categories";
+
+/// Add the specified `(key, value)` pairs to `component` as metadata, returning the result.  `component` is taken
+/// by value so it can be freed as soon as the result has been encoded, or returned as is if there's nothing to add.
+///
+/// `name` sets the component's name (as recorded in its `component-name` section), and `version` is recorded in a
+/// `version` custom section, following the OCI convention used by newer tooling.  The remaining keys are recorded
+/// in a `registry-metadata` section; `authors` and `categories` may be specified more than once to list several
+/// values.  We also record `componentize-py` in the `producers` section whenever any metadata is specified.
+pub fn add(component: Vec<u8>, metadata: &[(&str, &str)]) -> Result<Vec<u8>> {
+    if metadata.is_empty() {
+        return Ok(component);
+    }
+
+    let (add, version) = parse(metadata)?;
+    let mut component = add.to_wasm(&component)?;
+
+    if let Some(version) = version {
+        append_custom_section(&mut component, "version", version.as_bytes());
+    }
+
+    Ok(component)
+}
+
+/// Append a custom section named `name` containing `data` to `component`.
+///
+/// This grows `component` by exactly the size of the section, whereas encoding the section directly into it may
+/// reallocate it with as much again in spare capacity, which matters for components of 100MB or more.
+pub fn append_custom_section(component: &mut Vec<u8>, name: &str, data: &[u8]) {
+    let mut section = vec![ComponentSectionId::CoreCustom.into()];
+    CustomSection {
+        name: name.into(),
+        data: data.into(),
+    }
+    .encode(&mut section);
+
+    component.reserve_exact(section.len());
+    component.extend_from_slice(&section);
+}
+
+/// Check that each key in `metadata` is one `add` accepts, so that mistakes can be reported before building
+/// anything.
+pub fn validate(metadata: &[(&str, &str)]) -> Result<()> {
+    parse(metadata).map(drop)
+}
+
+fn parse<'a>(metadata: &[(&str, &'a str)]) -> Result<(AddMetadata, Option<&'a str>)> {
+    let mut add = AddMetadata {
+        processed_by: vec![(
+            "componentize-py".to_owned(),
+            env!("CARGO_PKG_VERSION").to_owned(),
+        )],
+        ..AddMetadata::default()
+    };
+    let mut registry = RegistryMetadata::default();
+    let mut version = None;
+
+    for &(key, value) in metadata {
+        match key {
+            "name" => add.name = Some(value.to_owned()),
+            "version" => version = Some(value),
+            "authors" => registry
+                .authors
+                .get_or_insert_with(Vec::new)
+                .push(value.to_owned()),
+            "description" => registry.description = Some(value.to_owned()),
+            "license" => registry.license = Some(value.to_owned()),
+            "homepage" | "repository" | "documentation" => {
+                registry.links.get_or_insert_with(Vec::new).push(Link {
+                    ty: match key {
+                        "homepage" => LinkType::Homepage,
+                        "repository" => LinkType::Repository,
+                        _ => LinkType::Documentation,
+                    },
+                    value: value.to_owned(),
+                })
+            }
+            "categories" => registry
+                .categories
+                .get_or_insert_with(Vec::new)
+                .push(value.to_owned()),
+            _ => bail!("unknown metadata key `{key}`; expected one of {KEYS}"),
+        }
+    }
+
+    if metadata
+        .iter()
+        .any(|(key, _)| !matches!(*key, "name" | "version"))
+    {
+        add.registry_metadata = Some(registry);
+    }
+
+    Ok((add, version))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        std::str,
+        wasmparser::{Parser, Payload},
+    };
+
+    fn custom_sections(component: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+        let mut sections = Vec::new();
+        for payload in Parser::new(0).parse_all(component) {
+            if let Payload::CustomSection(section) = payload? {
+                sections.push((section.name().to_owned(), section.data().to_vec()));
+            }
+        }
+        Ok(sections)
+    }
+
+    #[test]
+    fn metadata_embedded() -> Result<()> {
+        let component = wasm_encoder::Component::new().finish();
+
+        let component = add(
+            component,
+            &[
+                ("name", "my-app"),
+                ("version", "1.2.3"),
+                ("authors", "Alice"),
+                ("authors", "Bob"),
+                ("repository", "https://example.com/my-app"),
+            ],
+        )?;
+
+        let sections = custom_sections(&component)?;
+        let names = sections
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+
+        assert!(names.contains(&"component-name"));
+        assert!(names.contains(&"producers"));
+        assert!(sections.contains(&("version".to_owned(), b"1.2.3".to_vec())));
+
+        let (_, registry) = sections
+            .iter()
+            .find(|(name, _)| name == "registry-metadata")
+            .expect("expected a `registry-metadata` section");
+        let registry = str::from_utf8(registry)?;
+        assert!(registry.contains("Alice") && registry.contains("Bob"));
+        assert!(registry.contains("https://example.com/my-app"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_key_rejected() {
+        let error = validate(&[("name", "my-app"), ("colour", "blue")]).unwrap_err();
+        assert!(error.to_string().contains("unknown metadata key `colour`"));
+    }
+}
@@ -0,0 +1,219 @@
+//! Support for the `migrate-bindings` subcommand, which describes how code written against the bindings for one
+//! version of a world needs to change to work with the bindings for another.
+
+use {crate::summary::ApiItem, std::collections::BTreeMap};
+
+/// Compare the APIs (as returned by `Summary::api`) of two versions of the bindings, returning a note for each
+/// change which may require code written against `old` to be updated, in order of the affected item's path.
+///
+/// An item which was removed is considered renamed if exactly one item with the same signature was added
+/// alongside it, in which case the members of a renamed class are compared with their renamed counterparts rather
+/// than reported as removed.  Added items are only reported if the app must implement them.
+pub fn notes(old: &BTreeMap<String, ApiItem>, new: &BTreeMap<String, ApiItem>) -> Vec<String> {
+    let mut notes = Vec::new();
+    let mut added = new
+        .keys()
+        .filter(|path| !old.contains_key(*path))
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let mut removed = old
+        .keys()
+        .filter(|path| !new.contains_key(*path))
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    // Visit classes before their members so the latter can be matched using the former's new names.
+    removed.sort_by_key(|path| (path.matches('.').count(), *path));
+
+    let action = |item: &ApiItem| {
+        if item.implemented {
+            "update your implementation to match"
+        } else {
+            "update any code which uses it"
+        }
+    };
+
+    for path in old.keys().filter(|path| new.contains_key(*path)) {
+        let (old_item, new_item) = (&old[path], &new[path]);
+        if old_item != new_item {
+            notes.push((
+                path.clone(),
+                format!(
+                    "`{path}` changed from `{}` to `{}`; {}",
+                    old_item.signature,
+                    new_item.signature,
+                    action(new_item)
+                ),
+            ));
+        }
+    }
+
+    let mut renamed = BTreeMap::<&str, String>::new();
+    for path in removed {
+        let old_item = &old[path];
+        let (parent, name) = split(path);
+
+        // If the parent was renamed, look for this item under the parent's new name.
+        if let Some(new_parent) = renamed.get(parent) {
+            let new_path = format!("{new_parent}.{name}");
+            if let Some(new_item) = new.get(&new_path) {
+                added.retain(|path| *path != new_path);
+                if old_item != new_item {
+                    notes.push((
+                        path.to_owned(),
+                        format!(
+                            "`{new_path}` (formerly `{path}`) changed from `{}` to `{}`; {}",
+                            old_item.signature,
+                            new_item.signature,
+                            action(new_item)
+                        ),
+                    ));
+                }
+                renamed.insert(path, new_path);
+                continue;
+            }
+        }
+
+        let parent = renamed.get(parent).map(String::as_str).unwrap_or(parent);
+        let candidates = added
+            .iter()
+            .copied()
+            .filter(|candidate| split(candidate).0 == parent && new[*candidate] == *old_item)
+            .collect::<Vec<_>>();
+
+        if let [new_path] = candidates[..] {
+            added.retain(|path| *path != new_path);
+            notes.push((
+                path.to_owned(),
+                format!(
+                    "`{path}` was renamed to `{new_path}`; {}",
+                    if old_item.implemented {
+                        "rename your implementation"
+                    } else {
+                        "update any code which uses it"
+                    }
+                ),
+            ));
+            renamed.insert(path, new_path.to_owned());
+        } else {
+            notes.push((
+                path.to_owned(),
+                format!(
+                    "`{path}` was removed; {}",
+                    if old_item.implemented {
+                        "your implementation is no longer needed"
+                    } else {
+                        "remove any code which uses it"
+                    }
+                ),
+            ));
+        }
+    }
+
+    for path in added {
+        let new_item = &new[path];
+        if new_item.implemented {
+            notes.push((
+                path.to_owned(),
+                format!(
+                    "`{path}` was added; implement it with the signature `{}`",
+                    new_item.signature
+                ),
+            ));
+        }
+    }
+
+    notes.sort();
+    notes.into_iter().map(|(_, note)| note).collect()
+}
+
+/// Split `path` into the path of its parent and its own name.
+fn split(path: &str) -> (&str, &str) {
+    path.rsplit_once('.').unwrap_or(("", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api(items: &[(&str, &str, bool)]) -> BTreeMap<String, ApiItem> {
+        items
+            .iter()
+            .map(|(path, signature, implemented)| {
+                (
+                    (*path).to_owned(),
+                    ApiItem {
+                        signature: (*signature).to_owned(),
+                        implemented: *implemented,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn changes_described() {
+        let old = api(&[
+            ("exports.Handler.handle", "(request: str) -> str", true),
+            ("exports.Handler.close", "() -> None", true),
+            ("imports.types.Point", "record(x: int, y: int)", false),
+            ("imports.types.Fields", "resource", false),
+            (
+                "imports.types.Fields.get",
+                "(self, name: str) -> bytes",
+                false,
+            ),
+            (
+                "imports.types.Fields.set",
+                "(self, name: str) -> None",
+                false,
+            ),
+            ("imports.types.unchanged", "() -> None", false),
+        ]);
+        let new = api(&[
+            ("exports.Handler.handle", "(request: bytes) -> str", true),
+            ("exports.Handler.shutdown", "() -> None", true),
+            ("exports.Handler.ready", "() -> bool", true),
+            ("imports.types.Point", "record(x: int, y: int)", false),
+            ("imports.types.Headers", "resource", false),
+            (
+                "imports.types.Headers.get",
+                "(self, name: str) -> List[bytes]",
+                false,
+            ),
+            (
+                "imports.types.Headers.set",
+                "(self, name: str) -> None",
+                false,
+            ),
+            ("imports.types.unchanged", "() -> None", false),
+            ("imports.types.added", "() -> None", false),
+        ]);
+
+        assert_eq!(
+            vec![
+                "`exports.Handler.close` was renamed to `exports.Handler.shutdown`; rename your implementation",
+                "`exports.Handler.handle` changed from `(request: str) -> str` to `(request: bytes) -> str`; \
+                 update your implementation to match",
+                "`exports.Handler.ready` was added; implement it with the signature `() -> bool`",
+                "`imports.types.Fields` was renamed to `imports.types.Headers`; update any code which uses it",
+                "`imports.types.Headers.get` (formerly `imports.types.Fields.get`) changed from \
+                 `(self, name: str) -> bytes` to `(self, name: str) -> List[bytes]`; update any code which uses it",
+            ],
+            notes(&old, &new)
+        );
+    }
+
+    #[test]
+    fn ambiguous_renames_reported_as_removals() {
+        let old = api(&[("imports.host.log", "(message: str) -> None", false)]);
+        let new = api(&[
+            ("imports.host.info", "(message: str) -> None", false),
+            ("imports.host.warn", "(message: str) -> None", false),
+        ]);
+
+        assert_eq!(
+            vec!["`imports.host.log` was removed; remove any code which uses it"],
+            notes(&old, &new)
+        );
+    }
+}
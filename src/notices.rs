@@ -0,0 +1,253 @@
+//! Support for gathering the license texts and notices of the third-party code a component contains (i.e. CPython,
+//! the WASI SDK's libraries, the Rust crates linked into the runtime, and any distributions found in
+//! `python_path`), so that those shipping the component can comply with their terms.
+
+use {
+    crate::{artifacts::Artifacts, prelink},
+    anyhow::{Context, Result},
+    std::{
+        fmt::Write as _,
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Name of the custom section in which the notices are embedded when using `Notices::Embed`
+pub const SECTION: &str = "componentize-py-notices";
+
+/// The Apache License 2.0 with LLVM exceptions, which covers `componentize-py` itself (including the runtime and
+/// helper modules embedded in every component), wasi-libc, libc++, and libc++abi
+static APACHE_2_0_WITH_LLVM_EXCEPTION: &str = include_str!("../LICENSE");
+
+/// License of musl, portions of which wasi-libc is derived from
+static MUSL_LICENSE: &str = "\
+Copyright © 2005-2020 Rich Felker, et al.
+
+Permission is hereby granted, free of charge, to any person obtaining
+a copy of this software and associated documentation files (the
+\"Software\"), to deal in the Software without restriction, including
+without limitation the rights to use, copy, modify, merge, publish,
+distribute, sublicense, and/or sell copies of the Software, and to
+permit persons to whom the Software is furnished to do so, subject to
+the following conditions:
+
+The above copyright notice and this permission notice shall be
+included in all copies or substantial portions of the Software.
+
+THE SOFTWARE IS PROVIDED \"AS IS\", WITHOUT WARRANTY OF ANY KIND,
+EXPRESS OR IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF
+MERCHANTABILITY, FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT.
+IN NO EVENT SHALL THE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY
+CLAIM, DAMAGES OR OTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT,
+TORT OR OTHERWISE, ARISING FROM, OUT OF OR IN CONNECTION WITH THE
+SOFTWARE OR THE USE OR OTHER DEALINGS IN THE SOFTWARE.
+";
+
+/// Prefixes (compared case-insensitively) of the names of files in a `*.dist-info` directory which we treat as
+/// license files, for distributions which predate the `licenses` subdirectory introduced by PEP 639
+static LICENSE_FILE_PREFIXES: &[&str] = &["license", "licence", "copying", "notice", "authors"];
+
+/// Return the path to which the notices for the component at `output_path` are written when using
+/// `Notices::File`.
+pub fn path(output_path: &Path) -> PathBuf {
+    let mut path = output_path.as_os_str().to_owned();
+    path.push(".NOTICES");
+    path.into()
+}
+
+/// Collect the notices for a component built from the specified artifacts and `python_path` directories.
+pub fn collect(artifacts: &Artifacts, python_path: &[&str]) -> Result<String> {
+    let mut notices = format!(
+        "This component was built using componentize-py {} and contains the third-party software listed \
+         below, subject to the accompanying license terms.\n",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    section(
+        &mut notices,
+        "CPython 3.12 (interpreter and standard library)",
+        &prelink::embedded_python_license(artifacts)?,
+    );
+    section(
+        &mut notices,
+        "componentize-py runtime and helper modules, wasi-libc, libc++, and libc++abi",
+        &format!(
+            "Licensed under the Apache License 2.0 with LLVM exceptions.  The Rust crates linked into the runtime \
+             (anyhow, num-bigint, once_cell, pyo3, serde, serde_json, and wit-bindgen) are each available under \
+             the Apache License 2.0 as well.\n\n{APACHE_2_0_WITH_LLVM_EXCEPTION}"
+        ),
+    );
+    section(&mut notices, "musl (portions of wasi-libc)", MUSL_LICENSE);
+
+    distributions(&mut notices, python_path)?;
+
+    Ok(notices)
+}
+
+/// Append the notices for each installed distribution (i.e. `*.dist-info` directory) in each `python_path`
+/// directory to `notices`.
+///
+/// If a distribution appears in more than one directory, only the first is included, since that's the one Python
+/// will import.
+fn distributions(notices: &mut String, python_path: &[&str]) -> Result<()> {
+    let mut seen = Vec::new();
+    for dir in python_path {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+
+        let mut paths = entries
+            .map(|entry| Ok(entry?.path()))
+            .collect::<Result<Vec<_>>>()?;
+        paths.sort();
+
+        for path in paths {
+            if path.extension().and_then(|e| e.to_str()) != Some("dist-info") {
+                continue;
+            }
+
+            let Ok(metadata) = fs::read_to_string(path.join("METADATA")) else {
+                continue;
+            };
+
+            // Only consider the headers, not the description which follows them.
+            let headers = metadata.split("\n\n").next().unwrap_or_default();
+            let field = |name: &str| {
+                headers.lines().find_map(|line| {
+                    line.strip_prefix(name)
+                        .and_then(|rest| rest.strip_prefix(':'))
+                        .map(|value| value.trim().to_owned())
+                })
+            };
+
+            let (Some(name), Some(version)) = (field("Name"), field("Version")) else {
+                continue;
+            };
+
+            let normalized = name.to_lowercase().replace(['-', '.'], "_");
+            if seen.contains(&normalized) {
+                continue;
+            }
+            seen.push(normalized);
+
+            let mut text = String::new();
+            if let Some(license) = field("License-Expression").or_else(|| field("License")) {
+                writeln!(text, "License: {license}\n").unwrap();
+            }
+
+            let files = license_files(&path)?;
+            if files.is_empty() {
+                text.push_str("(no license files found in the distribution's metadata)\n");
+            }
+            for file in files {
+                let contents = fs::read(&file).with_context(|| file.display().to_string())?;
+                let name = file.strip_prefix(&path).unwrap_or(&file).display();
+                write!(
+                    text,
+                    "--- {name} ---\n\n{}\n",
+                    String::from_utf8_lossy(&contents).trim_end()
+                )
+                .unwrap();
+            }
+
+            section(notices, &format!("{name} {version}"), &text);
+        }
+    }
+
+    Ok(())
+}
+
+/// Return the paths of the license files in the `*.dist-info` directory `dir`, sorted by name.
+///
+/// These are everything in its `licenses` subdirectory (per PEP 639), plus any files at its top level whose names
+/// start with one of `LICENSE_FILE_PREFIXES` (as older versions of `setuptools` and other build backends write
+/// them).
+fn license_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    fn visit(path: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+        if path.is_dir() {
+            for entry in fs::read_dir(path)? {
+                visit(&entry?.path(), files)?;
+            }
+        } else {
+            files.push(path.to_owned());
+        }
+        Ok(())
+    }
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        if name == "licenses" {
+            visit(&path, &mut files)?;
+        } else if path.is_file()
+            && LICENSE_FILE_PREFIXES
+                .iter()
+                .any(|prefix| name.starts_with(prefix))
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    Ok(files)
+}
+
+fn section(notices: &mut String, title: &str, text: &str) {
+    let rule = "=".repeat(79);
+    write!(
+        notices,
+        "\n{rule}\n{title}\n{rule}\n\n{}\n",
+        text.trim_end()
+    )
+    .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distribution_licenses_collected() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let dist_info = dir.path().join("foo_bar-1.0.dist-info");
+        fs::create_dir_all(dist_info.join("licenses/vendored"))?;
+        fs::write(
+            dist_info.join("METADATA"),
+            "Metadata-Version: 2.4\nName: foo-bar\nVersion: 1.0\nLicense-Expression: MIT\n\n\
+             License: not a header\n",
+        )?;
+        fs::write(dist_info.join("licenses/LICENSE"), "Foo license text\n")?;
+        fs::write(
+            dist_info.join("licenses/vendored/COPYING"),
+            "Vendored license text\n",
+        )?;
+        fs::write(dist_info.join("RECORD"), "")?;
+
+        let other = dir.path().join("baz-2.0.dist-info");
+        fs::create_dir_all(&other)?;
+        fs::write(other.join("METADATA"), "Name: baz\nVersion: 2.0\n")?;
+        fs::write(other.join("LICENSE.txt"), "Baz license text\n")?;
+
+        let mut notices = String::new();
+        let dir = dir.path().to_str().unwrap();
+        // Listing the same directory twice should not list each distribution twice.
+        distributions(&mut notices, &[dir, dir])?;
+
+        assert_eq!(1, notices.matches("foo-bar 1.0\n").count());
+        assert!(notices.contains("License: MIT\n"));
+        assert!(!notices.contains("not a header"));
+        assert!(notices.contains("Foo license text"));
+        assert!(notices.contains("Vendored license text"));
+        assert!(notices.contains("baz 2.0\n"));
+        assert!(notices.contains("Baz license text"));
+        assert!(!notices.contains("RECORD"));
+
+        Ok(())
+    }
+}
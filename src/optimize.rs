@@ -0,0 +1,380 @@
+//! Optional post-link size optimizations and reporting for generated components.
+
+use {
+    crate::Library,
+    anyhow::{Context, Result},
+    std::{
+        cmp::Reverse,
+        fmt::Write as _,
+        fs::{self, File},
+        ops::Range,
+        path::{Path, PathBuf},
+    },
+    wasm_encoder::{Module, RawSection},
+    wasmparser::{Encoding, KnownCustom, Name, NameSectionReader, Parser, Payload},
+};
+
+/// Options controlling which size optimizations (if any) are applied to a component, and what is produced
+/// alongside it.
+#[derive(Default, Debug, Clone, Copy)]
+pub struct Options<'a> {
+    /// Python modules which should be made unavailable to the app, e.g. `["tkinter", "unittest"]`.
+    ///
+    /// Each entry also applies to any submodules.  This prunes both the standard library and any native
+    /// extensions found in `python_path`, which shrinks the component by removing libraries and by preventing
+    /// modules which are only imported opportunistically (e.g. within a `try`/`except ImportError` block) from
+    /// being included in the pre-initialized heap.
+    pub exclude_modules: &'a [&'a str],
+
+    /// Python modules which should remain available even if they (or one of their parents) match
+    /// `exclude_modules`.
+    pub include_modules: &'a [&'a str],
+
+    /// If true, strip `name` and DWARF (`.debug_*`) custom sections from all libraries prior to linking.
+    pub strip_debug: bool,
+
+    /// If set, also write a `zstd`-compressed copy of the component (with a `.zst` suffix appended to the output
+    /// path) using the specified compression level.
+    pub compression: Option<i32>,
+
+    /// If true, print a report of the component's size, broken down by core module and section.
+    pub size_report: bool,
+}
+
+impl Options<'_> {
+    /// Return true if the specified (dot-separated) module should be available to the app.
+    fn keep(&self, module: &str) -> bool {
+        let within = |module: &str, package: &str| {
+            module == package
+                || module
+                    .strip_prefix(package)
+                    .is_some_and(|rest| rest.starts_with('.'))
+        };
+
+        !self
+            .exclude_modules
+            .iter()
+            .any(|pattern| within(module, pattern))
+            // Keep any explicitly included module, plus its parents so it remains importable.
+            || self
+                .include_modules
+                .iter()
+                .any(|pattern| within(module, pattern) || within(pattern, module))
+    }
+
+    /// Remove any excluded modules from the standard library, which has been unpacked to `dir`.
+    pub fn prune_standard_library(&self, dir: &Path) -> Result<()> {
+        if self.exclude_modules.is_empty() {
+            Ok(())
+        } else {
+            self.prune_directory(dir, None)
+        }
+    }
+
+    fn prune_directory(&self, dir: &Path, package: Option<&str>) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+
+            if name == "__pycache__" {
+                self.prune_directory(&path, package)?;
+                continue;
+            }
+
+            let is_dir = entry.file_type()?.is_dir();
+            let stem = if is_dir {
+                name
+            } else if name.ends_with(".py") || name.ends_with(".pyc") {
+                // Note that this also handles e.g. `foo.cpython-312.pyc`
+                name.split('.').next().unwrap()
+            } else {
+                continue;
+            };
+
+            let module = match (package, stem) {
+                (Some(package), "__init__") => package.to_owned(),
+                (Some(package), _) => format!("{package}.{stem}"),
+                (None, _) => stem.to_owned(),
+            };
+
+            if self.keep(&module) {
+                if is_dir {
+                    self.prune_directory(&path, Some(&module))?;
+                }
+            } else if is_dir {
+                fs::remove_dir_all(&path)?;
+            } else {
+                fs::remove_file(&path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Remove any native extensions corresponding to excluded modules from `libraries` and, if requested, strip
+    /// debug information from the rest.
+    pub fn prune_libraries(&self, libraries: &mut Vec<Library>) -> Result<()> {
+        libraries.retain(|library| match extension_module_name(&library.name) {
+            Some(module) if library.dl_openable => self.keep(&module),
+            _ => true,
+        });
+
+        if self.strip_debug {
+            for library in libraries {
+                library.module = strip_debug(&library.module)
+                    .with_context(|| format!("failed to strip debug info from {}", library.name))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write the component to `output_path`, plus any additional artifacts these options call for.
+    pub fn write_output(&self, output_path: &Path, component: &[u8]) -> Result<()> {
+        fs::write(output_path, component)?;
+
+        // Stream the compressed copy to disk rather than holding it in memory alongside the component.
+        if let Some(level) = self.compression {
+            let path = compressed_path(output_path);
+            let file = File::create(&path).with_context(|| path.display().to_string())?;
+            zstd::stream::copy_encode(component, file, level)?;
+        }
+
+        if self.size_report {
+            print!("{}", size_report(output_path, component)?);
+        }
+
+        Ok(())
+    }
+}
+
+/// Return the path to which a compressed copy of the component at `output_path` will be written.
+pub fn compressed_path(output_path: &Path) -> PathBuf {
+    let mut path = output_path.as_os_str().to_owned();
+    path.push(".zst");
+    path.into()
+}
+
+/// Derive the Python module name for a native extension library named e.g.
+/// `/0/numpy/core/_multiarray_umath.cpython-312-wasm32-wasi.so`.
+fn extension_module_name(library: &str) -> Option<String> {
+    let (_, path) = library.strip_prefix('/')?.split_once('/')?;
+    let (dir, file) = path.rsplit_once('/').unwrap_or(("", path));
+    let stem = file.split('.').next()?;
+    Some(if dir.is_empty() {
+        stem.to_owned()
+    } else {
+        format!("{}.{stem}", dir.replace('/', "."))
+    })
+}
+
+/// Return a copy of the specified core module without any `name` or DWARF custom sections.
+fn strip_debug(module: &[u8]) -> Result<Vec<u8>> {
+    let mut result = Module::new();
+    for payload in Parser::new(0).parse_all(module) {
+        let payload = payload?;
+        if let Payload::CustomSection(section) = &payload {
+            if section.name() == "name" || section.name().starts_with(".debug_") {
+                continue;
+            }
+        }
+
+        if let Some((id, range)) = payload.as_section() {
+            result.section(&RawSection {
+                id,
+                data: &module[range],
+            });
+        }
+    }
+
+    Ok(result.finish())
+}
+
+#[derive(Default)]
+struct ModuleSizes {
+    name: Option<String>,
+    total: usize,
+    code: usize,
+    data: usize,
+    custom: usize,
+}
+
+/// Generate a human-readable report of the size of the specified component, broken down by core module (named
+/// according to the library it came from, where known) and by section.
+fn size_report(output_path: &Path, component: &[u8]) -> Result<String> {
+    let mut modules = Vec::<ModuleSizes>::new();
+    let mut sections = Vec::<(String, usize)>::new();
+    // Index into `modules` for each (possibly nested) module or component we're currently parsing; `None` for
+    // components.
+    let mut stack = Vec::<Option<usize>>::new();
+
+    for payload in Parser::new(0).parse_all(component) {
+        let payload = payload?;
+        let current = stack.last().copied().flatten();
+
+        match &payload {
+            Payload::Version {
+                encoding, range, ..
+            } => {
+                stack.push(if let Encoding::Module = encoding {
+                    modules.push(ModuleSizes::default());
+                    Some(modules.len() - 1)
+                } else {
+                    None
+                });
+                if let Some(index) = stack.last().copied().flatten() {
+                    modules[index].total += range.len();
+                }
+                continue;
+            }
+            Payload::End(_) => {
+                stack.pop();
+                continue;
+            }
+            _ => {}
+        }
+
+        let Some(index) = current else {
+            continue;
+        };
+
+        let Some((_, range)) = payload.as_section() else {
+            continue;
+        };
+
+        let sizes = &mut modules[index];
+        let size = section_size(&range);
+        sizes.total += size;
+
+        let name = match &payload {
+            Payload::CodeSectionStart { .. } => {
+                sizes.code += size;
+                "code".to_owned()
+            }
+            Payload::DataSection(_) => {
+                sizes.data += size;
+                "data".to_owned()
+            }
+            Payload::CustomSection(section) => {
+                sizes.custom += size;
+                if let KnownCustom::Name(reader) = section.as_known() {
+                    sizes.name = sizes.name.take().or(module_name(reader));
+                }
+                format!("custom:{}", section.name())
+            }
+            _ => "other".to_owned(),
+        };
+
+        if let Some((_, total)) = sections.iter_mut().find(|(n, _)| *n == name) {
+            *total += size;
+        } else {
+            sections.push((name, size));
+        }
+    }
+
+    modules.sort_by_key(|sizes| Reverse(sizes.total));
+    sections.sort_by_key(|(_, size)| Reverse(*size));
+
+    let mut report = String::new();
+    writeln!(
+        report,
+        "Size report for {} ({} total)\n\nBy core module:",
+        output_path.display(),
+        human(component.len())
+    )?;
+    for (index, sizes) in modules.iter().enumerate() {
+        writeln!(
+            report,
+            "  {:<40} {:>10}  (code {}, data {}, custom {})",
+            sizes
+                .name
+                .clone()
+                .unwrap_or_else(|| format!("<module {index}>")),
+            human(sizes.total),
+            human(sizes.code),
+            human(sizes.data),
+            human(sizes.custom),
+        )?;
+    }
+    writeln!(report, "\nBy section:")?;
+    for (name, size) in &sections {
+        writeln!(report, "  {name:<40} {:>10}", human(*size))?;
+    }
+
+    Ok(report)
+}
+
+/// Return the size of a section, including its ID and (approximate) length prefix, given the range of its
+/// contents.
+fn section_size(range: &Range<usize>) -> usize {
+    1 + leb128_len(range.len()) + range.len()
+}
+
+fn leb128_len(mut value: usize) -> usize {
+    let mut len = 1;
+    while value >= 0x80 {
+        value >>= 7;
+        len += 1;
+    }
+    len
+}
+
+fn module_name(reader: NameSectionReader) -> Option<String> {
+    reader.into_iter().find_map(|name| match name {
+        Ok(Name::Module { name, .. }) => Some(name.to_owned()),
+        _ => None,
+    })
+}
+
+pub(crate) fn human(size: usize) -> String {
+    const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB"];
+    let mut size = size as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{size} {}", UNITS[unit])
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn module_matching() {
+        let options = Options {
+            exclude_modules: &["email", "tkinter"],
+            include_modules: &["email.utils"],
+            ..Default::default()
+        };
+
+        assert!(options.keep("os"));
+        assert!(options.keep("emailx"));
+        assert!(!options.keep("tkinter"));
+        assert!(!options.keep("tkinter.ttk"));
+        assert!(!options.keep("email.mime"));
+        assert!(options.keep("email"));
+        assert!(options.keep("email.utils"));
+    }
+
+    #[test]
+    fn extension_module_names() {
+        assert_eq!(
+            Some("numpy.core._multiarray_umath".to_owned()),
+            extension_module_name("/0/numpy/core/_multiarray_umath.cpython-312-wasm32-wasi.so")
+        );
+        assert_eq!(
+            Some("_foo".to_owned()),
+            extension_module_name("/1/_foo.cpython-312-wasm32-wasi.so")
+        );
+        assert_eq!(None, extension_module_name("libc.so"));
+    }
+}
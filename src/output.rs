@@ -0,0 +1,280 @@
+use {
+    async_trait::async_trait,
+    bytes::Bytes,
+    std::{
+        collections::VecDeque,
+        fmt,
+        io::{self, Write},
+        sync::{Arc, Mutex},
+    },
+    wasmtime_wasi::{HostOutputStream, StdoutStream, StreamError, Subscribe},
+};
+
+/// Default maximum number of bytes of each of `stdout` and `stderr` to retain during pre-initialization
+pub const DEFAULT_CAPTURE_LIMIT: usize = 1024 * 1024;
+
+/// Callback which receives chunks of output as they are written
+pub type OutputCallback = Arc<dyn Fn(&[u8]) + Send + Sync>;
+
+/// Callback which is notified as each `Phase` of a build begins
+pub type ProgressCallback = Arc<dyn Fn(Phase) + Send + Sync>;
+
+/// Optional callbacks through which `componentize` reports output and progress as it happens, e.g. so that a GUI
+/// or notebook can show live logs.
+#[derive(Default, Clone)]
+pub struct Callbacks {
+    /// Receives anything the app writes to `stdout` during pre-initialization, instead of it being echoed to the
+    /// host's `stdout`
+    pub stdout: Option<OutputCallback>,
+
+    /// Receives anything the app writes to `stderr` during pre-initialization, instead of it being echoed to the
+    /// host's `stderr`
+    pub stderr: Option<OutputCallback>,
+
+    /// Notified as each phase of the build begins
+    pub progress: Option<ProgressCallback>,
+}
+
+impl Callbacks {
+    /// Notify the progress callback, if any, that `phase` has begun.
+    pub fn report(&self, phase: Phase) {
+        if let Some(progress) = &self.progress {
+            progress(phase);
+        }
+    }
+
+    /// Return how output written to `stdout` during pre-initialization should be echoed.
+    pub fn stdout_echo(&self, quiet: bool) -> Echo {
+        Self::echo(&self.stdout, quiet, Echo::Stdout)
+    }
+
+    /// Return how output written to `stderr` during pre-initialization should be echoed.
+    pub fn stderr_echo(&self, quiet: bool) -> Echo {
+        Self::echo(&self.stderr, quiet, Echo::Stderr)
+    }
+
+    fn echo(callback: &Option<OutputCallback>, quiet: bool, default: Echo) -> Echo {
+        match callback {
+            Some(callback) => Echo::Callback(callback.clone()),
+            None if quiet => Echo::None,
+            None => default,
+        }
+    }
+}
+
+/// Phases of a `componentize` build, in the order they occur
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Phase {
+    /// Searching `python_path` for native extensions and `componentize-py.toml` files
+    Prelinking,
+    /// Parsing WIT files and generating bindings
+    GeneratingBindings,
+    /// Linking libraries into a component
+    Linking,
+    /// Running the app's top-level code and snapshotting the result
+    PreInitializing,
+    /// Writing the component (including, if applicable, when it was found in the build cache)
+    Writing,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Self::Prelinking => "prelinking",
+            Self::GeneratingBindings => "generating-bindings",
+            Self::Linking => "linking",
+            Self::PreInitializing => "pre-initializing",
+            Self::Writing => "writing",
+        })
+    }
+}
+
+/// Output captured from the app during a failed pre-initialization
+///
+/// This is attached as context to the resulting error so that callers (e.g. `report::Diagnostic`) can extract a
+/// traceback; its `Display` output is the raw `stdout` followed by `stderr`.
+#[derive(Debug)]
+pub struct InitFailure {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl fmt::Display for InitFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            String::from_utf8_lossy(&self.stdout),
+            String::from_utf8_lossy(&self.stderr)
+        )
+    }
+}
+
+/// Where (if anywhere) a `TeeOutputPipe` should echo output
+#[derive(Clone)]
+pub enum Echo {
+    None,
+    Stdout,
+    Stderr,
+    Callback(OutputCallback),
+}
+
+/// Output retained by a `TeeOutputPipe`
+///
+/// Once more than `limit` bytes have been written, we keep the first half of the limit's worth (e.g. any warnings
+/// printed early on) and a rolling window of the most recent half (e.g. the eventual traceback), discarding what
+/// comes in between.
+struct Capture {
+    limit: usize,
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    omitted: usize,
+}
+
+impl Capture {
+    fn write(&mut self, bytes: &[u8]) {
+        let head_limit = self.limit / 2;
+        let (head, rest) =
+            bytes.split_at(head_limit.saturating_sub(self.head.len()).min(bytes.len()));
+        self.head.extend_from_slice(head);
+        self.tail.extend(rest);
+
+        let tail_limit = self.limit - head_limit;
+        if self.tail.len() > tail_limit {
+            let excess = self.tail.len() - tail_limit;
+            self.tail.drain(..excess);
+            self.omitted += excess;
+        }
+    }
+
+    fn contents(&self) -> Vec<u8> {
+        let mut contents = self.head.clone();
+        if self.omitted > 0 {
+            contents.extend_from_slice(
+                format!("\n[... {} bytes of output omitted ...]\n", self.omitted).as_bytes(),
+            );
+        }
+        contents.extend(&self.tail);
+        contents
+    }
+}
+
+/// Output stream for use during pre-initialization which retains what is written to it and optionally echoes it
+/// live to the host's `stdout` or `stderr`.
+///
+/// Unlike `wasmtime_wasi::pipe::MemoryOutputPipe`, which silently drops anything beyond its capacity, this keeps
+/// both the start and the end of the output once it exceeds the limit, so a long import log followed by a
+/// traceback still ends with the traceback.  Echoed output is never truncated.
+#[derive(Clone)]
+pub struct TeeOutputPipe {
+    capture: Arc<Mutex<Capture>>,
+    echo: Echo,
+}
+
+impl TeeOutputPipe {
+    /// Create a pipe which retains at most `limit` bytes of output (plus a marker noting how much was omitted, if
+    /// anything).
+    pub fn new(echo: Echo, limit: usize) -> Self {
+        Self {
+            capture: Arc::new(Mutex::new(Capture {
+                limit,
+                head: Vec::new(),
+                tail: VecDeque::new(),
+                omitted: 0,
+            })),
+            echo,
+        }
+    }
+
+    /// Return the output retained so far.
+    pub fn contents(&self) -> Vec<u8> {
+        self.capture.lock().unwrap().contents()
+    }
+}
+
+impl Echo {
+    /// Echo `bytes` to the host's `stdout` or `stderr` or the callback, as appropriate.
+    pub fn write(&self, bytes: &[u8]) -> io::Result<()> {
+        match self {
+            Self::None => Ok(()),
+            Self::Stdout => {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(bytes)?;
+                stdout.flush()
+            }
+            Self::Stderr => {
+                let mut stderr = io::stderr().lock();
+                stderr.write_all(bytes)?;
+                stderr.flush()
+            }
+            Self::Callback(callback) => {
+                callback(bytes);
+                Ok(())
+            }
+        }
+    }
+}
+
+impl HostOutputStream for TeeOutputPipe {
+    fn write(&mut self, bytes: Bytes) -> Result<(), StreamError> {
+        self.capture.lock().unwrap().write(&bytes);
+        // Failing to echo (e.g. because the host's stream has been closed) is not fatal since we still have the
+        // output in `capture`.
+        let _ = self.echo.write(&bytes);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), StreamError> {
+        Ok(())
+    }
+
+    fn check_write(&mut self) -> Result<usize, StreamError> {
+        Ok(usize::MAX)
+    }
+}
+
+#[async_trait]
+impl Subscribe for TeeOutputPipe {
+    async fn ready(&mut self) {}
+}
+
+impl StdoutStream for TeeOutputPipe {
+    fn stream(&self) -> Box<dyn HostOutputStream> {
+        Box::new(self.clone())
+    }
+
+    fn isatty(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_output_retained() -> Result<(), StreamError> {
+        let mut pipe = TeeOutputPipe::new(Echo::None, 16);
+        pipe.write(Bytes::from_static(b"hello, "))?;
+        pipe.write(Bytes::from_static(b"world"))?;
+
+        assert_eq!(b"hello, world".to_vec(), pipe.contents());
+
+        Ok(())
+    }
+
+    #[test]
+    fn long_output_keeps_head_and_tail() -> Result<(), StreamError> {
+        let mut pipe = TeeOutputPipe::new(Echo::None, 8);
+        pipe.write(Bytes::from_static(b"abc"))?;
+        pipe.write(Bytes::from_static(b"defghijklm"))?;
+        pipe.write(Bytes::from_static(b"nop"))?;
+
+        assert_eq!(
+            "abcd\n[... 8 bytes of output omitted ...]\nmnop",
+            String::from_utf8(pipe.contents()).unwrap()
+        );
+
+        Ok(())
+    }
+}
@@ -2,162 +2,642 @@
 
 use std::{
     collections::{HashMap, HashSet},
+    env,
+    fmt::Write as _,
     fs::{self},
-    io::Cursor,
+    io::{Cursor, Read},
     ops::Deref,
     path::{Path, PathBuf},
 };
 
 use anyhow::{anyhow, bail, Context, Result};
 use indexmap::IndexMap;
+use rayon::prelude::*;
+use sha2::{Digest, Sha256};
 use tar::Archive;
-use tempfile::TempDir;
+use wasmparser::{FuncType, Parser, Payload, TypeRef, ValType};
+use zip::ZipArchive;
 use zstd::Decoder;
 
-use crate::{ComponentizePyConfig, ConfigContext, Library, RawComponentizePyConfig};
+use crate::{
+    artifacts::{self, Artifacts},
+    temp::{self, TempDir},
+    util, ComponentizePyConfig, ConfigContext, Library, RawComponentizePyConfig,
+};
 
 static NATIVE_EXTENSION_SUFFIX: &str = ".cpython-312-wasm32-wasi.so";
 
+/// Suffixes of `python_path` entries which are treated as archives to be extracted rather than as directories
+///
+/// `.pyz` files are zipapps (see the `zipapp` module), i.e. ZIP archives which may be preceded by a shebang line;
+/// `ZipArchive` skips any such prefix for us.
+static ARCHIVE_SUFFIXES: &[&str] = &[".whl", ".zip", ".pyz"];
+
+/// Release of the WASI SDK used to build the embedded libraries (see `.github/workflows/release.yaml`), which
+/// native extensions must be built with as well
+static WASI_SDK_RELEASE: &str = "wasi-sockets-alpha-5";
+
+/// Version of the WASI SDK used to build the embedded libraries
+static WASI_SDK_VERSION: &str = "20.46gf3a1f8991535";
+
+/// Globals which a shared library imports from the dynamic linker, all of which must be 32-bit pointers or
+/// offsets for `wasm32`
+static ADDRESS_GLOBALS: &[&str] = &["__stack_pointer", "__memory_base", "__table_base"];
+
 type ConfigsMatchedWorlds<'a> =
     IndexMap<String, (ConfigContext<ComponentizePyConfig>, Option<&'a str>)>;
 
-pub fn embedded_python_standard_library() -> Result<TempDir> {
+/// Native extension libraries found in each `python_path` directory
+pub type LibraryPath<'a> = Vec<(&'a str, Vec<PathBuf>)>;
+
+/// Paths excluded from each `python_path` directory, in the same order as `python_path`
+pub type ExcludedPaths = Vec<HashSet<PathBuf>>;
+
+/// Patterns excluded from every `python_path` directory unless re-included by a `!` pattern (see `ExcludePattern`
+/// for the syntax)
+static DEFAULT_EXCLUDE_PATTERNS: &[&str] = &[
+    "__pycache__/",
+    ".git/",
+    ".hg/",
+    ".svn/",
+    ".tox/",
+    ".nox/",
+    ".mypy_cache/",
+    ".pytest_cache/",
+    ".ruff_cache/",
+];
+
+/// Name of the optional file at the top of a `python_path` directory which lists patterns to exclude from it, one
+/// per line
+static IGNORE_FILE: &str = ".componentizeignore";
+
+pub fn embedded_python_standard_library(artifacts: &Artifacts, keep_temp: bool) -> Result<TempDir> {
     // Untar the embedded copy of the Python standard library into a temporary directory
-    let stdlib = tempfile::tempdir()?;
+    let stdlib = temp::dir(keep_temp)?;
 
-    Archive::new(Decoder::new(Cursor::new(include_bytes!(concat!(
-        env!("OUT_DIR"),
-        "/python-lib.tar.zst"
-    ))))?)
+    Archive::new(Decoder::new(Cursor::new(
+        artifacts.get(artifacts::PYTHON_STANDARD_LIBRARY),
+    ))?)
     .unpack(stdlib.path())
-    .unwrap();
+    .context("failed to unpack the Python standard library")?;
 
     Ok(stdlib)
 }
 
-pub fn embedded_helper_utils() -> Result<TempDir> {
+/// Return the text of CPython's license, which is installed alongside (and embedded with) the standard library.
+pub fn embedded_python_license(artifacts: &Artifacts) -> Result<String> {
+    for entry in Archive::new(Decoder::new(Cursor::new(
+        artifacts.get(artifacts::PYTHON_STANDARD_LIBRARY),
+    ))?)
+    .entries()?
+    {
+        let mut entry = entry?;
+        if entry.path()? == Path::new("LICENSE.txt") {
+            let mut license = String::new();
+            entry.read_to_string(&mut license)?;
+            return Ok(license);
+        }
+    }
+
+    bail!("embedded Python standard library does not include `LICENSE.txt`")
+}
+
+pub fn embedded_helper_utils(artifacts: &Artifacts, keep_temp: bool) -> Result<TempDir> {
     // Untar the embedded copy of helper utilities into a temporary directory
-    let bundled = tempfile::tempdir()?;
+    let bundled = temp::dir(keep_temp)?;
 
-    Archive::new(Decoder::new(Cursor::new(include_bytes!(concat!(
-        env!("OUT_DIR"),
-        "/bundled.tar.zst"
-    ))))?)
+    Archive::new(Decoder::new(Cursor::new(
+        artifacts.get(artifacts::HELPER_UTILS),
+    ))?)
     .unpack(bundled.path())
-    .unwrap();
+    .context("failed to unpack helper utilities")?;
 
     Ok(bundled)
 }
 
-pub fn bundle_libraries(library_path: Vec<(&str, Vec<PathBuf>)>) -> Result<Vec<Library>> {
-    let mut libraries = vec![
-        Library {
-            name: "libcomponentize_py_runtime.so".into(),
-            module: zstd::decode_all(Cursor::new(include_bytes!(concat!(
-                env!("OUT_DIR"),
-                "/libcomponentize_py_runtime.so.zst"
-            ))))?,
-            dl_openable: false,
-        },
-        Library {
-            name: "libpython3.12.so".into(),
-            module: zstd::decode_all(Cursor::new(include_bytes!(concat!(
-                env!("OUT_DIR"),
-                "/libpython3.12.so.zst"
-            ))))?,
-            dl_openable: false,
-        },
-        Library {
-            name: "libc.so".into(),
-            module: zstd::decode_all(Cursor::new(include_bytes!(concat!(
-                env!("OUT_DIR"),
-                "/libc.so.zst"
-            ))))?,
-            dl_openable: false,
-        },
-        Library {
-            name: "libwasi-emulated-mman.so".into(),
-            module: zstd::decode_all(Cursor::new(include_bytes!(concat!(
-                env!("OUT_DIR"),
-                "/libwasi-emulated-mman.so.zst"
-            ))))?,
-            dl_openable: false,
-        },
-        Library {
-            name: "libwasi-emulated-process-clocks.so".into(),
-            module: zstd::decode_all(Cursor::new(include_bytes!(concat!(
-                env!("OUT_DIR"),
-                "/libwasi-emulated-process-clocks.so.zst"
-            ))))?,
-            dl_openable: false,
-        },
-        Library {
-            name: "libwasi-emulated-getpid.so".into(),
-            module: zstd::decode_all(Cursor::new(include_bytes!(concat!(
-                env!("OUT_DIR"),
-                "/libwasi-emulated-getpid.so.zst"
-            ))))?,
-            dl_openable: false,
-        },
-        Library {
-            name: "libwasi-emulated-signal.so".into(),
-            module: zstd::decode_all(Cursor::new(include_bytes!(concat!(
-                env!("OUT_DIR"),
-                "/libwasi-emulated-signal.so.zst"
-            ))))?,
-            dl_openable: false,
-        },
-        Library {
-            name: "libc++.so".into(),
-            module: zstd::decode_all(Cursor::new(include_bytes!(concat!(
-                env!("OUT_DIR"),
-                "/libc++.so.zst"
-            ))))?,
-            dl_openable: false,
-        },
-        Library {
-            name: "libc++abi.so".into(),
-            module: zstd::decode_all(Cursor::new(include_bytes!(concat!(
-                env!("OUT_DIR"),
-                "/libc++abi.so.zst"
-            ))))?,
-            dl_openable: false,
-        },
-    ];
-
-    for (index, (path, libs)) in library_path.iter().enumerate() {
-        for library in libs {
-            let path = library
-                .strip_prefix(path)
-                .unwrap()
-                .to_str()
-                .context("non-UTF-8 path")
-                .unwrap()
-                .replace('\\', "/");
+/// Function exports of the embedded libraries, i.e. the ABI which native extensions are expected to link against,
+/// mapped to the name of the library providing each one and its signature
+type Exports<'a> = HashMap<&'a str, (&'a str, FuncType)>;
+
+pub fn bundle_libraries(artifacts: &Artifacts, library_path: LibraryPath) -> Result<Vec<Library>> {
+    // Decompress the embedded libraries and read the discovered ones in parallel, since there may be dozens of the
+    // latter (e.g. NumPy and its dependencies).
+    let mut libraries = artifacts
+        .libraries()
+        .par_iter()
+        .map(|(name, module)| {
+            Ok(Library {
+                name: (*name).into(),
+                module: zstd::decode_all(Cursor::new(*module))?,
+                dl_openable: false,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    libraries.extend(
+        library_path
+            .par_iter()
+            .enumerate()
+            .flat_map(|(index, (path, libs))| {
+                libs.par_iter().map(move |library| {
+                    let path = library
+                        .strip_prefix(path)
+                        .unwrap()
+                        .to_str()
+                        .context("non-UTF-8 path")
+                        .unwrap()
+                        .replace('\\', "/");
+
+                    Ok(Library {
+                        name: format!("/{index}/{path}"),
+                        module: fs::read(library).with_context(|| library.display().to_string())?,
+                        dl_openable: true,
+                    })
+                })
+            })
+            .collect::<Result<Vec<_>>>()?,
+    );
+
+    Ok(libraries)
+}
+
+/// Check that each native extension in `libraries` was built for the same ABI as the embedded libraries, failing
+/// with a list of any incompatibilities found.
+///
+/// `python_path` is used to map library names back to the host paths they were found at.
+pub fn check_abi(libraries: &[Library], python_path: &[&str]) -> Result<()> {
+    let mut exports = Exports::new();
+    for library in libraries.iter().filter(|library| !library.dl_openable) {
+        add_exports(&library.name, &library.module, &mut exports)?;
+    }
+
+    let problems = libraries
+        .par_iter()
+        .filter(|library| library.dl_openable)
+        .map(|library| {
+            Ok((
+                library,
+                find_abi_problems(&library.module, &exports)
+                    .with_context(|| format!("unable to parse {}", library.name))?,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut message = String::new();
+    for (library, problems) in problems {
+        if problems.is_empty() {
+            continue;
+        }
+
+        // Discovered libraries are named `/<index into python_path>/<path relative to that directory>`.
+        let (path, package) = library
+            .name
+            .strip_prefix('/')
+            .and_then(|name| name.split_once('/'))
+            .and_then(|(index, path)| {
+                let root = python_path.get(index.parse::<usize>().ok()?)?;
+                Some((
+                    Path::new(root).join(path).display().to_string(),
+                    path.split('/').next()?,
+                ))
+            })
+            .unwrap_or((library.name.clone(), ""));
+
+        writeln!(message, "  {path} (from `{package}`):")?;
+        for problem in problems {
+            writeln!(message, "    - {problem}")?;
+        }
+    }
+
+    if !message.is_empty() {
+        bail!(
+            "the following native extension(s) are incompatible with the libraries embedded in \
+             componentize-py:\n{message}\
+             Please rebuild the affected package(s) using WASI SDK {WASI_SDK_VERSION} (release \
+             `{WASI_SDK_RELEASE}`), targeting `wasm32-wasi` with `-fPIC` and linking with `-shared`."
+        );
+    }
+
+    Ok(())
+}
+
+/// Add the function exports of the library named `name` to `exports`.
+fn add_exports<'a>(name: &'a str, module: &'a [u8], exports: &mut Exports<'a>) -> Result<()> {
+    let mut types = Vec::new();
+    let mut functions = Vec::new();
+    for payload in Parser::new(0).parse_all(module) {
+        match payload? {
+            Payload::TypeSection(reader) => {
+                types = reader
+                    .into_iter_err_on_gc_types()
+                    .collect::<Result<Vec<_>, _>>()?;
+            }
+
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    if let TypeRef::Func(ty) = import?.ty {
+                        functions.push(ty);
+                    }
+                }
+            }
+
+            Payload::FunctionSection(reader) => {
+                for ty in reader {
+                    functions.push(ty?);
+                }
+            }
+
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export?;
+                    if let wasmparser::ExternalKind::Func = export.kind {
+                        let ty =
+                            &types[usize::try_from(functions[usize::try_from(export.index)?])?];
+                        exports
+                            .entry(export.name)
+                            .or_insert_with(|| (name, ty.clone()));
+                    }
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Look for ways in which the native extension `module` is incompatible with the embedded libraries, returning a
+/// description of each one found.
+///
+/// Libraries built using a different WASI SDK may link successfully but misbehave at runtime, so we look for
+/// symptoms such as functions imported with a signature other than the one exported by the embedded library
+/// which provides them.
+fn find_abi_problems(module: &[u8], exports: &Exports) -> Result<Vec<String>> {
+    let mut problems = Vec::new();
+    let mut types = Vec::new();
+    let mut dylink = false;
+    for payload in Parser::new(0).parse_all(module) {
+        match payload? {
+            Payload::TypeSection(reader) => {
+                types = reader
+                    .into_iter_err_on_gc_types()
+                    .collect::<Result<Vec<_>, _>>()?;
+            }
+
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    let import = import?;
+                    match (import.module, import.ty) {
+                        ("env", TypeRef::Func(ty)) => {
+                            if let Some((library, expected)) = exports.get(import.name) {
+                                let ty = &types[usize::try_from(ty)?];
+                                if ty != expected {
+                                    problems.push(format!(
+                                        "imports `{}` with signature `{}`, but `{library}` exports it \
+                                         with signature `{}`",
+                                        import.name,
+                                        signature(ty),
+                                        signature(expected)
+                                    ));
+                                }
+                            }
+                        }
 
+                        ("env", TypeRef::Global(ty))
+                            if ADDRESS_GLOBALS.contains(&import.name)
+                                && ty.content_type != ValType::I32 =>
+                        {
+                            problems.push(format!(
+                                "imports `{}` as `{}` rather than `i32`; was it built for `wasm64`?",
+                                import.name, ty.content_type
+                            ));
+                        }
+
+                        ("wasi_unstable", _) => {
+                            problems.push(format!(
+                                "imports `{}` from the obsolete `wasi_unstable` module",
+                                import.name
+                            ));
+                        }
+
+                        _ => {}
+                    }
+                }
+            }
+
+            Payload::CustomSection(section) if section.name() == "dylink.0" => dylink = true,
+
+            _ => {}
+        }
+    }
+
+    if !dylink {
+        problems.push("has no `dylink.0` section; was it linked with `-shared`?".to_owned());
+    }
+
+    Ok(problems)
+}
+
+/// Format `ty` as e.g. `(i32, i32) -> i32`.
+fn signature(ty: &FuncType) -> String {
+    let list = |types: &[ValType]| {
+        types
+            .iter()
+            .map(|ty| ty.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    format!("({}) -> ({})", list(ty.params()), list(ty.results()))
+}
+
+/// Override how the libraries matching `static_libraries` and `dynamic_libraries` are linked.
+///
+/// By default, the embedded libraries are linked statically (i.e. they satisfy other libraries' imports but can't
+/// be loaded using `dlopen`), while native extensions are `dlopen`-able, since that's how Python loads them.
+/// Marking a library static keeps its symbols out of the `dlopen` lookup table, making the component smaller;
+/// marking one dynamic makes it available to e.g. `ctypes.CDLL`.  Shared libraries in `python_path` which aren't
+/// native extensions (e.g. those vendored in a `<package>.libs` directory) are only linked if matched by a pattern.
+///
+/// Patterns are matched against each embedded library's name (e.g. `libc++.so`) and each other library's path
+/// relative to the `python_path` directory it was found in (e.g. `numpy.libs/libopenblas.so`).  Patterns
+/// containing a `/` must match the whole path, while others need only match the file name.  `*` matches any
+/// sequence of characters and `?` any single character.
+///
+/// Since nothing can `dlopen` a static library, each must be imported from by another library, and none may be a
+/// native extension.
+pub fn override_linking(
+    libraries: &mut Vec<Library>,
+    python_path: &[&str],
+    excluded_paths: &ExcludedPaths,
+    static_libraries: &[&str],
+    dynamic_libraries: &[&str],
+) -> Result<()> {
+    fn visit(
+        root: &Path,
+        path: &Path,
+        excluded: &HashSet<PathBuf>,
+        patterns: &[&str],
+        found: &mut Vec<PathBuf>,
+    ) -> Result<()> {
+        if excluded.contains(path) {
+            return Ok(());
+        }
+
+        if path.is_dir() {
+            let mut entries = fs::read_dir(path)
+                .with_context(|| path.display().to_string())?
+                .map(|entry| Ok(entry?.path()))
+                .collect::<Result<Vec<_>>>()?;
+            entries.sort();
+            for entry in entries {
+                visit(root, &entry, excluded, patterns, found)?;
+            }
+        } else if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
+            if (name.ends_with(".so") || name.contains(".so."))
+                && !name.ends_with(NATIVE_EXTENSION_SUFFIX)
+            {
+                let relative = path
+                    .strip_prefix(root)?
+                    .to_str()
+                    .context("non-UTF-8 path")?
+                    .replace('\\', "/");
+                if patterns
+                    .iter()
+                    .any(|pattern| library_matches(pattern, &relative))
+                {
+                    found.push(path.to_owned());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    if static_libraries.is_empty() && dynamic_libraries.is_empty() {
+        return Ok(());
+    }
+
+    let patterns = static_libraries
+        .iter()
+        .chain(dynamic_libraries)
+        .copied()
+        .collect::<Vec<_>>();
+    for (index, (root, excluded)) in python_path.iter().zip(excluded_paths).enumerate() {
+        let mut found = Vec::new();
+        visit(
+            Path::new(root),
+            Path::new(root),
+            excluded,
+            &patterns,
+            &mut found,
+        )?;
+        for path in found {
+            let relative = path
+                .strip_prefix(root)?
+                .to_str()
+                .context("non-UTF-8 path")?;
             libraries.push(Library {
-                name: format!("/{index}/{path}"),
-                module: fs::read(library).with_context(|| library.display().to_string())?,
-                dl_openable: true,
+                name: format!("/{index}/{}", relative.replace('\\', "/")),
+                module: fs::read(&path).with_context(|| path.display().to_string())?,
+                dl_openable: false,
             });
         }
     }
 
-    Ok(libraries)
+    fn matching(libraries: &[Library], patterns: &[&str], kind: &str) -> Result<HashSet<usize>> {
+        let mut matched = HashSet::new();
+        for pattern in patterns {
+            let indexes = libraries
+                .iter()
+                .enumerate()
+                .filter(|(_, library)| library_matches(pattern, library_path(&library.name)))
+                .map(|(index, _)| index)
+                .collect::<Vec<_>>();
+            if indexes.is_empty() {
+                bail!("{kind} library pattern `{pattern}` did not match any libraries");
+            }
+            matched.extend(indexes);
+        }
+        Ok(matched)
+    }
+
+    let static_indexes = matching(libraries, static_libraries, "static")?;
+    let dynamic_indexes = matching(libraries, dynamic_libraries, "dynamic")?;
+
+    if let Some(&index) = static_indexes.intersection(&dynamic_indexes).next() {
+        bail!(
+            "library `{}` matches both static and dynamic library patterns",
+            library_path(&libraries[index].name)
+        );
+    }
+
+    for &index in &static_indexes {
+        libraries[index].dl_openable = false;
+    }
+    for &index in &dynamic_indexes {
+        libraries[index].dl_openable = true;
+    }
+
+    // Gather every symbol each library imports from the dynamic linker so we can tell whether anything would be
+    // left able to reach each static library.
+    let imports = libraries
+        .par_iter()
+        .map(|library| {
+            linker_imports(&library.module)
+                .with_context(|| format!("unable to parse {}", library.name))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut indexes = static_indexes.into_iter().collect::<Vec<_>>();
+    indexes.sort();
+    for index in indexes {
+        let library = &libraries[index];
+        let path = library_path(&library.name);
+        let exports = exported_names(&library.module)
+            .with_context(|| format!("unable to parse {}", library.name))?;
+
+        if exports.iter().any(|name| name.starts_with("PyInit_")) {
+            bail!(
+                "library `{path}` is a native extension, which Python loads using `dlopen`, so it cannot be \
+                 linked statically"
+            );
+        }
+
+        let imported = imports.iter().enumerate().any(|(other, imports)| {
+            other != index && imports.iter().any(|name| exports.contains(name))
+        });
+        if !imported {
+            bail!(
+                "library `{path}` is marked static, but no other library imports any of its symbols, so nothing \
+                 could use it; mark it dynamic if it is loaded using `dlopen` (e.g. via `ctypes`)"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Return the path of the library named `name` relative to the `python_path` directory it was found in (i.e. the
+/// `<path>` part of `/<index>/<path>`), or else `name` itself for an embedded library.
+fn library_path(name: &str) -> &str {
+    name.strip_prefix('/')
+        .and_then(|name| name.split_once('/'))
+        .map_or(name, |(_, path)| path)
+}
+
+/// Whether `pattern` matches the library at `path` (as returned by `library_path`), per `override_linking`.
+fn library_matches(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.trim_start_matches('/');
+    let path = if pattern.contains('/') {
+        path
+    } else {
+        path.rsplit('/').next().unwrap_or(path)
+    };
+    glob(pattern.as_bytes(), path.as_bytes())
+}
+
+/// Return the names of the functions and globals `module` imports from the dynamic linker.
+fn linker_imports(module: &[u8]) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for payload in Parser::new(0).parse_all(module) {
+        if let Payload::ImportSection(reader) = payload? {
+            for import in reader {
+                let import = import?;
+                if matches!(import.module, "env" | "GOT.func" | "GOT.mem") {
+                    names.push(import.name.to_owned());
+                }
+            }
+        }
+    }
+    Ok(names)
+}
+
+/// Return the names of the functions and globals `module` exports.
+fn exported_names(module: &[u8]) -> Result<HashSet<String>> {
+    let mut names = HashSet::new();
+    for payload in Parser::new(0).parse_all(module) {
+        if let Payload::ExportSection(reader) = payload? {
+            for export in reader {
+                let export = export?;
+                if matches!(
+                    export.kind,
+                    wasmparser::ExternalKind::Func | wasmparser::ExternalKind::Global
+                ) {
+                    names.insert(export.name.to_owned());
+                }
+            }
+        }
+    }
+    Ok(names)
+}
+
+/// Return a copy of `python_path` with each `.whl`, `.zip`, or `.pyz` file replaced by a directory containing its
+/// contents.
+///
+/// Archives are extracted to an `archives` subdirectory of `util::cache_dir()`, keyed by a digest of their
+/// contents, so each one need only be extracted once.
+pub fn extract_archives(python_path: &[&str]) -> Result<Vec<String>> {
+    python_path
+        .iter()
+        .map(|&path| {
+            if ARCHIVE_SUFFIXES.iter().any(|suffix| path.ends_with(suffix))
+                && Path::new(path).is_file()
+            {
+                Ok(extract_archive(Path::new(path))?
+                    .to_str()
+                    .context("non-UTF-8 path")?
+                    .to_owned())
+            } else {
+                Ok(path.to_owned())
+            }
+        })
+        .collect()
+}
+
+fn extract_archive(path: &Path) -> Result<PathBuf> {
+    let archive = fs::read(path).with_context(|| path.display().to_string())?;
+    let dir = util::cache_dir()
+        .unwrap_or_else(|| env::temp_dir().join("componentize-py"))
+        .join("archives")
+        .join(format!("{:x}", Sha256::digest(&archive)));
+
+    if !dir.exists() {
+        let parent = dir.parent().unwrap();
+        fs::create_dir_all(parent).with_context(|| parent.display().to_string())?;
+
+        // Extract to a temporary directory first and then rename it so that concurrent builds never observe a
+        // partially-extracted archive.
+        let temp = tempfile::tempdir_in(parent)?;
+        ZipArchive::new(Cursor::new(archive))
+            .and_then(|mut archive| archive.extract(temp.path()))
+            .with_context(|| format!("failed to extract {}", path.display()))?;
+
+        if let Err(e) = fs::rename(temp.path(), &dir) {
+            // Another build may have beaten us to it.
+            if !dir.exists() {
+                return Err(e).with_context(|| dir.display().to_string());
+            }
+        }
+    }
+
+    Ok(dir)
 }
 
 pub fn search_for_libraries_and_configs<'a>(
     python_path: &'a Vec<&'a str>,
+    excluded_paths: &ExcludedPaths,
     module_worlds: &'a [(&'a str, &'a str)],
     world: Option<&'a str>,
-) -> Result<(ConfigsMatchedWorlds<'a>, Vec<Library>)> {
+) -> Result<(ConfigsMatchedWorlds<'a>, LibraryPath<'a>)> {
     let mut raw_configs: Vec<ConfigContext<RawComponentizePyConfig>> = Vec::new();
-    let mut library_path: Vec<(&str, Vec<PathBuf>)> = Vec::with_capacity(python_path.len());
-    for path in python_path {
+    let mut library_path: LibraryPath = Vec::with_capacity(python_path.len());
+    for (path, excluded) in python_path.iter().zip(excluded_paths) {
         let mut libraries = Vec::new();
         search_directory(
             Path::new(path),
             Path::new(path),
+            excluded,
             &mut libraries,
             &mut raw_configs,
             &mut HashSet::new(),
@@ -165,12 +645,14 @@ pub fn search_for_libraries_and_configs<'a>(
         library_path.push((*path, libraries));
     }
 
-    let libraries = bundle_libraries(library_path)?;
-
     // Validate the paths parsed from any componentize-py.toml files discovered above and match them up with
     // `module_worlds` entries.  Note that we use an `IndexMap` to preserve the order specified in `module_worlds`,
     // which is required to be topologically sorted with respect to package dependencies.
     //
+    // An entry may name its module(s) using a glob pattern (e.g. `services.*`), in which case the matching modules
+    // take its place in alphabetical order.  Modules with an entry of their own are skipped, and a module matched
+    // by patterns specifying different worlds is reported as an error.
+    //
     // For any packages which contain componentize-py.toml files but no corresponding `module_worlds` entry, we use
     // the `world` parameter as a default.
     let configs: IndexMap<String, (ConfigContext<ComponentizePyConfig>, Option<&str>)> = {
@@ -192,40 +674,98 @@ pub fn search_for_libraries_and_configs<'a>(
             })
             .collect::<Result<HashMap<_, _>>>()?;
 
+        let is_pattern = |module: &str| module.contains(['*', '?']);
+        let exact = module_worlds
+            .iter()
+            .filter(|(module, _)| !is_pattern(module))
+            .map(|(module, _)| *module)
+            .collect::<HashSet<_>>();
+
         let mut ordered = IndexMap::new();
+        let mut matched_by = HashMap::new();
         for (module, world) in module_worlds {
-            if let Some(config) = configs.remove(*module) {
+            if is_pattern(module) {
+                let mut names = configs
+                    .keys()
+                    .chain(matched_by.keys())
+                    .filter(|name| {
+                        !exact.contains(name.as_str()) && glob(module.as_bytes(), name.as_bytes())
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if names.is_empty() {
+                    bail!(
+                        "no `componentize-py.toml` file found for any module matching `{module}`"
+                    );
+                }
+                names.sort();
+
+                for name in names {
+                    if let Some((other, other_world)) = matched_by.get(&name) {
+                        if other_world != world {
+                            bail!(
+                                "module `{name}` matches both `{other}={other_world}` and `{module}={world}`; \
+                                 please specify its world explicitly"
+                            );
+                        }
+                    } else {
+                        let config = configs.remove(&name).unwrap();
+                        matched_by.insert(name.clone(), (*module, *world));
+                        ordered.insert(name, (config, Some(*world)));
+                    }
+                }
+            } else if let Some(config) = configs.remove(*module) {
                 ordered.insert((*module).to_owned(), (config, Some(*world)));
             } else {
                 bail!("no `componentize-py.toml` file found for module `{module}`");
             }
         }
 
-        for (module, config) in configs {
+        // Sort the remainder by module name so the order in which their WIT directories are merged (and thus the
+        // output) doesn't depend on `HashMap` iteration order.
+        let mut rest = configs.into_iter().collect::<Vec<_>>();
+        rest.sort_by(|(a, _), (b, _)| a.cmp(b));
+        for (module, config) in rest {
             ordered.insert(module, (config, world));
         }
 
         ordered
     };
 
-    Ok((configs, libraries))
+    Ok((configs, library_path))
 }
 
 fn search_directory(
     root: &Path,
     path: &Path,
+    excluded: &HashSet<PathBuf>,
     libraries: &mut Vec<PathBuf>,
     configs: &mut Vec<ConfigContext<RawComponentizePyConfig>>,
     modules_seen: &mut HashSet<String>,
 ) -> Result<()> {
+    if excluded.contains(path) {
+        // The app won't be able to see this (see `filtered_view`), so neither should we.
+        return Ok(());
+    }
+
     if path.is_dir() {
-        for entry in fs::read_dir(path).with_context(|| path.display().to_string())? {
-            search_directory(root, &entry?.path(), libraries, configs, modules_seen)?;
+        // Visit entries in sorted order so that the order in which libraries are linked (and thus the output)
+        // doesn't depend on the filesystem.
+        let mut entries = fs::read_dir(path)
+            .with_context(|| path.display().to_string())?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<Result<Vec<_>>>()?;
+        entries.sort();
+        for entry in entries {
+            search_directory(root, &entry, excluded, libraries, configs, modules_seen)?;
         }
     } else if let Some(name) = path.file_name().and_then(|name| name.to_str()) {
         if name.ends_with(NATIVE_EXTENSION_SUFFIX) {
             libraries.push(path.to_owned());
-        } else if name == "componentize-py.toml" {
+        } else if name == "componentize-py.toml" && path.parent() != Some(root) {
+            // Note that a file directly under `root` (e.g. when the project directory is itself part of the Python
+            // path) is project configuration for the command line interface rather than package configuration, so
+            // we skip it.
             let root = root
                 .canonicalize()
                 .with_context(|| root.display().to_string())?;
@@ -294,3 +834,503 @@ fn module_name(root: &Path, path: &Path) -> Option<String> {
         None
     }
 }
+
+/// A pattern matching paths within a `python_path` directory, using a subset of `.gitignore` syntax:
+///
+/// - `*` matches any sequence of characters other than `/`, `?` matches any single character other than `/`, and
+///   `**` matches any number of directories.
+/// - A pattern containing a `/` anywhere but at the end (e.g. `/tests` or `docs/*.py`) is matched against the
+///   whole path relative to the `python_path` directory, while any other pattern (e.g. `*.log`) is matched against
+///   the name of each file and directory at any depth.
+/// - A trailing `/` makes the pattern match only directories.
+/// - A leading `!` re-includes anything matched by an earlier pattern, unless one of its parent directories is
+///   excluded.  Blank lines and lines starting with `#` are ignored.
+struct ExcludePattern {
+    negated: bool,
+    directory_only: bool,
+    anchored: bool,
+    segments: Vec<String>,
+}
+
+impl ExcludePattern {
+    fn parse(pattern: &str) -> Option<Self> {
+        let pattern = pattern.trim();
+        if pattern.is_empty() || pattern.starts_with('#') {
+            return None;
+        }
+
+        let (negated, pattern) = match pattern.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+        let (directory_only, pattern) = match pattern.strip_suffix('/') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+
+        Some(Self {
+            negated,
+            directory_only,
+            anchored: pattern.contains('/'),
+            segments: pattern
+                .trim_start_matches('/')
+                .split('/')
+                .map(str::to_owned)
+                .collect(),
+        })
+    }
+
+    /// Return true if this pattern matches `path`, given as its components relative to the `python_path`
+    /// directory.
+    fn matches(&self, path: &[&str], is_dir: bool) -> bool {
+        if self.directory_only && !is_dir {
+            false
+        } else if self.anchored {
+            match_segments(&self.segments, path)
+        } else {
+            path.last()
+                .is_some_and(|name| glob(self.segments[0].as_bytes(), name.as_bytes()))
+        }
+    }
+}
+
+/// Return true if the last of `patterns` to match `path` (see `ExcludePattern::matches`) excludes it.
+fn is_excluded(patterns: &[ExcludePattern], path: &[&str], is_dir: bool) -> bool {
+    patterns
+        .iter()
+        .rev()
+        .find(|pattern| pattern.matches(path, is_dir))
+        .is_some_and(|pattern| !pattern.negated)
+}
+
+fn match_segments(pattern: &[String], path: &[&str]) -> bool {
+    match (pattern.split_first(), path.split_first()) {
+        (None, None) => true,
+        (Some((first, rest)), _) if first == "**" => {
+            (0..=path.len()).any(|skip| match_segments(rest, &path[skip..]))
+        }
+        (Some((first, rest)), Some((name, path))) => {
+            glob(first.as_bytes(), name.as_bytes()) && match_segments(rest, path)
+        }
+        _ => false,
+    }
+}
+
+fn glob(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.split_first(), name.split_first()) {
+        (None, None) => true,
+        (Some((b'*', rest)), _) => (0..=name.len()).any(|skip| glob(rest, &name[skip..])),
+        (Some((b'?', rest)), Some((_, name))) => glob(rest, name),
+        (Some((expected, rest)), Some((actual, name))) => expected == actual && glob(rest, name),
+        _ => false,
+    }
+}
+
+/// Find the paths within each `python_path` directory which should be hidden from the app.
+///
+/// Each directory is matched against `DEFAULT_EXCLUDE_PATTERNS`, then the patterns in its `.componentizeignore`
+/// file (if any), then `patterns`, with the last matching pattern deciding whether a path is excluded.  Only the
+/// top-most excluded directory is listed, not its contents.
+pub fn find_excluded_paths(python_path: &[&str], patterns: &[&str]) -> Result<ExcludedPaths> {
+    fn visit(
+        dir: &Path,
+        relative: &[&str],
+        patterns: &[ExcludePattern],
+        excluded: &mut HashSet<PathBuf>,
+    ) -> Result<()> {
+        for entry in fs::read_dir(dir).with_context(|| dir.display().to_string())? {
+            let path = entry?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let relative = relative.iter().copied().chain([name]).collect::<Vec<_>>();
+            let is_dir = path.is_dir();
+
+            if is_excluded(patterns, &relative, is_dir) {
+                excluded.insert(path);
+            } else if is_dir {
+                visit(&path, &relative, patterns, excluded)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    python_path
+        .iter()
+        .map(|root| {
+            let root = Path::new(root);
+            let ignore_file = root.join(IGNORE_FILE);
+            let ignored = if ignore_file.is_file() {
+                fs::read_to_string(&ignore_file)
+                    .with_context(|| ignore_file.display().to_string())?
+            } else {
+                String::new()
+            };
+
+            let patterns = DEFAULT_EXCLUDE_PATTERNS
+                .iter()
+                .copied()
+                .chain(ignored.lines())
+                .chain(patterns.iter().copied())
+                .filter_map(ExcludePattern::parse)
+                .collect::<Vec<_>>();
+
+            let mut excluded = HashSet::new();
+            if root.is_dir() {
+                visit(root, &[], &patterns, &mut excluded)?;
+            }
+            Ok(excluded)
+        })
+        .collect()
+}
+
+/// Return the host directories to mount in place of the `python_path` directory `path` so that the app can't see
+/// the `excluded` paths beneath it, each paired with its guest path relative to where `path` itself is mounted,
+/// plus the temporary directory holding the staged part of the view, if any.
+///
+/// WASI resolves each guest path using the longest matching mount, so rather than copying the whole directory, we
+/// stage only those directories which have something excluded beneath them, hard-linking their files (or copying
+/// them, e.g. if the temporary directory is on a different filesystem) and mounting each of their remaining
+/// subdirectories as it is.  If nothing beneath `path` is excluded, it is mounted as it is.
+pub fn filtered_view(
+    path: &str,
+    excluded: &HashSet<PathBuf>,
+    keep_temp: bool,
+) -> Result<(Vec<(PathBuf, String)>, Option<TempDir>)> {
+    fn stage(
+        src: &Path,
+        dst: &Path,
+        relative: &str,
+        excluded: &HashSet<PathBuf>,
+        staged: &HashSet<&Path>,
+        mounts: &mut Vec<(PathBuf, String)>,
+    ) -> Result<()> {
+        for entry in fs::read_dir(src).with_context(|| src.display().to_string())? {
+            let path = entry?.path();
+            if excluded.contains(&path) {
+                continue;
+            }
+
+            let name = path.file_name().unwrap();
+            let target = dst.join(name);
+            if path.is_dir() {
+                fs::create_dir(&target).with_context(|| target.display().to_string())?;
+                let relative = format!(
+                    "{relative}/{}",
+                    name.to_str()
+                        .ok_or_else(|| anyhow!("non-UTF-8 path: {}", path.display()))?
+                );
+                if staged.contains(path.as_path()) {
+                    stage(&path, &target, &relative, excluded, staged, mounts)?;
+                } else {
+                    // `target` stays empty, but it makes the directory visible when listing its parent.
+                    mounts.push((path, relative));
+                }
+            } else if fs::hard_link(&path, &target).is_err() {
+                fs::copy(&path, &target).with_context(|| path.display().to_string())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    let root = Path::new(path);
+    if excluded.is_empty() {
+        return Ok((vec![(root.to_owned(), String::new())], None));
+    }
+
+    let staged = excluded
+        .iter()
+        .flat_map(|path| path.ancestors().skip(1))
+        .filter(|path| path.starts_with(root))
+        .collect::<HashSet<_>>();
+
+    let dir = temp::dir(keep_temp)?;
+    let mut mounts = vec![(dir.path().to_owned(), String::new())];
+    stage(root, dir.path(), "", excluded, &staged, &mut mounts)?;
+    Ok((mounts, Some(dir)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn excluded(patterns: &[&str], path: &str, is_dir: bool) -> bool {
+        let patterns = patterns
+            .iter()
+            .filter_map(|pattern| ExcludePattern::parse(pattern))
+            .collect::<Vec<_>>();
+        is_excluded(&patterns, &path.split('/').collect::<Vec<_>>(), is_dir)
+    }
+
+    #[test]
+    fn exclude_patterns() {
+        assert!(excluded(&["__pycache__/"], "pkg/__pycache__", true));
+        assert!(!excluded(&["__pycache__/"], "pkg/__pycache__", false));
+        assert!(excluded(&["*.log"], "pkg/debug.log", false));
+        assert!(!excluded(&["*.log"], "pkg/debug.log.py", false));
+        assert!(excluded(&["/tests/"], "tests", true));
+        assert!(!excluded(&["/tests/"], "pkg/tests", true));
+        assert!(excluded(&["docs/*.md"], "docs/index.md", false));
+        assert!(!excluded(&["docs/*.md"], "docs/api/index.md", false));
+        assert!(excluded(&["docs/**/*.md"], "docs/api/index.md", false));
+        assert!(excluded(&["docs/**/*.md"], "docs/index.md", false));
+        assert!(excluded(&["data_?.bin"], "data_1.bin", false));
+        assert!(!excluded(&["/tests/", "!/tests/"], "tests", true));
+        assert!(!excluded(&["# /tests/"], "tests", true));
+    }
+
+    #[test]
+    fn excluded_paths_found_and_skipped() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path();
+        fs::create_dir_all(root.join("app/__pycache__"))?;
+        fs::create_dir_all(root.join("tests"))?;
+        fs::create_dir_all(root.join("fixtures"))?;
+        fs::write(root.join("app/__init__.py"), "")?;
+        fs::write(root.join("app/__pycache__/__init__.cpython-312.pyc"), "")?;
+        fs::write(root.join("tests/test_app.py"), "")?;
+        fs::write(root.join("fixtures/big.json"), "")?;
+        fs::create_dir_all(root.join("fixtures/sdk"))?;
+        fs::write(root.join("fixtures/sdk/componentize-py.toml"), "")?;
+        fs::write(root.join("notes.txt"), "")?;
+        fs::write(root.join(IGNORE_FILE), "# Test data\n/fixtures/\n")?;
+
+        let root_str = root.to_str().unwrap();
+        let excluded = find_excluded_paths(&[root_str], &["*.txt"])?;
+
+        assert_eq!(
+            [
+                root.join("app/__pycache__"),
+                root.join("fixtures"),
+                root.join("notes.txt"),
+            ]
+            .into_iter()
+            .collect::<HashSet<_>>(),
+            excluded[0]
+        );
+
+        // Configuration beneath an excluded directory isn't found.
+        let (configs, _) = search_for_libraries_and_configs(&vec![root_str], &excluded, &[], None)?;
+        assert!(configs.is_empty());
+
+        // Only directories with something excluded beneath them are staged; the rest are mounted as they are.
+        let (mounts, staged) = filtered_view(root_str, &excluded[0], false)?;
+        let staged = staged.unwrap();
+        let mut mounts = mounts
+            .into_iter()
+            .map(|(host, guest)| (guest, host))
+            .collect::<Vec<_>>();
+        mounts.sort();
+        assert_eq!(
+            vec![
+                (String::new(), staged.path().to_owned()),
+                ("/tests".to_owned(), root.join("tests")),
+            ],
+            mounts
+        );
+        assert!(staged.path().join("app/__init__.py").exists());
+        assert!(staged.path().join(IGNORE_FILE).exists());
+        assert!(staged.path().join("tests").is_dir());
+        assert!(!staged.path().join("app/__pycache__").exists());
+        assert!(!staged.path().join("fixtures").exists());
+        assert!(!staged.path().join("notes.txt").exists());
+
+        Ok(())
+    }
+
+    #[test]
+    fn module_world_patterns_expanded() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        for module in ["billing_service", "auth_service", "legacy_service", "sdk"] {
+            fs::create_dir_all(dir.path().join(module))?;
+            fs::write(dir.path().join(module).join("componentize-py.toml"), "")?;
+        }
+        let root = dir.path().to_str().unwrap();
+        let python_path = vec![root];
+        let excluded = vec![HashSet::new()];
+
+        // Matching modules are listed in alphabetical order in place of the pattern, skipping those with their own
+        // entry.
+        let (configs, _) = search_for_libraries_and_configs(
+            &python_path,
+            &excluded,
+            &[
+                ("sdk", "sdk-world"),
+                ("*_service", "service-world"),
+                ("legacy_service", "legacy-world"),
+            ],
+            None,
+        )?;
+        assert_eq!(
+            vec![
+                ("sdk", Some("sdk-world")),
+                ("auth_service", Some("service-world")),
+                ("billing_service", Some("service-world")),
+                ("legacy_service", Some("legacy-world")),
+            ],
+            configs
+                .iter()
+                .map(|(module, (_, world))| (module.as_str(), *world))
+                .collect::<Vec<_>>()
+        );
+
+        // Patterns assigning different worlds to the same module are reported.
+        let error = search_for_libraries_and_configs(
+            &python_path,
+            &excluded,
+            &[("*_service", "service-world"), ("auth_*", "auth-world")],
+            None,
+        )
+        .err()
+        .unwrap();
+        assert!(error.to_string().contains(
+            "module `auth_service` matches both `*_service=service-world` and `auth_*=auth-world`"
+        ));
+
+        // As are patterns which match nothing.
+        assert!(search_for_libraries_and_configs(
+            &python_path,
+            &excluded,
+            &[("*_worker", "worker-world")],
+            None
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    /// Make a shared library importing and exporting the specified functions.
+    fn library(imports: &[&str], exports: &[&str]) -> Vec<u8> {
+        use wasm_encoder::{
+            CodeSection, EntityType, ExportKind, ExportSection, Function, FunctionSection,
+            ImportSection, Instruction, Module, TypeSection,
+        };
+
+        let mut types = TypeSection::new();
+        types.ty().function([], []);
+        let mut import_section = ImportSection::new();
+        for name in imports {
+            import_section.import("env", name, EntityType::Function(0));
+        }
+        let mut functions = FunctionSection::new();
+        let mut export_section = ExportSection::new();
+        let mut code = CodeSection::new();
+        for (index, name) in exports.iter().enumerate() {
+            functions.function(0);
+            export_section.export(
+                name,
+                ExportKind::Func,
+                u32::try_from(imports.len() + index).unwrap(),
+            );
+            let mut function = Function::new([]);
+            function.instruction(&Instruction::End);
+            code.function(&function);
+        }
+
+        let mut module = Module::new();
+        module
+            .section(&types)
+            .section(&import_section)
+            .section(&functions)
+            .section(&export_section)
+            .section(&code);
+        module.finish()
+    }
+
+    #[test]
+    fn linking_overridden() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir_all(dir.path().join("pkg.libs"))?;
+        fs::write(
+            dir.path().join("pkg.libs/libfoo.so"),
+            library(&[], &["foo"]),
+        )?;
+        fs::write(
+            dir.path().join("pkg.libs/libbar.so"),
+            library(&[], &["bar"]),
+        )?;
+        let root = dir.path().to_str().unwrap();
+        let excluded = vec![HashSet::new()];
+        let extension = || Library {
+            name: format!("/0/pkg/_ext{NATIVE_EXTENSION_SUFFIX}"),
+            module: library(&["foo"], &["PyInit__ext"]),
+            dl_openable: true,
+        };
+
+        // A vendored library is only linked if matched, and an embedded library may be made dynamic.
+        let mut libraries = vec![
+            Library {
+                name: "libc.so".into(),
+                module: library(&[], &["malloc"]),
+                dl_openable: false,
+            },
+            extension(),
+        ];
+        override_linking(
+            &mut libraries,
+            &[root],
+            &excluded,
+            &["pkg.libs/libfoo.so"],
+            &["libc.so"],
+        )?;
+        assert_eq!(
+            vec![
+                ("libc.so".to_owned(), true),
+                (format!("/0/pkg/_ext{NATIVE_EXTENSION_SUFFIX}"), true),
+                ("/0/pkg.libs/libfoo.so".to_owned(), false),
+            ],
+            libraries
+                .iter()
+                .map(|library| (library.name.clone(), library.dl_openable))
+                .collect::<Vec<_>>()
+        );
+
+        let error = |static_libraries: &[&str], dynamic_libraries: &[&str]| {
+            override_linking(
+                &mut vec![extension()],
+                &[root],
+                &excluded,
+                static_libraries,
+                dynamic_libraries,
+            )
+            .unwrap_err()
+            .to_string()
+        };
+        assert!(error(&["_ext*"], &[]).contains("native extension"));
+        assert!(error(&["libbar.so"], &[]).contains("no other library imports"));
+        assert!(error(&["libqux.so"], &[]).contains("did not match"));
+        assert!(error(&["libfoo.so"], &["lib*.so"]).contains("both"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn zipapp_extracted() -> Result<()> {
+        use std::io::Write;
+
+        let dir = tempfile::tempdir()?;
+        let pyz = dir.path().join("app.pyz");
+
+        // Like `python -m zipapp -p ...`, write the shebang line followed by an archive whose offsets are relative
+        // to its own start rather than that of the file.
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in [("__main__.py", "import app\n"), ("app.py", "x = 42\n")] {
+            writer.start_file(name, zip::write::SimpleFileOptions::default())?;
+            writer.write_all(contents.as_bytes())?;
+        }
+        let mut file = b"#!/usr/bin/env python3\n".to_vec();
+        file.extend(writer.finish()?.into_inner());
+        fs::write(&pyz, file)?;
+
+        let extracted = extract_archives(&[pyz.to_str().unwrap()])?;
+
+        assert_eq!(
+            "x = 42\n",
+            fs::read_to_string(Path::new(&extracted[0]).join("app.py"))?
+        );
+
+        Ok(())
+    }
+}
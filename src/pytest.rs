@@ -0,0 +1,284 @@
+//! Support for `componentize-py test`, which runs an app's pytest suite inside a component built from its world.
+//!
+//! The component is built with `componentize-py:pytest/runner` added to the world's exports and
+//! `componentize-py:pytest/reporter` added to its imports (see `pytest.wit`), using a generated app module which
+//! re-exports the app's own classes and has the bundled `componentize_py_pytest` module collect the tests during
+//! pre-initialization.  We then run the component with the Python path mounted just as it was during
+//! pre-initialization, so pytest can still find the test files, and gather the outcome of each test as the guest
+//! reports it.
+
+use {
+    crate::{prelink, source::Source, temp, ComponentizeBuilder},
+    anyhow::{Context, Result},
+    async_trait::async_trait,
+    bindings::{
+        componentize_py::pytest::reporter::{self, Outcome, Report},
+        TestRunnerPre,
+    },
+    std::{env, fs, path::Path},
+    wasmtime::{
+        component::{Component, Linker, ResourceTable},
+        Config, Engine, Store,
+    },
+    wasmtime_wasi::{DirPerms, FilePerms, WasiCtx, WasiCtxBuilder, WasiView},
+};
+
+mod bindings {
+    wasmtime::component::bindgen!({
+        path: "src/pytest.wit",
+        world: "test-runner",
+        async: true,
+    });
+}
+
+/// Name of the generated module used as the app
+const APP_MODULE: &str = "componentize_py_test_app";
+
+struct State {
+    wasi: WasiCtx,
+    table: ResourceTable,
+    reports: Vec<Report>,
+}
+
+impl WasiView for State {
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.wasi
+    }
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+}
+
+#[async_trait]
+impl reporter::Host for State {
+    async fn report(&mut self, report: Report) {
+        self.reports.push(report);
+    }
+}
+
+/// Implementation of `ComponentizeBuilder::test`.
+pub async fn run(
+    builder: ComponentizeBuilder<'_>,
+    app: Option<&str>,
+    args: &[&str],
+    junit_xml: Option<&Path>,
+) -> Result<i32> {
+    // The runtime mounts each Python path directory at `/0`, `/1`, etc., so extract archives and skip missing
+    // directories just as `ComponentizeBuilder::build` does to arrive at the same numbering.
+    let mut python_path = prelink::extract_archives(builder.python_path)?
+        .into_iter()
+        .filter(|path| Path::new(path).exists())
+        .collect::<Vec<_>>();
+
+    let current_dir = env::current_dir()?.canonicalize()?;
+    let index = if let Some(index) = python_path.iter().position(|path| {
+        Path::new(path).canonicalize().ok().as_deref() == Some(current_dir.as_path())
+    }) {
+        index
+    } else {
+        python_path.push(
+            current_dir
+                .to_str()
+                .context("non-UTF-8 current directory name")?
+                .to_owned(),
+        );
+        python_path.len() - 1
+    };
+
+    let app_dir = temp::dir(builder.keep_temp)?;
+    fs::write(
+        app_dir.path().join(format!("{APP_MODULE}.py")),
+        app_module(app, &format!("/{index}"), args)?,
+    )?;
+    python_path.push(
+        app_dir
+            .path()
+            .to_str()
+            .context("non-UTF-8 temporary directory name")?
+            .to_owned(),
+    );
+
+    let output_dir = temp::dir(builder.keep_temp)?;
+    let output_path = output_dir.path().join("tests.wasm");
+
+    {
+        let python_path = python_path.iter().map(String::as_str).collect::<Vec<_>>();
+        // Tests often import standard library modules lazily (as does pytest itself), so make all of them
+        // available rather than only those imported while collecting the tests.
+        ComponentizeBuilder {
+            python_path: &python_path,
+            app_name: APP_MODULE,
+            output_path: &output_path,
+            embed_stdlib: true,
+            pytest: true,
+            ..builder
+        }
+        .build()
+        .await?;
+    }
+
+    let mut config = Config::new();
+    config.wasm_component_model(true);
+    config.async_support(true);
+
+    let engine = Engine::new(&config)?;
+    let component = Component::from_file(&engine, &output_path)?;
+
+    let mut linker = Linker::new(&engine);
+    wasmtime_wasi::add_to_linker_async(&mut linker)?;
+    bindings::TestRunner::add_to_linker(&mut linker, |state: &mut State| state)?;
+    // Nothing implements the world's own imports here, so any test which calls one will fail with a trap.
+    linker.define_unknown_imports_as_traps(&component)?;
+
+    let mut wasi = WasiCtxBuilder::new();
+    wasi.inherit_stdout().inherit_stderr();
+    for (index, path) in python_path.iter().enumerate() {
+        wasi.preopened_dir(path, index.to_string(), DirPerms::all(), FilePerms::all())?;
+    }
+
+    let mut store = Store::new(
+        &engine,
+        State {
+            wasi: wasi.build(),
+            table: ResourceTable::new(),
+            reports: Vec::new(),
+        },
+    );
+
+    let runner = TestRunnerPre::new(linker.instantiate_pre(&component)?)?
+        .instantiate_async(&mut store)
+        .await?;
+    let code = runner
+        .componentize_py_pytest_runner()
+        .call_run(&mut store)
+        .await?;
+
+    if let Some(path) = junit_xml {
+        fs::write(path, junit(&store.data().reports))
+            .with_context(|| path.display().to_string())?;
+    }
+
+    Ok(code)
+}
+
+/// Generate the module used as the app, which imports everything from `app` (if specified) so the runtime can
+/// find the classes implementing the world's exports, then collects the tests from `directory` (a guest path)
+/// using `args`.
+fn app_module(app: Option<&str>, directory: &str, args: &[&str]) -> Result<String> {
+    let mut source = Source::default();
+    source.docstring(Some(
+        "The app module for `componentize-py test`, generated by `componentize-py`.",
+    ));
+    source.blank_line();
+    if let Some(app) = app {
+        source.line(format!("from {app} import *"));
+    }
+    source.line("import componentize_py_pytest");
+    source.blank_line();
+    // JSON strings and arrays of them are valid Python literals.
+    source.line(format!(
+        "componentize_py_pytest.collect({}, {})",
+        serde_json::to_string(directory)?,
+        serde_json::to_string(args)?
+    ));
+    Ok(source.into())
+}
+
+/// Describe `reports` using the JUnit XML format, naming each test case as pytest's own `--junitxml` option does.
+fn junit(reports: &[Report]) -> String {
+    let count = |outcome| {
+        reports
+            .iter()
+            .filter(|report| report.outcome == outcome)
+            .count()
+    };
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<testsuites>\n");
+    xml.push_str(&format!(
+        "  <testsuite name=\"pytest\" errors=\"0\" failures=\"{}\" skipped=\"{}\" tests=\"{}\" time=\"{:.3}\">\n",
+        count(Outcome::Failed),
+        count(Outcome::Skipped),
+        reports.len(),
+        reports.iter().map(|report| report.duration).sum::<f64>()
+    ));
+
+    for report in reports {
+        // E.g. `tests/test_app.py::TestAdd::test_zero` becomes `test_zero` in class `tests.test_app.TestAdd`.
+        let mut parts = report.node_id.split("::").collect::<Vec<_>>();
+        let name = parts.pop().unwrap_or_default();
+        let classname = parts
+            .iter()
+            .enumerate()
+            .map(|(index, part)| {
+                if index == 0 {
+                    part.trim_end_matches(".py").replace('/', ".")
+                } else {
+                    (*part).to_owned()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(".");
+
+        xml.push_str(&format!(
+            "    <testcase classname=\"{}\" name=\"{}\" time=\"{:.3}\"",
+            escape(&classname),
+            escape(name),
+            report.duration
+        ));
+
+        let message = report.message.as_deref().unwrap_or_default();
+        match report.outcome {
+            Outcome::Passed => xml.push_str("/>\n"),
+            Outcome::Skipped => xml.push_str(&format!(
+                ">\n      <skipped message=\"{}\"/>\n    </testcase>\n",
+                escape(message)
+            )),
+            Outcome::Failed => xml.push_str(&format!(
+                ">\n      <failure message=\"{}\">{}</failure>\n    </testcase>\n",
+                escape(message.lines().last().unwrap_or_default()),
+                escape(message)
+            )),
+        }
+    }
+
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+/// Escape `text` for use in XML content or a double-quoted attribute.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn junit_names() {
+        let xml = junit(&[
+            Report {
+                node_id: "tests/test_app.py::TestAdd::test_zero".into(),
+                outcome: Outcome::Passed,
+                duration: 0.5,
+                message: None,
+            },
+            Report {
+                node_id: "test_other.py::test_fails".into(),
+                outcome: Outcome::Failed,
+                duration: 0.25,
+                message: Some("assert 1 < 0\nE   AssertionError".into()),
+            },
+        ]);
+
+        assert!(xml.contains("failures=\"1\" skipped=\"0\" tests=\"2\" time=\"0.750\""));
+        assert!(xml.contains("classname=\"tests.test_app.TestAdd\" name=\"test_zero\""));
+        assert!(xml.contains("classname=\"test_other\" name=\"test_fails\""));
+        assert!(xml.contains(
+            "<failure message=\"E   AssertionError\">assert 1 &lt; 0\nE   AssertionError</failure>"
+        ));
+    }
+}
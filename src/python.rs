@@ -1,19 +1,26 @@
 use {
+    crate::{
+        optimize,
+        output::{Callbacks, OutputCallback, Phase},
+        BindingsFormat, NamingStyle, Notices, Profile, TypingStyle, VersionCollisions,
+    },
+    clap::ValueEnum,
     pyo3::{
         exceptions::PyAssertionError,
         pybacked::PyBackedStr,
-        types::{PyAnyMethods, PyModule, PyModuleMethods},
-        Bound, PyResult, Python,
+        types::{PyAnyMethods, PyBytes, PyModule, PyModuleMethods},
+        Bound, IntoPy, PyObject, PyResult, Python,
     },
-    std::{ffi::OsString, path::PathBuf},
+    std::{ffi::OsString, path::PathBuf, sync::Arc, time::Duration},
     tokio::runtime::Runtime,
 };
 
 #[allow(clippy::too_many_arguments)]
 #[pyo3::pyfunction]
 #[pyo3(name = "componentize")]
-#[pyo3(signature = (wit_path, world, features, all_features, python_path, module_worlds, app_name, output_path, stub_wasi, import_interface_names, export_interface_names))]
+#[pyo3(signature = (wit_path, world, features, all_features, python_path, module_worlds, app_name, output_path, stub_wasi, import_interface_names, export_interface_names, map_source_paths=false, quiet=true, exclude_modules=Vec::new(), include_modules=Vec::new(), exclude_paths=Vec::new(), static_libraries=Vec::new(), dynamic_libraries=Vec::new(), strip_debug=false, compression=None, size_report=false, exception_mappings=Vec::new(), diagnostics=false, strict=false, trace_imports=false, shutdown_hook=false, unbuffered=false, embed_stdlib=false, compose=Vec::new(), wasi_adapter=None, import_time_budget=None, pre_init_timeout=None, output_capture_limit=None, metadata=Vec::new(), notices=None, primary_package=None, deterministic=false, keep_temp=false, naming_style=None, version_collisions=None, pre_init_hook=None, artifact_dir=None, profile=None, stdout=None, stderr=None, progress=None, cache=true))]
 fn python_componentize(
+    py: Python,
     wit_path: Option<PathBuf>,
     world: Option<&str>,
     features: Vec<String>,
@@ -25,39 +32,164 @@ fn python_componentize(
     stub_wasi: bool,
     import_interface_names: Vec<(PyBackedStr, PyBackedStr)>,
     export_interface_names: Vec<(PyBackedStr, PyBackedStr)>,
+    map_source_paths: bool,
+    quiet: bool,
+    exclude_modules: Vec<PyBackedStr>,
+    include_modules: Vec<PyBackedStr>,
+    exclude_paths: Vec<PyBackedStr>,
+    static_libraries: Vec<PyBackedStr>,
+    dynamic_libraries: Vec<PyBackedStr>,
+    strip_debug: bool,
+    compression: Option<i32>,
+    size_report: bool,
+    exception_mappings: Vec<(PyBackedStr, PyBackedStr)>,
+    diagnostics: bool,
+    strict: bool,
+    trace_imports: bool,
+    shutdown_hook: bool,
+    unbuffered: bool,
+    embed_stdlib: bool,
+    compose: Vec<PathBuf>,
+    wasi_adapter: Option<PathBuf>,
+    import_time_budget: Option<f64>,
+    pre_init_timeout: Option<f64>,
+    output_capture_limit: Option<usize>,
+    metadata: Vec<(PyBackedStr, PyBackedStr)>,
+    notices: Option<&str>,
+    primary_package: Option<&str>,
+    deterministic: bool,
+    keep_temp: bool,
+    naming_style: Option<&str>,
+    version_collisions: Option<&str>,
+    pre_init_hook: Option<&str>,
+    artifact_dir: Option<PathBuf>,
+    profile: Option<&str>,
+    stdout: Option<PyObject>,
+    stderr: Option<PyObject>,
+    progress: Option<PyObject>,
+    cache: bool,
 ) -> PyResult<()> {
-    (|| {
-        Runtime::new()?.block_on(crate::componentize(
-            wit_path.as_deref(),
-            world,
-            &features,
-            all_features,
-            &python_path.iter().map(|s| s.as_ref()).collect::<Vec<_>>(),
-            &module_worlds
-                .iter()
-                .map(|(a, b)| (a.as_ref(), b.as_ref()))
-                .collect::<Vec<_>>(),
-            app_name,
-            &output_path,
-            None,
-            stub_wasi,
-            &import_interface_names
-                .iter()
-                .map(|(a, b)| (a.as_ref(), b.as_ref()))
-                .collect(),
-            &export_interface_names
-                .iter()
-                .map(|(a, b)| (a.as_ref(), b.as_ref()))
-                .collect(),
-        ))
-    })()
+    let naming_style = parse_naming_style(naming_style)?;
+    let version_collisions = parse_version_collisions(version_collisions)?;
+    let profile = parse_profile(profile)?;
+    let notices = parse_notices(notices)?;
+    let import_time_budget = import_time_budget.map(parse_seconds).transpose()?;
+    let pre_init_timeout = pre_init_timeout.map(parse_seconds).transpose()?;
+    let callbacks = Callbacks {
+        stdout: stdout.map(output_callback),
+        stderr: stderr.map(output_callback),
+        progress: progress.map(|function| {
+            Arc::new(move |phase: Phase| {
+                Python::with_gil(|py| call(py, &function, phase.to_string()))
+            }) as _
+        }),
+    };
+
+    py.allow_threads(|| {
+        Runtime::new()?.block_on(
+            crate::ComponentizeBuilder {
+                wit_path: wit_path.as_deref(),
+                world,
+                primary_package,
+                features: &features,
+                all_features,
+                python_path: &python_path.iter().map(|s| s.as_ref()).collect::<Vec<_>>(),
+                exclude_paths: &exclude_paths.iter().map(|s| s.as_ref()).collect::<Vec<_>>(),
+                static_libraries: &static_libraries
+                    .iter()
+                    .map(|s| s.as_ref())
+                    .collect::<Vec<_>>(),
+                dynamic_libraries: &dynamic_libraries
+                    .iter()
+                    .map(|s| s.as_ref())
+                    .collect::<Vec<_>>(),
+                module_worlds: &module_worlds
+                    .iter()
+                    .map(|(a, b)| (a.as_ref(), b.as_ref()))
+                    .collect::<Vec<_>>(),
+                app_name,
+                output_path: &output_path,
+                add_to_linker: None,
+                stub_wasi,
+                profile,
+                import_interface_names: import_interface_names
+                    .iter()
+                    .map(|(a, b)| (a.as_ref(), b.as_ref()))
+                    .collect(),
+                export_interface_names: export_interface_names
+                    .iter()
+                    .map(|(a, b)| (a.as_ref(), b.as_ref()))
+                    .collect(),
+                naming_style,
+                version_collisions,
+                map_source_paths,
+                quiet,
+                optimize: optimize::Options {
+                    exclude_modules: &exclude_modules
+                        .iter()
+                        .map(|s| s.as_ref())
+                        .collect::<Vec<_>>(),
+                    include_modules: &include_modules
+                        .iter()
+                        .map(|s| s.as_ref())
+                        .collect::<Vec<_>>(),
+                    strip_debug,
+                    compression,
+                    size_report,
+                },
+                exception_mappings: &exception_mappings
+                    .iter()
+                    .map(|(a, b)| (a.as_ref(), b.as_ref()))
+                    .collect::<Vec<_>>(),
+                diagnostics,
+                strict,
+                trace_imports,
+                shutdown_hook,
+                unbuffered,
+                embed_stdlib,
+                compose: &compose
+                    .iter()
+                    .map(|path| path.as_path())
+                    .collect::<Vec<_>>(),
+                wasi_adapter: wasi_adapter.as_deref(),
+                artifact_dir: artifact_dir.as_deref(),
+                import_time_budget,
+                pre_init_timeout,
+                pre_init_hook,
+                output_capture_limit,
+                metadata: &metadata
+                    .iter()
+                    .map(|(a, b)| (a.as_ref(), b.as_ref()))
+                    .collect::<Vec<_>>(),
+                notices,
+                deterministic,
+                keep_temp,
+                cache,
+                callbacks,
+                ..crate::ComponentizeBuilder::new()
+            }
+            .build(),
+        )
+    })
     .map_err(|e| PyAssertionError::new_err(format!("{e:?}")))
 }
 
+fn output_callback(function: PyObject) -> OutputCallback {
+    Arc::new(move |bytes| Python::with_gil(|py| call(py, &function, PyBytes::new_bound(py, bytes))))
+}
+
+/// Call a user-supplied callback, reporting (rather than propagating) any exception it raises, since there's no
+/// sensible way to abort the build from within an output pipe.
+fn call(py: Python, function: &PyObject, arg: impl IntoPy<PyObject>) {
+    if let Err(e) = function.call1(py, (arg,)) {
+        e.write_unraisable_bound(py, Some(function.bind(py)));
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 #[pyo3::pyfunction]
 #[pyo3(name = "generate_bindings")]
-#[pyo3(signature = (wit_path, world, features, all_features, world_module, output_dir, import_interface_names, export_interface_names))]
+#[pyo3(signature = (wit_path, world, features, all_features, world_module, output_dir, import_interface_names, export_interface_names, modern_typing=false, primary_package=None, naming_style=None, version_collisions=None, wheel=false))]
 fn python_generate_bindings(
     wit_path: PathBuf,
     world: Option<&str>,
@@ -67,10 +199,16 @@ fn python_generate_bindings(
     output_dir: PathBuf,
     import_interface_names: Vec<(PyBackedStr, PyBackedStr)>,
     export_interface_names: Vec<(PyBackedStr, PyBackedStr)>,
+    modern_typing: bool,
+    primary_package: Option<&str>,
+    naming_style: Option<&str>,
+    version_collisions: Option<&str>,
+    wheel: bool,
 ) -> PyResult<()> {
     crate::generate_bindings(
         &wit_path,
         world,
+        primary_package,
         &features,
         all_features,
         world_module,
@@ -83,10 +221,58 @@ fn python_generate_bindings(
             .iter()
             .map(|(a, b)| (a.as_ref(), b.as_ref()))
             .collect(),
+        if modern_typing {
+            TypingStyle::Modern
+        } else {
+            TypingStyle::Legacy
+        },
+        parse_naming_style(naming_style)?,
+        parse_version_collisions(version_collisions)?,
+        if wheel {
+            BindingsFormat::Wheel
+        } else {
+            BindingsFormat::Directory
+        },
     )
+    .map(drop)
     .map_err(|e| PyAssertionError::new_err(format!("{e:?}")))
 }
 
+/// Parse a `naming_style` argument, accepting the same names as the `--naming-style` command line option.
+fn parse_naming_style(name: Option<&str>) -> PyResult<NamingStyle> {
+    name.map(|name| NamingStyle::from_str(name, true))
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .map_err(PyAssertionError::new_err)
+}
+
+/// Parse a `version_collisions` argument, accepting the same names as the `--version-collisions` command line
+/// option.
+fn parse_version_collisions(name: Option<&str>) -> PyResult<VersionCollisions> {
+    name.map(|name| VersionCollisions::from_str(name, true))
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .map_err(PyAssertionError::new_err)
+}
+
+/// Parse a `profile` argument, accepting the same names as the `--profile` command line option.
+fn parse_profile(name: Option<&str>) -> PyResult<Profile> {
+    name.map(|name| Profile::from_str(name, true))
+        .transpose()
+        .map(Option::unwrap_or_default)
+        .map_err(PyAssertionError::new_err)
+}
+
+fn parse_notices(mode: Option<&str>) -> PyResult<Option<Notices>> {
+    mode.map(|mode| Notices::from_str(mode, true))
+        .transpose()
+        .map_err(PyAssertionError::new_err)
+}
+
+fn parse_seconds(seconds: f64) -> PyResult<Duration> {
+    Duration::try_from_secs_f64(seconds).map_err(|e| PyAssertionError::new_err(e.to_string()))
+}
+
 #[pyo3::pyfunction]
 #[pyo3(name = "script")]
 fn python_script(py: Python) -> PyResult<()> {
@@ -0,0 +1,164 @@
+use {
+    crate::output::{InitFailure, Phase},
+    anyhow::Error,
+    serde::Serialize,
+    std::fmt,
+};
+
+/// How errors should be reported by the CLI
+#[derive(clap::ValueEnum, Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// Human-readable text, including the full chain of causes
+    #[default]
+    Human,
+    /// A single line of JSON per error, for consumption by CI systems and other tools
+    Json,
+}
+
+/// Machine-readable description of an error, as printed when using `ErrorFormat::Json`
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The phase of the build during which the error occurred, if known (e.g. `pre-initializing`)
+    pub phase: Option<String>,
+    /// The top-level error message
+    pub message: String,
+    /// Any underlying causes of the error, outermost first
+    pub causes: Vec<String>,
+    /// The Python traceback printed by the app, if the error was due to an uncaught exception during
+    /// pre-initialization
+    pub traceback: Option<String>,
+    /// The location in a WIT file to which the error refers, if any
+    pub location: Option<Location>,
+}
+
+#[derive(Serialize, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl Diagnostic {
+    pub fn new(error: &Error, phase: Option<Phase>) -> Self {
+        let failure = error.downcast_ref::<InitFailure>();
+
+        // Output captured from the app is reported via `traceback` rather than as part of the message.
+        let output = failure.map(|failure| failure.to_string());
+        let mut messages = error
+            .chain()
+            .map(|cause| cause.to_string())
+            .filter(|message| Some(message) != output.as_ref());
+
+        let traceback = failure.and_then(|failure| {
+            let stderr = String::from_utf8_lossy(&failure.stderr);
+            let start = stderr.find("Traceback (most recent call last):")?;
+            Some(stderr[start..].trim_end().to_owned())
+        });
+
+        let location = error
+            .chain()
+            .find_map(|cause| parse_location(&cause.to_string()));
+
+        Self {
+            phase: phase.map(|phase| phase.to_string()),
+            message: messages.next().unwrap_or_default(),
+            causes: messages.collect(),
+            traceback,
+            location,
+        }
+    }
+}
+
+/// Find the location (if any) highlighted by a `wit-parser` error message, e.g. `--> foo.wit:3:9`.
+fn parse_location(message: &str) -> Option<Location> {
+    message.lines().find_map(|line| {
+        let location = line.trim_start().strip_prefix("--> ")?;
+        let (location, column) = location.trim_end().rsplit_once(':')?;
+        let (file, line) = location.rsplit_once(':')?;
+        Some(Location {
+            file: file.to_owned(),
+            line: line.parse().ok()?,
+            column: column.parse().ok()?,
+        })
+    })
+}
+
+/// An error which `command::run` has already printed (e.g. as JSON), so its caller need only fail without printing
+/// it again
+///
+/// This wraps the original error, whose message and causes it reports as its own.
+#[derive(Debug)]
+pub struct Reported(pub Error);
+
+impl fmt::Display for Reported {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl std::error::Error for Reported {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.0.source()
+    }
+}
+
+/// Print `error` to `stderr` in the specified format.
+pub fn print(error: &Error, format: ErrorFormat, phase: Option<Phase>) {
+    match format {
+        ErrorFormat::Human => eprintln!("Error: {error:?}"),
+        ErrorFormat::Json => eprintln!(
+            "{}",
+            serde_json::to_string(&Diagnostic::new(error, phase)).unwrap()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, anyhow::anyhow};
+
+    #[test]
+    fn wit_location() {
+        let error = anyhow!(
+            "expected `;`, found keyword `world`\n     --> /tmp/hello.wit:3:1\n      |\n    3 | world hello {{\n      | ^"
+        )
+        .context("failed to parse package: /tmp/hello.wit");
+
+        let diagnostic = Diagnostic::new(&error, Some(Phase::GeneratingBindings));
+
+        assert_eq!(
+            Diagnostic {
+                phase: Some("generating-bindings".to_owned()),
+                message: "failed to parse package: /tmp/hello.wit".to_owned(),
+                causes: vec![error.root_cause().to_string()],
+                traceback: None,
+                location: Some(Location {
+                    file: "/tmp/hello.wit".to_owned(),
+                    line: 3,
+                    column: 1
+                }),
+            },
+            diagnostic
+        );
+    }
+
+    #[test]
+    fn python_traceback() {
+        let error = anyhow!("exit status 1").context(InitFailure {
+            stdout: b"starting up\n".to_vec(),
+            stderr: b"Traceback (most recent call last):\n  File \"/0/app.py\", line 1, in <module>\nValueError: oops\n"
+                .to_vec(),
+        });
+
+        let diagnostic = Diagnostic::new(&error, Some(Phase::PreInitializing));
+
+        assert_eq!("exit status 1", diagnostic.message);
+        assert!(diagnostic.causes.is_empty());
+        assert_eq!(
+            Some(
+                "Traceback (most recent call last):\n  File \"/0/app.py\", line 1, in <module>\nValueError: oops"
+            ),
+            diagnostic.traceback.as_deref()
+        );
+    }
+}
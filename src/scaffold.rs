@@ -0,0 +1,850 @@
+//! Support for the `scaffold-host` subcommand, which generates a minimal host project for running components
+//! targeting a world, using either `wasmtime`'s Rust API or `wasmtime-py`.
+//!
+//! The project provides a stub for each of the world's imports (other than the WASI interfaces the host runtime
+//! implements itself) for the user to fill in, plus commented-out examples of calling the world's exports.
+
+use {
+    crate::{
+        source::Source,
+        summary::{Escape, FunctionKind, MyFunction, Summary},
+        HostLanguage,
+    },
+    anyhow::Result,
+    heck::{ToKebabCase, ToSnakeCase, ToUpperCamelCase},
+    std::{
+        fs, iter,
+        path::{self, Path, PathBuf},
+    },
+    wit_parser::{
+        Handle, InterfaceId, Resolve, Results, Type, TypeDefKind, TypeId, TypeOwner, WorldId,
+        WorldItem,
+    },
+};
+
+/// Version of `wasmtime` (and `wasmtime-wasi`) the generated Rust host depends on, i.e. the one `componentize-py`
+/// itself is built with.
+const WASMTIME_VERSION: &str = "25.0.2";
+
+/// WASI packages implemented by `wasmtime-wasi`, whose interfaces the generated Rust host takes from there rather
+/// than stubbing out.
+const WASMTIME_WASI_PACKAGES: &[&str] = &["cli", "clocks", "filesystem", "io", "random", "sockets"];
+
+/// Write a host project for `world` to `output_dir` (creating it if necessary), returning the paths of the files
+/// written.
+///
+/// `component` is the path of the component the host runs unless told otherwise.
+pub fn write(
+    summary: &Summary,
+    world: WorldId,
+    language: HostLanguage,
+    wit_path: &Path,
+    output_dir: &Path,
+    component: &str,
+) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(output_dir)?;
+
+    let files = match language {
+        HostLanguage::Rust => {
+            // `bindgen!` resolves the WIT path relative to the crate's manifest, so make it relative to that.
+            let wit_path =
+                relative_path(&fs::canonicalize(output_dir)?, &fs::canonicalize(wit_path)?);
+            rust_files(summary, world, &wit_path, component)
+        }
+        HostLanguage::Python => python_files(summary, world, component),
+    };
+
+    files
+        .into_iter()
+        .map(|(path, contents)| {
+            let path = output_dir.join(path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&path, contents)?;
+            Ok(path)
+        })
+        .collect()
+}
+
+/// Return the path of `to` relative to `from`, both of which must be absolute.
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    iter::repeat(path::Component::ParentDir)
+        .take(from.components().count() - common)
+        .chain(to.components().skip(common))
+        .collect()
+}
+
+/// The imports of a world which the host must implement, grouped the way both `wasmtime` and `wasmtime-py` expect
+/// them to be implemented.
+struct Imports<'a> {
+    /// Functions and resources imported directly by the world
+    world: Scope<'a>,
+    /// Imported interfaces other than those provided by `wasmtime-wasi`
+    interfaces: Vec<(InterfaceId, Scope<'a>)>,
+    /// Imported packages provided by `wasmtime-wasi`
+    wasi_packages: Vec<&'a str>,
+}
+
+/// The functions and resources imported by the world itself or by one of its interfaces
+struct Scope<'a> {
+    functions: Vec<&'a MyFunction<'a>>,
+    resources: Vec<(TypeId, Vec<&'a MyFunction<'a>>)>,
+}
+
+impl<'a> Scope<'a> {
+    fn new(summary: &'a Summary, interface: Option<InterfaceId>, resources: Vec<TypeId>) -> Self {
+        let mut scope = Self {
+            functions: Vec::new(),
+            resources: resources.into_iter().map(|id| (id, Vec::new())).collect(),
+        };
+
+        for function in summary.functions.iter().filter(|function| {
+            matches!(function.kind, FunctionKind::Import)
+                && function.interface.as_ref().map(|i| i.id) == interface
+        }) {
+            match function.wit_kind {
+                wit_parser::FunctionKind::Freestanding => scope.functions.push(function),
+                wit_parser::FunctionKind::Method(id)
+                | wit_parser::FunctionKind::Static(id)
+                | wit_parser::FunctionKind::Constructor(id) => {
+                    if let Some((_, functions)) = scope.resources.iter_mut().find(|(r, _)| *r == id)
+                    {
+                        functions.push(function);
+                    }
+                }
+            }
+        }
+
+        scope
+    }
+
+    fn is_empty(&self) -> bool {
+        self.functions.is_empty() && self.resources.is_empty()
+    }
+}
+
+impl<'a> Imports<'a> {
+    fn new(summary: &'a Summary, world: WorldId) -> Self {
+        let resolve = summary.resolve;
+        let is_resource = |id: &TypeId| matches!(resolve.types[*id].kind, TypeDefKind::Resource);
+
+        let mut interfaces = Vec::new();
+        let mut wasi_packages = Vec::new();
+        let mut world_resources = Vec::new();
+
+        for item in resolve.worlds[world].imports.values() {
+            match item {
+                WorldItem::Interface { id, .. } => {
+                    if let Some(package) = wasmtime_wasi_package(resolve, *id) {
+                        if !wasi_packages.contains(&package) {
+                            wasi_packages.push(package);
+                        }
+                    } else {
+                        let resources = resolve.interfaces[*id]
+                            .types
+                            .values()
+                            .copied()
+                            .filter(is_resource)
+                            .collect();
+                        interfaces.push((*id, Scope::new(summary, Some(*id), resources)));
+                    }
+                }
+                WorldItem::Type(id) if is_resource(id) => world_resources.push(*id),
+                WorldItem::Type(_) | WorldItem::Function(_) => {}
+            }
+        }
+
+        Self {
+            world: Scope::new(summary, None, world_resources),
+            interfaces,
+            wasi_packages,
+        }
+    }
+}
+
+/// Return the name of the `wasmtime-wasi` package providing the specified interface, if any.
+fn wasmtime_wasi_package(resolve: &Resolve, interface: InterfaceId) -> Option<&str> {
+    let package = &resolve.packages[resolve.interfaces[interface].package?].name;
+    (package.namespace == "wasi" && WASMTIME_WASI_PACKAGES.contains(&package.name.as_str()))
+        .then_some(package.name.as_str())
+}
+
+/// Return the name of `function` within its resource (if any), e.g. `get` for `[method]fields.get`.
+fn item_name<'a>(function: &MyFunction<'a>) -> &'a str {
+    match function.wit_kind {
+        wit_parser::FunctionKind::Freestanding => function.name,
+        wit_parser::FunctionKind::Constructor(_) => "new",
+        wit_parser::FunctionKind::Method(_) | wit_parser::FunctionKind::Static(_) => function
+            .name
+            .split_once('.')
+            .map(|(_, name)| name)
+            .unwrap_or(function.name),
+    }
+}
+
+fn world_name(resolve: &Resolve, world: WorldId) -> &str {
+    &resolve.worlds[world].name
+}
+
+/// Return the fully qualified name of `interface` (without its version), or just its name if it doesn't belong
+/// to a package.
+fn interface_name(resolve: &Resolve, interface: InterfaceId) -> String {
+    let name = resolve.interfaces[interface].name.as_deref().unwrap_or("");
+    match resolve.interfaces[interface].package {
+        Some(package) => {
+            let package = &resolve.packages[package].name;
+            format!("{}:{}/{name}", package.namespace, package.name)
+        }
+        None => name.to_owned(),
+    }
+}
+
+fn rust_files(
+    summary: &Summary,
+    world: WorldId,
+    wit_path: &Path,
+    component: &str,
+) -> Vec<(PathBuf, String)> {
+    let resolve = summary.resolve;
+    let imports = Imports::new(summary, world);
+    let world_name = world_name(resolve, world);
+    let world_camel = world_name.to_upper_camel_case();
+
+    let manifest = format!(
+        "[package]
+name = \"{}-host\"
+version = \"0.1.0\"
+edition = \"2021\"
+
+[dependencies]
+anyhow = \"1.0\"
+wasmtime = \"{WASMTIME_VERSION}\"
+wasmtime-wasi = \"{WASMTIME_VERSION}\"
+",
+        world_name.to_kebab_case()
+    );
+
+    let mut lines = vec![
+        format!(
+            "//! Minimal host for components targeting the `{world_name}` world, generated by \
+             `componentize-py scaffold-host`."
+        ),
+        "//!".to_owned(),
+        "//! Replace each `todo!()` below with your implementation of the corresponding import, then run a \
+         component using"
+            .to_owned(),
+        format!("//! e.g. `cargo run -- {component}`."),
+        String::new(),
+        "use {".to_owned(),
+        "    anyhow::Result,".to_owned(),
+        "    wasmtime::{".to_owned(),
+        "        component::{Component, Linker, ResourceTable},".to_owned(),
+        "        Engine, Store,".to_owned(),
+        "    },".to_owned(),
+        "    wasmtime_wasi::{WasiCtx, WasiCtxBuilder, WasiView},".to_owned(),
+        "};".to_owned(),
+        String::new(),
+        "wasmtime::component::bindgen!({".to_owned(),
+        format!("    path: {:?},", wit_path.to_string_lossy()),
+        format!("    world: {:?},", bindgen_world_name(resolve, world)),
+    ];
+    if !imports.wasi_packages.is_empty() {
+        lines.push("    with: {".to_owned());
+        for package in &imports.wasi_packages {
+            lines.push(format!(
+                "        \"wasi:{package}\": wasmtime_wasi::bindings::{package},"
+            ));
+        }
+        lines.push("    },".to_owned());
+    }
+    lines.extend(
+        [
+            "});",
+            "",
+            "struct State {",
+            "    table: ResourceTable,",
+            "    wasi: WasiCtx,",
+            "}",
+            "",
+            "impl WasiView for State {",
+            "    fn table(&mut self) -> &mut ResourceTable {",
+            "        &mut self.table",
+            "    }",
+            "",
+            "    fn ctx(&mut self) -> &mut WasiCtx {",
+            "        &mut self.wasi",
+            "    }",
+            "}",
+        ]
+        .map(str::to_owned),
+    );
+
+    let mut add_to_linker = Vec::new();
+
+    if !imports.world.is_empty() {
+        rust_impls(
+            resolve,
+            &mut lines,
+            &imports.world,
+            "",
+            &format!("{world_camel}Imports"),
+        );
+        add_to_linker.push(format!(
+            "{world_camel}::add_to_linker_imports_get_host(&mut linker, |state: &mut State| state)?;"
+        ));
+    }
+
+    for (id, scope) in &imports.interfaces {
+        let path = rust_module_path(resolve, *id);
+        rust_impls(resolve, &mut lines, scope, &path, &format!("{path}::Host"));
+        add_to_linker.push(format!(
+            "{path}::add_to_linker(&mut linker, |state| state)?;"
+        ));
+    }
+
+    lines.extend(
+        [
+            "",
+            "fn main() -> Result<()> {",
+            &format!(
+                "    let path = std::env::args().nth(1).unwrap_or_else(|| {:?}.to_owned());",
+                component
+            ),
+            "",
+            "    let engine = Engine::default();",
+            "    let mut linker = Linker::new(&engine);",
+            "    wasmtime_wasi::add_to_linker_sync(&mut linker)?;",
+        ]
+        .map(str::to_owned),
+    );
+    lines.extend(add_to_linker.into_iter().map(|line| format!("    {line}")));
+    lines.extend(
+        [
+            "",
+            "    let mut store = Store::new(",
+            "        &engine,",
+            "        State {",
+            "            table: ResourceTable::new(),",
+            "            wasi: WasiCtxBuilder::new().inherit_stdio().inherit_args().build(),",
+            "        },",
+            "    );",
+            "    let component = Component::from_file(&engine, path)?;",
+            &format!(
+                "    let instance = {world_camel}::instantiate(&mut store, &component, &linker)?;"
+            ),
+        ]
+        .map(str::to_owned),
+    );
+
+    let calls = export_calls(resolve, world, |interface, function| match interface {
+        Some(id) => format!(
+            "instance.{}().call_{}(&mut store, ...)?;",
+            rust_export_getter(resolve, id),
+            rust_ident(function)
+        ),
+        None => format!("instance.call_{}(&mut store, ...)?;", rust_ident(function)),
+    });
+    if !calls.is_empty() {
+        lines.push(String::new());
+        lines.push("    // Call the component's exports, e.g.:".to_owned());
+        lines.extend(calls.into_iter().map(|call| format!("    // {call}")));
+    }
+
+    lines.extend(["", "    Ok(())", "}"].map(str::to_owned));
+
+    vec![
+        (PathBuf::from("Cargo.toml"), manifest),
+        (PathBuf::from("src/main.rs"), lines.join("\n") + "\n"),
+    ]
+}
+
+/// Push the `impl` blocks for the functions and resources in `scope` to `lines`.
+///
+/// `path` is the Rust module containing the bindings for the scope, and `host` is the trait its freestanding
+/// functions belong to.
+fn rust_impls(resolve: &Resolve, lines: &mut Vec<String>, scope: &Scope, path: &str, host: &str) {
+    let prefix = if path.is_empty() {
+        String::new()
+    } else {
+        format!("{path}::")
+    };
+
+    for (id, functions) in &scope.resources {
+        let name = resolve.types[*id]
+            .name
+            .as_deref()
+            .unwrap()
+            .to_upper_camel_case();
+        lines.push(String::new());
+        lines.push(format!("impl {prefix}Host{name} for State {{"));
+        for function in functions {
+            rust_function(resolve, lines, function);
+            lines.push(String::new());
+        }
+        lines.push(format!(
+            "    fn drop(&mut self, rep: wasmtime::component::Resource<{prefix}{name}>) -> wasmtime::Result<()> {{"
+        ));
+        lines.push("        todo!()".to_owned());
+        lines.push("    }".to_owned());
+        lines.push("}".to_owned());
+    }
+
+    lines.push(String::new());
+    if scope.functions.is_empty() {
+        lines.push(format!("impl {host} for State {{}}"));
+    } else {
+        lines.push(format!("impl {host} for State {{"));
+        for (index, function) in scope.functions.iter().enumerate() {
+            if index > 0 {
+                lines.push(String::new());
+            }
+            rust_function(resolve, lines, function);
+        }
+        lines.push("}".to_owned());
+    }
+}
+
+fn rust_function(resolve: &Resolve, lines: &mut Vec<String>, function: &MyFunction) {
+    let params = iter::once("&mut self".to_owned())
+        .chain(
+            function
+                .params
+                .iter()
+                .map(|(name, ty)| format!("{}: {}", rust_ident(name), rust_type(resolve, ty))),
+        )
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let result = match function.results {
+        Results::Anon(ty) => format!(" -> {}", rust_type(resolve, ty)),
+        Results::Named(results) if results.is_empty() => String::new(),
+        Results::Named(results) if results.len() == 1 => {
+            format!(" -> {}", rust_type(resolve, &results[0].1))
+        }
+        Results::Named(results) => format!(
+            " -> ({})",
+            results
+                .iter()
+                .map(|(_, ty)| rust_type(resolve, ty))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+
+    lines.push(format!(
+        "    fn {}({params}){result} {{",
+        rust_ident(item_name(function))
+    ));
+    lines.push("        todo!()".to_owned());
+    lines.push("    }".to_owned());
+}
+
+/// Return the Rust type `wasmtime::component::bindgen!` uses for `ty` in the signatures of host functions.
+fn rust_type(resolve: &Resolve, ty: &Type) -> String {
+    match ty {
+        Type::Bool => "bool".into(),
+        Type::U8 => "u8".into(),
+        Type::U16 => "u16".into(),
+        Type::U32 => "u32".into(),
+        Type::U64 => "u64".into(),
+        Type::S8 => "i8".into(),
+        Type::S16 => "i16".into(),
+        Type::S32 => "i32".into(),
+        Type::S64 => "i64".into(),
+        Type::F32 => "f32".into(),
+        Type::F64 => "f64".into(),
+        Type::Char => "char".into(),
+        Type::String => "String".into(),
+        Type::Id(id) => {
+            let ty = &resolve.types[*id];
+            if let Some(name) = &ty.name {
+                let name = name.to_upper_camel_case();
+                return match ty.owner {
+                    TypeOwner::Interface(interface) => {
+                        format!("{}::{name}", rust_module_path(resolve, interface))
+                    }
+                    TypeOwner::World(_) | TypeOwner::None => name,
+                };
+            }
+
+            match &ty.kind {
+                TypeDefKind::List(ty) => format!("Vec<{}>", rust_type(resolve, ty)),
+                TypeDefKind::Option(ty) => format!("Option<{}>", rust_type(resolve, ty)),
+                TypeDefKind::Result(result) => format!(
+                    "Result<{}, {}>",
+                    result
+                        .ok
+                        .map(|ty| rust_type(resolve, &ty))
+                        .unwrap_or_else(|| "()".into()),
+                    result
+                        .err
+                        .map(|ty| rust_type(resolve, &ty))
+                        .unwrap_or_else(|| "()".into())
+                ),
+                TypeDefKind::Tuple(tuple) => format!(
+                    "({}{})",
+                    tuple
+                        .types
+                        .iter()
+                        .map(|ty| rust_type(resolve, ty))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    if tuple.types.len() == 1 { "," } else { "" }
+                ),
+                TypeDefKind::Handle(Handle::Own(id) | Handle::Borrow(id)) => format!(
+                    "wasmtime::component::Resource<{}>",
+                    rust_type(resolve, &Type::Id(*id))
+                ),
+                TypeDefKind::Type(ty) => rust_type(resolve, ty),
+                // Anything else is either always named (and handled above) or not yet supported by `wasmtime`.
+                _ => "()".into(),
+            }
+        }
+    }
+}
+
+/// Return the path of the module `wasmtime::component::bindgen!` generates for the imported `interface`.
+fn rust_module_path(resolve: &Resolve, interface: InterfaceId) -> String {
+    let name = rust_ident(resolve.interfaces[interface].name.as_deref().unwrap_or(""));
+    match resolve.interfaces[interface].package {
+        Some(package) => {
+            let package = &resolve.packages[package].name;
+            if wasmtime_wasi_package(resolve, interface).is_some() {
+                format!("wasmtime_wasi::bindings::{}::{name}", package.name)
+            } else {
+                format!(
+                    "{}::{}::{name}",
+                    rust_ident(&package.namespace),
+                    rust_ident(&package.name)
+                )
+            }
+        }
+        None => name,
+    }
+}
+
+/// Return the name of the method `wasmtime::component::bindgen!` generates for accessing the exported
+/// `interface`.
+fn rust_export_getter(resolve: &Resolve, interface: InterfaceId) -> String {
+    let name = resolve.interfaces[interface].name.as_deref().unwrap_or("");
+    match resolve.interfaces[interface].package {
+        Some(package) => {
+            let package = &resolve.packages[package].name;
+            format!("{}-{}-{name}", package.namespace, package.name).to_snake_case()
+        }
+        None => name.to_snake_case(),
+    }
+}
+
+/// Return the name by which `wasmtime::component::bindgen!` should select `world`.
+///
+/// Versioned packages are awkward to name this way, so we rely on those worlds belonging to the package at the WIT
+/// path (which is usually the case) and use their unqualified names.
+fn bindgen_world_name(resolve: &Resolve, world: WorldId) -> String {
+    let name = world_name(resolve, world);
+    match resolve.worlds[world].package {
+        Some(package) if resolve.packages[package].name.version.is_none() => {
+            let package = &resolve.packages[package].name;
+            format!("{}:{}/{name}", package.namespace, package.name)
+        }
+        _ => name.to_owned(),
+    }
+}
+
+/// Convert `name` to a Rust identifier the way `wasmtime::component::bindgen!` does.
+fn rust_ident(name: &str) -> String {
+    let name = name.to_snake_case();
+    match name.as_str() {
+        "as" | "async" | "await" | "break" | "const" | "continue" | "crate" | "dyn" | "else"
+        | "enum" | "extern" | "false" | "fn" | "for" | "if" | "impl" | "in" | "let" | "loop"
+        | "match" | "mod" | "move" | "mut" | "pub" | "ref" | "return" | "self" | "static"
+        | "struct" | "super" | "trait" | "true" | "type" | "unsafe" | "use" | "where" | "while"
+        | "abstract" | "become" | "box" | "do" | "final" | "macro" | "override" | "priv"
+        | "try" | "typeof" | "unsized" | "virtual" | "yield" => format!("{name}_"),
+        _ => name,
+    }
+}
+
+/// Return an example call to each freestanding function exported by `world`, formatted using `call`, which is
+/// passed the exporting interface (if any) and the function's name.
+fn export_calls(
+    resolve: &Resolve,
+    world: WorldId,
+    call: impl Fn(Option<InterfaceId>, &str) -> String,
+) -> Vec<String> {
+    resolve.worlds[world]
+        .exports
+        .values()
+        .flat_map(|item| match item {
+            WorldItem::Function(function) => vec![call(None, &function.name)],
+            WorldItem::Interface { id, .. } => resolve.interfaces[*id]
+                .functions
+                .values()
+                .filter(|function| matches!(function.kind, wit_parser::FunctionKind::Freestanding))
+                .map(|function| call(Some(*id), &function.name))
+                .collect(),
+            WorldItem::Type(_) => Vec::new(),
+        })
+        .collect()
+}
+
+fn python_files(summary: &Summary, world: WorldId, component: &str) -> Vec<(PathBuf, String)> {
+    let resolve = summary.resolve;
+    let imports = Imports::new(summary, world);
+    let world_name = world_name(resolve, world);
+    let bindings = format!("{}_host", world_name.to_snake_case());
+
+    let mut source = Source::default();
+    source.docstring(Some(&format!(
+        "Minimal host for components targeting the `{world_name}` world, generated by `componentize-py \
+         scaffold-host`.\n\
+         \n\
+         Generate bindings for the component using `python3 -m wasmtime.bindgen {component} --out-dir {bindings}`\n\
+         (building it with `--stub-wasi` if it imports WASI interfaces), replace each `NotImplementedError` below\n\
+         with your implementation of the corresponding import, and then run this script."
+    )));
+    source.blank_line();
+    source.line("from wasmtime import Config, Engine, Store");
+
+    let mut fields = Vec::new();
+    if imports.world.is_empty() && imports.interfaces.is_empty() {
+        source.line(format!("from {bindings} import Root"));
+    } else {
+        source.line(format!("from {bindings} import Root, RootImports"));
+
+        if !imports.world.is_empty() {
+            python_classes(
+                resolve,
+                &mut source,
+                &imports.world,
+                "Host",
+                &format!("the `{world_name}` world"),
+            );
+            fields.push("host=Host()".to_owned());
+        }
+
+        for (id, scope) in &imports.interfaces {
+            let name = resolve.interfaces[*id].name.as_deref().unwrap_or("");
+            let class = name.to_upper_camel_case();
+            python_classes(
+                resolve,
+                &mut source,
+                scope,
+                &class,
+                &format!("`{}`", interface_name(resolve, *id)),
+            );
+            fields.push(format!("{}={class}()", name.to_snake_case().escape()));
+        }
+    }
+
+    source.blank_line();
+    source.push(
+        "config = Config()
+config.cache = True
+engine = Engine(config)
+store = Store(engine)",
+    );
+    if fields.is_empty() {
+        source.line("instance = Root(store)");
+    } else {
+        source.line(format!(
+            "instance = Root(store, RootImports({}))",
+            fields.join(", ")
+        ));
+    }
+
+    let calls = export_calls(resolve, world, |interface, function| match interface {
+        Some(id) => format!(
+            "instance.{}().{}(store, ...)",
+            resolve.interfaces[id]
+                .name
+                .as_deref()
+                .unwrap_or("")
+                .to_snake_case()
+                .escape(),
+            function.to_snake_case().escape()
+        ),
+        None => format!("instance.{}(store, ...)", function.to_snake_case().escape()),
+    });
+    if !calls.is_empty() {
+        source.blank_line();
+        source.line("# Call the component's exports, e.g.:");
+        for call in calls {
+            source.line(format!("# {call}"));
+        }
+    }
+
+    vec![(PathBuf::from("host.py"), source.into())]
+}
+
+/// Push the class implementing the freestanding functions in `scope` (named `class`), plus one for each of its
+/// resources, to `source`.
+///
+/// `description` describes where the imports come from, e.g. "the `proxy` world" or "`wasi:http/types`".
+fn python_classes(
+    resolve: &Resolve,
+    source: &mut Source,
+    scope: &Scope,
+    class: &str,
+    description: &str,
+) {
+    let python_function = |source: &mut Source, function: &MyFunction| {
+        let name = item_name(function);
+        let (name, skip) = match function.wit_kind {
+            wit_parser::FunctionKind::Constructor(_) => ("__init__".to_owned(), 0),
+            wit_parser::FunctionKind::Method(_) => (name.to_snake_case().escape(), 1),
+            _ => (name.to_snake_case().escape(), 0),
+        };
+        if matches!(function.wit_kind, wit_parser::FunctionKind::Static(_)) {
+            source.line("@staticmethod");
+        }
+        let params = (!matches!(function.wit_kind, wit_parser::FunctionKind::Static(_)))
+            .then(|| "self".to_owned())
+            .into_iter()
+            .chain(
+                function
+                    .params
+                    .iter()
+                    .skip(skip)
+                    .map(|(name, _)| name.to_snake_case().escape()),
+            )
+            .collect::<Vec<_>>()
+            .join(", ");
+        source.block(format!("def {name}({params}):"), |source| {
+            source.line("raise NotImplementedError");
+        });
+    };
+
+    for (id, functions) in &scope.resources {
+        let name = resolve.types[*id].name.as_deref().unwrap();
+        source.blank_line();
+        source.blank_line();
+        source.block(format!("class {}:", name.to_upper_camel_case()), |source| {
+            source.docstring(Some(&format!(
+                "Implementation of the `{name}` resource imported from {description}"
+            )));
+            for function in functions {
+                source.blank_line();
+                python_function(source, function);
+            }
+        });
+    }
+
+    source.blank_line();
+    source.blank_line();
+    source.block(format!("class {class}:"), |source| {
+        source.docstring(Some(&format!(
+            "Implementation of the functions imported from {description}"
+        )));
+        for function in &scope.functions {
+            source.blank_line();
+            python_function(source, function);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::NamingStyle, std::collections::HashMap};
+
+    fn generate(wit: &str, language: HostLanguage) -> Result<Vec<(PathBuf, String)>> {
+        let mut resolve = Resolve::default();
+        let package = resolve.push_str("test.wit", wit)?;
+        let world = resolve.select_world(package, None)?;
+        let summary = Summary::try_new(
+            &resolve,
+            &iter::once(world).collect(),
+            &HashMap::new(),
+            &HashMap::new(),
+            NamingStyle::default(),
+            Default::default(),
+        )?;
+        Ok(match language {
+            HostLanguage::Rust => rust_files(&summary, world, Path::new("../wit"), "app.wasm"),
+            HostLanguage::Python => python_files(&summary, world, "app.wasm"),
+        })
+    }
+
+    const WIT: &str = "
+package my:app;
+
+interface storage {
+    resource bucket {
+        constructor(name: string);
+        get: func(key: string) -> option<list<u8>>;
+    }
+
+    open: func(name: string) -> result<bucket, string>;
+}
+
+world app {
+    import storage;
+    import log: func(message: string);
+    export run: func(args: list<string>) -> u32;
+}
+";
+
+    #[test]
+    fn rust_host() -> Result<()> {
+        let files = generate(WIT, HostLanguage::Rust)?;
+        let main = &files[1].1;
+
+        assert!(files[0].1.contains("name = \"app-host\""));
+        assert!(main.contains("path: \"../wit\","));
+        assert!(main.contains("world: \"my:app/app\","));
+        assert!(
+            main.contains("impl AppImports for State {\n    fn log(&mut self, message: String) {")
+        );
+        assert!(main.contains("impl my::app::storage::HostBucket for State {"));
+        assert!(main.contains(
+            "    fn get(&mut self, self_: wasmtime::component::Resource<my::app::storage::Bucket>, key: String) \
+             -> Option<Vec<u8>> {"
+        ));
+        assert!(main.contains(
+            "    fn open(&mut self, name: String) -> \
+             Result<wasmtime::component::Resource<my::app::storage::Bucket>, String> {"
+        ));
+        assert!(main.contains("    my::app::storage::add_to_linker(&mut linker, |state| state)?;"));
+        assert!(main.contains("    // instance.call_run(&mut store, ...)?;"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn python_host() -> Result<()> {
+        let files = generate(WIT, HostLanguage::Python)?;
+        let host = &files[0].1;
+
+        assert!(host.contains("from app_host import Root, RootImports\n"));
+        assert!(host.contains("class Bucket:"));
+        assert!(host.contains("    def __init__(self, name):"));
+        assert!(host.contains("    def get(self, key):"));
+        assert!(host.contains("class Storage:"));
+        assert!(host.contains("class Host:"));
+        assert!(
+            host.contains("instance = Root(store, RootImports(host=Host(), storage=Storage()))")
+        );
+        assert!(host.contains("# instance.run(store, ...)"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn relative_paths() {
+        assert_eq!(
+            PathBuf::from("../wit"),
+            relative_path(Path::new("/a/host"), Path::new("/a/wit"))
+        );
+        assert_eq!(
+            PathBuf::from("../../b/wit"),
+            relative_path(Path::new("/a/c/host"), Path::new("/a/b/wit"))
+        );
+    }
+}
@@ -0,0 +1,142 @@
+/// Indentation used for each nesting level of generated Python code
+const INDENT: &str = "    ";
+
+/// Helper for incrementally building Python source code.
+///
+/// Each line pushed is indented according to the current nesting level, so the snippets passed in never need to
+/// embed indentation of their own.  This makes it possible to compose fragments (e.g. a method body inside a
+/// class inside a module) without worrying about which level each one will end up at.
+#[derive(Default)]
+pub struct Source {
+    text: String,
+    indent: usize,
+}
+
+impl Source {
+    /// Push a single line at the current indentation level.
+    pub fn line(&mut self, line: impl AsRef<str>) {
+        let line = line.as_ref();
+        if !line.is_empty() {
+            for _ in 0..self.indent {
+                self.text.push_str(INDENT);
+            }
+            self.text.push_str(line);
+        }
+        self.text.push('\n');
+    }
+
+    /// Push a (possibly multi-line) snippet, indenting each of its lines at the current level.
+    ///
+    /// Leading and trailing newlines in `code` are ignored.
+    pub fn push(&mut self, code: &str) {
+        let code = code.trim_matches('\n');
+        if self.indent == 0 && !code.is_empty() && !code.contains('\r') {
+            // There's nothing to indent, so copy the code wholesale rather than line by line, which matters when
+            // assembling the (potentially very large) modules generated for big worlds.
+            self.text.push_str(code);
+            self.text.push('\n');
+        } else {
+            for line in code.lines() {
+                self.line(line);
+            }
+        }
+    }
+
+    /// Ensure the code so far is followed by a blank line, unless it is empty or already ends with one.
+    pub fn blank_line(&mut self) {
+        if !(self.text.is_empty() || self.text.ends_with("\n\n")) {
+            self.text.push('\n');
+        }
+    }
+
+    /// Push `header` followed by a block containing whatever `body` pushes, indented one more level.
+    ///
+    /// If `body` pushes nothing, the block will contain a `pass` statement.
+    pub fn block(&mut self, header: impl AsRef<str>, body: impl FnOnce(&mut Self)) {
+        self.line(header);
+        self.indent += 1;
+        let start = self.text.len();
+        body(self);
+        if self.text[start..].trim().is_empty() {
+            self.text.truncate(start);
+            self.line("pass");
+        }
+        self.indent -= 1;
+    }
+
+    /// Push a docstring containing `docs`, if present.
+    pub fn docstring(&mut self, docs: Option<&str>) {
+        if let Some(docs) = docs {
+            self.line(r#"""""#);
+            for line in docs.lines() {
+                self.line(line);
+            }
+            self.line(r#"""""#);
+        }
+    }
+}
+
+impl From<Source> for String {
+    fn from(source: Source) -> Self {
+        source.text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nested_blocks() {
+        let mut source = Source::default();
+        source.block("class Foo:", |source| {
+            source.docstring(Some("A foo."));
+            source.block("def bar(self) -> int:", |source| {
+                source.push("if x:\n    return 1\nelse:\n    return 2\n");
+            });
+        });
+
+        assert_eq!(
+            String::from(source),
+            r#"class Foo:
+    """
+    A foo.
+    """
+    def bar(self) -> int:
+        if x:
+            return 1
+        else:
+            return 2
+"#
+        );
+    }
+
+    #[test]
+    fn push_at_any_level() {
+        let code = "\nx = 1\n\nif x:\n    y = 2\n\n";
+
+        let mut source = Source::default();
+        source.push(code);
+        source.block("def f():", |source| source.push(code));
+        source.push("");
+
+        assert_eq!(
+            String::from(source),
+            "x = 1\n\nif x:\n    y = 2\ndef f():\n    x = 1\n\n    if x:\n        y = 2\n"
+        );
+    }
+
+    #[test]
+    fn empty_block() {
+        let mut source = Source::default();
+        source.block("class Foo(Protocol):", |_| ());
+        source.blank_line();
+        source.blank_line();
+        source.line("x = 1");
+
+        assert_eq!(
+            String::from(source),
+            "class Foo(Protocol):\n    pass\n\nx = 1\n"
+        );
+    }
+}
@@ -1,18 +1,21 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, convert::Infallible};
 
 use anyhow::{bail, Error};
 use wasm_convert::IntoValType;
 use wasm_encoder::{
-    CodeSection, ExportKind, ExportSection, Function, FunctionSection, Instruction as Ins, Module,
-    TypeSection,
+    reencode::{self, Reencode},
+    CodeSection, ExportKind, ExportSection, Function, FunctionSection, ImportSection,
+    Instruction as Ins, Module, TypeSection,
+};
+use wasmparser::{
+    CodeSectionReader, FuncType, FunctionSectionReader, Import, Parser, Payload, TypeRef,
 };
-use wasmparser::{FuncType, Parser, Payload, TypeRef};
 
-use crate::Library;
+use crate::{link, Library};
 
 type LinkedStubModules = Option<(Vec<u8>, Box<dyn Fn(u32) -> u32>)>;
 
-pub fn link_stub_modules(libraries: Vec<Library>) -> Result<LinkedStubModules, Error> {
+pub fn link_stub_modules(libraries: &[Library]) -> Result<LinkedStubModules, Error> {
     let mut wasi_imports = HashMap::new();
     let mut linker = wit_component::Linker::default()
         .validate(true)
@@ -22,7 +25,7 @@ pub fn link_stub_modules(libraries: Vec<Library>) -> Result<LinkedStubModules, E
         name,
         module,
         dl_openable,
-    } in &libraries
+    } in libraries
     {
         add_wasi_imports(module, &mut wasi_imports)?;
         linker = linker.library(name, module, *dl_openable)?;
@@ -64,6 +67,143 @@ pub fn link_stub_modules(libraries: Vec<Library>) -> Result<LinkedStubModules, E
     )))
 }
 
+/// Link the specified libraries as `link::link_libraries` would, except that each import from a WASI interface not
+/// listed in `interfaces` (names without versions, e.g. `wasi:cli/stdout`) is replaced with a trapping stub, so
+/// that the resulting component does not import that interface.
+///
+/// This applies both to the WASI adapter and to libraries which import WASI interfaces directly (e.g. the runtime,
+/// which uses `wasi:cli/environment`).  The latter are restricted in place, since the caller has no further use
+/// for the unrestricted versions once the component has been linked.
+pub fn link_with_profile(
+    libraries: &mut [Library],
+    wasi_adapter: &[u8],
+    interfaces: &[&str],
+) -> Result<LinkedStubModules, Error> {
+    for library in libraries.iter_mut() {
+        if let Some(module) = restrict_imports(&library.module, interfaces)? {
+            library.module = module;
+        }
+    }
+    let adapter = restrict_imports(wasi_adapter, interfaces)?;
+    let component = link::link_libraries(libraries, adapter.as_deref().unwrap_or(wasi_adapter))?;
+
+    // Unlike `link_stub_modules`, this produces the same modules in the same order as the unrestricted component,
+    // so module indexes need no translation.
+    Ok(Some((component, Box::new(|index| index))))
+}
+
+/// Replace each function `module` imports from a WASI interface not listed in `interfaces` with a function which
+/// traps, returning the resulting module, or `None` if `module` imports nothing which needs replacing.
+fn restrict_imports(module: &[u8], interfaces: &[&str]) -> Result<Option<Vec<u8>>, Error> {
+    let mut restrict = Restrict::default();
+    let mut defined = 0;
+    for payload in Parser::new(0).parse_all(module) {
+        match payload? {
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    let import = import?;
+                    if let TypeRef::Func(ty) = import.ty {
+                        let interface = import.module.split('@').next().unwrap();
+                        let keep =
+                            !interface.starts_with("wasi:") || interfaces.contains(&interface);
+                        restrict.keep.push(keep);
+                        if !keep {
+                            restrict.stub_types.push(ty);
+                        }
+                    }
+                }
+            }
+            Payload::FunctionSection(reader) => defined = reader.count(),
+            _ => {}
+        }
+    }
+
+    if restrict.stub_types.is_empty() {
+        return Ok(None);
+    }
+
+    let kept = restrict.keep.iter().filter(|&&keep| keep).count();
+    let (mut next_kept, mut next_stub) = (0, u32::try_from(kept)? + defined);
+    for &keep in &restrict.keep {
+        let next = if keep { &mut next_kept } else { &mut next_stub };
+        restrict.imports.push(*next);
+        *next += 1;
+    }
+
+    let mut restricted = Module::new();
+    restrict.parse_core_module(&mut restricted, Parser::new(0), module)?;
+    Ok(Some(restricted.finish()))
+}
+
+/// `Reencode` implementation for `restrict_imports`
+///
+/// Kept imports retain their relative order, followed by the module's own functions, followed by the stubs which
+/// replace the removed imports.
+#[derive(Default)]
+struct Restrict {
+    /// Whether to keep each function import, in order
+    keep: Vec<bool>,
+    /// New index of each function import (or the stub replacing it), in order
+    imports: Vec<u32>,
+    /// Type of each stub, in order
+    stub_types: Vec<u32>,
+    /// Number of function imports visited so far while reencoding
+    visited: usize,
+}
+
+impl Reencode for Restrict {
+    type Error = Infallible;
+
+    fn function_index(&mut self, func: u32) -> u32 {
+        match self.imports.get(usize::try_from(func).unwrap()) {
+            Some(&index) => index,
+            // The module's own functions move down to fill the space left by removed imports.
+            None => func - u32::try_from(self.stub_types.len()).unwrap(),
+        }
+    }
+
+    fn parse_import(
+        &mut self,
+        imports: &mut ImportSection,
+        import: Import<'_>,
+    ) -> Result<(), reencode::Error<Infallible>> {
+        if let TypeRef::Func(_) = import.ty {
+            self.visited += 1;
+            if !self.keep[self.visited - 1] {
+                return Ok(());
+            }
+        }
+        reencode::utils::parse_import(self, imports, import)
+    }
+
+    fn parse_function_section(
+        &mut self,
+        functions: &mut FunctionSection,
+        section: FunctionSectionReader<'_>,
+    ) -> Result<(), reencode::Error<Infallible>> {
+        reencode::utils::parse_function_section(self, functions, section)?;
+        for ty in self.stub_types.clone() {
+            functions.function(self.type_index(ty));
+        }
+        Ok(())
+    }
+
+    fn parse_code_section(
+        &mut self,
+        code: &mut CodeSection,
+        section: CodeSectionReader<'_>,
+    ) -> Result<(), reencode::Error<Infallible>> {
+        reencode::utils::parse_code_section(self, code, section)?;
+        for _ in &self.stub_types {
+            let mut function = Function::new([]);
+            function.instruction(&Ins::Unreachable);
+            function.instruction(&Ins::End);
+            code.function(&function);
+        }
+        Ok(())
+    }
+}
+
 fn add_wasi_imports<'a>(
     module: &'a [u8],
     imports: &mut HashMap<&'a str, HashMap<&'a str, FuncType>>,
@@ -132,3 +272,79 @@ fn make_stub_adapter(_module: &str, stubs: &HashMap<&str, FuncType>) -> Vec<u8>
 
     module.finish()
 }
+
+#[cfg(test)]
+mod tests {
+    use {super::*, wasm_encoder::EntityType, wasmparser::Validator};
+
+    #[test]
+    fn adapter_restricted() -> Result<(), Error> {
+        let mut types = TypeSection::new();
+        types.ty().function([], []);
+        let mut imports = ImportSection::new();
+        for module in [
+            "wasi:clocks/monotonic-clock@0.2.0",
+            "wasi:cli/stdout@0.2.0",
+            "wasi:filesystem/types@0.2.0",
+        ] {
+            imports.import(module, "f", EntityType::Function(0));
+        }
+        let mut functions = FunctionSection::new();
+        functions.function(0);
+        let mut exports = ExportSection::new();
+        exports.export("call-all", ExportKind::Func, 3);
+        let mut code = CodeSection::new();
+        let mut function = Function::new([]);
+        for index in 0..3 {
+            function.instruction(&Ins::Call(index));
+        }
+        function.instruction(&Ins::End);
+        code.function(&function);
+
+        let mut module = Module::new();
+        module.section(&types);
+        module.section(&imports);
+        module.section(&functions);
+        module.section(&exports);
+        module.section(&code);
+        let module = module.finish();
+
+        let restricted = restrict_imports(&module, &["wasi:cli/stdout"])?.unwrap();
+        Validator::new().validate_all(&restricted)?;
+
+        let mut imported = Vec::new();
+        let mut calls = Vec::new();
+        for payload in Parser::new(0).parse_all(&restricted) {
+            match payload? {
+                Payload::ImportSection(reader) => {
+                    for import in reader {
+                        imported.push(import?.module.to_owned());
+                    }
+                }
+                Payload::CodeSectionEntry(body) => {
+                    let mut calls_in_body = Vec::new();
+                    for operator in body.get_operators_reader()? {
+                        match operator? {
+                            wasmparser::Operator::Call { function_index } => {
+                                calls_in_body.push(function_index)
+                            }
+                            wasmparser::Operator::Unreachable => calls_in_body.push(u32::MAX),
+                            _ => {}
+                        }
+                    }
+                    calls.push(calls_in_body);
+                }
+                _ => {}
+            }
+        }
+
+        assert_eq!(vec!["wasi:cli/stdout@0.2.0".to_owned()], imported);
+        // The remaining import is function 0, the module's own function is 1, and the stubs are 2 and 3.
+        assert_eq!(vec![vec![2, 0, 3], vec![u32::MAX], vec![u32::MAX]], calls);
+
+        // A module which imports only allowed interfaces is left alone.
+        assert!(restrict_imports(&restricted, &["wasi:cli/stdout"])?.is_none());
+
+        Ok(())
+    }
+}
@@ -1,26 +1,26 @@
 use {
     crate::{
         abi::{self, MAX_FLAT_PARAMS, MAX_FLAT_RESULTS},
+        api,
         bindgen::{self, DISPATCHABLE_CORE_PARAM_COUNT},
         exports::exports::{
-            self, Case, Constructor, Function, FunctionExport, LocalResource, OwnedKind, OwnedType,
-            RemoteResource, Resource, Static, Symbols,
+            self, Bundled, Case, Constructor, Function, FunctionExport, LocalResource, OwnedKind,
+            OwnedType, RemoteResource, Resource, Static, Symbols,
         },
+        source::Source,
         util::Types as _,
+        NamingStyle, TypingStyle, VersionCollisions,
     },
     anyhow::{bail, Result},
-    heck::{ToShoutySnakeCase, ToSnakeCase, ToUpperCamelCase},
+    heck::{ToLowerCamelCase, ToShoutySnakeCase, ToSnakeCase, ToUpperCamelCase},
     indexmap::{IndexMap, IndexSet},
     once_cell::sync,
     semver::Version,
     std::{
-        collections::{hash_map::Entry, HashMap, HashSet},
-        fmt::Write as _,
-        fs::{self, File},
-        io::Write as _,
-        iter,
+        collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet},
+        fs, iter,
         ops::Deref,
-        path::Path,
+        path::{Path, PathBuf},
         str,
     },
     wasm_encoder::ValType,
@@ -32,6 +32,18 @@ use {
 
 const NOT_IMPLEMENTED: &str = "raise NotImplementedError";
 
+const EXIT_SIGNATURE: &str = "def __exit__(self, exc_type: type[BaseException] | None, exc_value: BaseException | None, traceback: TracebackType | None) -> bool | None:";
+
+/// Number of definitions (i.e. types and functions) above which the module generated for an imported interface is
+/// split into a package of shards which are imported on demand
+const SHARD_THRESHOLD: usize = 256;
+
+/// Maximum number of definitions in each shard other than those for resources, which get a shard of their own
+const SHARD_SIZE: usize = 64;
+
+/// Maximum number of flags in a flags type, per the component model specification
+const MAX_FLAGS: usize = 32;
+
 #[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum Direction {
     Import,
@@ -184,7 +196,7 @@ struct FunctionCode {
     params: String,
     args: String,
     return_statement: String,
-    class_method: &'static str,
+    class_method: bool,
     return_type: String,
     result_count: usize,
     error: Option<String>,
@@ -202,6 +214,8 @@ enum Code {
 struct TypeLocation {
     module: String,
     aliases: Option<Code>,
+    /// The names bound by the type's definition
+    names: Vec<String>,
 }
 
 #[derive(Default)]
@@ -211,6 +225,15 @@ pub struct Locations {
     types_module: Option<String>,
 }
 
+/// A function, method, or type in the generated bindings, as described by `Summary::api`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ApiItem {
+    /// Its signature (if it's a function or method) or definition (if it's a type), not including its name
+    pub signature: String,
+    /// Whether the app implements it, i.e. it's an exported function or method
+    pub implemented: bool,
+}
+
 pub struct Summary<'a> {
     pub resolve: &'a Resolve,
     pub functions: Vec<MyFunction<'a>>,
@@ -229,6 +252,10 @@ pub struct Summary<'a> {
     world_keys: HashMap<WorldId, HashSet<(Direction, WorldKey)>>,
     imported_interface_names: HashMap<InterfaceId, String>,
     exported_interface_names: HashMap<InterfaceId, String>,
+    class_names: HashMap<TypeId, String>,
+    interface_packages: HashMap<InterfaceId, (&'static str, String)>,
+    pub typing_style: TypingStyle,
+    naming_style: NamingStyle,
 }
 
 impl<'a> Summary<'a> {
@@ -237,6 +264,8 @@ impl<'a> Summary<'a> {
         worlds: &IndexSet<WorldId>,
         import_interface_names: &HashMap<&str, &str>,
         export_interface_names: &HashMap<&str, &str>,
+        naming_style: NamingStyle,
+        version_collisions: VersionCollisions,
     ) -> Result<Self> {
         let mut me = Self {
             resolve,
@@ -256,6 +285,10 @@ impl<'a> Summary<'a> {
             world_keys: HashMap::new(),
             imported_interface_names: HashMap::new(),
             exported_interface_names: HashMap::new(),
+            class_names: HashMap::new(),
+            interface_packages: HashMap::new(),
+            typing_style: TypingStyle::default(),
+            naming_style,
         };
 
         let mut import_keys_seen = HashSet::new();
@@ -280,15 +313,139 @@ impl<'a> Summary<'a> {
         me.imported_interface_names = me.interface_names(
             me.imported_interfaces.keys().copied(),
             import_interface_names,
-        );
+            version_collisions,
+        )?;
         me.exported_interface_names = me.interface_names(
             me.exported_interfaces.keys().copied(),
             export_interface_names,
-        );
+            version_collisions,
+        )?;
+
+        me.check_names()?;
+        me.check_flags()?;
+
+        // Cache the Python names of types and interface modules, which would otherwise be recomputed for every
+        // reference to them in the generated code.
+        me.class_names = me
+            .types
+            .iter()
+            .enumerate()
+            .map(|(index, &id)| {
+                let name = if let Some(name) = &resolve.types[id].name {
+                    name.to_upper_camel_case().escape()
+                } else {
+                    format!("AnonymousType{index}")
+                };
+                (id, name)
+            })
+            .collect();
+        // Note that imported names take precedence for interfaces which are both imported and exported.
+        me.interface_packages = me
+            .exported_interface_names
+            .iter()
+            .map(|(&id, name)| (id, ("exports", name.to_snake_case().escape())))
+            .chain(
+                me.imported_interface_names
+                    .iter()
+                    .map(|(&id, name)| (id, ("imports", name.to_snake_case().escape()))),
+            )
+            .collect();
 
         Ok(me)
     }
 
+    /// Check that no two functions in the same scope (or parameters of the same function) map to the same Python
+    /// name using `self.naming_style`, in which case one would silently shadow the other.
+    fn check_names(&self) -> Result<()> {
+        let mut seen = HashMap::new();
+        for function in &self.functions {
+            let direction = match function.kind {
+                FunctionKind::Import => Direction::Import,
+                FunctionKind::Export => Direction::Export,
+                _ => continue,
+            };
+            let resource = match function.wit_kind {
+                wit_parser::FunctionKind::Freestanding => None,
+                wit_parser::FunctionKind::Method(id) | wit_parser::FunctionKind::Static(id) => {
+                    Some(id)
+                }
+                wit_parser::FunctionKind::Constructor(_) => continue,
+            };
+
+            // Functions belonging to an interface share a module with the rest of that interface, whereas
+            // world-level functions share one with the rest of each world they belong to.
+            let scopes = if let Some(interface) = &function.interface {
+                vec![(None, Some(interface.id))]
+            } else {
+                self.world_keys
+                    .iter()
+                    .filter(|(_, keys)| keys.contains(&(direction, function.key())))
+                    .map(|(world, _)| (Some(*world), None))
+                    .collect()
+            };
+
+            let name = self.function_name(function);
+            for (world, interface) in scopes {
+                let key = (direction, world, interface, resource, name.clone());
+                if let Some(other) = seen.insert(key, function.name) {
+                    bail!(
+                        "WIT functions `{other}` and `{}` would both be named `{name}` in Python \
+                         using the `{}` naming style",
+                        function.name,
+                        self.naming_style
+                    );
+                }
+            }
+
+            let mut params = HashSet::new();
+            for (param, _) in function.params.iter() {
+                let python = self.member_name(param);
+                if !params.insert(python.clone()) {
+                    bail!(
+                        "more than one parameter of WIT function `{}` would be named `{python}` in Python \
+                         using the `{}` naming style",
+                        function.name,
+                        self.naming_style
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that no flags type has more than `MAX_FLAGS` flags.
+    ///
+    /// Older versions of the component model allowed wider flags types, represented using multiple `u32`s, but
+    /// they've since been removed from the specification and validators reject them, so we report them here rather
+    /// than generate bindings for a component which can't be built.
+    fn check_flags(&self) -> Result<()> {
+        for &id in &self.types {
+            let ty = &self.resolve.types[id];
+            if let TypeDefKind::Flags(flags) = &ty.kind {
+                if flags.flags.len() > MAX_FLAGS {
+                    bail!(
+                        "flags type `{}` has {} flags, but the component model allows at most {MAX_FLAGS}",
+                        ty.name.as_deref().unwrap_or("<anonymous>"),
+                        flags.flags.len()
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Return the Python name for the specified WIT function or parameter name according to `self.naming_style`.
+    fn member_name(&self, name: &str) -> String {
+        match self.naming_style {
+            NamingStyle::Snake => name.to_snake_case(),
+            NamingStyle::Camel => name.to_lower_camel_case(),
+            NamingStyle::Preserve => name.replace('-', "_"),
+        }
+        .escape()
+    }
+
     fn push_function(&mut self, function: MyFunction<'a>) {
         if function.is_dispatchable() {
             self.dispatch_count += 1;
@@ -625,11 +782,7 @@ impl<'a> Summary<'a> {
     fn summarize_type(&self, id: TypeId, world_module: &str) -> exports::Type {
         let ty = &self.resolve.types[id];
         if let Some(package) = self.package(ty.owner, world_module) {
-            let name = if let Some(name) = &ty.name {
-                name.to_upper_camel_case().escape()
-            } else {
-                format!("AnonymousType{}", self.types.get_index_of(&id).unwrap())
-            };
+            let name = self.class_name(id).to_owned();
             let kind = match &ty.kind {
                 TypeDefKind::Record(record) => OwnedKind::Record(
                     record
@@ -709,6 +862,15 @@ impl<'a> Summary<'a> {
         let mut exports = Vec::new();
         for function in &self.functions {
             if let FunctionKind::Export = function.kind {
+                if let Some((module, protocol)) = self.bundled_implementation(function) {
+                    exports.push(FunctionExport::Bundled(Bundled {
+                        module: module.into(),
+                        protocol: protocol.into(),
+                        name: self.function_name(function),
+                    }));
+                    continue;
+                }
+
                 let scope = if let Some(interface) = &function.interface {
                     &self.exported_interface_names[&interface.id]
                 } else {
@@ -762,30 +924,483 @@ impl<'a> Summary<'a> {
         }
     }
 
+    /// Return the name of the `componentize-py:*` package (e.g. `diagnostics`) to which `interface` belongs, if
+    /// any.
+    fn componentize_py_package(&self, interface: InterfaceId) -> Option<&str> {
+        let name = &self.resolve.packages[self.resolve.interfaces[interface].package?].name;
+        (name.namespace == "componentize-py").then_some(name.name.as_str())
+    }
+
+    /// Return the module and class of the bundled implementation of `function`, if it belongs to an interface
+    /// (e.g. `componentize-py:diagnostics/counters`) which is implemented by `componentize-py` rather than by the
+    /// app.
+    fn bundled_implementation(
+        &self,
+        function: &MyFunction,
+    ) -> Option<(&'static str, &'static str)> {
+        let id = function.interface.as_ref()?.id;
+        match (
+            self.componentize_py_package(id)?,
+            self.resolve.interfaces[id].name.as_deref()?,
+        ) {
+            ("diagnostics", "counters") => Some(("componentize_py_diagnostics", "Counters")),
+            ("diagnostics", "memory") => Some(("componentize_py_diagnostics", "Memory")),
+            ("lifecycle", _) => Some(("componentize_py_lifecycle", "Shutdown")),
+            ("pytest", "runner") => Some(("componentize_py_pytest", "Runner")),
+            _ => None,
+        }
+    }
+
+    /// Return true if `function` belongs to one of the `componentize-py:diagnostics/*` interfaces, which are
+    /// implemented by the bundled `componentize_py_diagnostics` module rather than by the app.
+    fn is_diagnostics_function(&self, function: &MyFunction) -> bool {
+        function
+            .interface
+            .as_ref()
+            .and_then(|interface| self.componentize_py_package(interface.id))
+            == Some("diagnostics")
+    }
+
+    /// Return true if `id` is the `datetime` record of `wasi:clocks/wall-clock`, for which we generate methods to
+    /// convert to and from Python `datetime`s.
+    fn is_wasi_datetime(&self, id: TypeId) -> bool {
+        let ty = &self.resolve.types[id];
+        ty.name.as_deref() == Some("datetime")
+            && matches!(
+                ty.owner,
+                TypeOwner::Interface(interface)
+                    if self.is_wasi_clocks(interface)
+                        && self.resolve.interfaces[interface].name.as_deref() == Some("wall-clock")
+            )
+            && matches!(
+                &ty.kind,
+                TypeDefKind::Record(record)
+                    if record.fields.iter().map(|field| field.name.as_str()).eq(["seconds", "nanoseconds"])
+            )
+    }
+
+    fn is_wasi_clocks(&self, interface: InterfaceId) -> bool {
+        self.resolve.interfaces[interface]
+            .package
+            .is_some_and(|package| {
+                let name = &self.resolve.packages[package].name;
+                name.namespace == "wasi" && name.name == "clocks"
+            })
+    }
+
+    /// Return true if `world` imports or exports any `wasi:clocks` interface, in which case we generate a
+    /// `time_helpers` module in its package.
+    fn uses_wasi_clocks(&self, world: WorldId) -> bool {
+        self.world_keys.get(&world).is_some_and(|keys| {
+            keys.iter()
+                .any(|(_, key)| matches!(key, WorldKey::Interface(id) if self.is_wasi_clocks(*id)))
+        })
+    }
+
+    /// Return true if `world` uses an `option` whose payload is itself an `option` (and thus represented using the
+    /// `Some` class), in which case we generate an `option` module of helpers in its package.
+    fn uses_nesting_options(&self, world: WorldId) -> bool {
+        self.world_types.get(&world).is_some_and(|types| {
+            types.iter().any(|&id| {
+                matches!(
+                    &self.resolve.types[id].kind,
+                    TypeDefKind::Option(some) if abi::is_option(self.resolve, *some)
+                )
+            })
+        })
+    }
+
+    /// Return true if `world` exports the `componentize-py:lifecycle/shutdown` interface, in which case its
+    /// module includes an `on_shutdown` function for registering callbacks.
+    fn exports_shutdown(&self, world: WorldId) -> bool {
+        self.world_keys.get(&world).is_some_and(|keys| {
+            keys.iter().any(|(direction, key)| match (direction, key) {
+                (Direction::Export, WorldKey::Interface(id)) => {
+                    self.componentize_py_package(*id) == Some("lifecycle")
+                }
+                _ => false,
+            })
+        })
+    }
+
+    /// Generate a listing of the names of the exported and imported functions for which the runtime maintains
+    /// counters, plus the types whose instances it counts, one per line, for use by the bundled
+    /// `componentize_py_diagnostics` module.
+    ///
+    /// Each line has the form `export\t<index>\t<name>` or `import\t<index>\t<name>`, where `<index>` is the
+    /// index the runtime uses for that function, or `type\t<index>\t<name>`, where `<index>` is the type's index
+    /// in the runtime's type table.
+    pub fn diagnostics_manifest(&self) -> String {
+        let exports = self
+            .functions
+            .iter()
+            .filter(|function| matches!(function.kind, FunctionKind::Export))
+            .enumerate()
+            .filter(|(_, function)| !self.is_diagnostics_function(function))
+            .map(|(index, function)| format!("export\t{index}\t{}", self.wit_name(function)));
+
+        let imports = self
+            .functions
+            .iter()
+            .filter(|function| function.is_dispatchable())
+            .enumerate()
+            .filter(|(_, function)| matches!(function.kind, FunctionKind::Import))
+            .map(|(index, function)| format!("import\t{index}\t{}", self.wit_name(function)));
+
+        let types = self
+            .types
+            .iter()
+            .enumerate()
+            .filter(|(_, &id)| {
+                let ty = &self.resolve.types[id];
+                matches!(
+                    ty.kind,
+                    TypeDefKind::Record(_) | TypeDefKind::Variant(_) | TypeDefKind::Resource
+                ) && !matches!(
+                    ty.owner,
+                    TypeOwner::Interface(interface)
+                        if self.componentize_py_package(interface) == Some("diagnostics")
+                )
+            })
+            .map(|(index, &id)| format!("type\t{index}\t{}", self.type_wit_name(id)));
+
+        exports
+            .chain(imports)
+            .chain(types)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Resolve the `exports` tables of `componentize-py.toml` files, which map exported interfaces (by qualified
+    /// name, with or without a version) to `module:class` paths, into `<protocol>\t<path>` lines for the runtime.
+    pub fn export_classes(&self, classes: &HashMap<&str, &str>) -> Result<String> {
+        let mut used = HashSet::new();
+        let mut lines = Vec::new();
+        for (id, info) in &self.exported_interfaces {
+            let unversioned = info.package.map(|package| PackageName {
+                version: None,
+                ..package
+            });
+            let Some((&name, &path)) =
+                [info.package, unversioned].into_iter().find_map(|package| {
+                    classes.get_key_value(qualified_name(info.name, package).as_str())
+                })
+            else {
+                continue;
+            };
+
+            if !path.contains(':') {
+                bail!("expected a path of the form `module:class` for exported interface `{name}`, got `{path}`");
+            }
+
+            used.insert(name);
+            lines.push(format!(
+                "{}\t{path}",
+                self.exported_interface_names[id]
+                    .to_upper_camel_case()
+                    .escape()
+            ));
+        }
+
+        if let Some(name) = classes.keys().find(|name| !used.contains(*name)) {
+            bail!("a class was specified for exported interface `{name}`, but no world exports it");
+        }
+
+        // Sort so the result doesn't depend on hash map iteration order.
+        lines.sort();
+        Ok(lines.join("\n"))
+    }
+
+    /// Generate a description of the types of the values which Python code passes to the host, for use by the
+    /// runtime when the component is built with `--strict` (in which case `full` is true) or uses exception
+    /// mappings.
+    ///
+    /// Each line is one of:
+    ///
+    /// - `record\t<type>\t<name>\t<field>...`, `variant\t<type>\t<name>\t<case>...`, or `type\t<type>\t<name>`
+    ///   (for enums, flags, and resources), describing an entry in the runtime's type table,
+    /// - `error\t<index>\t<name>\t<error>`, describing the `error` payload of an exported function which returns
+    ///   a `result`, against which the output of an exception mapping is checked,
+    /// - `export\t<index>\t<name>\t<result>`, describing the value returned by an exported function (or just
+    ///   its `ok` payload, if it returns a `result`), or
+    /// - `import\t<index>\t<name>(\t<parameter>\t<type>)*`, describing the parameters of an imported function.
+    ///
+    /// The latter two are omitted unless `full` is true.  Types are written using WIT syntax (see `strict_type`),
+    /// with `_` standing for "no value".
+    pub fn strict_manifest(&self, full: bool) -> String {
+        let types = self.types.iter().enumerate().filter_map(|(index, &id)| {
+            let ty = &self.resolve.types[id];
+            let name = ty.name.as_deref().unwrap_or("anonymous");
+            Some(match &ty.kind {
+                TypeDefKind::Record(record) => iter::once(format!("record\t{index}\t{name}"))
+                    .chain(record.fields.iter().map(|field| self.strict_type(field.ty)))
+                    .collect::<Vec<_>>()
+                    .join("\t"),
+                TypeDefKind::Variant(variant) => iter::once(format!("variant\t{index}\t{name}"))
+                    .chain(
+                        variant
+                            .cases
+                            .iter()
+                            .map(|case| self.strict_type_or_unit(case.ty)),
+                    )
+                    .collect::<Vec<_>>()
+                    .join("\t"),
+                TypeDefKind::Enum(_) | TypeDefKind::Flags(_) | TypeDefKind::Resource => {
+                    format!("type\t{index}\t{name}")
+                }
+                _ => return None,
+            })
+        });
+
+        let exports = self
+            .functions
+            .iter()
+            .filter(|function| matches!(function.kind, FunctionKind::Export))
+            .enumerate()
+            .map(|(index, function)| {
+                let result = match function.results.types().collect::<Vec<_>>().as_slice() {
+                    [] => "_".to_owned(),
+                    // This must match the `ReturnStyle` chosen in `bindgen::compile_export`:
+                    &[Type::Id(id)] => match &self.resolve.types[id].kind {
+                        TypeDefKind::Result(result) => self.strict_type_or_unit(result.ok),
+                        _ => self.strict_type(Type::Id(id)),
+                    },
+                    &[ty] => self.strict_type(ty),
+                    types => format!(
+                        "tuple<{}>",
+                        types
+                            .iter()
+                            .map(|ty| self.strict_type(*ty))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                };
+                format!("export\t{index}\t{}\t{result}", self.wit_name(function))
+            })
+            .filter(|_| full);
+
+        let errors = self
+            .functions
+            .iter()
+            .filter(|function| matches!(function.kind, FunctionKind::Export))
+            .enumerate()
+            .filter_map(|(index, function)| {
+                let results = function.results.types().collect::<Vec<_>>();
+                let &[Type::Id(id)] = results.as_slice() else {
+                    return None;
+                };
+                let TypeDefKind::Result(result) = &self.resolve.types[id].kind else {
+                    return None;
+                };
+                Some(format!(
+                    "error\t{index}\t{}\t{}",
+                    self.wit_name(function),
+                    self.strict_type_or_unit(result.err)
+                ))
+            });
+
+        let imports = self
+            .functions
+            .iter()
+            .filter(|function| function.is_dispatchable())
+            .enumerate()
+            .filter(|(_, function)| matches!(function.kind, FunctionKind::Import))
+            .map(|(index, function)| {
+                iter::once(format!("import\t{index}\t{}", self.wit_name(function)))
+                    .chain(
+                        function
+                            .params
+                            .iter()
+                            .flat_map(|(name, ty)| [name.clone(), self.strict_type(*ty)]),
+                    )
+                    .collect::<Vec<_>>()
+                    .join("\t")
+            })
+            .filter(|_| full);
+
+        types
+            .chain(errors)
+            .chain(exports)
+            .chain(imports)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Generate a description of the functions whose calls are logged by the runtime when the component is built
+    /// with `--trace-imports`.
+    ///
+    /// Each line is `export\t<index>\t<name>` or `import\t<index>\t<name>`, indexed as for
+    /// `diagnostics_manifest`.  If the world imports `wasi:logging/logging`, a final `logging\t<index>\t<type>` line
+    /// gives the index of its `log` function and the index of its `level` enum in the type table, so that the
+    /// runtime can send messages there rather than to `stderr`.
+    pub fn trace_manifest(&self) -> String {
+        let exports = self
+            .functions
+            .iter()
+            .filter(|function| matches!(function.kind, FunctionKind::Export))
+            .enumerate()
+            .map(|(index, function)| format!("export\t{index}\t{}", self.wit_name(function)));
+
+        let imports = self
+            .functions
+            .iter()
+            .filter(|function| function.is_dispatchable())
+            .enumerate()
+            .filter(|(_, function)| matches!(function.kind, FunctionKind::Import));
+
+        let logging = imports.clone().find_map(|(index, function)| {
+            let interface = self.resolve.id_of(function.interface.as_ref()?.id)?;
+            if function.name != "log" || interface.split('@').next() != Some("wasi:logging/logging")
+            {
+                return None;
+            }
+            let Type::Id(level) = function.params.first()?.1 else {
+                return None;
+            };
+            Some(format!(
+                "logging\t{index}\t{}",
+                self.types.get_index_of(&level)?
+            ))
+        });
+
+        exports
+            .chain(
+                imports.map(|(index, function)| {
+                    format!("import\t{index}\t{}", self.wit_name(function))
+                }),
+            )
+            .chain(logging)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Describe `ty` using WIT syntax for the runtime's `--strict` checks.
+    ///
+    /// Types with their own Python classes (records, variants, enums, flags, and resources, including handles to
+    /// the latter) are written as `#<index>`, where `<index>` is the type's index in the runtime's type table.
+    /// `list<u8>` is written as `bytes` since it is represented as a Python `bytes` object, and an `option` whose
+    /// payload is itself an `option` is written as `nesting-option` since its `Some` case is represented as an
+    /// instance of the `Some` class.
+    fn strict_type(&self, ty: Type) -> String {
+        match ty {
+            Type::Bool => "bool".into(),
+            Type::U8 => "u8".into(),
+            Type::S8 => "s8".into(),
+            Type::U16 => "u16".into(),
+            Type::S16 => "s16".into(),
+            Type::U32 => "u32".into(),
+            Type::S32 => "s32".into(),
+            Type::U64 => "u64".into(),
+            Type::S64 => "s64".into(),
+            Type::F32 => "f32".into(),
+            Type::F64 => "f64".into(),
+            Type::Char => "char".into(),
+            Type::String => "string".into(),
+            Type::Id(id) => {
+                let index = |id| {
+                    self.types
+                        .get_index_of(&id)
+                        .map_or_else(|| "any".to_owned(), |index| format!("#{index}"))
+                };
+
+                match &self.resolve.types[id].kind {
+                    TypeDefKind::Record(_)
+                    | TypeDefKind::Variant(_)
+                    | TypeDefKind::Enum(_)
+                    | TypeDefKind::Flags(_)
+                    | TypeDefKind::Resource => index(id),
+                    TypeDefKind::Handle(Handle::Own(resource) | Handle::Borrow(resource)) => {
+                        index(bindgen::dealias(self.resolve, *resource))
+                    }
+                    TypeDefKind::List(Type::U8) => "bytes".into(),
+                    TypeDefKind::List(ty) => format!("list<{}>", self.strict_type(*ty)),
+                    TypeDefKind::Option(ty) => {
+                        if abi::is_option(self.resolve, *ty) {
+                            format!("nesting-option<{}>", self.strict_type(*ty))
+                        } else {
+                            format!("option<{}>", self.strict_type(*ty))
+                        }
+                    }
+                    TypeDefKind::Result(result) => format!(
+                        "result<{}, {}>",
+                        self.strict_type_or_unit(result.ok),
+                        self.strict_type_or_unit(result.err)
+                    ),
+                    TypeDefKind::Tuple(tuple) => format!(
+                        "tuple<{}>",
+                        tuple
+                            .types
+                            .iter()
+                            .map(|ty| self.strict_type(*ty))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ),
+                    TypeDefKind::Type(ty) => self.strict_type(*ty),
+                    _ => "any".into(),
+                }
+            }
+        }
+    }
+
+    fn strict_type_or_unit(&self, ty: Option<Type>) -> String {
+        ty.map_or_else(|| "_".to_owned(), |ty| self.strict_type(ty))
+    }
+
+    /// Return the fully-qualified WIT name of `function`, e.g. `wasi:http/handler#handle`.
+    fn wit_name(&self, function: &MyFunction) -> String {
+        if let Some(interface) = &function.interface {
+            format!(
+                "{}#{}",
+                self.resolve
+                    .id_of(interface.id)
+                    .unwrap_or_else(|| interface.name.to_owned()),
+                function.name
+            )
+        } else {
+            function.name.to_owned()
+        }
+    }
+
+    fn type_wit_name(&self, id: TypeId) -> String {
+        let ty = &self.resolve.types[id];
+        let name = ty.name.as_deref().unwrap_or("anonymous");
+        if let TypeOwner::Interface(interface) = ty.owner {
+            format!(
+                "{}#{name}",
+                self.resolve
+                    .id_of(interface)
+                    .unwrap_or_else(|| self.resolve.interfaces[interface]
+                        .name
+                        .clone()
+                        .unwrap_or_default())
+            )
+        } else {
+            name.to_owned()
+        }
+    }
+
     fn function_name(&self, function: &MyFunction) -> String {
         self.function_name_with(&function.wit_kind, function.name)
     }
 
     fn function_name_with(&self, kind: &wit_parser::FunctionKind, name: &str) -> String {
         match kind {
-            wit_parser::FunctionKind::Freestanding => name.to_snake_case().escape(),
+            wit_parser::FunctionKind::Freestanding => self.member_name(name),
             wit_parser::FunctionKind::Constructor(_) => "__init__".into(),
-            wit_parser::FunctionKind::Method(id) => name
-                .strip_prefix(&format!(
+            wit_parser::FunctionKind::Method(id) => self.member_name(
+                name.strip_prefix(&format!(
                     "[method]{}.",
                     self.resolve.types[*id].name.as_deref().unwrap()
                 ))
-                .unwrap()
-                .to_snake_case()
-                .escape(),
-            wit_parser::FunctionKind::Static(id) => name
-                .strip_prefix(&format!(
+                .unwrap(),
+            ),
+            wit_parser::FunctionKind::Static(id) => self.member_name(
+                name.strip_prefix(&format!(
                     "[static]{}.",
                     self.resolve.types[*id].name.as_deref().unwrap()
                 ))
-                .unwrap()
-                .to_snake_case()
-                .escape(),
+                .unwrap(),
+            ),
         }
     }
 
@@ -824,7 +1439,19 @@ impl<'a> Summary<'a> {
             wit_parser::FunctionKind::Static(_) => (0, Some("cls")),
         };
 
-        let mut type_name = |ty| names.type_name(ty, seen, resource);
+        // A `list<u8>` passed from the guest to the host (i.e. an import parameter or export result) may be given as
+        // any object supporting the buffer protocol, whereas one passed the other way is always `bytes`.
+        let (lower_params, lower_results) = (
+            direction == Direction::Import,
+            direction == Direction::Export,
+        );
+        let mut type_name = |ty, lowered| {
+            if lowered {
+                names.lowered_type_name(ty, seen, resource)
+            } else {
+                names.type_name(ty, seen, resource)
+            }
+        };
 
         let absolute_type_name = |ty| {
             format!(
@@ -851,8 +1478,11 @@ impl<'a> Summary<'a> {
             .map(|s| s.to_string())
             .into_iter()
             .chain(function.params.iter().skip(skip_count).map(|(name, ty)| {
-                let snake = name.to_snake_case().escape();
-                format!("{snake}: {}", type_name(*ty))
+                format!(
+                    "{}: {}",
+                    self.member_name(name),
+                    type_name(*ty, lower_params)
+                )
             }))
             .collect::<Vec<_>>()
             .join(", ");
@@ -860,7 +1490,7 @@ impl<'a> Summary<'a> {
         let args = function
             .params
             .iter()
-            .map(|(name, _)| name.to_snake_case().escape())
+            .map(|(name, _)| self.member_name(name))
             .collect::<Vec<_>>()
             .join(", ");
 
@@ -870,36 +1500,34 @@ impl<'a> Summary<'a> {
             if let wit_parser::FunctionKind::Constructor(_) = function.wit_kind {
                 ("return".to_owned(), "None".to_owned(), None)
             } else {
-                let indent = if let wit_parser::FunctionKind::Freestanding = function.wit_kind {
-                    ""
-                } else {
-                    "    "
-                };
-
                 match result_types.as_slice() {
                     [] => ("return".to_owned(), "None".to_owned(), None),
                     [ty] => match special_return(*ty) {
                         SpecialReturn::Result(result) => {
-                            let error = if let Some(ty) = result.err {
-                                Some(absolute_type_name(ty))
-                            } else {
-                                Some("None".into())
-                            };
+                            let error = Some(match result.err {
+                                Some(Type::String) => "WitError".into(),
+                                Some(ty) => format!("Err({})", absolute_type_name(ty)),
+                                None => "Err(None)".into(),
+                            });
 
                             (
-                                format!(
-                                    "if isinstance(result[0], Err):
-{indent}        raise result[0]
-{indent}    else:
-{indent}        return result[0].value"
-                                ),
-                                result.ok.map(type_name).unwrap_or_else(|| "None".into()),
+                                "if isinstance(result[0], Err):
+    raise result[0]
+else:
+    return result[0].value"
+                                    .to_owned(),
+                                result
+                                    .ok
+                                    .map(|ty| type_name(ty, lower_results))
+                                    .unwrap_or_else(|| "None".into()),
                                 error,
                             )
                         }
-                        SpecialReturn::None => {
-                            ("return result[0]".to_owned(), type_name(*ty), None)
-                        }
+                        SpecialReturn::None => (
+                            "return result[0]".to_owned(),
+                            type_name(*ty, lower_results),
+                            None,
+                        ),
                     },
                     _ => (
                         "return result".to_owned(),
@@ -907,7 +1535,7 @@ impl<'a> Summary<'a> {
                             "({})",
                             result_types
                                 .iter()
-                                .map(|ty| type_name(*ty))
+                                .map(|ty| type_name(*ty, lower_results))
                                 .collect::<Vec<_>>()
                                 .join(", ")
                         ),
@@ -918,11 +1546,7 @@ impl<'a> Summary<'a> {
 
         let result_count = result_types.len();
 
-        let class_method = if let wit_parser::FunctionKind::Static(_) = function.wit_kind {
-            "\n    @classmethod"
-        } else {
-            ""
-        };
+        let class_method = matches!(function.wit_kind, wit_parser::FunctionKind::Static(_));
 
         FunctionCode {
             snake,
@@ -1062,11 +1686,19 @@ impl<'a> Summary<'a> {
         sorted
     }
 
+    /// Choose the name of the Python module for each of the specified interfaces.
+    ///
+    /// Each interface is named using an exact match for its fully-qualified name (e.g. `wasi:http/types@0.2.0`) in
+    /// `interface_names`, if any, or else the most specific wildcard pattern there which matches it (see
+    /// `match_pattern`).  Failing that, its own name is used, qualified with its package's namespace and name if it
+    /// would otherwise clash with an interface from another package, and with its version (according to
+    /// `version_collisions`) if it would otherwise clash with another version of the same interface.
     fn interface_names(
         &self,
         ids: impl Iterator<Item = InterfaceId>,
         interface_names: &HashMap<&str, &str>,
-    ) -> HashMap<InterfaceId, String> {
+        version_collisions: VersionCollisions,
+    ) -> Result<HashMap<InterfaceId, String>> {
         let mut tree = HashMap::<_, HashMap<_, HashMap<_, _>>>::new();
         for id in ids {
             let info = if let Some(info) = self.imported_interfaces.get(&id) {
@@ -1077,71 +1709,115 @@ impl<'a> Summary<'a> {
                 unreachable!()
             };
 
-            assert!(tree
+            if tree
                 .entry(info.name)
                 .or_default()
                 .entry(info.package.map(|p| (p.namespace, p.name)))
                 .or_default()
                 .insert(info.package.and_then(|p| p.version), id)
-                .is_none());
+                .is_some()
+            {
+                bail!(
+                    "more than one interface is named `{}`",
+                    qualified_name(info.name, info.package)
+                );
+            }
         }
 
         let mut names = HashMap::new();
         for (name, packages) in &tree {
             for (package, versions) in packages {
-                if let Some((package_namespace, package_name)) = package {
-                    for (version, id) in versions {
-                        assert!(names
-                            .insert(
-                                *id,
-                                if let Some(version) = version {
-                                    if let Some(name) = interface_names.get(
-                                        format!(
-                                            "{package_namespace}:{package_name}/{name}@{version}"
-                                        )
-                                        .as_str(),
-                                    ) {
-                                        (*name).to_owned()
-                                    } else if versions.len() == 1 {
-                                        if packages.len() == 1 {
-                                            (*name).to_owned()
-                                        } else {
-                                            format!("{}-{}-{name}", package_namespace, package_name)
-                                        }
+                // When there's more than one version of the same interface, `VersionCollisions::DuplicateIfNecessary`
+                // leaves the unversioned one, if any, or else the latest one unqualified by version.
+                let latest = if versions.contains_key(&None) {
+                    None
+                } else {
+                    versions.keys().max().copied().flatten()
+                };
+
+                for (version, id) in versions {
+                    let qualified = qualified_name(
+                        name,
+                        package.map(|(namespace, package_name)| PackageName {
+                            namespace,
+                            name: package_name,
+                            version: *version,
+                        }),
+                    );
+
+                    let python_name = if let Some(python_name) =
+                        interface_names.get(qualified.as_str())
+                    {
+                        (*python_name).to_owned()
+                    } else if let Some(python_name) = match_pattern(interface_names, &qualified)? {
+                        python_name
+                    } else if let Some((package_namespace, package_name)) = package {
+                        let base = if packages.len() == 1 {
+                            (*name).to_owned()
+                        } else {
+                            format!("{package_namespace}-{package_name}-{name}")
+                        };
+
+                        match version {
+                            Some(version) if versions.len() > 1 => match version_collisions {
+                                VersionCollisions::Qualify => format!(
+                                    "{package_namespace}-{package_name}-{name}-{}",
+                                    version.to_string().replace('.', "-")
+                                ),
+                                VersionCollisions::DuplicateIfNecessary => {
+                                    if latest == Some(*version) {
+                                        base
                                     } else {
-                                        format!(
-                                            "{}-{}-{name}-{}",
-                                            package_namespace,
-                                            package_name,
-                                            version.to_string().replace('.', "-")
-                                        )
+                                        format!("{base}-{}", version.to_string().replace('.', "-"))
                                     }
-                                } else if let Some(name) = interface_names.get(
-                                    format!("{package_namespace}:{package_name}/{name}").as_str()
-                                ) {
-                                    (*name).to_owned()
-                                } else if packages.len() == 1 {
-                                    (*name).to_owned()
-                                } else {
-                                    format!("{}-{}-{name}", package_namespace, package_name)
                                 }
-                            )
-                            .is_none());
-                    }
-                } else {
-                    assert!(names
-                        .insert(
-                            *versions.get(&None).unwrap(),
-                            (*interface_names.get(*name).unwrap_or(name)).to_owned()
-                        )
-                        .is_none());
+                            },
+                            _ => base,
+                        }
+                    } else {
+                        (*name).to_owned()
+                    };
+
+                    names.insert(*id, python_name);
                 }
             }
         }
 
-        names
+        // Make sure no two interfaces would share a module, in which case one would silently overwrite the other.
+        let mut modules = HashMap::<_, Vec<_>>::new();
+        for (id, python_name) in &names {
+            let info = self
+                .imported_interfaces
+                .get(id)
+                .or_else(|| self.exported_interfaces.get(id))
+                .unwrap();
+            modules
+                .entry(python_name.to_snake_case().escape())
+                .or_default()
+                .push(qualified_name(info.name, info.package));
+        }
+        let mut clashes = modules
+            .into_iter()
+            .filter(|(_, interfaces)| interfaces.len() > 1)
+            .collect::<Vec<_>>();
+        clashes.sort();
+        if let Some((module, mut interfaces)) = clashes.into_iter().next() {
+            interfaces.sort();
+            bail!(
+                "interfaces {} would all use the Python module name `{module}`; \
+                 please use `--import-interface-name` or `--export-interface-name` to give them unique names",
+                interfaces
+                    .iter()
+                    .map(|name| format!("`{name}`"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        Ok(names)
     }
 
+    /// Generate the bindings for `world` in the directory `path`, returning the paths of the files generated.
     pub fn generate_code(
         &self,
         path: &Path,
@@ -1149,106 +1825,198 @@ impl<'a> Summary<'a> {
         world_module: &str,
         locations: &mut Locations,
         stub_runtime_calls: bool,
-    ) -> Result<()> {
+    ) -> Result<BTreeSet<PathBuf>> {
+        /// A top-level definition in a generated module
+        struct Definition {
+            code: String,
+            /// The names bound by `code`
+            names: Vec<String>,
+            /// Whether `code` defines a resource, which gets a shard of its own (see `SHARD_THRESHOLD`)
+            resource: bool,
+        }
+
         #[derive(Default)]
         struct Definitions<'a> {
-            types: Vec<String>,
-            functions: Vec<String>,
+            types: Vec<Definition>,
+            functions: Vec<Definition>,
             type_imports: HashSet<InterfaceId>,
             function_imports: HashSet<InterfaceId>,
             docs: Option<&'a str>,
             alias_module: Option<String>,
+            /// Whether the types use the `time_helpers` module (see `is_wasi_datetime`)
+            time_helpers: bool,
         }
 
-        let mut interface_imports = HashMap::<InterfaceId, Definitions>::new();
-        let mut interface_exports = HashMap::<InterfaceId, Definitions>::new();
-        let mut world_imports = Definitions::default();
-        let mut world_exports = Definitions::default();
-        let mut seen = HashSet::new();
-        for (index, id) in self.types.iter().copied().enumerate() {
-            if !self
-                .world_types
-                .get(&world)
-                .map(|types| types.contains(&id))
-                .unwrap_or(false)
-            {
+        /// Split `definitions` into named shards, preserving their order.
+        ///
+        /// Each resource (along with its methods) gets a shard of its own, while other definitions are grouped into
+        /// shards of up to `SHARD_SIZE` each.
+        fn shards<'a>(
+            definitions: impl Iterator<Item = &'a Definition>,
+        ) -> Vec<(String, Vec<&'a Definition>)> {
+            let mut shards = Vec::<(String, Vec<&Definition>)>::new();
+            let mut groups = 0;
+            for definition in definitions {
+                match shards.last_mut() {
+                    Some((shard, group))
+                        if !definition.resource
+                            && shard.starts_with("_group")
+                            && group.len() < SHARD_SIZE =>
+                    {
+                        group.push(definition)
+                    }
+                    _ => {
+                        let shard = if definition.resource {
+                            format!("_resource_{}", definition.names[0].to_snake_case())
+                        } else {
+                            groups += 1;
+                            format!("_group{}", groups - 1)
+                        };
+                        shards.push((shard, vec![definition]));
+                    }
+                }
+            }
+            shards
+        }
+
+        /// Generate the `__init__.py` of a sharded module, which imports each shard the first time one of the names
+        /// it defines is used.
+        fn shard_index(docs: Option<&str>, shards: &[(String, Vec<&Definition>)]) -> String {
+            let mut source = Source::default();
+            source.docstring(docs);
+            source.line("from typing import TYPE_CHECKING, Any, List");
+            source.line("import importlib");
+            source.blank_line();
+            source.block("if TYPE_CHECKING:", |source| {
+                for (shard, _) in shards {
+                    source.line(format!("from .{shard} import *"));
+                }
+            });
+            source.block("else:", |source| {
+                source.line("_SHARDS = {");
+                for (shard, definitions) in shards {
+                    for name in definitions.iter().flat_map(|definition| &definition.names) {
+                        source.line(format!("    \"{name}\": \"{shard}\","));
+                    }
+                }
+                source.line("}");
+                source.blank_line();
+                source.line("__all__ = list(_SHARDS)");
+                source.blank_line();
+                source.push(
+                    "def __getattr__(name: str) -> Any:
+    shard = _SHARDS.get(name)
+    if shard is None:
+        raise AttributeError(f\"module {__name__!r} has no attribute {name!r}\")
+    value = getattr(importlib.import_module(f\".{shard}\", __name__), name)
+    # Cache the value so later uses of the name don't come back here.
+    globals()[name] = value
+    return value
+
+def __dir__() -> List[str]:
+    return sorted({*globals(), *_SHARDS})",
+                );
+            });
+            source.into()
+        }
+
+        // Note that we use `IndexMap`s here (and sort any `import` statements below) so that the generated code is
+        // deterministic.
+        let mut interface_imports = IndexMap::<InterfaceId, Definitions>::new();
+        let mut interface_exports = IndexMap::<InterfaceId, Definitions>::new();
+        let mut world_imports = Definitions::default();
+        let mut world_exports = Definitions::default();
+        let mut seen = HashSet::new();
+        let mut uses_time_helpers = self.uses_wasi_clocks(world);
+        for id in self.types.iter().copied() {
+            if !self
+                .world_types
+                .get(&world)
+                .map(|types| types.contains(&id))
+                .unwrap_or(false)
+            {
                 continue;
             }
 
             let ty = &self.resolve.types[id];
             let mut names = TypeNames::new(self, ty.owner);
 
-            let camel = || {
-                if let Some(name) = &ty.name {
-                    name.to_upper_camel_case().escape()
-                } else {
-                    format!("AnonymousType{index}")
-                }
-            };
-
-            let make_class = |names: &mut TypeNames, name, docs, fields: Vec<(String, Type)>| {
-                let mut fields = fields
-                    .iter()
-                    .map(|(field_name, field_type)| {
-                        format!(
+            let camel = || self.class_name(id).to_owned();
+
+            let make_class = |names: &mut TypeNames,
+                              name,
+                              docs,
+                              fields: Vec<(String, Type)>,
+                              methods: Vec<String>| {
+                let mut source = Source::default();
+                source.line("@dataclass");
+                source.block(format!("class {name}:"), |source| {
+                    source.docstring(docs);
+                    for (field_name, field_type) in &fields {
+                        source.line(format!(
                             "{field_name}: {}",
                             names.type_name(*field_type, &seen, None)
-                        )
-                    })
-                    .collect::<Vec<_>>()
-                    .join("\n    ");
-
-                if fields.is_empty() {
-                    "pass".to_owned().clone_into(&mut fields)
-                }
-
-                let docs = docstring(world_module, docs, 1, None);
-
-                format!(
-                    "
-@dataclass
-class {name}:
-    {docs}{fields}
-"
-                )
+                        ));
+                    }
+                    for method in &methods {
+                        source.blank_line();
+                        source.push(method);
+                    }
+                });
+                String::from(source)
             };
 
-            let code = if let Some(location) = locations.types.get(&id) {
-                location.aliases.clone()
+            let mut time_helpers = false;
+            let (code, defined) = if let Some(location) = locations.types.get(&id) {
+                (location.aliases.clone(), location.names.clone())
             } else {
                 let (code, names) = match &ty.kind {
-                    TypeDefKind::Record(record) => (
-                        Some(Code::Shared(make_class(
-                            &mut names,
-                            camel(),
-                            ty.docs.contents.as_deref(),
-                            record
-                                .fields
-                                .iter()
-                                .map(|field| (field.name.to_snake_case().escape(), field.ty))
-                                .collect::<Vec<_>>(),
-                        ))),
-                        vec![camel()],
-                    ),
-                    TypeDefKind::Variant(variant) => {
-                        let camel = camel();
-                        let classes = variant
-                            .cases
+                    TypeDefKind::Record(record) => {
+                        let fields = record
+                            .fields
                             .iter()
-                            .map(|case| {
-                                make_class(
-                                    &mut names,
-                                    format!("{camel}_{}", case.name.to_upper_camel_case().escape()),
-                                    None,
-                                    if let Some(ty) = case.ty {
-                                        vec![("value".into(), ty)]
-                                    } else {
-                                        Vec::new()
-                                    },
+                            .map(|field| {
+                                (
+                                    field.name.to_snake_case().escape(),
+                                    field.name.as_str(),
+                                    field.ty,
                                 )
                             })
-                            .collect::<Vec<_>>()
-                            .join("\n");
+                            .collect::<Vec<_>>();
+                        let mut methods = names.dict_methods(&fields);
+                        if self.is_wasi_datetime(id) {
+                            methods.extend(datetime_methods());
+                            time_helpers = true;
+                            uses_time_helpers = true;
+                        }
+                        (
+                            Some(Code::Shared(make_class(
+                                &mut names,
+                                camel(),
+                                ty.docs.contents.as_deref(),
+                                fields.into_iter().map(|(name, _, ty)| (name, ty)).collect(),
+                                methods,
+                            ))),
+                            vec![camel()],
+                        )
+                    }
+                    TypeDefKind::Variant(variant) => {
+                        let camel = camel();
+                        let mut source = Source::default();
+                        for case in &variant.cases {
+                            source.push(&make_class(
+                                &mut names,
+                                format!("{camel}_{}", case.name.to_upper_camel_case().escape()),
+                                None,
+                                if let Some(ty) = case.ty {
+                                    vec![("value".into(), ty)]
+                                } else {
+                                    Vec::new()
+                                },
+                                Vec::new(),
+                            ));
+                            source.blank_line();
+                        }
 
                         let cases = variant
                             .cases
@@ -1256,20 +2024,16 @@ class {name}:
                             .map(|case| {
                                 format!("{camel}_{}", case.name.to_upper_camel_case().escape())
                             })
-                            .collect::<Vec<_>>()
-                            .join(", ");
+                            .collect::<Vec<_>>();
 
-                        let docs = docstring(world_module, ty.docs.contents.as_deref(), 0, None);
+                        source.line(match self.typing_style {
+                            TypingStyle::Legacy => format!("{camel} = Union[{}]", cases.join(", ")),
+                            TypingStyle::Modern => format!("type {camel} = {}", cases.join(" | ")),
+                        });
+                        source.docstring(ty.docs.contents.as_deref());
 
                         (
-                            Some(Code::Shared(format!(
-                                "
-{classes}
-
-{camel} = Union[{cases}]
-{docs}
-"
-                            ))),
+                            Some(Code::Shared(source.into())),
                             variant
                                 .cases
                                 .iter()
@@ -1284,60 +2048,35 @@ class {name}:
                     }
                     TypeDefKind::Enum(en) => {
                         let camel = camel();
-                        let cases = en
-                            .cases
-                            .iter()
-                            .enumerate()
-                            .map(|(index, case)| {
-                                format!("{} = {index}", case.name.to_shouty_snake_case())
-                            })
-                            .collect::<Vec<_>>()
-                            .join("\n    ");
-
-                        let docs = docstring(world_module, ty.docs.contents.as_deref(), 1, None);
+                        let mut source = Source::default();
+                        source.block(format!("class {camel}(Enum):"), |source| {
+                            source.docstring(ty.docs.contents.as_deref());
+                            for (index, case) in en.cases.iter().enumerate() {
+                                source.line(format!(
+                                    "{} = {index}",
+                                    case.name.to_shouty_snake_case()
+                                ));
+                            }
+                        });
 
-                        (
-                            Some(Code::Shared(format!(
-                                "
-class {camel}(Enum):
-    {docs}{cases}
-"
-                            ))),
-                            vec![camel],
-                        )
+                        (Some(Code::Shared(source.into())), vec![camel])
                     }
                     TypeDefKind::Flags(flags) => {
                         let camel = camel();
-                        let flags = flags
-                            .flags
-                            .iter()
-                            .map(|flag| format!("{} = auto()", flag.name.to_shouty_snake_case()))
-                            .collect::<Vec<_>>()
-                            .join("\n    ");
-
-                        let flags = if flags.is_empty() {
-                            "pass".to_owned()
-                        } else {
-                            flags
-                        };
-
-                        let docs = docstring(world_module, ty.docs.contents.as_deref(), 1, None);
+                        let mut source = Source::default();
+                        source.block(format!("class {camel}(Flag):"), |source| {
+                            source.docstring(ty.docs.contents.as_deref());
+                            for flag in &flags.flags {
+                                source
+                                    .line(format!("{} = auto()", flag.name.to_shouty_snake_case()));
+                            }
+                        });
 
-                        (
-                            Some(Code::Shared(format!(
-                                "
-class {camel}(Flag):
-    {docs}{flags}
-"
-                            ))),
-                            vec![camel],
-                        )
+                        (Some(Code::Shared(source.into())), vec![camel])
                     }
                     TypeDefKind::Resource => {
                         let camel = camel();
 
-                        let docs = docstring(world_module, ty.docs.contents.as_deref(), 1, None);
-
                         let empty = &ResourceInfo::default();
 
                         let import = if self
@@ -1347,119 +2086,130 @@ class {camel}(Flag):
                             .remote_dispatch_index
                             .is_some()
                         {
-                            let method = |(index, function)| {
-                                let FunctionCode {
-                                    snake,
-                                    params,
-                                    args,
-                                    return_type,
-                                    return_statement,
-                                    class_method,
-                                    result_count,
-                                    error,
-                                } = self.function_code(
-                                    Direction::Import,
-                                    world_module,
-                                    function,
-                                    &mut names,
-                                    &seen,
-                                    Some(id),
-                                );
-
-                                let docs =
-                                    docstring(world_module, function.docs, 2, error.as_deref());
-
-                                if let wit_parser::FunctionKind::Constructor(_) = function.wit_kind
-                                {
-                                    if stub_runtime_calls {
-                                        format!(
-                                            "
-    def {snake}({params}){return_type}:
-        {docs}{NOT_IMPLEMENTED}
-"
-                                        )
-                                    } else {
-                                        format!(
-                                            "
-    def {snake}({params}){return_type}:
-        {docs}tmp = componentize_py_runtime.call_import({index}, [{args}], {result_count})[0]
-        (_, func, args, _) = tmp.finalizer.detach()
-        self.handle = tmp.handle
-        self.finalizer = weakref.finalize(self, func, args[0], args[1])
-"
-                                        )
-                                    }
-                                } else if stub_runtime_calls {
-                                    format!(
-                                        "{class_method}
-    def {snake}({params}){return_type}:
-        {docs}{NOT_IMPLEMENTED}"
-                                    )
-                                } else {
-                                    format!(
-                                        "{class_method}
-    def {snake}({params}){return_type}:
-        {docs}result = componentize_py_runtime.call_import({index}, [{args}], {result_count})
-        {return_statement}"
-                                    )
-                                }
-                            };
+                            let mut source = Source::default();
+                            source.block(format!("class {camel}:"), |source| {
+                                source.docstring(ty.docs.contents.as_deref());
 
-                            let methods = self
-                            .functions
-                            .iter()
-                            .filter_map({
                                 let mut index = 0;
-                                move |function| {
-                                    let result = matches_resource(function, id, Direction::Import)
-                                        .then_some((index, function));
+                                for function in &self.functions {
+                                    if matches_resource(function, id, Direction::Import) {
+                                        let FunctionCode {
+                                            snake,
+                                            params,
+                                            args,
+                                            return_type,
+                                            return_statement,
+                                            class_method,
+                                            result_count,
+                                            error,
+                                        } = self.function_code(
+                                            Direction::Import,
+                                            world_module,
+                                            function,
+                                            &mut names,
+                                            &seen,
+                                            Some(id),
+                                        );
+
+                                        let docs = docs(world_module, function.docs, error.as_deref());
+
+                                        source.blank_line();
+                                        if class_method {
+                                            source.line("@classmethod");
+                                        }
+                                        source.block(
+                                            format!("def {snake}({params}){return_type}:"),
+                                            |source| {
+                                                source.docstring(docs.as_deref());
+                                                if stub_runtime_calls {
+                                                    source.line(NOT_IMPLEMENTED);
+                                                } else if let wit_parser::FunctionKind::Constructor(_) =
+                                                    function.wit_kind
+                                                {
+                                                    source.push(&format!(
+                                                        "tmp = componentize_py_runtime.call_import({index}, [{args}], {result_count})[0]
+(_, func, args, _) = tmp.finalizer.detach()
+self.handle = tmp.handle
+self.finalizer = weakref.finalize(self, func, args[0], args[1])"
+                                                    ));
+                                                } else {
+                                                    source.line(format!(
+                                                        "result = componentize_py_runtime.call_import({index}, [{args}], {result_count})"
+                                                    ));
+                                                    source.push(&return_statement);
+                                                }
+                                            },
+                                        );
+                                    }
 
                                     if function.is_dispatchable() {
                                         index += 1;
                                     }
-
-                                    result
-                                }
-                            })
-                            .map(method)
-                            .chain(iter::once({
-                                let newline = '\n';
-                                let indent = "        ";
-                                let doc = "Release this resource.";
-                                let docs =
-                                    format!(r#""""{newline}{indent}{doc}{newline}{indent}"""{newline}{indent}"#);
-                                let enter = r#"
-    def __enter__(self) -> Self:
-        """Returns self"""
-        return self
-                                "#;
-                                if stub_runtime_calls {
-                                    format!(
-                                        "{enter}
-    def __exit__(self, exc_type: type[BaseException] | None, exc_value: BaseException | None, traceback: TracebackType | None) -> bool | None:
-        {docs}{NOT_IMPLEMENTED}
-"
-                                    )
-                                } else {
-                                    format!(
-                                        "{enter}
-    def __exit__(self, exc_type: type[BaseException] | None, exc_value: BaseException | None, traceback: TracebackType | None) -> bool | None:
-        {docs}(_, func, args, _) = self.finalizer.detach()
-        self.handle = None
-        func(args[0], args[1])
-"
-                                    )
                                 }
-                            }))
-                            .collect::<Vec<_>>()
-                            .concat();
 
-                            Some(format!(
-                                "
-class {camel}:
-    {docs}{methods}
-"
-                            ))
+                                source.blank_line();
+                                source.block("def __enter__(self) -> Self:", |source| {
+                                    source.line(r#""""Returns self""""#);
+                                    source.line("return self");
+                                });
+
+                                source.blank_line();
+                                source.block(
+                                    EXIT_SIGNATURE,
+                                    |source| {
+                                        source.docstring(Some("Release this resource."));
+                                        if stub_runtime_calls {
+                                            source.line(NOT_IMPLEMENTED);
+                                        } else {
+                                            source.push(
+                                                "(_, func, args, _) = self.finalizer.detach()
+self.handle = None
+func(args[0], args[1])",
+                                            );
+                                        }
+                                    },
+                                );
+
+                                source.blank_line();
+                                source.block("def __eq__(self, other: object) -> bool:", |source| {
+                                    source.docstring(Some(
+                                        "Returns whether `other` refers to the same resource handle as this one.",
+                                    ));
+                                    source.push(&format!(
+                                        "if not isinstance(other, {camel}):
+    return NotImplemented
+return self is other or (self.handle is not None and self.handle == other.handle)"
+                                    ));
+                                });
+
+                                source.blank_line();
+                                source.block("def __hash__(self) -> int:", |source| {
+                                    source.docstring(Some(
+                                        "Returns a hash of this resource's handle.
+
+The hash is computed the first time it's needed and stays the same once the resource has
+been released, so the resource may remain in a `set` or `dict`.  A resource which was released
+before ever being hashed is unhashable.",
+                                    ));
+                                    source.push(
+                                        "value = getattr(self, \"_hash\", None)
+if value is None:
+    if self.handle is None:
+        raise TypeError(\"unhashable released resource\")
+    value = self._hash = hash(self.handle)
+return value",
+                                    );
+                                });
+
+                                source.blank_line();
+                                source.block("def __repr__(self) -> str:", |source| {
+                                    source.line(
+                                        r#"return f"<{type(self).__module__}.{type(self).__qualname__} handle={self.handle}>""#,
+                                    );
+                                });
+                            });
+
+                            Some(source.into())
                         } else {
                             None
                         };
@@ -1471,51 +2221,102 @@ class {camel}:
                             .local_dispatch_index
                             .is_some()
                         {
-                            let method = |function| {
-                                let FunctionCode {
-                                    snake,
-                                    params,
-                                    return_type,
-                                    class_method,
-                                    error,
-                                    ..
-                                } = self.function_code(
-                                    Direction::Export,
-                                    world_module,
-                                    function,
-                                    &mut names,
-                                    &seen,
-                                    Some(id),
-                                );
-
-                                let docs =
-                                    docstring(world_module, function.docs, 2, error.as_deref());
+                            // A resource with no constructor, methods, or static functions can only be used as
+                            // an opaque token, so we generate a concrete class which wraps an arbitrary value
+                            // rather than a protocol for the app to implement.
+                            let token = !self
+                                .functions
+                                .iter()
+                                .any(|function| matches_resource(function, id, Direction::Export));
 
-                                format!(
-                                    "{class_method}
-    @abstractmethod
-    def {snake}({params}){return_type}:
-        {docs}{NOT_IMPLEMENTED}
-"
-                                )
+                            let mut source = Source::default();
+                            let header = if token {
+                                format!("class {camel}:")
+                            } else {
+                                format!("class {camel}(Protocol):")
                             };
+                            source.block(header, |source| {
+                                source.docstring(ty.docs.contents.as_deref());
+
+                                if token {
+                                    source.blank_line();
+                                    source.line("__componentize_py_token__ = True");
+
+                                    source.blank_line();
+                                    source.block("def __init__(self, value: Any = None) -> None:", |source| {
+                                        source.docstring(Some(
+                                            "Wrap `value` in a token which may be passed to the host.
+
+Any other value lowered where this resource is expected is wrapped automatically, so e.g. a `str`
+or `int` may be returned directly.  Returning the same instance more than once reuses its handle
+for as long as the host holds it, whereas each automatically wrapped value gets a new handle.
+
+Lowering a token as `own` transfers its handle to the host, which keeps the token (and thus
+`value`) alive until it drops the handle, at which point `__exit__` is called.  A token the host
+hands back is the same instance that was originally lowered, with `value` unchanged.",
+                                        ));
+                                        source.line("self.value = value");
+                                    });
+                                }
 
-                            let methods = self
-                                .functions
-                                .iter()
-                                .filter(|function| {
+                                for function in self.functions.iter().filter(|function| {
                                     matches_resource(function, id, Direction::Export)
-                                })
-                                .map(method)
-                                .collect::<Vec<_>>()
-                                .concat();
+                                }) {
+                                    let FunctionCode {
+                                        snake,
+                                        params,
+                                        return_type,
+                                        class_method,
+                                        error,
+                                        ..
+                                    } = self.function_code(
+                                        Direction::Export,
+                                        world_module,
+                                        function,
+                                        &mut names,
+                                        &seen,
+                                        Some(id),
+                                    );
+
+                                    let docs = docs(world_module, function.docs, error.as_deref());
+
+                                    source.blank_line();
+                                    if class_method {
+                                        source.line("@classmethod");
+                                    }
+                                    source.line("@abstractmethod");
+                                    source.block(
+                                        format!("def {snake}({params}){return_type}:"),
+                                        |source| {
+                                            source.docstring(docs.as_deref());
+                                            source.line(NOT_IMPLEMENTED);
+                                        },
+                                    );
+                                }
 
-                            Some(format!(
-                                "
-class {camel}(Protocol):
-    {docs}{methods}
-"
-                            ))
+                                source.blank_line();
+                                source.block(EXIT_SIGNATURE, |source| {
+                                    source.docstring(Some(
+                                        "Called when the last handle to this resource is dropped (e.g. by the host).
+
+Override this to release any state associated with the resource deterministically
+rather than waiting for it to be garbage collected.  By default, this does nothing.",
+                                    ));
+                                    source.line("return None");
+                                });
+
+                                source.blank_line();
+                                source.block("def __repr__(self) -> str:", |source| {
+                                    source.line(
+                                        r#"handle = getattr(self, "__componentize_py_handle", None)"#,
+                                    );
+                                    source.line(
+                                        r#"return f"<{type(self).__module__}.{type(self).__qualname__} handle={handle}>""#,
+                                    );
+                                });
+                            });
+
+                            Some(source.into())
                         } else {
                             None
                         };
@@ -1564,10 +2365,11 @@ class {camel}(Protocol):
                     TypeLocation {
                         module: world_module.to_owned(),
                         aliases,
+                        names: names.clone(),
                     },
                 );
 
-                code
+                (code, names)
             };
 
             if let Some(code) = code {
@@ -1618,9 +2420,14 @@ class {camel}(Protocol):
                 };
 
                 for (code, (definitions, docs)) in tuples {
-                    definitions.types.push(code);
+                    definitions.types.push(Definition {
+                        code,
+                        names: defined.clone(),
+                        resource: matches!(ty.kind, TypeDefKind::Resource),
+                    });
                     definitions.type_imports.extend(names.imports.clone());
                     definitions.docs = docs;
+                    definitions.time_helpers |= time_helpers;
                 }
             }
 
@@ -1679,26 +2486,22 @@ class {camel}(Protocol):
                         None,
                     );
 
+                    let function_docs = docs(world_module, function.docs, error.as_deref());
+
                     match function.kind {
                         FunctionKind::Import => {
-                            let docs = docstring(world_module, function.docs, 1, error.as_deref());
-
-                            let code = if stub_runtime_calls {
-                                format!(
-                                    "
-def {snake}({params}){return_type}:
-    {docs}{NOT_IMPLEMENTED}
-"
-                                )
-                            } else {
-                                format!(
-                                    "
-def {snake}({params}){return_type}:
-    {docs}result = componentize_py_runtime.call_import({index}, [{args}], {result_count})
-    {return_statement}
-"
-                                )
-                            };
+                            let mut source = Source::default();
+                            source.block(format!("def {snake}({params}){return_type}:"), |source| {
+                                source.docstring(function_docs.as_deref());
+                                if stub_runtime_calls {
+                                    source.line(NOT_IMPLEMENTED);
+                                } else {
+                                    source.line(format!(
+                                        "result = componentize_py_runtime.call_import({index}, [{args}], {result_count})"
+                                    ));
+                                    source.push(&return_statement);
+                                }
+                            });
 
                             let (definitions, docs) = if let Some(interface) = &function.interface {
                                 (
@@ -1712,7 +2515,11 @@ def {snake}({params}){return_type}:
                                 )
                             };
 
-                            definitions.functions.push(code);
+                            definitions.functions.push(Definition {
+                                code: source.into(),
+                                names: vec![snake],
+                                resource: false,
+                            });
                             definitions.function_imports.extend(names.imports);
                             definitions.docs = docs;
                         }
@@ -1741,18 +2548,21 @@ def {snake}({params}){return_type}:
                                     format!("self, {params}")
                                 };
 
-                                let function_docs =
-                                    docstring(world_module, function.docs, 2, error.as_deref());
-
-                                let code = format!(
-                                    "
-    @abstractmethod
-    def {snake}({params}){return_type}:
-        {function_docs}{NOT_IMPLEMENTED}
-"
+                                let mut source = Source::default();
+                                source.line("@abstractmethod");
+                                source.block(
+                                    format!("def {snake}({params}){return_type}:"),
+                                    |source| {
+                                        source.docstring(function_docs.as_deref());
+                                        source.line(NOT_IMPLEMENTED);
+                                    },
                                 );
 
-                                definitions.functions.push(code);
+                                definitions.functions.push(Definition {
+                                    code: source.into(),
+                                    names: vec![snake],
+                                    resource: false,
+                                });
                                 definitions.function_imports.extend(names.imports);
                                 definitions.docs = docs;
                             } else {
@@ -1771,34 +2581,42 @@ def {snake}({params}){return_type}:
         }
 
         let python_imports =
-            "from typing import TypeVar, Generic, Union, Optional, Protocol, Tuple, List, Any, Self
+            "from typing import TypeVar, Generic, Union, Optional, Protocol, Tuple, List, Dict, Any, Self
 from types import TracebackType
+import sys
+if sys.version_info >= (3, 12):
+    from collections.abc import Buffer
+else:
+    Buffer = Union[bytes, bytearray, memoryview]
 from enum import Flag, Enum, auto
 from dataclasses import dataclass
 from abc import abstractmethod
-import weakref
-";
+import weakref";
+
+        let mut written = BTreeSet::new();
 
         {
-            let mut file = File::create(path.join("types.py"))?;
+            let mut source = Source::default();
             if let Some(module) = locations.types_module.as_ref() {
-                writeln!(file, "{}", world_module_import(module, "peer"))?;
-                write!(
-                    file,
+                source.line(world_module_import(module, "peer"));
+                source.push(
                     "Some = peer.types.Some
 Ok = peer.types.Ok
 Err = peer.types.Err
+WitError = peer.types.WitError
 Result = peer.types.Result
-"
-                )?;
+json_dumps = peer.types.json_dumps
+json_loads = peer.types.json_loads
+ExportFunction = peer.types.ExportFunction
+register_export = peer.types.register_export",
+                );
             } else {
                 locations.types_module = Some(world_module.to_owned());
 
-                write!(
-                    file,
-                    "{python_imports}
-
-S = TypeVar('S')
+                source.push(python_imports);
+                source.blank_line();
+                source.push(
+                    "S = TypeVar('S')
 @dataclass
 class Some(Generic[S]):
     value: S
@@ -1813,10 +2631,77 @@ E = TypeVar('E')
 class Err(Generic[E], Exception):
     value: E
 
-Result = Union[Ok[T], Err[E]]
-"
-                )?;
+class WitError(Err[str], ValueError):
+    \"\"\"
+    The `Err` raised by imports whose error type is `string`, e.g. `result<T, string>`.
+
+    Being a `ValueError` whose `str()` is the message, it may be handled like any other Python exception; exports
+    may likewise raise it (or any other `Err`) to return an error to the caller.
+    \"\"\"
+
+    def __str__(self) -> str:
+        return self.value
+
+",
+                );
+                source.blank_line();
+                source.line(match self.typing_style {
+                    TypingStyle::Legacy => "Result = Union[Ok[T], Err[E]]",
+                    TypingStyle::Modern => "type Result[T, E] = Ok[T] | Err[E]",
+                });
+                source.blank_line();
+                source.push(
+                    "try:
+    from componentize_py_runtime import json_dumps, json_loads
+except ImportError:
+    # Either we're not running in a component or the runtime was built without `serde_json` support, so fall
+    # back to the `json` module, formatting output the same way the runtime does.
+    import json
+
+    def json_dumps(value: Any) -> str:
+        return json.dumps(value, separators=(\",\", \":\"), ensure_ascii=False)
+
+    def json_loads(s: str) -> Any:
+        return json.loads(s)",
+                );
+                source.blank_line();
+                source.push(
+                    "from typing import Callable
+from types import SimpleNamespace
+
+ExportFunction = TypeVar('ExportFunction', bound=Callable[..., Any])
+
+_EXPORTS: Dict[str, SimpleNamespace] = {}
+
+def register_export(protocol: str, function: ExportFunction) -> ExportFunction:
+    \"\"\"
+    Register `function` as the implementation of the `protocol` export of the same name.
+
+    This is used by the generated `export` decorators.  The runtime only consults the functions registered for a
+    protocol if the app does not define a class implementing it.
+    \"\"\"
+    setattr(_EXPORTS.setdefault(protocol, SimpleNamespace()), function.__name__, function)
+    return function",
+                );
             }
+
+            write_if_changed(&mut written, &path.join("types.py"), String::from(source))?;
+        }
+
+        if uses_time_helpers {
+            write_if_changed(
+                &mut written,
+                &path.join("time_helpers.py"),
+                time_helpers_module(self.typing_style),
+            )?;
+        }
+
+        if self.uses_nesting_options(world) {
+            write_if_changed(
+                &mut written,
+                &path.join("option.py"),
+                option_module(self.typing_style),
+            )?;
         }
 
         let import = |prefix, interface| {
@@ -1824,192 +2709,935 @@ Result = Union[Ok[T], Err[E]]
             format!("from {prefix}{module} import {package}")
         };
 
+        // Generate the common header for a module, consisting of an optional docstring followed by `import`
+        // statements.
+        let header =
+            |docs: Option<&str>, types_prefix: &str, imports: Vec<String>, runtime: bool| {
+                let mut source = Source::default();
+                source.docstring(docs);
+                source.push(python_imports);
+                source.blank_line();
+                source.line(format!(
+                    "from {types_prefix}types import Result, Ok, Err, Some, json_dumps, json_loads"
+                ));
+                if runtime && !stub_runtime_calls {
+                    source.line("import componentize_py_runtime");
+                }
+                for import in imports.into_iter().collect::<BTreeSet<_>>() {
+                    source.line(import);
+                }
+                source
+            };
+
+        let time_helpers_imports = |definitions: &Definitions, prefix: &str| {
+            if definitions.time_helpers {
+                vec![
+                    "from datetime import datetime".to_owned(),
+                    format!("from {prefix} import time_helpers"),
+                ]
+            } else {
+                Vec::new()
+            }
+        };
+
+        let append = |source: &mut Source, definitions: &[Definition]| {
+            for definition in definitions {
+                source.blank_line();
+                source.push(&definition.code);
+            }
+        };
+
         if !interface_imports.is_empty() {
             let dir = path.join("imports");
-            fs::create_dir(&dir)?;
-            File::create(dir.join("__init__.py"))?;
+            fs::create_dir_all(&dir)?;
+            write_if_changed(&mut written, &dir.join("__init__.py"), String::new())?;
             for (id, code) in interface_imports {
-                let name = self.imported_interface_names.get(&id).unwrap();
-                let mut file =
-                    File::create(dir.join(format!("{}.py", name.to_snake_case().escape())))?;
-                let types = code.types.concat();
-                let functions = code.functions.concat();
-                let imports = code
-                    .type_imports
-                    .union(&code.function_imports)
-                    .map(|&interface| import("..", interface))
-                    .collect::<Vec<_>>()
-                    .join("\n");
-                let docs = docstring(world_module, code.docs, 0, None);
+                let module = self.interface_package(id).1;
+                let docs = docs(world_module, code.docs, None);
+                let imports = |prefix: &str| {
+                    code.type_imports
+                        .union(&code.function_imports)
+                        .map(|&interface| import(prefix, interface))
+                        .chain(time_helpers_imports(&code, prefix))
+                        .collect::<Vec<_>>()
+                };
+
+                let file = dir.join(format!("{module}.py"));
+                let package = dir.join(module);
+                if code.types.len() + code.functions.len() > SHARD_THRESHOLD {
+                    // Split the module into a package of shards, each of which is only loaded once something
+                    // defined in it (or in a later shard) is used.
+                    if file.exists() {
+                        fs::remove_file(&file)?;
+                    }
+                    fs::create_dir_all(&package)?;
+
+                    let shards = shards(code.types.iter().chain(&code.functions));
+                    for (index, (shard, definitions)) in shards.iter().enumerate() {
+                        let mut source = header(None, "...", imports("..."), true);
+                        // Definitions may refer to any of those which precede them, so import the earlier shards
+                        // first.
+                        for (earlier, _) in &shards[..index] {
+                            source.line(format!("from .{earlier} import *"));
+                        }
+                        for definition in definitions {
+                            source.blank_line();
+                            source.push(&definition.code);
+                        }
+                        write_if_changed(
+                            &mut written,
+                            &package.join(format!("{shard}.py")),
+                            source.into(),
+                        )?;
+                    }
 
-                let imports = if stub_runtime_calls {
-                    imports
+                    write_if_changed(
+                        &mut written,
+                        &package.join("__init__.py"),
+                        shard_index(docs.as_deref(), &shards),
+                    )?;
                 } else {
-                    format!("import componentize_py_runtime\n{imports}")
-                };
+                    if package.is_dir() {
+                        fs::remove_dir_all(&package)?;
+                    }
 
-                write!(
-                    file,
-                    "{docs}{python_imports}
-from ..types import Result, Ok, Err, Some
-{imports}
-{types}
-{functions}
-"
-                )?;
+                    let mut source = header(docs.as_deref(), "..", imports(".."), true);
+                    append(&mut source, &code.types);
+                    append(&mut source, &code.functions);
+
+                    write_if_changed(&mut written, &file, String::from(source))?;
+                }
             }
         }
 
         if !interface_exports.is_empty() {
             let dir = path.join("exports");
-            fs::create_dir(&dir)?;
+            fs::create_dir_all(&dir)?;
 
             let mut protocol_imports = HashSet::new();
-            let mut protocols = String::new();
+            let mut protocols = Source::default();
             for (id, code) in interface_exports {
                 let name = self.exported_interface_names.get(&id).unwrap();
-                let mut file =
-                    File::create(dir.join(format!("{}.py", name.to_snake_case().escape())))?;
-                let types = code.types.concat();
-                let imports = code
-                    .type_imports
-                    .into_iter()
-                    .map(|interface| import("..", interface))
-                    .collect::<Vec<_>>()
-                    .join("\n");
-                let docs = docstring(world_module, code.docs, 0, None);
-
-                write!(
-                    file,
-                    "{docs}{python_imports}
-from ..types import Result, Ok, Err, Some
-{imports}
-{types}
-"
-                )?;
+                let mut source = header(
+                    docs(world_module, code.docs, None).as_deref(),
+                    "..",
+                    code.type_imports
+                        .iter()
+                        .map(|&interface| import("..", interface))
+                        .chain(time_helpers_imports(&code, ".."))
+                        .collect(),
+                    false,
+                );
+                append(&mut source, &code.types);
 
                 let camel = name.to_upper_camel_case().escape();
 
+                if code.alias_module.is_none() && !code.functions.is_empty() {
+                    export_decorator(&mut source, "..", &camel);
+                }
+
+                write_if_changed(
+                    &mut written,
+                    &dir.join(format!("{}.py", name.to_snake_case().escape())),
+                    String::from(source),
+                )?;
+
+                protocols.blank_line();
                 if let Some(alias_module) = code.alias_module {
-                    writeln!(
-                        &mut protocols,
+                    protocols.line(format!(
                         "import {}",
                         if let Some((start, _)) = alias_module.split_once('.') {
                             start
                         } else {
                             &alias_module
                         }
-                    )?;
-                    writeln!(&mut protocols, "{camel} = {alias_module}.{camel}")?;
+                    ));
+                    protocols.line(format!("{camel} = {alias_module}.{camel}"));
                 } else {
-                    let methods = if code.functions.is_empty() {
-                        "    pass".to_owned()
-                    } else {
-                        code.functions.concat()
-                    };
-
                     protocol_imports.extend(code.function_imports);
-                    write!(
-                        &mut protocols,
-                        "
-class {camel}(Protocol):
-{methods}
-"
-                    )?;
+                    protocols.block(format!("class {camel}(Protocol):"), |source| {
+                        append(source, &code.functions);
+                    });
                 }
             }
 
-            let mut init = File::create(dir.join("__init__.py"))?;
-            let imports = protocol_imports
-                .into_iter()
-                .map(|interface| import("..", interface))
-                .collect::<Vec<_>>()
-                .join("\n");
-
-            write!(
-                init,
-                "{python_imports}
-from ..types import Result, Ok, Err, Some
-{imports}
-{protocols}
-"
-            )?;
+            let mut source = header(
+                None,
+                "..",
+                protocol_imports
+                    .into_iter()
+                    .map(|interface| import("..", interface))
+                    .collect(),
+                false,
+            );
+            source.blank_line();
+            source.push(&String::from(protocols));
+
+            write_if_changed(&mut written, &dir.join("__init__.py"), String::from(source))?;
         }
 
         {
-            let mut file = File::create(path.join("__init__.py"))?;
-            let function_imports = world_imports.functions.concat();
-            let type_exports = world_exports.types.concat();
             let camel = self.resolve.worlds[world]
                 .name
                 .to_upper_camel_case()
                 .escape();
 
-            let protocol = if let Some(alias_module) = world_exports.alias_module {
-                format!("{camel} = {alias_module}.{camel}")
-            } else {
-                let methods = if world_exports.functions.is_empty() {
-                    "    pass".to_owned()
-                } else {
-                    world_exports.functions.concat()
-                };
-
-                format!(
-                    "class {camel}(Protocol):
-{methods}"
-                )
-            };
-
-            let imports = world_imports
-                .function_imports
-                .union(
-                    &world_exports
-                        .type_imports
-                        .union(&world_exports.function_imports)
-                        .copied()
-                        .collect(),
-                )
-                .map(|&interface| import(".", interface))
-                .collect::<Vec<_>>()
-                .join("\n");
+            let mut source = header(
+                docs(world_module, world_exports.docs, None).as_deref(),
+                ".",
+                world_imports
+                    .function_imports
+                    .union(
+                        &world_exports
+                            .type_imports
+                            .union(&world_exports.function_imports)
+                            .copied()
+                            .collect(),
+                    )
+                    .map(|&interface| import(".", interface))
+                    .collect(),
+                true,
+            );
+            append(&mut source, &world_exports.types);
+            append(&mut source, &world_imports.functions);
+
+            if self.exports_shutdown(world) {
+                source.blank_line();
+                source.line("from typing import Callable");
+                source.blank_line();
+                source.block(
+                    "def on_shutdown(callback: Callable[[], None]) -> None:",
+                    |source| {
+                        source.docstring(Some(
+                            "Register `callback` to be called when the host calls \
+                             `componentize-py:lifecycle/shutdown#shutdown`.\n\
+                             \n\
+                             Use this to flush buffers, close resources, etc. before the instance is dropped.  \
+                             Callbacks are\n\
+                             called most recently registered first, and at most once each.",
+                        ));
+                        if stub_runtime_calls {
+                            source.line(NOT_IMPLEMENTED);
+                        } else {
+                            source.line("import componentize_py_lifecycle");
+                            source.line("componentize_py_lifecycle.on_shutdown(callback)");
+                        }
+                    },
+                );
+            }
 
-            let docs = docstring(world_module, world_exports.docs, 0, None);
+            // A world with no exports at all (e.g. one for an import-only, library-style component) has nothing
+            // for the app to implement, so we don't generate an (empty) protocol for it.
+            if let Some(alias_module) = world_exports.alias_module {
+                source.blank_line();
+                source.line(format!("{camel} = {alias_module}.{camel}"));
+            } else if !self.resolve.worlds[world].exports.is_empty() {
+                source.blank_line();
+                source.block(format!("class {camel}(Protocol):"), |source| {
+                    append(source, &world_exports.functions);
+                });
 
-            let imports = if stub_runtime_calls {
-                imports
-            } else {
-                format!("import componentize_py_runtime\n{imports}")
-            };
+                if !world_exports.functions.is_empty() {
+                    export_decorator(&mut source, ".", &camel);
+                }
+            }
 
-            write!(
-                file,
-                "{docs}{python_imports}
-from .types import Result, Ok, Err, Some
-{imports}
-{type_exports}
-{function_imports}
-{protocol}
-"
+            write_if_changed(
+                &mut written,
+                &path.join("__init__.py"),
+                String::from(source),
             )?;
         }
 
-        Ok(())
+        Ok(written)
     }
 
-    fn interface_package(&self, interface: InterfaceId) -> (&'static str, String) {
-        if let Some(name) = self.imported_interface_names.get(&interface) {
-            ("imports", name.to_snake_case().escape())
-        } else {
-            (
-                "exports",
-                self.exported_interface_names[&interface]
-                    .to_snake_case()
-                    .escape(),
-            )
-        }
-    }
+    /// Generate the modules used by those bundled helpers (e.g. `http_client`) whose interfaces `world` imports,
+    /// returning the name and contents of each.
+    ///
+    /// Each module re-exports the bindings its helper needs under fixed names regardless of how the world and its
+    /// interfaces are named.
+    pub fn generate_helper_bindings(
+        &self,
+        world: WorldId,
+        world_module: &str,
+    ) -> Vec<(&'static str, String)> {
+        let find = |package, name| self.latest_import(world, "wasi", package, name);
 
-    fn package(&self, owner: TypeOwner, world_module: &str) -> Option<String> {
+        let helpers = [
+            (
+                "http_client_bindings",
+                "http_client",
+                vec![
+                    ("types", find("http", "types")),
+                    ("outgoing_handler", find("http", "outgoing-handler")),
+                    ("streams", find("io", "streams")),
+                    ("poll", find("io", "poll")),
+                ],
+            ),
+            (
+                "blobstore_bindings",
+                "blobstore_files",
+                vec![
+                    ("blobstore", find("blobstore", "blobstore")),
+                    ("container", find("blobstore", "container")),
+                    ("types", find("blobstore", "types")),
+                    ("streams", find("io", "streams")),
+                ],
+            ),
+            (
+                "componentize_py_pytest_bindings",
+                "componentize_py_pytest",
+                vec![(
+                    "reporter",
+                    self.latest_import(world, "componentize-py", "pytest", "reporter"),
+                )],
+            ),
+        ];
+
+        helpers
+            .into_iter()
+            .filter_map(|(module, helper, interfaces)| {
+                let mut source = Source::default();
+                source.docstring(Some(&format!(
+                    "Bindings used by the `{helper}` module, generated by `componentize-py`."
+                )));
+                source.blank_line();
+                source.line(format!("from {world_module}.types import Ok, Err"));
+                for (alias, id) in interfaces {
+                    let (_, package) = self.interface_package(id?);
+                    source.line(format!(
+                        "from {world_module}.imports import {package} as {alias}"
+                    ));
+                }
+
+                Some((module, source.into()))
+            })
+            .collect()
+    }
+
+    /// Return the latest version of the `{namespace}:{package}/{name}` interface imported by `world`, if any.
+    fn latest_import(
+        &self,
+        world: WorldId,
+        namespace: &str,
+        package: &str,
+        name: &str,
+    ) -> Option<InterfaceId> {
+        self.world_keys
+            .get(&world)?
+            .iter()
+            .filter_map(|(direction, key)| match (direction, key) {
+                (Direction::Import, WorldKey::Interface(id)) => {
+                    let info = self.imported_interfaces.get(id)?;
+                    let info_package = info.package?;
+                    (info_package.namespace == namespace
+                        && info_package.name == package
+                        && info.name == name)
+                        .then_some((info_package.version, *id))
+                }
+                _ => None,
+            })
+            .max_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(_, id)| id)
+    }
+
+    /// Describe each function, method, and named type in the bindings generated for `world`, keyed by its path
+    /// relative to the world module (e.g. `imports.types.Fields.get` or `exports.Handler.handle`).
+    ///
+    /// This is used to compare bindings generated from different versions of a world, so type names are always
+    /// qualified with their modules and the name of the world module is omitted.
+    pub fn api(&self, world: WorldId) -> BTreeMap<String, ApiItem> {
+        let world_module = self.resolve.worlds[world].name.to_snake_case().escape();
+        let seen = self.types.iter().copied().collect::<HashSet<_>>();
+
+        let mut api = BTreeMap::new();
+        for function in &self.functions {
+            let Some((direction, path)) = self.api_path(world, function) else {
+                continue;
+            };
+
+            let mut names = TypeNames::new(self, TypeOwner::None);
+            let FunctionCode {
+                params,
+                return_type,
+                error,
+                ..
+            } = self.function_code(direction, &world_module, function, &mut names, &seen, None);
+            let error = self
+                .raises(function, error, &mut names, &seen)
+                .map(|error| format!(" raises {error}"))
+                .unwrap_or_default();
+
+            api.insert(
+                path,
+                ApiItem {
+                    signature: format!("({params}){return_type}{error}"),
+                    implemented: direction == Direction::Export,
+                },
+            );
+        }
+
+        for &id in &self.types {
+            let ty = &self.resolve.types[id];
+            if ty.name.is_none() {
+                continue;
+            }
+
+            let mut names = TypeNames::new(self, TypeOwner::None);
+            let mut type_name = |ty| names.type_name(ty, &seen, None);
+            let signature = match &ty.kind {
+                TypeDefKind::Record(record) => format!(
+                    "record({})",
+                    record
+                        .fields
+                        .iter()
+                        .map(|field| format!(
+                            "{}: {}",
+                            field.name.to_snake_case().escape(),
+                            type_name(field.ty)
+                        ))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                TypeDefKind::Variant(variant) => format!(
+                    "variant({})",
+                    variant
+                        .cases
+                        .iter()
+                        .map(|case| {
+                            let name = case.name.to_upper_camel_case().escape();
+                            if let Some(ty) = case.ty {
+                                format!("{name}({})", type_name(ty))
+                            } else {
+                                name
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                TypeDefKind::Enum(en) => format!(
+                    "enum({})",
+                    en.cases
+                        .iter()
+                        .map(|case| case.name.to_shouty_snake_case())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                TypeDefKind::Flags(flags) => format!(
+                    "flags({})",
+                    flags
+                        .flags
+                        .iter()
+                        .map(|flag| flag.name.to_shouty_snake_case())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+                TypeDefKind::Resource => "resource".to_owned(),
+                _ => format!("= {}", type_name(Type::Id(id))),
+            };
+
+            api.insert(
+                self.type_path(id),
+                ApiItem {
+                    signature,
+                    implemented: false,
+                },
+            );
+        }
+
+        api
+    }
+
+    /// Describe the Python API of the bindings generated for `world` in the `world_module` package, using the same
+    /// paths and type names as `api` but as structured data.
+    pub fn describe(&self, world: WorldId, world_module: &str) -> api::WorldApi {
+        let seen = self.types.iter().copied().collect::<HashSet<_>>();
+        let mut names = TypeNames::new(self, TypeOwner::None);
+        let docs = |docs: Option<&str>| docs.map(str::to_owned);
+        let uses = |found: HashSet<TypeId>| {
+            found
+                .into_iter()
+                .map(|id| self.type_path(id))
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>()
+        };
+
+        let mut interfaces = [
+            (
+                api::Direction::Import,
+                &self.imported_interfaces,
+                &self.imported_interface_names,
+            ),
+            (
+                api::Direction::Export,
+                &self.exported_interfaces,
+                &self.exported_interface_names,
+            ),
+        ]
+        .into_iter()
+        .flat_map(|(direction, interfaces, interface_names)| {
+            interfaces.iter().map(move |(&id, info)| api::InterfaceApi {
+                name: self
+                    .resolve
+                    .id_of(id)
+                    .unwrap_or_else(|| info.name.to_owned()),
+                direction,
+                module: format!(
+                    "{direction}s.{}",
+                    interface_names[&id].to_snake_case().escape()
+                ),
+                docs: docs(info.docs),
+            })
+        })
+        .collect::<Vec<_>>();
+        interfaces.sort_by(|a, b| a.module.cmp(&b.module));
+
+        let mut types = Vec::new();
+        for &id in &self.types {
+            let ty = &self.resolve.types[id];
+            let Some(wit_name) = &ty.name else {
+                continue;
+            };
+
+            let mut fields = Vec::new();
+            let mut cases = Vec::new();
+            let mut target = None;
+            let kind = match &ty.kind {
+                TypeDefKind::Record(record) => {
+                    fields = record
+                        .fields
+                        .iter()
+                        .map(|field| api::Param {
+                            name: field.name.to_snake_case().escape(),
+                            ty: names.type_name(field.ty, &seen, None),
+                        })
+                        .collect();
+                    api::TypeKind::Record
+                }
+                TypeDefKind::Variant(variant) => {
+                    cases = variant
+                        .cases
+                        .iter()
+                        .map(|case| api::Case {
+                            name: case.name.to_upper_camel_case().escape(),
+                            ty: case.ty.map(|ty| names.type_name(ty, &seen, None)),
+                        })
+                        .collect();
+                    api::TypeKind::Variant
+                }
+                TypeDefKind::Enum(en) => {
+                    cases = en
+                        .cases
+                        .iter()
+                        .map(|case| api::Case {
+                            name: case.name.to_shouty_snake_case(),
+                            ty: None,
+                        })
+                        .collect();
+                    api::TypeKind::Enum
+                }
+                TypeDefKind::Flags(flags) => {
+                    cases = flags
+                        .flags
+                        .iter()
+                        .map(|flag| api::Case {
+                            name: flag.name.to_shouty_snake_case(),
+                            ty: None,
+                        })
+                        .collect();
+                    api::TypeKind::Flags
+                }
+                TypeDefKind::Resource => api::TypeKind::Resource,
+                _ => {
+                    target = Some(names.type_name(Type::Id(id), &seen, None));
+                    api::TypeKind::Alias
+                }
+            };
+
+            let mut found = HashSet::new();
+            self.named_types_in(id, &mut found);
+
+            types.push(api::TypeApi {
+                path: self.type_path(id),
+                wit_name: wit_name.clone(),
+                kind,
+                fields,
+                cases,
+                target,
+                docs: docs(ty.docs.contents.as_deref()),
+                uses: uses(found),
+            });
+        }
+
+        let mut functions = Vec::new();
+        for function in &self.functions {
+            let Some((direction, path)) = self.api_path(world, function) else {
+                continue;
+            };
+
+            let FunctionCode {
+                return_type, error, ..
+            } = self.function_code(direction, world_module, function, &mut names, &seen, None);
+
+            let (kind, skip_count) = match function.wit_kind {
+                wit_parser::FunctionKind::Freestanding => (api::FunctionKind::Freestanding, 0),
+                wit_parser::FunctionKind::Method(_) => (api::FunctionKind::Method, 1),
+                wit_parser::FunctionKind::Static(_) => (api::FunctionKind::Static, 0),
+                wit_parser::FunctionKind::Constructor(_) => (api::FunctionKind::Constructor, 0),
+            };
+
+            let mut found = HashSet::new();
+            let params = function
+                .params
+                .iter()
+                .skip(skip_count)
+                .map(|(name, ty)| {
+                    self.named_types(*ty, &mut found);
+                    api::Param {
+                        name: self.member_name(name),
+                        ty: if direction == Direction::Import {
+                            names.lowered_type_name(*ty, &seen, None)
+                        } else {
+                            names.type_name(*ty, &seen, None)
+                        },
+                    }
+                })
+                .collect();
+            for ty in function.results.types() {
+                self.named_types(ty, &mut found);
+            }
+
+            functions.push(api::FunctionApi {
+                path,
+                wit_name: function.name.to_owned(),
+                direction: match direction {
+                    Direction::Import => api::Direction::Import,
+                    Direction::Export => api::Direction::Export,
+                },
+                kind,
+                params,
+                returns: return_type.trim_start_matches(" -> ").to_owned(),
+                raises: self.raises(function, error, &mut names, &seen),
+                docs: docs(function.docs),
+                uses: uses(found),
+            });
+        }
+
+        api::WorldApi {
+            world: self.resolve.id_of_name(
+                self.resolve.worlds[world].package.unwrap(),
+                &self.resolve.worlds[world].name,
+            ),
+            module: world_module.to_owned(),
+            interfaces,
+            types,
+            functions,
+        }
+    }
+
+    /// Return the direction of `function` and its path relative to the world module, e.g.
+    /// `imports.types.Fields.get` or `exports.Handler.handle`, or `None` if it's neither called nor implemented by
+    /// the app (e.g. because a bundled module implements it).
+    fn api_path(&self, world: WorldId, function: &MyFunction) -> Option<(Direction, String)> {
+        let direction = match function.kind {
+            FunctionKind::Import => Direction::Import,
+            FunctionKind::Export if self.bundled_implementation(function).is_none() => {
+                Direction::Export
+            }
+            _ => return None,
+        };
+        let resource = match function.wit_kind {
+            wit_parser::FunctionKind::Freestanding => None,
+            wit_parser::FunctionKind::Method(id)
+            | wit_parser::FunctionKind::Static(id)
+            | wit_parser::FunctionKind::Constructor(id) => Some(id),
+        };
+
+        // Exported freestanding functions belong to a protocol named after their interface (or the world), whereas
+        // everything else belongs to the interface's module (or the world module).
+        let scope = match (direction, &function.interface, resource) {
+            (Direction::Import, Some(interface), _) => format!(
+                "imports.{}",
+                self.imported_interface_names[&interface.id]
+                    .to_snake_case()
+                    .escape()
+            ),
+            (Direction::Export, Some(interface), None) => format!(
+                "exports.{}",
+                self.exported_interface_names[&interface.id]
+                    .to_upper_camel_case()
+                    .escape()
+            ),
+            (Direction::Export, Some(interface), Some(_)) => format!(
+                "exports.{}",
+                self.exported_interface_names[&interface.id]
+                    .to_snake_case()
+                    .escape()
+            ),
+            (Direction::Import, None, _) => String::new(),
+            (Direction::Export, None, _) => self.resolve.worlds[world]
+                .name
+                .to_upper_camel_case()
+                .escape(),
+        };
+
+        let path = [
+            scope.as_str(),
+            resource.map(|id| self.class_name(id)).unwrap_or_default(),
+            self.function_name(function).as_str(),
+        ]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(".");
+
+        Some((direction, path))
+    }
+
+    /// Return the path of the class generated for the specified named type relative to the world module, e.g.
+    /// `imports.types.Fields`.
+    fn type_path(&self, id: TypeId) -> String {
+        match self.resolve.types[id].owner {
+            TypeOwner::Interface(interface) => {
+                let (direction, module) = self.interface_package(interface);
+                format!("{direction}.{module}.{}", self.class_name(id))
+            }
+            _ => self.class_name(id).to_owned(),
+        }
+    }
+
+    /// Return the name of the `Err` payload type `function` raises, given the `error` returned for it by
+    /// `function_code`.
+    ///
+    /// `function_code` qualifies the error type with the world module, so we name it ourselves instead.
+    fn raises(
+        &self,
+        function: &MyFunction,
+        error: Option<String>,
+        names: &mut TypeNames,
+        seen: &HashSet<TypeId>,
+    ) -> Option<String> {
+        match (error, function.results.types().next()) {
+            (Some(_), Some(Type::Id(id))) => Some(match &self.resolve.types[id].kind {
+                TypeDefKind::Result(Result_ { err: Some(ty), .. }) => {
+                    names.type_name(*ty, seen, None)
+                }
+                _ => "None".to_owned(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Add the named types `ty` refers to (or `ty` itself, if it's named) to `found`, looking through anonymous
+    /// types such as `list<T>` and `option<T>`.
+    fn named_types(&self, ty: Type, found: &mut HashSet<TypeId>) {
+        if let Type::Id(id) = ty {
+            if self.resolve.types[id].name.is_some() {
+                found.insert(id);
+            } else {
+                self.named_types_in(id, found);
+            }
+        }
+    }
+
+    /// Add the named types the definition of `id` refers to to `found` (see `named_types`).
+    fn named_types_in(&self, id: TypeId, found: &mut HashSet<TypeId>) {
+        let mut visit = |ty| self.named_types(ty, found);
+        match &self.resolve.types[id].kind {
+            TypeDefKind::Record(record) => record.fields.iter().for_each(|field| visit(field.ty)),
+            TypeDefKind::Variant(variant) => variant
+                .cases
+                .iter()
+                .filter_map(|case| case.ty)
+                .for_each(visit),
+            TypeDefKind::Tuple(tuple) => tuple.types.iter().copied().for_each(visit),
+            TypeDefKind::Option(ty) | TypeDefKind::List(ty) | TypeDefKind::Type(ty) => visit(*ty),
+            TypeDefKind::Result(result) => result.ok.into_iter().chain(result.err).for_each(visit),
+            TypeDefKind::Handle(Handle::Own(id) | Handle::Borrow(id)) => visit(Type::Id(*id)),
+            _ => (),
+        }
+    }
+
+    pub fn generate_proptests(&self, world: WorldId, world_module: &str, app_name: &str) -> String {
+        let mut names = ProptestNames {
+            summary: self,
+            world_module,
+            modules: BTreeSet::new(),
+        };
+        let world_camel = self.resolve.worlds[world]
+            .name
+            .to_upper_camel_case()
+            .escape();
+        let exported = self
+            .world_keys
+            .get(&world)
+            .map(|keys| {
+                keys.iter()
+                    .filter(|(direction, _)| matches!(direction, Direction::Export))
+                    .map(|(_, key)| key.clone())
+                    .collect::<HashSet<_>>()
+            })
+            .unwrap_or_default();
+
+        let display_name = |function: &MyFunction| {
+            if let Some(interface) = &function.interface {
+                format!(
+                    "{}#{}",
+                    self.resolve
+                        .id_of(interface.id)
+                        .unwrap_or_else(|| interface.name.to_owned()),
+                    function.name
+                )
+            } else {
+                function.name.to_owned()
+            }
+        };
+
+        let mut tests = Vec::new();
+        let mut skipped = Vec::new();
+        for function in &self.functions {
+            if !(matches!(function.kind, FunctionKind::Export)
+                && matches!(function.wit_kind, wit_parser::FunctionKind::Freestanding)
+                && exported.contains(&function.key())
+                && self.bundled_implementation(function).is_none())
+            {
+                continue;
+            }
+
+            let (protocol, scope) = if let Some(interface) = &function.interface {
+                let name = &self.exported_interface_names[&interface.id];
+                (name.to_upper_camel_case().escape(), name.to_snake_case())
+            } else {
+                (world_camel.clone(), String::new())
+            };
+            let name = self.function_name(function);
+            let test_name = if scope.is_empty() {
+                format!("test_{name}")
+            } else {
+                format!("test_{scope}_{name}")
+            };
+
+            let strategies = function
+                .params
+                .iter()
+                .map(|(param, ty)| Some((self.member_name(param), names.strategy(*ty)?)))
+                .collect::<Option<Vec<_>>>();
+            let Some(strategies) = strategies else {
+                skipped.push(display_name(function));
+                continue;
+            };
+
+            let result_types = function.results.iter_types().copied().collect::<Vec<_>>();
+            let checks = match result_types.as_slice() {
+                [] => Some(("result is None".to_owned(), None)),
+                [Type::Id(id)]
+                    if matches!(self.resolve.types[*id].kind, TypeDefKind::Result(_)) =>
+                {
+                    let TypeDefKind::Result(result) = &self.resolve.types[*id].kind else {
+                        unreachable!()
+                    };
+                    names.optional_check(result.ok, "result", 0).and_then(|ok| {
+                        Some((ok, Some(names.optional_check(result.err, "e.value", 0)?)))
+                    })
+                }
+                [ty] => names.check(*ty, "result", 0).map(|check| (check, None)),
+                types => names
+                    .check_tuple(types, "result", 0)
+                    .map(|check| (check, None)),
+            };
+            let Some((check, err_check)) = checks else {
+                skipped.push(display_name(function));
+                continue;
+            };
+
+            let args = strategies
+                .iter()
+                .map(|(param, _)| param.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let call = format!("{app_name}.{protocol}().{name}({args})");
+
+            let mut test = Source::default();
+            if !strategies.is_empty() {
+                test.line(format!(
+                    "@given({})",
+                    strategies
+                        .iter()
+                        .map(|(param, strategy)| format!("{param}={strategy}"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+            test.block(format!("def {test_name}({args}):"), |source| {
+                if let Some(err_check) = err_check {
+                    source.block("try:", |source| source.line(format!("result = {call}")));
+                    source.block("except Err as e:", |source| {
+                        source.line(format!(
+                            "assert {err_check}, f\"unexpected error: {{e.value!r}}\""
+                        ));
+                    });
+                    source.block("else:", |source| {
+                        source.line(format!(
+                            "assert {check}, f\"unexpected result: {{result!r}}\""
+                        ));
+                    });
+                } else {
+                    source.line(format!("result = {call}"));
+                    source.line(format!(
+                        "assert {check}, f\"unexpected result: {{result!r}}\""
+                    ));
+                }
+            });
+            tests.push(String::from(test));
+        }
+
+        let mut docs = format!(
+            "Property-based contract tests for the exports of the `{}` world, generated by `componentize-py`.\n\
+             \n\
+             Run these using `pytest` with Hypothesis installed, and with both the `{app_name}` module and bindings\n\
+             for the world (e.g. as generated by `componentize-py bindings`) available to import.",
+            self.resolve.worlds[world].name
+        );
+        if !skipped.is_empty() {
+            docs.push_str(
+                "\n\nThe following functions were skipped since they take or return resource handles:\n",
+            );
+            for name in skipped {
+                docs.push_str(&format!("\n- `{name}`"));
+            }
+        }
+
+        let mut source = Source::default();
+        source.docstring(Some(&docs));
+        source.blank_line();
+        source.line("from hypothesis import given, strategies as st");
+        source.blank_line();
+        source.line(format!("import {app_name}"));
+        for module in iter::once(world_module.to_owned()).chain(names.modules) {
+            source.line(format!("import {module}"));
+        }
+        source.line(format!("from {world_module}.types import Ok, Err, Some"));
+        for test in tests {
+            source.blank_line();
+            source.push(&test);
+        }
+
+        source.into()
+    }
+
+    fn interface_package(&self, interface: InterfaceId) -> (&'static str, &str) {
+        let (direction, package) = &self.interface_packages[&interface];
+        (*direction, package.as_str())
+    }
+
+    /// Return the name of the Python class (or alias) generated for the specified type.
+    fn class_name(&self, id: TypeId) -> &str {
+        &self.class_names[&id]
+    }
+
+    fn package(&self, owner: TypeOwner, world_module: &str) -> Option<String> {
         match owner {
             TypeOwner::Interface(interface) => {
                 let (module, package) = self.interface_package(interface);
@@ -2075,6 +3703,34 @@ from .types import Result, Ok, Err, Some
             },
         }
     }
+
+    /// Return whether `ty` is or contains a resource handle.
+    fn has_resource(&self, ty: Type) -> bool {
+        match ty {
+            Type::Id(id) => match &self.resolve.types[id].kind {
+                TypeDefKind::Record(record) => record
+                    .fields
+                    .iter()
+                    .any(|field| self.has_resource(field.ty)),
+                TypeDefKind::Variant(variant) => variant
+                    .cases
+                    .iter()
+                    .any(|case| case.ty.map(|ty| self.has_resource(ty)).unwrap_or(false)),
+                TypeDefKind::Result(result) => {
+                    result.ok.map(|ty| self.has_resource(ty)).unwrap_or(false)
+                        || result.err.map(|ty| self.has_resource(ty)).unwrap_or(false)
+                }
+                TypeDefKind::Tuple(tuple) => tuple.types.iter().any(|ty| self.has_resource(*ty)),
+                TypeDefKind::Option(ty) | TypeDefKind::List(ty) | TypeDefKind::Type(ty) => {
+                    self.has_resource(*ty)
+                }
+                TypeDefKind::Enum(_) | TypeDefKind::Flags(_) => false,
+                TypeDefKind::Resource | TypeDefKind::Handle(_) => true,
+                kind => todo!("{kind:?}"),
+            },
+            _ => false,
+        }
+    }
 }
 
 struct TypeNames<'a> {
@@ -2092,6 +3748,26 @@ impl<'a> TypeNames<'a> {
         }
     }
 
+    /// Like `type_name`, but for a value to be lowered (i.e. passed from the guest to the host), in which case a
+    /// `list<u8>` may be given as any object supporting the buffer protocol rather than just `bytes`.
+    fn lowered_type_name(
+        &mut self,
+        ty: Type,
+        seen: &HashSet<TypeId>,
+        resource: Option<TypeId>,
+    ) -> String {
+        if let Type::Id(id) = ty {
+            let id = bindgen::dealias(self.summary.resolve, id);
+            if let TypeDefKind::List(Type::U8 | Type::S8) = self.summary.resolve.types[id].kind {
+                return match self.summary.typing_style {
+                    TypingStyle::Legacy => "Union[Buffer, bytes]".into(),
+                    TypingStyle::Modern => "Buffer | bytes".into(),
+                };
+            }
+        }
+        self.type_name(ty, seen, resource)
+    }
+
     fn type_name(&mut self, ty: Type, seen: &HashSet<TypeId>, resource: Option<TypeId>) -> String {
         match ty {
             Type::Bool => "bool".into(),
@@ -2114,29 +3790,7 @@ impl<'a> TypeNames<'a> {
                     | TypeDefKind::Flags(_)
                     | TypeDefKind::Resource => {
                         if seen.contains(&id) {
-                            let package = if ty.owner == self.owner {
-                                String::new()
-                            } else {
-                                match ty.owner {
-                                    TypeOwner::Interface(interface) => {
-                                        self.imports.insert(interface);
-                                        format!("{}.", self.summary.interface_package(interface).1)
-                                    }
-                                    // todo: place anonymous types in types.py and import them from there
-                                    _ => String::new(),
-                                }
-                            };
-
-                            let name = if let Some(name) = &ty.name {
-                                name.to_upper_camel_case().escape()
-                            } else {
-                                format!(
-                                    "AnonymousType{}",
-                                    self.summary.types.get_index_of(&id).unwrap()
-                                )
-                            };
-
-                            format!("{package}{name}")
+                            self.class(id, "")
                         } else {
                             // As of this writing, there's no concept of forward declaration in Python, so we must
                             // either use `Any` or `Self` for types which have not yet been fully declared.
@@ -2144,10 +3798,14 @@ impl<'a> TypeNames<'a> {
                         }
                     }
                     TypeDefKind::Option(some) => {
-                        if abi::is_option(self.summary.resolve, *some) {
-                            format!("Optional[Some[{}]]", self.type_name(*some, seen, resource))
+                        let some = if abi::is_option(self.summary.resolve, *some) {
+                            format!("Some[{}]", self.type_name(*some, seen, resource))
                         } else {
-                            format!("Optional[{}]", self.type_name(*some, seen, resource))
+                            self.type_name(*some, seen, resource)
+                        };
+                        match self.summary.typing_style {
+                            TypingStyle::Legacy => format!("Optional[{some}]"),
+                            TypingStyle::Modern => format!("{some} | None"),
                         }
                     }
                     TypeDefKind::Result(result) => format!(
@@ -2165,7 +3823,11 @@ impl<'a> TypeNames<'a> {
                         if let Type::U8 | Type::S8 = ty {
                             "bytes".into()
                         } else {
-                            format!("List[{}]", self.type_name(*ty, seen, resource))
+                            let ty = self.type_name(*ty, seen, resource);
+                            match self.summary.typing_style {
+                                TypingStyle::Legacy => format!("List[{ty}]"),
+                                TypingStyle::Modern => format!("list[{ty}]"),
+                            }
                         }
                     }
                     TypeDefKind::Tuple(tuple) => {
@@ -2180,7 +3842,10 @@ impl<'a> TypeNames<'a> {
                         } else {
                             types
                         };
-                        format!("Tuple[{types}]")
+                        match self.summary.typing_style {
+                            TypingStyle::Legacy => format!("Tuple[{types}]"),
+                            TypingStyle::Modern => format!("tuple[{types}]"),
+                        }
                     }
                     TypeDefKind::Handle(Handle::Own(ty) | Handle::Borrow(ty)) => {
                         self.type_name(Type::Id(*ty), seen, resource)
@@ -2191,6 +3856,727 @@ impl<'a> TypeNames<'a> {
             }
         }
     }
+
+    /// Return the name of the class generated for the named type `id`, followed by `suffix` and qualified with
+    /// its module if that differs from `self.owner`'s.
+    fn class(&mut self, id: TypeId, suffix: &str) -> String {
+        let ty = &self.summary.resolve.types[id];
+        let package = if ty.owner == self.owner {
+            String::new()
+        } else {
+            match ty.owner {
+                TypeOwner::Interface(interface) => {
+                    self.imports.insert(interface);
+                    format!("{}.", self.summary.interface_package(interface).1)
+                }
+                // todo: place anonymous types in types.py and import them from there
+                _ => String::new(),
+            }
+        };
+
+        format!("{package}{}{suffix}", self.summary.class_name(id))
+    }
+
+    /// Return the `to_dict`, `from_dict`, `to_json`, and `from_json` methods for a record with the specified
+    /// fields, or nothing if any of them is or contains a resource handle, since handles have no meaningful `dict`
+    /// representation.
+    ///
+    /// Each field is keyed by its WIT name, and its value is converted as described for `dict_encode`.  The JSON
+    /// methods use `componentize_py_runtime`'s `serde_json`-backed functions when available (see the world's
+    /// `types` module).
+    fn dict_methods(&mut self, fields: &[(String, &str, Type)]) -> Vec<String> {
+        if fields
+            .iter()
+            .any(|(_, _, ty)| self.summary.has_resource(*ty))
+        {
+            return Vec::new();
+        }
+
+        let dict = match self.summary.typing_style {
+            TypingStyle::Legacy => "Dict[str, Any]",
+            TypingStyle::Modern => "dict[str, Any]",
+        };
+
+        let encoded = fields
+            .iter()
+            .map(|(field, wit, ty)| {
+                let value = self.dict_encode(*ty, &format!("self.{field}"), 0);
+                format!("\"{wit}\": {value}")
+            })
+            .collect();
+        let decoded = fields
+            .iter()
+            .map(|(field, wit, ty)| {
+                let value = self.dict_decode(*ty, &format!("d[\"{wit}\"]"), 0);
+                format!("{field}={value}")
+            })
+            .collect();
+
+        let mut to_dict = Source::default();
+        to_dict.block(format!("def to_dict(self) -> {dict}:"), |source| {
+            source.docstring(Some(
+                "Returns a `dict` representation of this record containing only `dict`s, `list`s, `str`s,
+numbers, booleans, and `None`, e.g. for use with `json.dumps`.",
+            ));
+            source.push(&format!("return {{{}}}", block_items(encoded)));
+        });
+
+        let mut from_dict = Source::default();
+        from_dict.line("@classmethod");
+        from_dict.block(
+            format!("def from_dict(cls, d: {dict}) -> Self:"),
+            |source| {
+                source.docstring(Some(
+                    "Returns a record created from a `dict` in the format returned by `to_dict`.",
+                ));
+                source.push(&format!("return cls({})", block_items(decoded)));
+            },
+        );
+
+        let mut to_json = Source::default();
+        to_json.block("def to_json(self) -> str:", |source| {
+            source.docstring(Some(
+                "Returns a JSON representation of this record, in the format described for `to_dict`.",
+            ));
+            source.line("return json_dumps(self.to_dict())");
+        });
+
+        let mut from_json = Source::default();
+        from_json.line("@classmethod");
+        from_json.block("def from_json(cls, s: str) -> Self:", |source| {
+            source.docstring(Some(
+                "Returns a record parsed from JSON in the format returned by `to_json`.",
+            ));
+            source.line("return cls.from_dict(json_loads(s))");
+        });
+
+        vec![
+            to_dict.into(),
+            from_dict.into(),
+            to_json.into(),
+            from_json.into(),
+        ]
+    }
+
+    /// Return a Python expression which converts `value` (of type `ty`) to a value made of only `dict`s, `list`s,
+    /// `str`s, numbers, booleans, and `None`.
+    ///
+    /// Records are converted using their `to_dict` methods, variants to `{"tag": <case>, "value": <payload>}`,
+    /// enums to their case names, flags to lists of flag names, results to `{"ok": <payload>}` or
+    /// `{"err": <payload>}`, and tuples and `bytes` to lists.  Nested options (i.e. those represented using `Some`)
+    /// are converted to `{"value": <payload>}`.
+    ///
+    /// `depth` is used to generate unique names for the variables of any nested comprehensions.
+    fn dict_encode(&mut self, ty: Type, value: &str, depth: usize) -> String {
+        let Type::Id(id) = ty else {
+            return value.to_owned();
+        };
+
+        match &self.summary.resolve.types[id].kind {
+            TypeDefKind::Record(_) => format!("{value}.to_dict()"),
+            TypeDefKind::Variant(variant) => {
+                let cases = variant
+                    .cases
+                    .iter()
+                    .map(|case| {
+                        let dict = if let Some(ty) = case.ty {
+                            format!(
+                                "{{\"tag\": \"{}\", \"value\": {}}}",
+                                case.name,
+                                self.dict_encode(ty, &format!("{value}.value"), depth)
+                            )
+                        } else {
+                            format!("{{\"tag\": \"{}\"}}", case.name)
+                        };
+                        let class = self.class(
+                            id,
+                            &format!("_{}", case.name.to_upper_camel_case().escape()),
+                        );
+                        (dict, class)
+                    })
+                    .collect::<Vec<_>>();
+                let (last, _) = cases.last().unwrap();
+                let tests = cases[..cases.len() - 1]
+                    .iter()
+                    .map(|(dict, class)| format!("{dict} if isinstance({value}, {class}) else "))
+                    .collect::<String>();
+                format!("({tests}{last})")
+            }
+            TypeDefKind::Enum(en) => format!(
+                "{}[{value}.value]",
+                tuple_literal(en.cases.iter().map(|case| format!("\"{}\"", case.name)))
+            ),
+            TypeDefKind::Flags(flags) => {
+                let names =
+                    tuple_literal(flags.flags.iter().map(|flag| format!("\"{}\"", flag.name)));
+                format!(
+                    "[x{depth} for i{depth}, x{depth} in enumerate({names}) if {value}.value & (1 << i{depth})]"
+                )
+            }
+            TypeDefKind::Option(some) => {
+                if abi::is_option(self.summary.resolve, *some) {
+                    let some = self.dict_encode(*some, &format!("{value}.value"), depth);
+                    format!("(None if {value} is None else {{\"value\": {some}}})")
+                } else {
+                    let some = self.dict_encode(*some, value, depth);
+                    if some == value {
+                        some
+                    } else {
+                        format!("(None if {value} is None else {some})")
+                    }
+                }
+            }
+            TypeDefKind::Result(result) => {
+                let payload = format!("{value}.value");
+                let mut convert = |ty: Option<Type>| match ty {
+                    Some(ty) => self.dict_encode(ty, &payload, depth),
+                    None => "None".into(),
+                };
+                let ok = convert(result.ok);
+                let err = convert(result.err);
+                format!("({{\"ok\": {ok}}} if isinstance({value}, Ok) else {{\"err\": {err}}})")
+            }
+            TypeDefKind::List(Type::U8 | Type::S8) => format!("list({value})"),
+            TypeDefKind::List(ty) => {
+                let element = format!("x{depth}");
+                let convert = self.dict_encode(*ty, &element, depth + 1);
+                if convert == element {
+                    value.to_owned()
+                } else {
+                    format!("[{convert} for {element} in {value}]")
+                }
+            }
+            TypeDefKind::Tuple(tuple) => {
+                let elements = tuple
+                    .types
+                    .iter()
+                    .enumerate()
+                    .map(|(index, ty)| (format!("{value}[{index}]"), *ty))
+                    .map(|(element, ty)| (self.dict_encode(ty, &element, depth), element))
+                    .collect::<Vec<_>>();
+                if elements.iter().all(|(convert, element)| convert == element) {
+                    format!("list({value})")
+                } else {
+                    let elements = elements
+                        .into_iter()
+                        .map(|(convert, _)| convert)
+                        .collect::<Vec<_>>();
+                    format!("[{}]", elements.join(", "))
+                }
+            }
+            TypeDefKind::Type(ty) => self.dict_encode(*ty, value, depth),
+            kind => unreachable!("{kind:?}"),
+        }
+    }
+
+    /// Return a Python expression which converts `value`, as returned by `dict_encode` for type `ty`, back to
+    /// that type.
+    ///
+    /// `depth` is used to generate unique names for the variables of any nested comprehensions.
+    fn dict_decode(&mut self, ty: Type, value: &str, depth: usize) -> String {
+        let Type::Id(id) = ty else {
+            return value.to_owned();
+        };
+
+        match &self.summary.resolve.types[id].kind {
+            TypeDefKind::Record(_) => format!("{}.from_dict({value})", self.class(id, "")),
+            TypeDefKind::Variant(variant) => {
+                let cases = variant
+                    .cases
+                    .iter()
+                    .map(|case| {
+                        let class = self.class(
+                            id,
+                            &format!("_{}", case.name.to_upper_camel_case().escape()),
+                        );
+                        let make = if let Some(ty) = case.ty {
+                            let payload =
+                                self.dict_decode(ty, &format!("{value}[\"value\"]"), depth);
+                            format!("lambda: {class}({payload})")
+                        } else {
+                            class
+                        };
+                        format!("\"{}\": {make}", case.name)
+                    })
+                    .collect::<Vec<_>>();
+                format!("{{{}}}[{value}[\"tag\"]]()", cases.join(", "))
+            }
+            TypeDefKind::Enum(en) => format!(
+                "{}({}.index({value}))",
+                self.class(id, ""),
+                tuple_literal(en.cases.iter().map(|case| format!("\"{}\"", case.name)))
+            ),
+            TypeDefKind::Flags(flags) => {
+                let names =
+                    tuple_literal(flags.flags.iter().map(|flag| format!("\"{}\"", flag.name)));
+                format!(
+                    "{}(sum(1 << {names}.index(x{depth}) for x{depth} in {value}))",
+                    self.class(id, "")
+                )
+            }
+            TypeDefKind::Option(some) => {
+                if abi::is_option(self.summary.resolve, *some) {
+                    let some = self.dict_decode(*some, &format!("{value}[\"value\"]"), depth);
+                    format!("(None if {value} is None else Some({some}))")
+                } else {
+                    let some = self.dict_decode(*some, value, depth);
+                    if some == value {
+                        some
+                    } else {
+                        format!("(None if {value} is None else {some})")
+                    }
+                }
+            }
+            TypeDefKind::Result(result) => {
+                let mut convert = |ty: Option<Type>, key: &str| match ty {
+                    Some(ty) => self.dict_decode(ty, &format!("{value}[\"{key}\"]"), depth),
+                    None => "None".into(),
+                };
+                let ok = convert(result.ok, "ok");
+                let err = convert(result.err, "err");
+                format!("(Ok({ok}) if \"ok\" in {value} else Err({err}))")
+            }
+            TypeDefKind::List(Type::U8 | Type::S8) => format!("bytes({value})"),
+            TypeDefKind::List(ty) => {
+                let element = format!("x{depth}");
+                let convert = self.dict_decode(*ty, &element, depth + 1);
+                if convert == element {
+                    value.to_owned()
+                } else {
+                    format!("[{convert} for {element} in {value}]")
+                }
+            }
+            TypeDefKind::Tuple(tuple) => {
+                let elements = tuple
+                    .types
+                    .iter()
+                    .enumerate()
+                    .map(|(index, ty)| (format!("{value}[{index}]"), *ty))
+                    .map(|(element, ty)| (self.dict_decode(ty, &element, depth), element))
+                    .collect::<Vec<_>>();
+                if elements.iter().all(|(convert, element)| convert == element) {
+                    format!("tuple({value})")
+                } else {
+                    tuple_literal(elements.into_iter().map(|(convert, _)| convert))
+                }
+            }
+            TypeDefKind::Type(ty) => self.dict_decode(*ty, value, depth),
+            kind => unreachable!("{kind:?}"),
+        }
+    }
+}
+
+/// Generates Hypothesis strategies and type checks for use by `Summary::generate_proptests`, recording which
+/// generated modules they refer to.
+struct ProptestNames<'a> {
+    summary: &'a Summary<'a>,
+    world_module: &'a str,
+    modules: BTreeSet<String>,
+}
+
+impl ProptestNames<'_> {
+    /// Return the fully-qualified name of the class generated for the named type `id`, followed by `suffix`.
+    fn class(&mut self, id: TypeId, suffix: &str) -> String {
+        let ty = &self.summary.resolve.types[id];
+        let module = match ty.owner {
+            TypeOwner::Interface(interface) => {
+                let (direction, name) = self.summary.interface_package(interface);
+                format!("{}.{direction}.{name}", self.world_module)
+            }
+            _ => self.world_module.to_owned(),
+        };
+        let name = ty.name.as_deref().unwrap().to_upper_camel_case().escape();
+        self.modules.insert(module.clone());
+        format!("{module}.{name}{suffix}")
+    }
+
+    fn int_range(ty: Type) -> Option<(&'static str, &'static str)> {
+        Some(match ty {
+            Type::U8 => ("0", "255"),
+            Type::S8 => ("-128", "127"),
+            Type::U16 => ("0", "65535"),
+            Type::S16 => ("-32768", "32767"),
+            Type::U32 => ("0", "2**32 - 1"),
+            Type::S32 => ("-(2**31)", "2**31 - 1"),
+            Type::U64 => ("0", "2**64 - 1"),
+            Type::S64 => ("-(2**63)", "2**63 - 1"),
+            _ => return None,
+        })
+    }
+
+    /// Return a Hypothesis strategy for generating values of type `ty`, or `None` if `ty` is or contains a
+    /// resource handle.
+    fn strategy(&mut self, ty: Type) -> Option<String> {
+        if let Some((min, max)) = Self::int_range(ty) {
+            return Some(format!("st.integers({min}, {max})"));
+        }
+
+        Some(match ty {
+            Type::Bool => "st.booleans()".into(),
+            Type::F32 => "st.floats(width=32)".into(),
+            Type::F64 => "st.floats()".into(),
+            Type::Char => "st.characters(exclude_categories=(\"Cs\",))".into(),
+            Type::String => "st.text()".into(),
+            Type::Id(id) => match &self.summary.resolve.types[id].kind {
+                TypeDefKind::Record(record) => {
+                    let class = self.class(id, "");
+                    let fields = record
+                        .fields
+                        .iter()
+                        .map(|field| {
+                            Some(format!(
+                                "{}={}",
+                                field.name.to_snake_case().escape(),
+                                self.strategy(field.ty)?
+                            ))
+                        })
+                        .collect::<Option<Vec<_>>>()?;
+                    format!("st.builds({class}{})", prefix_comma(&fields))
+                }
+                TypeDefKind::Variant(variant) => {
+                    let cases = variant
+                        .cases
+                        .iter()
+                        .map(|case| {
+                            let class = self.class(
+                                id,
+                                &format!("_{}", case.name.to_upper_camel_case().escape()),
+                            );
+                            Some(if let Some(ty) = case.ty {
+                                format!("st.builds({class}, {})", self.strategy(ty)?)
+                            } else {
+                                format!("st.builds({class})")
+                            })
+                        })
+                        .collect::<Option<Vec<_>>>()?;
+                    format!("st.one_of({})", cases.join(", "))
+                }
+                TypeDefKind::Enum(_) => format!("st.sampled_from({})", self.class(id, "")),
+                TypeDefKind::Flags(flags) => format!(
+                    "st.integers(0, 2**{} - 1).map({})",
+                    flags.flags.len(),
+                    self.class(id, "")
+                ),
+                TypeDefKind::Option(some) => {
+                    let strategy = self.strategy(*some)?;
+                    if abi::is_option(self.summary.resolve, *some) {
+                        format!("st.one_of(st.none(), st.builds(Some, {strategy}))")
+                    } else {
+                        format!("st.one_of(st.none(), {strategy})")
+                    }
+                }
+                TypeDefKind::Result(result) => {
+                    let mut payload = |ty: Option<Type>| match ty {
+                        Some(ty) => self.strategy(ty),
+                        None => Some("st.none()".into()),
+                    };
+                    let ok = payload(result.ok)?;
+                    let err = payload(result.err)?;
+                    format!("st.one_of(st.builds(Ok, {ok}), st.builds(Err, {err}))")
+                }
+                TypeDefKind::List(Type::U8 | Type::S8) => "st.binary()".into(),
+                TypeDefKind::List(ty) => format!("st.lists({}, max_size=8)", self.strategy(*ty)?),
+                TypeDefKind::Tuple(tuple) => {
+                    let types = tuple
+                        .types
+                        .iter()
+                        .map(|ty| self.strategy(*ty))
+                        .collect::<Option<Vec<_>>>()?;
+                    format!("st.tuples({})", types.join(", "))
+                }
+                TypeDefKind::Type(ty) => self.strategy(*ty)?,
+                _ => return None,
+            },
+            _ => unreachable!(),
+        })
+    }
+
+    /// Return a Python expression which is true if and only if `value` has type `ty`, or `None` if `ty` is or
+    /// contains a resource handle.
+    ///
+    /// `depth` is used to generate unique names for the variables of any nested generator expressions.
+    fn check(&mut self, ty: Type, value: &str, depth: usize) -> Option<String> {
+        if let Some((min, max)) = Self::int_range(ty) {
+            return Some(format!(
+                "(isinstance({value}, int) and {min} <= {value} <= {max})"
+            ));
+        }
+
+        Some(match ty {
+            Type::Bool => format!("isinstance({value}, bool)"),
+            Type::F32 | Type::F64 => format!("isinstance({value}, (int, float))"),
+            Type::Char => format!("(isinstance({value}, str) and len({value}) == 1)"),
+            Type::String => format!("isinstance({value}, str)"),
+            Type::Id(id) => match &self.summary.resolve.types[id].kind {
+                TypeDefKind::Record(record) => {
+                    let class = self.class(id, "");
+                    let fields = record
+                        .fields
+                        .iter()
+                        .map(|field| {
+                            let name = field.name.to_snake_case().escape();
+                            self.check(field.ty, &format!("{value}.{name}"), depth)
+                        })
+                        .collect::<Option<Vec<_>>>()?;
+                    format!("(isinstance({value}, {class}){})", prefix_and(&fields))
+                }
+                TypeDefKind::Variant(variant) => {
+                    let cases = variant
+                        .cases
+                        .iter()
+                        .map(|case| {
+                            let class = self.class(
+                                id,
+                                &format!("_{}", case.name.to_upper_camel_case().escape()),
+                            );
+                            let payload = if let Some(ty) = case.ty {
+                                vec![self.check(ty, &format!("{value}.value"), depth)?]
+                            } else {
+                                Vec::new()
+                            };
+                            Some(format!(
+                                "(isinstance({value}, {class}){})",
+                                prefix_and(&payload)
+                            ))
+                        })
+                        .collect::<Option<Vec<_>>>()?;
+                    format!("({})", cases.join(" or "))
+                }
+                TypeDefKind::Enum(_) | TypeDefKind::Flags(_) => {
+                    format!("isinstance({value}, {})", self.class(id, ""))
+                }
+                TypeDefKind::Option(some) => {
+                    if abi::is_option(self.summary.resolve, *some) {
+                        let check = self.check(*some, &format!("{value}.value"), depth)?;
+                        format!("({value} is None or (isinstance({value}, Some) and {check}))")
+                    } else {
+                        format!("({value} is None or {})", self.check(*some, value, depth)?)
+                    }
+                }
+                TypeDefKind::Result(result) => {
+                    let payload = format!("{value}.value");
+                    let ok = self.optional_check(result.ok, &payload, depth)?;
+                    let err = self.optional_check(result.err, &payload, depth)?;
+                    format!(
+                        "((isinstance({value}, Ok) and {ok}) or (isinstance({value}, Err) and {err}))"
+                    )
+                }
+                TypeDefKind::List(Type::U8 | Type::S8) => {
+                    format!("isinstance({value}, (bytes, bytearray, memoryview))")
+                }
+                TypeDefKind::List(ty) => {
+                    let element = format!("x{depth}");
+                    let check = self.check(*ty, &element, depth + 1)?;
+                    format!("(isinstance({value}, list) and all({check} for {element} in {value}))")
+                }
+                TypeDefKind::Tuple(tuple) => self.check_tuple(&tuple.types, value, depth)?,
+                TypeDefKind::Type(ty) => self.check(*ty, value, depth)?,
+                _ => return None,
+            },
+            _ => unreachable!(),
+        })
+    }
+
+    /// Like `check`, but for the optional payload of a `result`, which is `None` if absent.
+    fn optional_check(&mut self, ty: Option<Type>, value: &str, depth: usize) -> Option<String> {
+        match ty {
+            Some(ty) => self.check(ty, value, depth),
+            None => Some(format!("{value} is None")),
+        }
+    }
+
+    fn check_tuple(&mut self, types: &[Type], value: &str, depth: usize) -> Option<String> {
+        let elements = types
+            .iter()
+            .enumerate()
+            .map(|(index, ty)| self.check(*ty, &format!("{value}[{index}]"), depth))
+            .collect::<Option<Vec<_>>>()?;
+        Some(format!(
+            "(isinstance({value}, tuple) and len({value}) == {}{})",
+            types.len(),
+            prefix_and(&elements)
+        ))
+    }
+}
+
+/// Return a Python tuple expression containing `items`.
+/// Generate the `to_datetime` and `from_datetime` methods of the `wasi:clocks/wall-clock` `datetime` record.
+fn datetime_methods() -> Vec<String> {
+    let mut to_datetime = Source::default();
+    to_datetime.block("def to_datetime(self) -> datetime:", |source| {
+        source.docstring(Some(
+            "Returns this time as a timezone-aware `datetime` in UTC (see `time_helpers.to_datetime`).",
+        ));
+        source.line("return time_helpers.to_datetime(self.seconds, self.nanoseconds)");
+    });
+
+    let mut from_datetime = Source::default();
+    from_datetime.line("@classmethod");
+    from_datetime.block(
+        "def from_datetime(cls, value: datetime) -> Self:",
+        |source| {
+            source.docstring(Some(
+                "Returns the record corresponding to `value` (see `time_helpers.from_datetime`).",
+            ));
+            source.line("return cls(*time_helpers.from_datetime(value))");
+        },
+    );
+
+    vec![to_datetime.into(), from_datetime.into()]
+}
+
+/// Generate the `time_helpers` module, which converts between the representations of time used by `wasi:clocks`
+/// and Python's `datetime` module.
+fn time_helpers_module(typing_style: TypingStyle) -> String {
+    let (typing_import, tuple) = match typing_style {
+        TypingStyle::Legacy => ("\nfrom typing import Tuple", "Tuple[int, int]"),
+        TypingStyle::Modern => ("", "tuple[int, int]"),
+    };
+
+    format!(
+        r#""""
+Conversions between the representations of time used by `wasi:clocks` and Python's `datetime` module, generated
+by `componentize-py`.
+
+`wasi:clocks` represents durations (and monotonic clock instants) as integer nanoseconds, and wall clock times as
+`datetime` records containing the seconds and nanoseconds elapsed since the Unix epoch.  Python's `timedelta` and
+`datetime` have microsecond resolution, so any remaining nanoseconds are truncated when converting to them.
+"""
+from datetime import datetime, timedelta, timezone{typing_import}
+
+EPOCH = datetime(1970, 1, 1, tzinfo=timezone.utc)
+
+def to_timedelta(nanoseconds: int) -> timedelta:
+    """
+    Returns the `timedelta` corresponding to a duration in nanoseconds.
+    """
+    return timedelta(microseconds=nanoseconds // 1000)
+
+def from_timedelta(delta: timedelta) -> int:
+    """
+    Returns the number of nanoseconds in `delta`, for use as a duration.
+    """
+    return (delta // timedelta(microseconds=1)) * 1000
+
+def to_datetime(seconds: int, nanoseconds: int) -> datetime:
+    """
+    Returns the timezone-aware `datetime` (in UTC) corresponding to a wall clock time.
+    """
+    return EPOCH + timedelta(seconds=seconds, microseconds=nanoseconds // 1000)
+
+def from_datetime(value: datetime) -> {tuple}:
+    """
+    Returns the seconds and nanoseconds elapsed since the Unix epoch at `value`, for use as a wall clock time.
+
+    A naive `datetime` is assumed to be in local time, as for `datetime.timestamp`.
+    """
+    if value.tzinfo is None:
+        value = value.astimezone()
+    microseconds = (value - EPOCH) // timedelta(microseconds=1)
+    if microseconds < 0:
+        raise ValueError(f"{{value}} is earlier than the Unix epoch")
+    return microseconds // 1_000_000, (microseconds % 1_000_000) * 1000
+"#
+    )
+}
+
+/// Generate the `option` module, which converts between nested `option` values (e.g. `option<option<T>>`, whose
+/// `some` case is wrapped in `Some`) and plain optional values.
+fn option_module(typing_style: TypingStyle) -> String {
+    let (typing_import, type_params, nested, flat) = match typing_style {
+        TypingStyle::Legacy => (
+            "from typing import Any, Optional, TypeVar\n",
+            "",
+            "Optional[Some[Optional[T]]]",
+            "Optional[T]",
+        ),
+        TypingStyle::Modern => (
+            "from typing import Any\n",
+            "[T]",
+            "Some[T | None] | None",
+            "T | None",
+        ),
+    };
+    let type_var = match typing_style {
+        TypingStyle::Legacy => "\nT = TypeVar('T')\n",
+        TypingStyle::Modern => "",
+    };
+
+    format!(
+        r#""""
+Helpers for nested `option` values, generated by `componentize-py`.
+
+An `option<T>` is normally represented as either `None` or a value of type `T`.  When `T` is itself an `option`,
+that would make `none` indistinguishable from `some(none)`, so the `some` case is wrapped in `Some` instead: the
+values of an `option<option<u32>>` are `None`, `Some(None)`, and e.g. `Some(42)`.  Passing a bare value such as
+`42` where `Some(...)` is required is an error.
+"""
+{typing_import}
+from .types import Some
+{type_var}
+def flatten{type_params}(value: {nested}) -> {flat}:
+    """
+    Returns the payload of a nested `option`, treating `Some(None)` the same as `None`.
+
+    Raises `TypeError` if `value` is neither `None` nor an instance of `Some`.
+    """
+    if value is None:
+        return None
+    check(value)
+    return value.value
+
+def nest{type_params}(value: {flat}) -> {nested}:
+    """
+    Returns `value` as a nested `option`, mapping `None` to `None` and any other value `x` to `Some(x)`.
+
+    This is the inverse of `flatten` for all values other than `Some(None)`.
+    """
+    return None if value is None else Some(value)
+
+def check(value: Any) -> None:
+    """
+    Raises `TypeError` unless `value` is a valid nested `option`, i.e. either `None` or an instance of `Some`.
+    """
+    if value is not None and not isinstance(value, Some):
+        raise TypeError(
+            f"expected `None` or `Some(...)` for a nested `option`, got {{type(value).__qualname__}}; "
+            f"use `Some({{value!r}})` (or `option.nest`) to wrap it"
+        )
+"#
+    )
+}
+
+fn tuple_literal(items: impl Iterator<Item = String>) -> String {
+    let items = items.collect::<Vec<_>>();
+    if let [item] = &items[..] {
+        format!("({item},)")
+    } else {
+        format!("({})", items.join(", "))
+    }
+}
+
+/// Return `items` formatted as the contents of a bracketed expression with one item per line, or an empty string
+/// if there are no items.
+fn block_items(items: Vec<String>) -> String {
+    if items.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "\n{}",
+            items
+                .iter()
+                .map(|item| format!("    {item},\n"))
+                .collect::<String>()
+        )
+    }
+}
+
+fn prefix_comma(items: &[String]) -> String {
+    items.iter().map(|item| format!(", {item}")).collect()
+}
+
+fn prefix_and(items: &[String]) -> String {
+    items.iter().map(|item| format!(" and {item}")).collect()
 }
 
 pub trait Escape {
@@ -2227,6 +4613,40 @@ fn matches_resource(function: &MyFunction, resource: TypeId, direction: Directio
     }
 }
 
+/// Write `contents` to `path` unless it already has exactly those contents, adding `path` to `written` either way.
+///
+/// This leaves unchanged files (and their modification times) alone when regenerating bindings in place, so that
+/// editors and other tools watching the output directory only see the files which actually changed.
+fn write_if_changed(written: &mut BTreeSet<PathBuf>, path: &Path, contents: String) -> Result<()> {
+    if fs::read(path).ok().as_deref() != Some(contents.as_bytes()) {
+        fs::write(path, contents)?;
+    }
+    written.insert(path.to_owned());
+    Ok(())
+}
+
+/// Append an `export` decorator to `source`, which registers a function as the implementation of the `protocol`
+/// export of the same name, allowing the app to implement `protocol` using free functions rather than a class.
+fn export_decorator(source: &mut Source, types_prefix: &str, protocol: &str) {
+    source.blank_line();
+    source.line(format!(
+        "from {types_prefix}types import ExportFunction, register_export"
+    ));
+    source.blank_line();
+    source.block(
+        "def export(function: ExportFunction) -> ExportFunction:",
+        |source| {
+            source.docstring(Some(&format!(
+                "Register `function` as the implementation of the `{protocol}` export of the same name.\n\
+                 \n\
+                 This is an alternative to defining a `{protocol}` class in the app module, which takes precedence \
+                 if present."
+            )));
+            source.line(format!("return register_export(\"{protocol}\", function)"));
+        },
+    );
+}
+
 fn world_module_import(name: &str, alias: &str) -> String {
     if let Some((front, rear)) = name.rsplit_once('.') {
         format!("from {front} import {rear} as {alias}")
@@ -2235,35 +4655,99 @@ fn world_module_import(name: &str, alias: &str) -> String {
     }
 }
 
-fn docstring(
-    world_module: &str,
-    docs: Option<&str>,
-    indent_level: usize,
-    error: Option<&str>,
-) -> String {
-    let docs = match (
+fn docs(world_module: &str, docs: Option<&str>, error: Option<&str>) -> Option<String> {
+    match (
         docs,
-        error.map(|e| format!("Raises: `{world_module}.types.Err({e})`")),
+        error.map(|e| format!("Raises: `{world_module}.types.{e}`")),
     ) {
         (Some(docs), Some(error_docs)) => Some(format!("{docs}\n\n{error_docs}")),
         (Some(docs), None) => Some(docs.to_owned()),
         (None, Some(error_docs)) => Some(error_docs),
         (None, None) => None,
+    }
+}
+
+/// Return the fully-qualified WIT name of an interface, e.g. `wasi:http/types@0.2.0`, or just its name if it
+/// doesn't belong to a package.
+fn qualified_name(name: &str, package: Option<PackageName>) -> String {
+    match package {
+        Some(PackageName {
+            namespace,
+            name: package_name,
+            version: Some(version),
+        }) => format!("{namespace}:{package_name}/{name}@{version}"),
+        Some(PackageName {
+            namespace,
+            name: package_name,
+            version: None,
+        }) => format!("{namespace}:{package_name}/{name}"),
+        None => name.to_owned(),
+    }
+}
+
+/// Find the most specific (i.e. longest, with ties broken alphabetically) wildcard pattern among the keys of
+/// `interface_names` which matches the fully-qualified interface name `qualified`, returning the corresponding
+/// Python name, if any.
+///
+/// Each `*` in a pattern matches any sequence of characters, and each `*` in the corresponding Python name is
+/// replaced by what the `*` in the same position in the pattern matched.  For example, `wasi:http/*@0.2.*=http-*`
+/// names `wasi:http/types@0.2.0` `http-types`.
+fn match_pattern(interface_names: &HashMap<&str, &str>, qualified: &str) -> Result<Option<String>> {
+    let mut patterns = interface_names
+        .iter()
+        .filter(|(pattern, _)| pattern.contains('*'))
+        .filter_map(|(pattern, python_name)| {
+            Some((
+                *pattern,
+                *python_name,
+                wildcard_captures(pattern, qualified)?,
+            ))
+        })
+        .collect::<Vec<_>>();
+    patterns.sort_by(|(a, ..), (b, ..)| b.len().cmp(&a.len()).then(a.cmp(b)));
+
+    let Some((pattern, python_name, captures)) = patterns.into_iter().next() else {
+        return Ok(None);
     };
 
-    if let Some(docs) = docs {
-        let newline = '\n';
-        let indent = (0..indent_level)
-            .map(|_| "    ")
-            .collect::<Vec<_>>()
-            .concat();
-        let docs = docs
-            .lines()
-            .map(|line| format!("{indent}{line}\n"))
-            .collect::<Vec<_>>()
-            .concat();
-        format!(r#""""{newline}{docs}{indent}"""{newline}{indent}"#)
-    } else {
-        String::new()
+    let mut parts = python_name.split('*');
+    let mut result = parts.next().unwrap().to_owned();
+    for (index, part) in parts.enumerate() {
+        let Some(capture) = captures.get(index) else {
+            bail!(
+                "interface name `{python_name}` contains more `*` wildcards than the pattern `{pattern}` it is \
+                 given for"
+            );
+        };
+        result.push_str(capture);
+        result.push_str(part);
     }
+
+    Ok(Some(result))
+}
+
+/// If the wildcard `pattern` matches `name`, return what each of its `*` wildcards matched.
+///
+/// Each wildcard but the last matches as little as possible.
+fn wildcard_captures<'b>(pattern: &str, name: &'b str) -> Option<Vec<&'b str>> {
+    let mut literals = pattern.split('*');
+    let prefix = literals.next().unwrap();
+    let mut rest = name.strip_prefix(prefix)?;
+    let mut literals = literals.collect::<Vec<_>>();
+    let suffix = literals.pop()?;
+    let mut rest_len = rest.len().checked_sub(suffix.len())?;
+    if !rest.ends_with(suffix) {
+        return None;
+    }
+
+    let mut captures = Vec::new();
+    for literal in literals {
+        let index = rest[..rest_len].find(literal)?;
+        captures.push(&rest[..index]);
+        rest = &rest[index + literal.len()..];
+        rest_len -= index + literal.len();
+    }
+    captures.push(&rest[..rest_len]);
+
+    Some(captures)
 }
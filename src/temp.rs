@@ -0,0 +1,95 @@
+//! Temporary directories used while building a component (e.g. for the unpacked standard library and generated
+//! bindings).
+//!
+//! Each directory is removed when dropped, whether or not the build succeeded, unless the caller asked to keep it
+//! for debugging.  Directories are also recorded in a process-wide registry so that `remove_all` can clean up
+//! after a build which is interrupted (e.g. by Ctrl-C) before they are dropped, and they share a recognizable
+//! prefix so that `remove_stale` can find any left behind by a process which was killed outright.
+
+use {
+    anyhow::Result,
+    std::{
+        env, fs,
+        path::{Path, PathBuf},
+        sync::Mutex,
+        time::{Duration, SystemTime},
+    },
+};
+
+const PREFIX: &str = "componentize-py-";
+
+/// Directories which have been created but not yet dropped (excluding those which are to be kept)
+static LIVE: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+pub struct TempDir {
+    dir: Option<tempfile::TempDir>,
+    keep: bool,
+}
+
+impl TempDir {
+    pub fn path(&self) -> &Path {
+        self.dir.as_ref().unwrap().path()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let dir = self.dir.take().unwrap();
+        if self.keep {
+            eprintln!("keeping temporary directory {}", dir.into_path().display());
+        } else {
+            LIVE.lock().unwrap().retain(|path| path != dir.path());
+            // `dir` removes itself when dropped.
+        }
+    }
+}
+
+/// Create a new temporary directory, which will be left in place rather than removed if `keep` is true.
+pub fn dir(keep: bool) -> Result<TempDir> {
+    let dir = tempfile::Builder::new().prefix(PREFIX).tempdir()?;
+    if !keep {
+        LIVE.lock().unwrap().push(dir.path().to_owned());
+    }
+    Ok(TempDir {
+        dir: Some(dir),
+        keep,
+    })
+}
+
+/// Remove all temporary directories which have not yet been dropped.
+///
+/// This is intended to be called just before the process exits abnormally, e.g. in response to Ctrl-C.
+pub fn remove_all() {
+    for path in LIVE.lock().unwrap().drain(..) {
+        let _ = fs::remove_dir_all(path);
+    }
+}
+
+/// Remove any temporary directories left behind by earlier processes which were killed before they could clean up,
+/// returning their paths.
+///
+/// Directories modified within the last day are skipped, since they may belong to a build which is still running.
+pub fn remove_stale() -> Result<Vec<PathBuf>> {
+    let stale = find_stale()?;
+    for path in &stale {
+        fs::remove_dir_all(path)?;
+    }
+    Ok(stale)
+}
+
+/// Return the paths of any temporary directories left behind by earlier processes, as described for
+/// `remove_stale`, without removing them.
+pub fn find_stale() -> Result<Vec<PathBuf>> {
+    let cutoff = SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+    let mut stale = Vec::new();
+    for entry in fs::read_dir(env::temp_dir())? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with(PREFIX)
+            && entry.file_type()?.is_dir()
+            && entry.metadata()?.modified()? < cutoff
+        {
+            stale.push(entry.path());
+        }
+    }
+    Ok(stale)
+}
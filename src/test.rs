@@ -1,15 +1,15 @@
 #![deny(warnings)]
 
 use {
-    crate::Ctx,
-    anyhow::{anyhow, Result},
+    crate::{ComponentizeBuilder, Ctx},
+    anyhow::{anyhow, bail, Context, Result},
     async_trait::async_trait,
     once_cell::sync::Lazy,
     proptest::{
         prelude::Strategy,
         test_runner::{self, TestRng, TestRunner},
     },
-    std::{collections::HashMap, env, fs, iter, marker::PhantomData},
+    std::{env, fs, iter, marker::PhantomData, process::Command},
     tokio::runtime::Runtime,
     wasmtime::{
         component::{Component, InstancePre, Linker, ResourceTable},
@@ -20,6 +20,8 @@ use {
 
 mod echoes;
 mod echoes_generated;
+mod golden;
+mod keyvalue;
 mod tests;
 
 fn get_seed() -> Result<[u8; 32]> {
@@ -60,29 +62,65 @@ async fn make_component(
         fs::write(&path, content)?;
     }
 
-    crate::componentize(
-        Some(&tempdir.path().join("app.wit")),
-        None,
-        &[],
-        false,
-        &python_path
-            .iter()
-            .copied()
-            .chain(iter::once(tempdir.path().to_str().ok_or_else(|| {
-                anyhow!("unable to parse temporary directory path as UTF-8")
-            })?))
-            .collect::<Vec<_>>(),
-        module_worlds,
-        "app",
-        &tempdir.path().join("app.wasm"),
-        add_to_linker,
-        false,
-        &HashMap::new(),
-        &HashMap::new(),
-    )
-    .await?;
-
-    Ok(fs::read(tempdir.path().join("app.wasm"))?)
+    let wit_path = tempdir.path().join("app.wit");
+    let output_path = tempdir.path().join("app.wasm");
+    let python_path = python_path
+        .iter()
+        .copied()
+        .chain(iter::once(tempdir.path().to_str().ok_or_else(|| {
+            anyhow!("unable to parse temporary directory path as UTF-8")
+        })?))
+        .collect::<Vec<_>>();
+
+    let mut builder = ComponentizeBuilder::new()
+        .wit_path(&wit_path)
+        .python_path(&python_path)
+        .module_worlds(module_worlds)
+        .output_path(&output_path)
+        .quiet(true);
+    if let Some(add_to_linker) = add_to_linker {
+        builder = builder.add_to_linker(add_to_linker);
+    }
+    builder.build().await?;
+
+    Ok(fs::read(&output_path)?)
+}
+
+/// Check that each `wasmtime` CLI binary listed (separated as in `PATH`) in `COMPONENTIZE_PY_TEST_WASMTIME`, if
+/// set, accepts the specified component.
+///
+/// The tests themselves run components using the version of wasmtime this crate depends on, so this helps catch
+/// components which other versions (e.g. those embedded in popular hosts) would reject.
+fn check_other_wasmtimes(component: &[u8]) -> Result<()> {
+    let Some(binaries) = env::var_os("COMPONENTIZE_PY_TEST_WASMTIME") else {
+        return Ok(());
+    };
+
+    let tempdir = tempfile::tempdir()?;
+    let path = tempdir.path().join("app.wasm");
+    fs::write(&path, component)?;
+
+    for binary in env::split_paths(&binaries) {
+        let output = Command::new(&binary)
+            .arg("compile")
+            .arg("-W")
+            .arg("component-model")
+            .arg("-o")
+            .arg(tempdir.path().join("app.cwasm"))
+            .arg(&path)
+            .output()
+            .with_context(|| format!("failed to run {}", binary.display()))?;
+
+        if !output.status.success() {
+            bail!(
+                "{} rejected the component:\n{}",
+                binary.display(),
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+    }
+
+    Ok(())
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -136,6 +174,7 @@ impl<H: Host> Tester<H> {
             module_worlds,
             Some(&H::add_to_linker),
         ))?;
+        check_other_wasmtimes(component)?;
         let mut linker = Linker::<Ctx>::new(&ENGINE);
         H::add_to_linker(&mut linker)?;
         Ok(Self {
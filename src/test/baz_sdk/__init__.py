@@ -0,0 +1,7 @@
+from baz_sdk.wit import exports
+from baz_sdk.wit.imports.foo_interface import test as foo_test
+
+# This doesn't match the protocol's name, so `componentize-py.toml` names it explicitly.
+class Handler(exports.BazInterface):
+    def test(self, s: str) -> str:
+        return foo_test(f"{s} Handler.test")
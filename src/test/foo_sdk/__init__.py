@@ -0,0 +1,2 @@
+def lookup_error(e: LookupError) -> str:
+    return f"not found: {e}"
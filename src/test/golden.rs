@@ -0,0 +1,215 @@
+//! Golden tests for the generated Python bindings.
+//!
+//! Each WIT file in `src/test/golden/wit` is used to generate bindings, which are then compared file-by-file with
+//! the expected output under `src/test/golden/expected/<name>`.  Bindings are generated twice for each file to
+//! check that the output is deterministic.
+//!
+//! When a change to the generated code is intentional, run the tests with `COMPONENTIZE_PY_UPDATE_GOLDEN=1` set to
+//! overwrite the expected output with the actual output, then review the result using e.g. `git diff`.
+
+use {
+    crate::{NamingStyle, TypingStyle, VersionCollisions},
+    anyhow::{anyhow, bail, Result},
+    std::{
+        collections::{BTreeMap, HashMap},
+        env, fs,
+        path::{Path, PathBuf},
+    },
+    tempfile::TempDir,
+};
+
+/// Environment variable which, if set, causes the expected output to be overwritten rather than checked
+const UPDATE: &str = "COMPONENTIZE_PY_UPDATE_GOLDEN";
+
+fn read_tree(root: &Path) -> Result<BTreeMap<PathBuf, String>> {
+    fn visit(root: &Path, dir: &Path, files: &mut BTreeMap<PathBuf, String>) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                visit(root, &path, files)?;
+            } else {
+                files.insert(
+                    path.strip_prefix(root)?.to_owned(),
+                    fs::read_to_string(&path)?,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    let mut files = BTreeMap::new();
+    visit(root, root, &mut files)?;
+    Ok(files)
+}
+
+/// Options to generate bindings with, other than the defaults
+#[derive(Default)]
+struct Options<'a> {
+    typing_style: TypingStyle,
+    naming_style: NamingStyle,
+    version_collisions: VersionCollisions,
+    import_interface_names: HashMap<&'a str, &'a str>,
+    export_interface_names: HashMap<&'a str, &'a str>,
+}
+
+fn check(name: &str) -> Result<()> {
+    check_with(name, name, &Options::default())
+}
+
+/// Like `check`, but using the specified options and comparing with `src/test/golden/expected/<expected>`.
+fn check_with(name: &str, expected: &str, options: &Options) -> Result<()> {
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/test/golden");
+    let wit_path = golden.join("wit").join(format!("{name}.wit"));
+    let expected_path = golden.join("expected").join(expected);
+
+    let tempdir = generate(&wit_path, options)?;
+    let actual = read_tree(tempdir.path())?;
+
+    if actual != read_tree(generate(&wit_path, options)?.path())? {
+        bail!("generated code for {name} differs between runs");
+    }
+
+    if env::var_os(UPDATE).is_some() {
+        if expected_path.exists() {
+            fs::remove_dir_all(&expected_path)?;
+        }
+        for (path, contents) in &actual {
+            let path = expected_path.join(path);
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(path, contents)?;
+        }
+        return Ok(());
+    }
+
+    let expected = read_tree(&expected_path)?;
+
+    if expected.keys().ne(actual.keys()) {
+        bail!(
+            "generated files differ from expected files (set {UPDATE}=1 to update them):\n\
+             expected: {:?}\nactual: {:?}",
+            expected.keys().collect::<Vec<_>>(),
+            actual.keys().collect::<Vec<_>>()
+        );
+    }
+
+    for (path, expected) in &expected {
+        let actual = &actual[path];
+        if expected != actual {
+            return Err(anyhow!(
+                "generated code for {} differs from expected (set {UPDATE}=1 to update it):\n\
+                 --- expected\n{expected}\n--- actual\n{actual}",
+                path.display()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn generate(wit_path: &Path, options: &Options) -> Result<TempDir> {
+    let tempdir = tempfile::tempdir()?;
+
+    crate::generate_bindings(
+        wit_path,
+        None,
+        None,
+        &[],
+        false,
+        None,
+        tempdir.path(),
+        &options.import_interface_names,
+        &options.export_interface_names,
+        options.typing_style,
+        options.naming_style,
+        options.version_collisions,
+        Default::default(),
+    )?;
+
+    Ok(tempdir)
+}
+
+#[test]
+fn golden() -> Result<()> {
+    check("golden")
+}
+
+#[test]
+fn golden_modern_typing() -> Result<()> {
+    check_with(
+        "golden",
+        "golden-modern",
+        &Options {
+            typing_style: TypingStyle::Modern,
+            ..Options::default()
+        },
+    )
+}
+
+#[test]
+fn resources() -> Result<()> {
+    check("resources")
+}
+
+#[test]
+fn multi_interface() -> Result<()> {
+    check("multi-interface")
+}
+
+#[test]
+fn collisions() -> Result<()> {
+    check("collisions")
+}
+
+#[test]
+fn renames() -> Result<()> {
+    check_with(
+        "renames",
+        "renames",
+        &Options {
+            naming_style: NamingStyle::Camel,
+            import_interface_names: [("componentize-py:renames/http-types@1.0.0", "types")].into(),
+            export_interface_names: [("componentize-py:renames/handler@1.0.0", "incoming")].into(),
+            ..Options::default()
+        },
+    )
+}
+
+#[test]
+fn clocks() -> Result<()> {
+    check("clocks")
+}
+
+#[test]
+fn versions() -> Result<()> {
+    check("versions")
+}
+
+#[test]
+fn versions_duplicate_if_necessary() -> Result<()> {
+    check_with(
+        "versions",
+        "versions-duplicate-if-necessary",
+        &Options {
+            version_collisions: VersionCollisions::DuplicateIfNecessary,
+            import_interface_names: [("componentize-py:calendar/*", "calendar-*")].into(),
+            ..Options::default()
+        },
+    )
+}
+
+#[test]
+fn options() -> Result<()> {
+    check("options")
+}
+
+#[test]
+fn options_modern_typing() -> Result<()> {
+    check_with(
+        "options",
+        "options-modern",
+        &Options {
+            typing_style: TypingStyle::Modern,
+            ..Options::default()
+        },
+    )
+}
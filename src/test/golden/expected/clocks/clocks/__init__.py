@@ -0,0 +1,29 @@
+from typing import TypeVar, Generic, Union, Optional, Protocol, Tuple, List, Dict, Any, Self
+from types import TracebackType
+import sys
+if sys.version_info >= (3, 12):
+    from collections.abc import Buffer
+else:
+    Buffer = Union[bytes, bytearray, memoryview]
+from enum import Flag, Enum, auto
+from dataclasses import dataclass
+from abc import abstractmethod
+import weakref
+
+from .types import Result, Ok, Err, Some, json_dumps, json_loads
+
+class Clocks(Protocol):
+
+    @abstractmethod
+    def run(self) -> None:
+        raise NotImplementedError
+
+from .types import ExportFunction, register_export
+
+def export(function: ExportFunction) -> ExportFunction:
+    """
+    Register `function` as the implementation of the `Clocks` export of the same name.
+
+    This is an alternative to defining a `Clocks` class in the app module, which takes precedence if present.
+    """
+    return register_export("Clocks", function)
@@ -0,0 +1,72 @@
+from typing import TypeVar, Generic, Union, Optional, Protocol, Tuple, List, Dict, Any, Self
+from types import TracebackType
+import sys
+if sys.version_info >= (3, 12):
+    from collections.abc import Buffer
+else:
+    Buffer = Union[bytes, bytearray, memoryview]
+from enum import Flag, Enum, auto
+from dataclasses import dataclass
+from abc import abstractmethod
+import weakref
+
+from ..types import Result, Ok, Err, Some, json_dumps, json_loads
+from .. import time_helpers
+from datetime import datetime
+
+@dataclass
+class Datetime:
+    seconds: int
+    nanoseconds: int
+
+    def to_dict(self) -> Dict[str, Any]:
+        """
+        Returns a `dict` representation of this record containing only `dict`s, `list`s, `str`s,
+        numbers, booleans, and `None`, e.g. for use with `json.dumps`.
+        """
+        return {
+            "seconds": self.seconds,
+            "nanoseconds": self.nanoseconds,
+        }
+
+    @classmethod
+    def from_dict(cls, d: Dict[str, Any]) -> Self:
+        """
+        Returns a record created from a `dict` in the format returned by `to_dict`.
+        """
+        return cls(
+            seconds=d["seconds"],
+            nanoseconds=d["nanoseconds"],
+        )
+
+    def to_json(self) -> str:
+        """
+        Returns a JSON representation of this record, in the format described for `to_dict`.
+        """
+        return json_dumps(self.to_dict())
+
+    @classmethod
+    def from_json(cls, s: str) -> Self:
+        """
+        Returns a record parsed from JSON in the format returned by `to_json`.
+        """
+        return cls.from_dict(json_loads(s))
+
+    def to_datetime(self) -> datetime:
+        """
+        Returns this time as a timezone-aware `datetime` in UTC (see `time_helpers.to_datetime`).
+        """
+        return time_helpers.to_datetime(self.seconds, self.nanoseconds)
+
+    @classmethod
+    def from_datetime(cls, value: datetime) -> Self:
+        """
+        Returns the record corresponding to `value` (see `time_helpers.from_datetime`).
+        """
+        return cls(*time_helpers.from_datetime(value))
+
+def now() -> Datetime:
+    raise NotImplementedError
+
+def resolution() -> Datetime:
+    raise NotImplementedError
@@ -0,0 +1,43 @@
+"""
+Conversions between the representations of time used by `wasi:clocks` and Python's `datetime` module, generated
+by `componentize-py`.
+
+`wasi:clocks` represents durations (and monotonic clock instants) as integer nanoseconds, and wall clock times as
+`datetime` records containing the seconds and nanoseconds elapsed since the Unix epoch.  Python's `timedelta` and
+`datetime` have microsecond resolution, so any remaining nanoseconds are truncated when converting to them.
+"""
+from datetime import datetime, timedelta, timezone
+from typing import Tuple
+
+EPOCH = datetime(1970, 1, 1, tzinfo=timezone.utc)
+
+def to_timedelta(nanoseconds: int) -> timedelta:
+    """
+    Returns the `timedelta` corresponding to a duration in nanoseconds.
+    """
+    return timedelta(microseconds=nanoseconds // 1000)
+
+def from_timedelta(delta: timedelta) -> int:
+    """
+    Returns the number of nanoseconds in `delta`, for use as a duration.
+    """
+    return (delta // timedelta(microseconds=1)) * 1000
+
+def to_datetime(seconds: int, nanoseconds: int) -> datetime:
+    """
+    Returns the timezone-aware `datetime` (in UTC) corresponding to a wall clock time.
+    """
+    return EPOCH + timedelta(seconds=seconds, microseconds=nanoseconds // 1000)
+
+def from_datetime(value: datetime) -> Tuple[int, int]:
+    """
+    Returns the seconds and nanoseconds elapsed since the Unix epoch at `value`, for use as a wall clock time.
+
+    A naive `datetime` is assumed to be in local time, as for `datetime.timestamp`.
+    """
+    if value.tzinfo is None:
+        value = value.astimezone()
+    microseconds = (value - EPOCH) // timedelta(microseconds=1)
+    if microseconds < 0:
+        raise ValueError(f"{value} is earlier than the Unix epoch")
+    return microseconds // 1_000_000, (microseconds % 1_000_000) * 1000
@@ -0,0 +1,137 @@
+"""
+Names which are Python keywords, builtins, or otherwise likely to collide.
+"""
+from typing import TypeVar, Generic, Union, Optional, Protocol, Tuple, List, Dict, Any, Self
+from types import TracebackType
+import sys
+if sys.version_info >= (3, 12):
+    from collections.abc import Buffer
+else:
+    Buffer = Union[bytes, bytearray, memoryview]
+from enum import Flag, Enum, auto
+from dataclasses import dataclass
+from abc import abstractmethod
+import weakref
+
+from ..types import Result, Ok, Err, Some, json_dumps, json_loads
+
+@dataclass
+class Class:
+    from_: str
+    lambda_: int
+    self: bool
+    is_: Optional[int]
+
+    def to_dict(self) -> Dict[str, Any]:
+        """
+        Returns a `dict` representation of this record containing only `dict`s, `list`s, `str`s,
+        numbers, booleans, and `None`, e.g. for use with `json.dumps`.
+        """
+        return {
+            "from": self.from_,
+            "lambda": self.lambda_,
+            "self": self.self,
+            "is": self.is_,
+        }
+
+    @classmethod
+    def from_dict(cls, d: Dict[str, Any]) -> Self:
+        """
+        Returns a record created from a `dict` in the format returned by `to_dict`.
+        """
+        return cls(
+            from_=d["from"],
+            lambda_=d["lambda"],
+            self=d["self"],
+            is_=d["is"],
+        )
+
+    def to_json(self) -> str:
+        """
+        Returns a JSON representation of this record, in the format described for `to_dict`.
+        """
+        return json_dumps(self.to_dict())
+
+    @classmethod
+    def from_json(cls, s: str) -> Self:
+        """
+        Returns a record parsed from JSON in the format returned by `to_json`.
+        """
+        return cls.from_dict(json_loads(s))
+
+class Match(Enum):
+    NONE = 0
+    TRUE = 1
+    FALSE = 2
+    ASYNC = 3
+
+class Global(Flag):
+    PASS = auto()
+    YIELD = auto()
+
+@dataclass
+class Try_Raise:
+    value: str
+
+@dataclass
+class Try_Return:
+    pass
+
+Try = Union[Try_Raise, Try_Return]
+
+class With:
+
+    def __init__(self, del_: int) -> None:
+        raise NotImplementedError
+
+    def import_(self, as_: str) -> Class:
+        raise NotImplementedError
+
+    @classmethod
+    def await_(cls) -> Self:
+        raise NotImplementedError
+
+    def __enter__(self) -> Self:
+        """Returns self"""
+        return self
+
+    def __exit__(self, exc_type: type[BaseException] | None, exc_value: BaseException | None, traceback: TracebackType | None) -> bool | None:
+        """
+        Release this resource.
+        """
+        raise NotImplementedError
+
+    def __eq__(self, other: object) -> bool:
+        """
+        Returns whether `other` refers to the same resource handle as this one.
+        """
+        if not isinstance(other, With):
+            return NotImplemented
+        return self is other or (self.handle is not None and self.handle == other.handle)
+
+    def __hash__(self) -> int:
+        """
+        Returns a hash of this resource's handle.
+
+        The hash is computed the first time it's needed and stays the same once the resource has
+        been released, so the resource may remain in a `set` or `dict`.  A resource which was released
+        before ever being hashed is unhashable.
+        """
+        value = getattr(self, "_hash", None)
+        if value is None:
+            if self.handle is None:
+                raise TypeError("unhashable released resource")
+            value = self._hash = hash(self.handle)
+        return value
+
+    def __repr__(self) -> str:
+        return f"<{type(self).__module__}.{type(self).__qualname__} handle={self.handle}>"
+
+def def_(in_: List[Class], nonlocal_: Match) -> Try:
+    raise NotImplementedError
+
+def assert_(g: Global) -> None:
+    """
+    Raises: `collisions.types.Err(collisions.imports.keywords.Try)`
+    """
+    raise NotImplementedError
@@ -0,0 +1,70 @@
+from typing import TypeVar, Generic, Union, Optional, Protocol, Tuple, List, Dict, Any, Self
+from types import TracebackType
+import sys
+if sys.version_info >= (3, 12):
+    from collections.abc import Buffer
+else:
+    Buffer = Union[bytes, bytearray, memoryview]
+from enum import Flag, Enum, auto
+from dataclasses import dataclass
+from abc import abstractmethod
+import weakref
+
+from .types import Result, Ok, Err, Some, json_dumps, json_loads
+
+@dataclass
+class Frame:
+    id: int
+
+    def to_dict(self) -> dict[str, Any]:
+        """
+        Returns a `dict` representation of this record containing only `dict`s, `list`s, `str`s,
+        numbers, booleans, and `None`, e.g. for use with `json.dumps`.
+        """
+        return {
+            "id": self.id,
+        }
+
+    @classmethod
+    def from_dict(cls, d: dict[str, Any]) -> Self:
+        """
+        Returns a record created from a `dict` in the format returned by `to_dict`.
+        """
+        return cls(
+            id=d["id"],
+        )
+
+    def to_json(self) -> str:
+        """
+        Returns a JSON representation of this record, in the format described for `to_dict`.
+        """
+        return json_dumps(self.to_dict())
+
+    @classmethod
+    def from_json(cls, s: str) -> Self:
+        """
+        Returns a record parsed from JSON in the format returned by `to_json`.
+        """
+        return cls.from_dict(json_loads(s))
+
+def render(frame: Frame) -> None:
+    """
+    Raises: `golden.types.WitError`
+    """
+    raise NotImplementedError
+
+class Golden(Protocol):
+
+    @abstractmethod
+    def run(self, frames: list[Frame]) -> None:
+        raise NotImplementedError
+
+from .types import ExportFunction, register_export
+
+def export(function: ExportFunction) -> ExportFunction:
+    """
+    Register `function` as the implementation of the `Golden` export of the same name.
+
+    This is an alternative to defining a `Golden` class in the app module, which takes precedence if present.
+    """
+    return register_export("Golden", function)
@@ -0,0 +1,184 @@
+from typing import TypeVar, Generic, Union, Optional, Protocol, Tuple, List, Dict, Any, Self
+from types import TracebackType
+import sys
+if sys.version_info >= (3, 12):
+    from collections.abc import Buffer
+else:
+    Buffer = Union[bytes, bytearray, memoryview]
+from enum import Flag, Enum, auto
+from dataclasses import dataclass
+from abc import abstractmethod
+import weakref
+
+from ..types import Result, Ok, Err, Some, json_dumps, json_loads
+
+@dataclass
+class Point:
+    """
+    A point in two dimensions.
+    """
+    x: int
+    y: int
+
+    def to_dict(self) -> dict[str, Any]:
+        """
+        Returns a `dict` representation of this record containing only `dict`s, `list`s, `str`s,
+        numbers, booleans, and `None`, e.g. for use with `json.dumps`.
+        """
+        return {
+            "x": self.x,
+            "y": self.y,
+        }
+
+    @classmethod
+    def from_dict(cls, d: dict[str, Any]) -> Self:
+        """
+        Returns a record created from a `dict` in the format returned by `to_dict`.
+        """
+        return cls(
+            x=d["x"],
+            y=d["y"],
+        )
+
+    def to_json(self) -> str:
+        """
+        Returns a JSON representation of this record, in the format described for `to_dict`.
+        """
+        return json_dumps(self.to_dict())
+
+    @classmethod
+    def from_json(cls, s: str) -> Self:
+        """
+        Returns a record parsed from JSON in the format returned by `to_json`.
+        """
+        return cls.from_dict(json_loads(s))
+
+@dataclass
+class Maybe_Nothing:
+    pass
+
+@dataclass
+class Maybe_Something:
+    value: str
+
+type Maybe = Maybe_Nothing | Maybe_Something
+"""
+Something which may or may not have a value.
+"""
+
+class Color(Enum):
+    RED = 0
+    GREEN = 1
+    BLUE = 2
+
+class Permissions(Flag):
+    READ = auto()
+    WRITE = auto()
+    EXECUTE = auto()
+
+@dataclass
+class Label:
+    """
+    A labelled point, exercising nested conversions in `to_dict`/`from_dict`.
+    """
+    text: str
+    at: Point
+    kind: Maybe
+    colors: list[Color]
+    access: Permissions | None
+
+    def to_dict(self) -> dict[str, Any]:
+        """
+        Returns a `dict` representation of this record containing only `dict`s, `list`s, `str`s,
+        numbers, booleans, and `None`, e.g. for use with `json.dumps`.
+        """
+        return {
+            "text": self.text,
+            "at": self.at.to_dict(),
+            "kind": ({"tag": "nothing"} if isinstance(self.kind, Maybe_Nothing) else {"tag": "something", "value": self.kind.value}),
+            "colors": [("red", "green", "blue")[x0.value] for x0 in self.colors],
+            "access": (None if self.access is None else [x0 for i0, x0 in enumerate(("read", "write", "execute")) if self.access.value & (1 << i0)]),
+        }
+
+    @classmethod
+    def from_dict(cls, d: dict[str, Any]) -> Self:
+        """
+        Returns a record created from a `dict` in the format returned by `to_dict`.
+        """
+        return cls(
+            text=d["text"],
+            at=Point.from_dict(d["at"]),
+            kind={"nothing": Maybe_Nothing, "something": lambda: Maybe_Something(d["kind"]["value"])}[d["kind"]["tag"]](),
+            colors=[Color(("red", "green", "blue").index(x0)) for x0 in d["colors"]],
+            access=(None if d["access"] is None else Permissions(sum(1 << ("read", "write", "execute").index(x0) for x0 in d["access"]))),
+        )
+
+    def to_json(self) -> str:
+        """
+        Returns a JSON representation of this record, in the format described for `to_dict`.
+        """
+        return json_dumps(self.to_dict())
+
+    @classmethod
+    def from_json(cls, s: str) -> Self:
+        """
+        Returns a record parsed from JSON in the format returned by `to_json`.
+        """
+        return cls.from_dict(json_loads(s))
+
+class Counter:
+    """
+    A resource with a constructor, methods, and a static function.
+    """
+
+    def __init__(self, initial: int) -> None:
+        raise NotImplementedError
+
+    def increment(self) -> int:
+        raise NotImplementedError
+
+    def get(self) -> int:
+        """
+        Raises: `golden.types.WitError`
+        """
+        raise NotImplementedError
+
+    @classmethod
+    def merge(cls, a: Self, b: Self) -> Self:
+        raise NotImplementedError
+
+    def __enter__(self) -> Self:
+        """Returns self"""
+        return self
+
+    def __exit__(self, exc_type: type[BaseException] | None, exc_value: BaseException | None, traceback: TracebackType | None) -> bool | None:
+        """
+        Release this resource.
+        """
+        raise NotImplementedError
+
+    def __eq__(self, other: object) -> bool:
+        """
+        Returns whether `other` refers to the same resource handle as this one.
+        """
+        if not isinstance(other, Counter):
+            return NotImplemented
+        return self is other or (self.handle is not None and self.handle == other.handle)
+
+    def __hash__(self) -> int:
+        """
+        Returns a hash of this resource's handle.
+
+        The hash is computed the first time it's needed and stays the same once the resource has
+        been released, so the resource may remain in a `set` or `dict`.  A resource which was released
+        before ever being hashed is unhashable.
+        """
+        value = getattr(self, "_hash", None)
+        if value is None:
+            if self.handle is None:
+                raise TypeError("unhashable released resource")
+            value = self._hash = hash(self.handle)
+        return value
+
+    def __repr__(self) -> str:
+        return f"<{type(self).__module__}.{type(self).__qualname__} handle={self.handle}>"
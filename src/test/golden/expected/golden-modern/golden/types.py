@@ -0,0 +1,69 @@
+from typing import TypeVar, Generic, Union, Optional, Protocol, Tuple, List, Dict, Any, Self
+from types import TracebackType
+import sys
+if sys.version_info >= (3, 12):
+    from collections.abc import Buffer
+else:
+    Buffer = Union[bytes, bytearray, memoryview]
+from enum import Flag, Enum, auto
+from dataclasses import dataclass
+from abc import abstractmethod
+import weakref
+
+S = TypeVar('S')
+@dataclass
+class Some(Generic[S]):
+    value: S
+
+T = TypeVar('T')
+@dataclass
+class Ok(Generic[T]):
+    value: T
+
+E = TypeVar('E')
+@dataclass(frozen=True)
+class Err(Generic[E], Exception):
+    value: E
+
+class WitError(Err[str], ValueError):
+    """
+    The `Err` raised by imports whose error type is `string`, e.g. `result<T, string>`.
+
+    Being a `ValueError` whose `str()` is the message, it may be handled like any other Python exception; exports
+    may likewise raise it (or any other `Err`) to return an error to the caller.
+    """
+
+    def __str__(self) -> str:
+        return self.value
+
+type Result[T, E] = Ok[T] | Err[E]
+
+try:
+    from componentize_py_runtime import json_dumps, json_loads
+except ImportError:
+    # Either we're not running in a component or the runtime was built without `serde_json` support, so fall
+    # back to the `json` module, formatting output the same way the runtime does.
+    import json
+
+    def json_dumps(value: Any) -> str:
+        return json.dumps(value, separators=(",", ":"), ensure_ascii=False)
+
+    def json_loads(s: str) -> Any:
+        return json.loads(s)
+
+from typing import Callable
+from types import SimpleNamespace
+
+ExportFunction = TypeVar('ExportFunction', bound=Callable[..., Any])
+
+_EXPORTS: Dict[str, SimpleNamespace] = {}
+
+def register_export(protocol: str, function: ExportFunction) -> ExportFunction:
+    """
+    Register `function` as the implementation of the `protocol` export of the same name.
+
+    This is used by the generated `export` decorators.  The runtime only consults the functions registered for a
+    protocol if the app does not define a class implementing it.
+    """
+    setattr(_EXPORTS.setdefault(protocol, SimpleNamespace()), function.__name__, function)
+    return function
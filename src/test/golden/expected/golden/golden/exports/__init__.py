@@ -0,0 +1,29 @@
+from typing import TypeVar, Generic, Union, Optional, Protocol, Tuple, List, Dict, Any, Self
+from types import TracebackType
+import sys
+if sys.version_info >= (3, 12):
+    from collections.abc import Buffer
+else:
+    Buffer = Union[bytes, bytearray, memoryview]
+from enum import Flag, Enum, auto
+from dataclasses import dataclass
+from abc import abstractmethod
+import weakref
+
+from ..types import Result, Ok, Err, Some, json_dumps, json_loads
+from ..imports import types
+
+class Guest(Protocol):
+
+    @abstractmethod
+    def translate(self, p: types.Point, dx: int, dy: int) -> types.Point:
+        """
+        Move a point, failing if it would leave the grid.
+
+        Raises: `golden.types.WitError`
+        """
+        raise NotImplementedError
+
+    @abstractmethod
+    def check(self, m: types.Maybe, p: types.Permissions) -> Tuple[bool, int]:
+        raise NotImplementedError
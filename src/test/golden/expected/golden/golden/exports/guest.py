@@ -0,0 +1,46 @@
+from typing import TypeVar, Generic, Union, Optional, Protocol, Tuple, List, Dict, Any, Self
+from types import TracebackType
+import sys
+if sys.version_info >= (3, 12):
+    from collections.abc import Buffer
+else:
+    Buffer = Union[bytes, bytearray, memoryview]
+from enum import Flag, Enum, auto
+from dataclasses import dataclass
+from abc import abstractmethod
+import weakref
+
+from ..types import Result, Ok, Err, Some, json_dumps, json_loads
+
+class Widget(Protocol):
+
+    @abstractmethod
+    def __init__(self, name: str) -> None:
+        raise NotImplementedError
+
+    @abstractmethod
+    def name(self) -> str:
+        raise NotImplementedError
+
+    def __exit__(self, exc_type: type[BaseException] | None, exc_value: BaseException | None, traceback: TracebackType | None) -> bool | None:
+        """
+        Called when the last handle to this resource is dropped (e.g. by the host).
+
+        Override this to release any state associated with the resource deterministically
+        rather than waiting for it to be garbage collected.  By default, this does nothing.
+        """
+        return None
+
+    def __repr__(self) -> str:
+        handle = getattr(self, "__componentize_py_handle", None)
+        return f"<{type(self).__module__}.{type(self).__qualname__} handle={handle}>"
+
+from ..types import ExportFunction, register_export
+
+def export(function: ExportFunction) -> ExportFunction:
+    """
+    Register `function` as the implementation of the `Guest` export of the same name.
+
+    This is an alternative to defining a `Guest` class in the app module, which takes precedence if present.
+    """
+    return register_export("Guest", function)
@@ -0,0 +1,27 @@
+from typing import TypeVar, Generic, Union, Optional, Protocol, Tuple, List, Dict, Any, Self
+from types import TracebackType
+import sys
+if sys.version_info >= (3, 12):
+    from collections.abc import Buffer
+else:
+    Buffer = Union[bytes, bytearray, memoryview]
+from enum import Flag, Enum, auto
+from dataclasses import dataclass
+from abc import abstractmethod
+import weakref
+
+from ..types import Result, Ok, Err, Some, json_dumps, json_loads
+from ..imports import componentize_py_multi_interface_types
+from ..imports import componentize_py_other_types
+
+class Handler(Protocol):
+
+    @abstractmethod
+    def handle(self, request: componentize_py_multi_interface_types.Request) -> componentize_py_other_types.Response:
+        raise NotImplementedError
+
+class Clock(Protocol):
+
+    @abstractmethod
+    def now(self) -> int:
+        raise NotImplementedError
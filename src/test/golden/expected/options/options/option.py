@@ -0,0 +1,42 @@
+"""
+Helpers for nested `option` values, generated by `componentize-py`.
+
+An `option<T>` is normally represented as either `None` or a value of type `T`.  When `T` is itself an `option`,
+that would make `none` indistinguishable from `some(none)`, so the `some` case is wrapped in `Some` instead: the
+values of an `option<option<u32>>` are `None`, `Some(None)`, and e.g. `Some(42)`.  Passing a bare value such as
+`42` where `Some(...)` is required is an error.
+"""
+from typing import Any, Optional, TypeVar
+
+from .types import Some
+
+T = TypeVar('T')
+
+def flatten(value: Optional[Some[Optional[T]]]) -> Optional[T]:
+    """
+    Returns the payload of a nested `option`, treating `Some(None)` the same as `None`.
+
+    Raises `TypeError` if `value` is neither `None` nor an instance of `Some`.
+    """
+    if value is None:
+        return None
+    check(value)
+    return value.value
+
+def nest(value: Optional[T]) -> Optional[Some[Optional[T]]]:
+    """
+    Returns `value` as a nested `option`, mapping `None` to `None` and any other value `x` to `Some(x)`.
+
+    This is the inverse of `flatten` for all values other than `Some(None)`.
+    """
+    return None if value is None else Some(value)
+
+def check(value: Any) -> None:
+    """
+    Raises `TypeError` unless `value` is a valid nested `option`, i.e. either `None` or an instance of `Some`.
+    """
+    if value is not None and not isinstance(value, Some):
+        raise TypeError(
+            f"expected `None` or `Some(...)` for a nested `option`, got {type(value).__qualname__}; "
+            f"use `Some({value!r})` (or `option.nest`) to wrap it"
+        )
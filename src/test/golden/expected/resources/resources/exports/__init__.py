@@ -0,0 +1,28 @@
+from typing import TypeVar, Generic, Union, Optional, Protocol, Tuple, List, Dict, Any, Self
+from types import TracebackType
+import sys
+if sys.version_info >= (3, 12):
+    from collections.abc import Buffer
+else:
+    Buffer = Union[bytes, bytearray, memoryview]
+from enum import Flag, Enum, auto
+from dataclasses import dataclass
+from abc import abstractmethod
+import weakref
+
+from ..types import Result, Ok, Err, Some, json_dumps, json_loads
+from ..exports import sessions
+
+class Sessions(Protocol):
+
+    @abstractmethod
+    def take_snapshot(self, session: sessions.Session) -> sessions.Snapshot:
+        raise NotImplementedError
+
+    @abstractmethod
+    def issue_ticket(self, session: sessions.Session) -> sessions.Ticket:
+        raise NotImplementedError
+
+    @abstractmethod
+    def redeem_ticket(self, ticket: sessions.Ticket) -> Optional[sessions.Session]:
+        raise NotImplementedError
@@ -0,0 +1,97 @@
+from typing import TypeVar, Generic, Union, Optional, Protocol, Tuple, List, Dict, Any, Self
+from types import TracebackType
+import sys
+if sys.version_info >= (3, 12):
+    from collections.abc import Buffer
+else:
+    Buffer = Union[bytes, bytearray, memoryview]
+from enum import Flag, Enum, auto
+from dataclasses import dataclass
+from abc import abstractmethod
+import weakref
+
+from ..types import Result, Ok, Err, Some, json_dumps, json_loads
+from ..imports import files
+
+class Session(Protocol):
+    """
+    A session which owns a set of files.
+    """
+
+    @abstractmethod
+    def __init__(self) -> None:
+        raise NotImplementedError
+
+    @abstractmethod
+    def attach(self, file: files.File) -> None:
+        raise NotImplementedError
+
+    @abstractmethod
+    def files(self) -> List[files.File]:
+        raise NotImplementedError
+
+    @abstractmethod
+    def current(self) -> Optional[files.File]:
+        raise NotImplementedError
+
+    def __exit__(self, exc_type: type[BaseException] | None, exc_value: BaseException | None, traceback: TracebackType | None) -> bool | None:
+        """
+        Called when the last handle to this resource is dropped (e.g. by the host).
+
+        Override this to release any state associated with the resource deterministically
+        rather than waiting for it to be garbage collected.  By default, this does nothing.
+        """
+        return None
+
+    def __repr__(self) -> str:
+        handle = getattr(self, "__componentize_py_handle", None)
+        return f"<{type(self).__module__}.{type(self).__qualname__} handle={handle}>"
+
+@dataclass
+class Snapshot:
+    owner: Session
+    open: List[files.File]
+
+class Ticket:
+    """
+    An opaque token which may later be redeemed for the session it was issued by.
+    """
+
+    __componentize_py_token__ = True
+
+    def __init__(self, value: Any = None) -> None:
+        """
+        Wrap `value` in a token which may be passed to the host.
+
+        Any other value lowered where this resource is expected is wrapped automatically, so e.g. a `str`
+        or `int` may be returned directly.  Returning the same instance more than once reuses its handle
+        for as long as the host holds it, whereas each automatically wrapped value gets a new handle.
+
+        Lowering a token as `own` transfers its handle to the host, which keeps the token (and thus
+        `value`) alive until it drops the handle, at which point `__exit__` is called.  A token the host
+        hands back is the same instance that was originally lowered, with `value` unchanged.
+        """
+        self.value = value
+
+    def __exit__(self, exc_type: type[BaseException] | None, exc_value: BaseException | None, traceback: TracebackType | None) -> bool | None:
+        """
+        Called when the last handle to this resource is dropped (e.g. by the host).
+
+        Override this to release any state associated with the resource deterministically
+        rather than waiting for it to be garbage collected.  By default, this does nothing.
+        """
+        return None
+
+    def __repr__(self) -> str:
+        handle = getattr(self, "__componentize_py_handle", None)
+        return f"<{type(self).__module__}.{type(self).__qualname__} handle={handle}>"
+
+from ..types import ExportFunction, register_export
+
+def export(function: ExportFunction) -> ExportFunction:
+    """
+    Register `function` as the implementation of the `Sessions` export of the same name.
+
+    This is an alternative to defining a `Sessions` class in the app module, which takes precedence if present.
+    """
+    return register_export("Sessions", function)
@@ -0,0 +1,144 @@
+from typing import TypeVar, Generic, Union, Optional, Protocol, Tuple, List, Dict, Any, Self
+from types import TracebackType
+import sys
+if sys.version_info >= (3, 12):
+    from collections.abc import Buffer
+else:
+    Buffer = Union[bytes, bytearray, memoryview]
+from enum import Flag, Enum, auto
+from dataclasses import dataclass
+from abc import abstractmethod
+import weakref
+
+from ..types import Result, Ok, Err, Some, json_dumps, json_loads
+
+@dataclass
+class Error_NotFound:
+    pass
+
+@dataclass
+class Error_Denied:
+    value: str
+
+Error = Union[Error_NotFound, Error_Denied]
+
+@dataclass
+class Metadata:
+    """
+    Metadata describing a file.
+    """
+    size: int
+    readonly: bool
+
+    def to_dict(self) -> Dict[str, Any]:
+        """
+        Returns a `dict` representation of this record containing only `dict`s, `list`s, `str`s,
+        numbers, booleans, and `None`, e.g. for use with `json.dumps`.
+        """
+        return {
+            "size": self.size,
+            "readonly": self.readonly,
+        }
+
+    @classmethod
+    def from_dict(cls, d: Dict[str, Any]) -> Self:
+        """
+        Returns a record created from a `dict` in the format returned by `to_dict`.
+        """
+        return cls(
+            size=d["size"],
+            readonly=d["readonly"],
+        )
+
+    def to_json(self) -> str:
+        """
+        Returns a JSON representation of this record, in the format described for `to_dict`.
+        """
+        return json_dumps(self.to_dict())
+
+    @classmethod
+    def from_json(cls, s: str) -> Self:
+        """
+        Returns a record parsed from JSON in the format returned by `to_json`.
+        """
+        return cls.from_dict(json_loads(s))
+
+class File:
+    """
+    An open file.
+    """
+
+    def __init__(self, path: str) -> None:
+        raise NotImplementedError
+
+    def read(self, len: int) -> bytes:
+        """
+        Raises: `resources.types.Err(resources.imports.files.Error)`
+        """
+        raise NotImplementedError
+
+    def write(self, data: Union[Buffer, bytes]) -> int:
+        """
+        Raises: `resources.types.Err(resources.imports.files.Error)`
+        """
+        raise NotImplementedError
+
+    def open_at(self, path: str) -> Self:
+        """
+        Open another file relative to this one.
+
+        Raises: `resources.types.Err(resources.imports.files.Error)`
+        """
+        raise NotImplementedError
+
+    def copy_to(self, other: Self) -> None:
+        """
+        Raises: `resources.types.Err(resources.imports.files.Error)`
+        """
+        raise NotImplementedError
+
+    @classmethod
+    def stat(cls, path: str) -> Optional[Metadata]:
+        raise NotImplementedError
+
+    def __enter__(self) -> Self:
+        """Returns self"""
+        return self
+
+    def __exit__(self, exc_type: type[BaseException] | None, exc_value: BaseException | None, traceback: TracebackType | None) -> bool | None:
+        """
+        Release this resource.
+        """
+        raise NotImplementedError
+
+    def __eq__(self, other: object) -> bool:
+        """
+        Returns whether `other` refers to the same resource handle as this one.
+        """
+        if not isinstance(other, File):
+            return NotImplemented
+        return self is other or (self.handle is not None and self.handle == other.handle)
+
+    def __hash__(self) -> int:
+        """
+        Returns a hash of this resource's handle.
+
+        The hash is computed the first time it's needed and stays the same once the resource has
+        been released, so the resource may remain in a `set` or `dict`.  A resource which was released
+        before ever being hashed is unhashable.
+        """
+        value = getattr(self, "_hash", None)
+        if value is None:
+            if self.handle is None:
+                raise TypeError("unhashable released resource")
+            value = self._hash = hash(self.handle)
+        return value
+
+    def __repr__(self) -> str:
+        return f"<{type(self).__module__}.{type(self).__qualname__} handle={self.handle}>"
+
+def open_all(paths: List[str]) -> List[Result[File, Error]]:
+    """
+    Open several files at once.
+    """
+    raise NotImplementedError
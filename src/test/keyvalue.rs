@@ -0,0 +1,230 @@
+//! Opt-in host implementation of `wasi:keyvalue/store`, plus tests which exercise it end-to-end.
+//!
+//! Buckets are kept in memory and shared by identifier across all instances in the process, so each test should
+//! use its own identifiers.
+
+use {
+    super::{Ctx, Tester, SEED},
+    anyhow::{anyhow, Result},
+    async_trait::async_trait,
+    once_cell::sync::Lazy,
+    std::{
+        collections::{BTreeMap, HashMap},
+        sync::{Arc, Mutex},
+    },
+    wasi::keyvalue::store::{self as kv, Host, HostBucket, KeyResponse},
+    wasmtime::{
+        component::{InstancePre, Linker, Resource},
+        Store,
+    },
+    wasmtime_wasi::WasiView,
+};
+
+wasmtime::component::bindgen!({
+    path: "src/test/wit/keyvalue.wit",
+    world: "keyvalue-tests",
+    async: true,
+    trappable_imports: true,
+    with: {
+        "wasi:keyvalue/store/bucket": MyBucket,
+    },
+});
+
+type Data = Arc<Mutex<BTreeMap<String, Vec<u8>>>>;
+
+static BUCKETS: Lazy<Mutex<HashMap<String, Data>>> = Lazy::new(Default::default);
+
+/// Maximum number of keys returned by each call to `bucket.list-keys`, kept small so that tests exercise paging.
+const PAGE_SIZE: usize = 2;
+
+pub struct MyBucket(Data);
+
+fn bucket(identifier: &str) -> Data {
+    BUCKETS
+        .lock()
+        .unwrap()
+        .entry(identifier.to_owned())
+        .or_default()
+        .clone()
+}
+
+#[async_trait]
+impl Host for Ctx {
+    async fn open(&mut self, identifier: String) -> Result<Result<Resource<MyBucket>, kv::Error>> {
+        Ok(Ok(self.table().push(MyBucket(bucket(&identifier)))?))
+    }
+}
+
+#[async_trait]
+impl HostBucket for Ctx {
+    async fn get(
+        &mut self,
+        this: Resource<MyBucket>,
+        key: String,
+    ) -> Result<Result<Option<Vec<u8>>, kv::Error>> {
+        Ok(Ok(self
+            .table()
+            .get(&this)?
+            .0
+            .lock()
+            .unwrap()
+            .get(&key)
+            .cloned()))
+    }
+
+    async fn set(
+        &mut self,
+        this: Resource<MyBucket>,
+        key: String,
+        value: Vec<u8>,
+    ) -> Result<Result<(), kv::Error>> {
+        self.table()
+            .get(&this)?
+            .0
+            .lock()
+            .unwrap()
+            .insert(key, value);
+        Ok(Ok(()))
+    }
+
+    async fn delete(
+        &mut self,
+        this: Resource<MyBucket>,
+        key: String,
+    ) -> Result<Result<(), kv::Error>> {
+        self.table().get(&this)?.0.lock().unwrap().remove(&key);
+        Ok(Ok(()))
+    }
+
+    async fn exists(
+        &mut self,
+        this: Resource<MyBucket>,
+        key: String,
+    ) -> Result<Result<bool, kv::Error>> {
+        Ok(Ok(self
+            .table()
+            .get(&this)?
+            .0
+            .lock()
+            .unwrap()
+            .contains_key(&key)))
+    }
+
+    async fn list_keys(
+        &mut self,
+        this: Resource<MyBucket>,
+        cursor: Option<u64>,
+    ) -> Result<Result<KeyResponse, kv::Error>> {
+        let start = usize::try_from(cursor.unwrap_or(0))?;
+        let keys = self
+            .table()
+            .get(&this)?
+            .0
+            .lock()
+            .unwrap()
+            .keys()
+            .skip(start)
+            .take(PAGE_SIZE + 1)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        Ok(Ok(if keys.len() > PAGE_SIZE {
+            KeyResponse {
+                keys: keys[..PAGE_SIZE].to_vec(),
+                cursor: Some(u64::try_from(start + PAGE_SIZE)?),
+            }
+        } else {
+            KeyResponse { keys, cursor: None }
+        }))
+    }
+
+    async fn drop(&mut self, this: Resource<MyBucket>) -> Result<()> {
+        Ok(self.table().delete(this).map(|_| ())?)
+    }
+}
+
+struct KeyValueHost;
+
+#[async_trait]
+impl super::Host for KeyValueHost {
+    type World = KeyvalueTests;
+
+    fn add_to_linker(linker: &mut Linker<Ctx>) -> Result<()> {
+        wasmtime_wasi::add_to_linker_async(linker)?;
+        KeyvalueTests::add_to_linker(linker, |ctx| ctx)?;
+        Ok(())
+    }
+
+    async fn instantiate_pre(store: &mut Store<Ctx>, pre: InstancePre<Ctx>) -> Result<Self::World> {
+        Ok(KeyvalueTestsPre::new(pre)?.instantiate_async(store).await?)
+    }
+}
+
+static TESTER: Lazy<Tester<KeyValueHost>> = Lazy::new(|| {
+    Tester::<KeyValueHost>::new(
+        include_str!("wit/keyvalue.wit"),
+        &[("app.py", include_str!("python_source/keyvalue.py"))],
+        &[],
+        &[],
+        *SEED,
+    )
+    .unwrap()
+});
+
+fn check<T>(result: Result<T, kv::Error>) -> Result<T> {
+    result.map_err(|e| anyhow!("{e:?}"))
+}
+
+#[test]
+fn keyvalue_bucket_handles() -> Result<()> {
+    TESTER.test(|world, store, runtime| {
+        runtime.block_on(async {
+            // The guest opens the bucket and hands the (host-owned) handle back to us...
+            let bucket = check(world.call_open_bucket(&mut *store, "handles").await?)?;
+
+            // ...which we then lend back to the guest.
+            for expected in 1..=3 {
+                let count = check(
+                    world
+                        .call_increment(&mut *store, Resource::new_borrow(bucket.rep()), "counter")
+                        .await?,
+                )?;
+                assert_eq!(expected, count);
+            }
+
+            store.data_mut().table().delete(bucket)?;
+
+            assert_eq!(
+                Some(b"3".as_slice()),
+                self::bucket("handles")
+                    .lock()
+                    .unwrap()
+                    .get("counter")
+                    .map(Vec::as_slice)
+            );
+
+            Ok(())
+        })
+    })
+}
+
+#[test]
+fn keyvalue_list_keys() -> Result<()> {
+    let expected = ["a", "b", "c", "d", "e"];
+    {
+        let data = bucket("list-keys");
+        let mut data = data.lock().unwrap();
+        for key in expected {
+            data.insert(key.to_owned(), Vec::new());
+        }
+    }
+
+    TESTER.test(|world, store, runtime| {
+        assert_eq!(
+            expected.map(str::to_owned).as_slice(),
+            check(runtime.block_on(world.call_list_keys(store, "list-keys"))?)?
+        );
+
+        Ok(())
+    })
+}
@@ -1,14 +1,18 @@
+import array
+import sys
 import traceback
 import tests
 import resource_borrow_export
+import resource_dtor_export
 import resource_aggregates
 import resource_alias1
 import resource_borrow_in_record
 from tests import exports, imports
 from tests.imports import resource_borrow_import
 from tests.imports import simple_import_and_export
+from tests.imports import wit_errors
 from tests.exports import resource_alias2
-from tests.types import Result, Ok, Err
+from tests.types import Result, Ok, Err, WitError
 from typing import Tuple, List, Optional
 from foo_sdk.wit import exports as foo_exports
 from foo_sdk.wit.imports.foo_interface import test as foo_test
@@ -30,6 +34,44 @@ class ResourceBorrowExport(exports.ResourceBorrowExport):
     def foo(self, v: resource_borrow_export.Thing) -> int:
         return v.value + 2
 
+class ResourceDtorExport(exports.ResourceDtorExport):
+    def dropped(self) -> List[int]:
+        return resource_dtor_export.DROPPED
+
+class ResourceTokenExport(exports.ResourceTokenExport):
+    def issue(self, v: str) -> exports.resource_token_export.Token:
+        # Plain values are wrapped in a `Token` automatically when lowered.
+        return v  # type: ignore
+
+    def peek(self, t: exports.resource_token_export.Token) -> str:
+        return t.value
+
+    def redeem(self, t: exports.resource_token_export.Token) -> str:
+        return t.value
+
+class ListSources(exports.ListSources):
+    def squares(self, n: int) -> List[int]:
+        return (i * i for i in range(n))  # type: ignore
+
+    def reverse(self, v: List[float]) -> List[float]:
+        return tuple(reversed(v))  # type: ignore
+
+    def halves(self, v: List[float]) -> List[float]:
+        return array.array("d", (x / 2 for x in v))  # type: ignore
+
+    def characters(self, s: str) -> List[str]:
+        return s  # type: ignore
+
+    def lengths(self, v: List[str]) -> List[str]:
+        return {x: len(x) for x in v}  # type: ignore
+
+class WitErrors(exports.WitErrors):
+    def parse(self, s: str) -> int:
+        try:
+            return wit_errors.parse(s)
+        except ValueError as e:
+            raise WitError(f"{e} (in guest)")
+
 class ResourceWithLists(exports.ResourceWithLists):
     pass
 
@@ -134,6 +176,18 @@ class Tests(tests.Tests):
                 return f.read()
         except:
             raise Err(traceback.format_exc())
+
+    def exit(self, code: Optional[int]) -> None:
+        sys.exit(code)
+
+    async def coroutine(self) -> int:
+        return 42
+
+    def lookup(self, key: str) -> str:
+        return {"a": "b"}[key]
+
+    def lookup_index(self, key: str) -> int:
+        return {"a": 0}[key]
    
 class FooInterface(foo_exports.FooInterface):
     def test(self, s: str) -> str:
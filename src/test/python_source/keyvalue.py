@@ -0,0 +1,24 @@
+import keyvalue_tests
+from keyvalue_tests.imports import store
+from typing import List
+
+class KeyvalueTests(keyvalue_tests.KeyvalueTests):
+    def open_bucket(self, identifier: str) -> store.Bucket:
+        return store.open(identifier)
+
+    def increment(self, bucket: store.Bucket, key: str) -> int:
+        value = bucket.get(key)
+        count = 1 if value is None else int(value.decode()) + 1
+        bucket.set(key, str(count).encode())
+        return count
+
+    def list_keys(self, identifier: str) -> List[str]:
+        with store.open(identifier) as bucket:
+            keys = []
+            cursor = None
+            while True:
+                response = bucket.list_keys(cursor)
+                keys.extend(response.keys)
+                cursor = response.cursor
+                if cursor is None:
+                    return keys
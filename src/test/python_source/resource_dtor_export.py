@@ -0,0 +1,17 @@
+from tests.exports import resource_dtor_export
+from types import TracebackType
+from typing import List, Optional
+
+DROPPED: List[int] = []
+
+class Thing(resource_dtor_export.Thing):
+    def __init__(self, v: int):
+        self.value = v
+
+    def __exit__(
+        self,
+        exc_type: Optional[type[BaseException]],
+        exc_value: Optional[BaseException],
+        traceback: Optional[TracebackType],
+    ) -> None:
+        DROPPED.append(self.value)
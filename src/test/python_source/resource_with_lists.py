@@ -6,22 +6,23 @@ class Thing(resource_with_lists.Thing):
     def __init__(self, v: bytes):
         x = bytearray(v)
         x.extend(b" Thing.__init__")
-        self.value = HostThing(bytes(x))
+        # Any object supporting the buffer protocol may be passed where `bytes` is expected.
+        self.value = HostThing(x)
 
-    def foo(self) -> bytes:
+    def foo(self) -> bytearray:
         x = bytearray(self.value.foo())
         x.extend(b" Thing.foo")
-        return bytes(x)
+        return x
 
     def bar(self, v: bytes):
         x = bytearray(v)
         x.extend(b" Thing.bar")
-        self.value.bar(bytes(x))
+        self.value.bar(memoryview(x))
 
     @staticmethod
-    def baz(v: bytes) -> bytes:
+    def baz(v: bytes) -> memoryview:
         x = bytearray(v)
         x.extend(b" Thing.baz")
-        y = bytearray(HostThing.baz(bytes(x)))
+        y = bytearray(HostThing.baz(x))
         y.extend(b" Thing.baz again")
-        return bytes(y)
\ No newline at end of file
+        return memoryview(y)
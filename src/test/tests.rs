@@ -10,7 +10,7 @@ use {
         component::{InstancePre, Linker, Resource, ResourceAny},
         Store,
     },
-    wasmtime_wasi::{DirPerms, FilePerms, WasiCtxBuilder, WasiView},
+    wasmtime_wasi::{DirPerms, FilePerms, I32Exit, WasiCtxBuilder, WasiView},
 };
 
 wasmtime::component::bindgen!({
@@ -52,6 +52,17 @@ mod bar_sdk {
     });
 }
 
+mod baz_sdk {
+    wasmtime::component::bindgen!({
+        path: "src/test/baz_sdk/wit",
+        world: "baz-world",
+        async: true,
+        with: {
+            "foo:sdk/foo-interface": super::foo_sdk::foo::sdk::foo_interface,
+        },
+    });
+}
+
 pub struct ThingU32(u32);
 pub struct ThingList(Vec<u8>);
 pub struct ThingString(String);
@@ -78,6 +89,7 @@ const GUEST_CODE: &[(&str, &str)] = load_guest_code!(
     "app.py",
     "resource_import_and_export.py",
     "resource_borrow_export.py",
+    "resource_dtor_export.py",
     "resource_with_lists.py",
     "resource_aggregates.py",
     "resource_alias1.py",
@@ -137,12 +149,33 @@ impl super::Host for BarHost {
     }
 }
 
+struct BazHost;
+
+#[async_trait]
+impl super::Host for BazHost {
+    type World = baz_sdk::BazWorld;
+
+    fn add_to_linker(_linker: &mut Linker<Ctx>) -> Result<()> {
+        unreachable!()
+    }
+
+    async fn instantiate_pre(store: &mut Store<Ctx>, pre: InstancePre<Ctx>) -> Result<Self::World> {
+        Ok(baz_sdk::BazWorldPre::new(pre)?
+            .instantiate_async(store)
+            .await?)
+    }
+}
+
 static TESTER: Lazy<Tester<Host>> = Lazy::new(|| {
     Tester::<Host>::new(
         include_str!("wit/tests.wit"),
         GUEST_CODE,
         &["src/test"],
-        &[("foo_sdk", "foo-world"), ("bar_sdk", "bar-world")],
+        &[
+            ("foo_sdk", "foo-world"),
+            ("bar_sdk", "bar-world"),
+            ("baz_sdk", "baz-world"),
+        ],
         *SEED,
     )
     .unwrap()
@@ -303,6 +336,123 @@ fn resource_borrow_export() -> Result<()> {
     })
 }
 
+#[test]
+fn resource_dtor_export() -> Result<()> {
+    TESTER.test(|world, store, runtime| {
+        runtime.block_on(async {
+            let instance = world.componentize_py_test_resource_dtor_export();
+            let thing = instance.thing();
+            let thing1 = thing.call_constructor(&mut *store, 42).await?;
+            let thing2 = thing.call_constructor(&mut *store, 43).await?;
+
+            assert!(instance.call_dropped(&mut *store).await?.is_empty());
+
+            thing1.resource_drop_async(&mut *store).await?;
+
+            assert_eq!(vec![42], instance.call_dropped(&mut *store).await?);
+
+            thing2.resource_drop_async(&mut *store).await?;
+
+            assert_eq!(vec![42, 43], instance.call_dropped(&mut *store).await?);
+
+            Ok(())
+        })
+    })
+}
+
+#[test]
+fn resource_token_export() -> Result<()> {
+    TESTER.test(|world, store, runtime| {
+        runtime.block_on(async {
+            let instance = world.componentize_py_test_resource_token_export();
+            let token = instance.call_issue(&mut *store, "Hi").await?;
+
+            assert_eq!("Hi", instance.call_peek(&mut *store, token).await?);
+            assert_eq!("Hi", instance.call_redeem(&mut *store, token).await?);
+
+            Ok(())
+        })
+    })
+}
+
+#[test]
+fn list_sources() -> Result<()> {
+    TESTER.test(|world, store, runtime| {
+        runtime.block_on(async {
+            let instance = world.componentize_py_test_list_sources();
+
+            assert_eq!(
+                vec![0, 1, 4, 9],
+                instance.call_squares(&mut *store, 4).await?
+            );
+            assert_eq!(
+                vec![3.0, 2.0, 1.0],
+                instance.call_reverse(&mut *store, &[1.0, 2.0, 3.0]).await?
+            );
+            assert_eq!(
+                vec![0.5, 1.0, 1.5],
+                instance.call_halves(&mut *store, &[1.0, 2.0, 3.0]).await?
+            );
+
+            Ok(())
+        })
+    })
+}
+
+#[test]
+fn list_from_str_traps() -> Result<()> {
+    TESTER.test(|world, store, runtime| {
+        runtime
+            .block_on(
+                world
+                    .componentize_py_test_list_sources()
+                    .call_characters(store, "abc"),
+            )
+            .expect_err("expected returning a `str` as a `list<string>` to trap");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn list_from_dict_traps() -> Result<()> {
+    TESTER.test(|world, store, runtime| {
+        runtime
+            .block_on(
+                world
+                    .componentize_py_test_list_sources()
+                    .call_lengths(store, &["a".to_owned(), "bc".to_owned()]),
+            )
+            .expect_err("expected returning a `dict` as a `list<string>` to trap");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn wit_errors() -> Result<()> {
+    #[async_trait]
+    impl componentize_py::test::wit_errors::Host for Ctx {
+        async fn parse(&mut self, s: String) -> Result<Result<u32, String>> {
+            Ok(s.parse().map_err(|_| format!("not a number: {s:?}")))
+        }
+    }
+
+    TESTER.test(|world, store, runtime| {
+        runtime.block_on(async {
+            let instance = world.componentize_py_test_wit_errors();
+
+            assert_eq!(Ok(42), instance.call_parse(&mut *store, "42").await?);
+            assert_eq!(
+                Err("not a number: \"forty-two\" (in guest)".to_owned()),
+                instance.call_parse(&mut *store, "forty-two").await?
+            );
+
+            Ok(())
+        })
+    })
+}
+
 #[test]
 fn resource_with_lists() -> Result<()> {
     use componentize_py::test::resource_with_lists::{Host, HostThing};
@@ -796,6 +946,23 @@ fn multiworld() -> Result<()> {
     })
 }
 
+#[test]
+fn export_class_from_config() -> Result<()> {
+    TESTER.test_with::<BazHost>(|world, store, runtime| {
+        runtime.block_on(async {
+            let result = world
+                .baz_sdk_baz_interface()
+                .call_test(store, "Howdy")
+                .await?;
+
+            // `baz_sdk`'s `componentize-py.toml` names `baz_sdk.Handler` as the implementation of `baz-interface`.
+            assert_eq!("Howdy Handler.test HostFoo::test", result);
+
+            Ok(())
+        })
+    })
+}
+
 #[test]
 fn filesystem() -> Result<()> {
     let filename = "foo.txt";
@@ -822,3 +989,72 @@ fn filesystem() -> Result<()> {
         })
     })
 }
+
+#[test]
+fn coroutine_export_traps() -> Result<()> {
+    TESTER.test(|world, store, runtime| {
+        runtime
+            .block_on(world.call_coroutine(store))
+            .expect_err("expected a coroutine-returning export to trap");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn exception_mapped_to_err() -> Result<()> {
+    TESTER.test(|world, store, runtime| {
+        assert_eq!(
+            Ok("b".to_owned()),
+            runtime.block_on(world.call_lookup(&mut *store, "a"))?
+        );
+
+        // `foo_sdk`'s `componentize-py.toml` maps `LookupError` (and thus `KeyError`) to an error string.
+        assert_eq!(
+            Err("not found: 'c'".to_owned()),
+            runtime.block_on(world.call_lookup(&mut *store, "c"))?
+        );
+
+        Ok(())
+    })
+}
+
+#[test]
+fn exception_mapped_to_wrong_type_traps() -> Result<()> {
+    TESTER.test(|world, store, runtime| {
+        assert_eq!(
+            Ok(0),
+            runtime.block_on(world.call_lookup_index(&mut *store, "a"))?
+        );
+
+        // The `LookupError` mapping produces a string, which doesn't match this function's `u32` error type.
+        runtime
+            .block_on(world.call_lookup_index(&mut *store, "c"))
+            .expect_err("expected a mapping producing the wrong error type to trap");
+
+        Ok(())
+    })
+}
+
+#[test]
+fn sys_exit() -> Result<()> {
+    for (code, expected) in [(None, 0), (Some(0), 0), (Some(3), 1)] {
+        TESTER.test(|world, store, runtime| {
+            let error = runtime
+                .block_on(world.call_exit(store, code))
+                .expect_err("expected `sys.exit` to exit the instance");
+
+            assert_eq!(
+                expected,
+                error
+                    .downcast_ref::<I32Exit>()
+                    .ok_or_else(|| anyhow!("expected `I32Exit`; got {error:?}"))?
+                    .0
+            );
+
+            Ok(())
+        })?;
+    }
+
+    Ok(())
+}
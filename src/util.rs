@@ -1,5 +1,9 @@
 use {
-    std::{iter, ops::Deref},
+    std::{
+        env, iter,
+        ops::Deref,
+        path::{Path, PathBuf},
+    },
     wit_parser::{Flags, FlagsRepr, Results, Type},
 };
 
@@ -36,3 +40,19 @@ impl Types for Flags {
         }
     }
 }
+
+/// Return the directory in which to cache build artifacts across runs, if one can be determined.
+///
+/// This is `$COMPONENTIZE_PY_CACHE_DIR` if set, or else a `componentize-py` subdirectory of the platform's
+/// conventional per-user cache directory.
+pub fn cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("COMPONENTIZE_PY_CACHE_DIR") {
+        Some(PathBuf::from(dir))
+    } else {
+        env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+            .map(|dir| dir.join("componentize-py"))
+    }
+}
@@ -0,0 +1,245 @@
+//! Support for packaging generated bindings as a wheel (per the binary distribution format specification, i.e.
+//! PEP 427), so they can be installed using `pip` or published to a package index rather than vendored.
+
+use {
+    anyhow::{Context, Result},
+    sha2::{Digest, Sha256},
+    std::{
+        fs,
+        io::Write,
+        path::{Path, PathBuf},
+    },
+    zip::{write::SimpleFileOptions, CompressionMethod, DateTime, ZipWriter},
+};
+
+/// Describes the distribution a wheel contains
+pub struct Distribution<'a> {
+    /// Project name, e.g. `my-world`
+    pub name: &'a str,
+    /// PEP 440 version, e.g. `1.2.3`
+    pub version: &'a str,
+    /// One-line description
+    pub summary: &'a str,
+    /// Python versions the code supports, e.g. `>=3.11`
+    pub requires_python: &'a str,
+}
+
+/// Package the contents of the directory `source` as a wheel for `distribution`, writing it to `output_dir` and
+/// returning its path.
+///
+/// Each top-level package in `source` gets a `py.typed` marker (per PEP 561), since generated bindings are fully
+/// annotated.  Entries are added in sorted order with fixed timestamps so that the same inputs always produce the
+/// same wheel.
+pub fn write(source: &Path, output_dir: &Path, distribution: &Distribution) -> Result<PathBuf> {
+    let entries = entries(source, distribution)?;
+
+    fs::create_dir_all(output_dir).with_context(|| output_dir.display().to_string())?;
+    let path = output_dir.join(format!(
+        "{}-{}-py3-none-any.whl",
+        escape(distribution.name),
+        distribution.version
+    ));
+
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .last_modified_time(DateTime::default())
+        .unix_permissions(0o644);
+    let mut zip =
+        ZipWriter::new(fs::File::create(&path).with_context(|| path.display().to_string())?);
+    for (name, contents) in entries {
+        zip.start_file(name, options)?;
+        zip.write_all(&contents)?;
+    }
+    zip.finish()?;
+
+    Ok(path)
+}
+
+/// Return the name and contents of each file in the wheel, in the order they should be written (i.e. with the
+/// `.dist-info` directory last and `RECORD` at the very end, as recommended by PEP 427).
+fn entries(source: &Path, distribution: &Distribution) -> Result<Vec<(String, Vec<u8>)>> {
+    fn visit(dir: &Path, prefix: &str, entries: &mut Vec<(String, Vec<u8>)>) -> Result<()> {
+        let mut paths = fs::read_dir(dir)
+            .with_context(|| dir.display().to_string())?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<Result<Vec<_>>>()?;
+        paths.sort();
+
+        for path in paths {
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .context("non-UTF-8 path")?;
+            let name = format!("{prefix}{name}");
+            if path.is_dir() {
+                if prefix.is_empty() {
+                    entries.push((format!("{name}/py.typed"), Vec::new()));
+                }
+                visit(&path, &format!("{name}/"), entries)?;
+            } else {
+                entries.push((
+                    name,
+                    fs::read(&path).with_context(|| path.display().to_string())?,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    let mut entries = Vec::new();
+    visit(source, "", &mut entries)?;
+
+    let Distribution {
+        name,
+        version,
+        summary,
+        requires_python,
+    } = distribution;
+    let generator = format!("componentize-py {}", env!("CARGO_PKG_VERSION"));
+    let dist_info = format!("{}-{version}.dist-info", escape(name));
+
+    entries.push((
+        format!("{dist_info}/METADATA"),
+        format!(
+            "Metadata-Version: 2.1\nName: {name}\nVersion: {version}\nSummary: {summary}\n\
+             Requires-Python: {requires_python}\n"
+        )
+        .into_bytes(),
+    ));
+    entries.push((
+        format!("{dist_info}/WHEEL"),
+        format!("Wheel-Version: 1.0\nGenerator: {generator}\nRoot-Is-Purelib: true\nTag: py3-none-any\n")
+            .into_bytes(),
+    ));
+
+    let mut record = String::new();
+    for (name, contents) in &entries {
+        record.push_str(&format!(
+            "{name},sha256={},{}\n",
+            base64_url(&Sha256::digest(contents)),
+            contents.len()
+        ));
+    }
+    record.push_str(&format!("{dist_info}/RECORD,,\n"));
+    entries.push((format!("{dist_info}/RECORD"), record.into_bytes()));
+
+    Ok(entries)
+}
+
+/// Convert `version` (a WIT package version, i.e. a semantic version) to an equivalent PEP 440 version.
+///
+/// Pre-release identifiers starting with `alpha`, `beta`, or `rc` become the corresponding PEP 440 pre-release
+/// segments and any others become a development release, numbered using the digits they contain, e.g.
+/// `0.2.0-rc-2023-11-10` becomes `0.2.0rc20231110`.  Build metadata is dropped.
+pub fn pep440_version(version: &semver::Version) -> String {
+    let base = format!("{}.{}.{}", version.major, version.minor, version.patch);
+    if version.pre.is_empty() {
+        return base;
+    }
+
+    let pre = version.pre.as_str().to_lowercase();
+    let (segment, rest) = [("alpha", "a"), ("beta", "b"), ("rc", "rc")]
+        .into_iter()
+        .find_map(|(prefix, segment)| Some((segment, pre.strip_prefix(prefix)?)))
+        .unwrap_or((".dev", &pre));
+    let digits = rest
+        .chars()
+        .filter(char::is_ascii_digit)
+        .collect::<String>();
+    let number = match digits.trim_start_matches('0') {
+        "" => "0",
+        number => number,
+    };
+
+    format!("{base}{segment}{number}")
+}
+
+/// Escape a project name for use in a wheel's file name or `.dist-info` directory name, i.e. replace each run of
+/// `-`, `_`, and `.` characters with a single `_`.
+fn escape(name: &str) -> String {
+    name.split(['-', '_', '.'])
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// Encode `bytes` using the URL-safe Base64 alphabet without padding, as required for `RECORD` digests.
+fn base64_url(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (index, &byte)| {
+            bits | (u32::from(byte) << (16 - 8 * index))
+        });
+        for index in 0..=chunk.len() {
+            encoded.push(char::from(
+                ALPHABET[((bits >> (18 - 6 * index)) & 0x3f) as usize],
+            ));
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_converted() -> Result<()> {
+        for (semver, pep440) in [
+            ("1.2.3", "1.2.3"),
+            ("0.2.0-rc-2023-11-10", "0.2.0rc20231110"),
+            ("1.0.0-alpha.2", "1.0.0a2"),
+            ("1.0.0-beta", "1.0.0b0"),
+            ("1.0.0-draft+build.5", "1.0.0.dev0"),
+        ] {
+            assert_eq!(pep440, pep440_version(&semver::Version::parse(semver)?));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn wheel_entries_recorded() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir_all(dir.path().join("my_world/imports"))?;
+        fs::write(dir.path().join("my_world/__init__.py"), "")?;
+        fs::write(dir.path().join("my_world/imports/__init__.py"), "x = 1\n")?;
+
+        let entries = entries(
+            dir.path(),
+            &Distribution {
+                name: "my-world",
+                version: "1.2.3",
+                summary: "Bindings",
+                requires_python: ">=3.11",
+            },
+        )?;
+        let names = entries
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                "my_world/py.typed",
+                "my_world/__init__.py",
+                "my_world/imports/__init__.py",
+                "my_world-1.2.3.dist-info/METADATA",
+                "my_world-1.2.3.dist-info/WHEEL",
+                "my_world-1.2.3.dist-info/RECORD",
+            ],
+            names
+        );
+
+        let record = String::from_utf8(entries.last().unwrap().1.clone())?;
+        // SHA-256 of `x = 1\n`
+        assert!(record.contains(
+            "my_world/imports/__init__.py,sha256=nia_NpkRxFwkPGhBR7I_yeHc_PJX0pmhxjIBam_NM_Q,6\n"
+        ));
+        assert!(record.ends_with("my_world-1.2.3.dist-info/RECORD,,\n"));
+
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use std::{
+    env,
     io::Write,
     path::{Path, PathBuf},
     process::Stdio,
@@ -18,6 +19,55 @@ fn cli_example() -> anyhow::Result<()> {
     )?;
     let path = dir.path().join("cli");
 
+    Command::cargo_bin("componentize-py")?
+        .current_dir(&path)
+        .args([
+            "-d",
+            "../wit",
+            "-w",
+            "wasi:cli/command@0.2.0",
+            "componentize",
+            "app",
+            "-o",
+            "cli.wasm",
+        ])
+        .assert()
+        .success()
+        .stdout("Component built successfully\n");
+
+    for wasmtime in wasmtimes() {
+        Command::new(wasmtime)
+            .current_dir(&path)
+            .args(["run", "cli.wasm"])
+            .assert()
+            .success()
+            .stdout("Hello, world!\n");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn cli_example_with_export_decorator() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    fs_extra::copy_items(
+        &["./examples/cli", "./wit"],
+        dir.path(),
+        &CopyOptions::new(),
+    )?;
+    let path = dir.path().join("cli");
+
+    std::fs::write(
+        path.join("app.py"),
+        "from command.exports import run as run_interface
+
+
+@run_interface.export
+def run() -> None:
+    print(\"Hello, world!\")
+",
+    )?;
+
     Command::cargo_bin("componentize-py")?
         .current_dir(&path)
         .args([
@@ -44,6 +94,48 @@ fn cli_example() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[test]
+fn cli_example_with_misspelled_method() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    fs_extra::copy_items(
+        &["./examples/cli", "./wit"],
+        dir.path(),
+        &CopyOptions::new(),
+    )?;
+    let path = dir.path().join("cli");
+
+    std::fs::write(
+        path.join("app.py"),
+        "from command import exports
+
+
+class Run(exports.Run):
+    def runn(self) -> None:
+        print(\"Hello, world!\")
+",
+    )?;
+
+    Command::cargo_bin("componentize-py")?
+        .current_dir(&path)
+        .args([
+            "-d",
+            "../wit",
+            "-w",
+            "wasi:cli/command@0.2.0",
+            "componentize",
+            "app",
+            "-o",
+            "cli.wasm",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "`app.Run` does not implement `run` (did you mean to name `runn` `run`?)",
+        ));
+
+    Ok(())
+}
+
 #[test]
 fn http_example() -> anyhow::Result<()> {
     let dir = tempfile::tempdir()?;
@@ -157,17 +249,19 @@ fn matrix_math_example() -> anyhow::Result<()> {
         .success()
         .stdout("Component built successfully\n");
 
-    Command::new("wasmtime")
-        .current_dir(&path)
-        .args([
-            "run",
-            "matrix-math.wasm",
-            "[[1, 2], [4, 5], [6, 7]]",
-            "[[1, 2, 3], [4, 5, 6]]",
-        ])
-        .assert()
-        .success()
-        .stdout("matrix_multiply received arguments [[1, 2], [4, 5], [6, 7]] and [[1, 2, 3], [4, 5, 6]]\n[[9, 12, 15], [24, 33, 42], [34, 47, 60]]\n");
+    for wasmtime in wasmtimes() {
+        Command::new(wasmtime)
+            .current_dir(&path)
+            .args([
+                "run",
+                "matrix-math.wasm",
+                "[[1, 2], [4, 5], [6, 7]]",
+                "[[1, 2, 3], [4, 5, 6]]",
+            ])
+            .assert()
+            .success()
+            .stdout("matrix_multiply received arguments [[1, 2], [4, 5], [6, 7]] and [[1, 2, 3], [4, 5, 6]]\n[[9, 12, 15], [24, 33, 42], [34, 47, 60]]\n");
+    }
 
     Ok(())
 }
@@ -287,6 +381,16 @@ fn tcp_example() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Return the `wasmtime` CLI binaries to run the example components with: those listed (separated as in `PATH`)
+/// in `COMPONENTIZE_PY_TEST_WASMTIME` if set, e.g. to check the components against several versions of wasmtime,
+/// or else just whichever `wasmtime` is in `PATH`.
+fn wasmtimes() -> Vec<PathBuf> {
+    match env::var_os("COMPONENTIZE_PY_TEST_WASMTIME") {
+        Some(binaries) => env::split_paths(&binaries).collect(),
+        None => vec![PathBuf::from("wasmtime")],
+    }
+}
+
 fn venv_path(path: &Path) -> PathBuf {
     path.join(".venv")
         .join(if cfg!(windows) { "Scripts" } else { "bin" })